@@ -44,60 +44,18 @@
 //!
 //! “CAPTION” keyword can contain objects in both VALUE and OPTIONAL fileds.
 
-use crate::cursor::REGEX_EMPTY_LINE;
 use crate::data::StringOrObject;
 use crate::data::SyntaxT;
-use crate::parser::Parser;
+use crate::parser::{ParseGranularity, Parser};
+use crate::regexps::{REGEX_AFFILIATED, REGEX_EMPTY_LINE};
 use regex::{Match, Regex};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::str::FromStr;
 
-lazy_static! {
-
-   /// Regexp matching any affiliated keyword
-   ///
-   /// This is different from original implementation for several reasons:
-   ///
-   /// ERE regexes support explicit numbering for capture groups,
-   /// which also can be repetetive. Rust's regex is PCRE, and does
-   /// not have such feature.
-   ///
-   /// Secondly, it uses different capture group arrangement to
-   /// simplify parsing and serialization of the keywords
-   ///
-   /// Thirdly, translation from old keywords already built-in
-   ///
-   /// Capture groups are named after each Keyword respectively.
-   ///
-   /// If you want to use group numbers:
-   /// CAPTION keyword is captured to group 1
-   /// RESULTS keyword is captured to group 2
-   ///
-   /// Secondary value of CAPTION or RESULTS is captured to group 3
-   ///
-   /// HEADER keyword is captured to group 4
-   /// PLOT keyword is captured to group 5
-   /// NAME keyword is captured to group 6
-   /// ATTR_ exported attribute is captured to group 7
-   ///
-   /// Warning! If you add more keywords then you must update this regex!
-   /// Original elisp implementation dynamically creates this regex based on
-   /// definitions lists of dual,regular and attribute keywords.
-   /// While this is possible to do in rust, and maybe it will be required in
-   /// the future, for now due laziness and lack of time static regex will be used.
-   ///
-   /// elisp: `org-element--affiliated-re`
-   pub static ref REGEX_AFFILIATED: Regex = Regex::new(
-           &format!(
-              r"(?i)^[ \t]*{}|{}|{}|{}|{}[ \t]*",
-              r"#\+(?:(?:(?P<CAPTION>CAPTION)|(?P<RESULTS>RESULTS?))(?:\[(?P<SECONDARY>.*)\])?",   // DUAL
-              r"(?P<HEADER>HEADERS?)",
-              r"(?P<PLOT>PLOT)",
-              r"(?P<NAME>(?:DATA|LABEL|NAME|RESNAME|(?:S(?:OURC|RCNAM)|TBLNAM)E))",
-              r"(?P<ATTR>ATTR_[-_A-Za-z0-9]+)):")
-       ).unwrap();
-}
+/// Keywords which can have a secondary value, in brackets
+/// elisp: `org-element-dual-keywords`
+pub const ORG_ELEMENT_DUAL_KEYWORDS: [&str; 2] = ["CAPTION", "RESULTS"];
 
 /// Since CAPTION is both DUAL and PARSED DualVal has to be able to store Strings or StringOrObject
 #[derive(Default, Debug, PartialEq)]
@@ -131,6 +89,10 @@ pub struct AffiliatedData<'a> {
 
     /// MULTI
     pub attr: HashMap<String, Vec<Cow<'a, str>>>,
+
+    /// Beginning of the element proper, right after its
+    /// affiliated keywords (integer).
+    pub post_affiliated: usize,
 }
 
 impl<'a> Default for AffiliatedData<'a> {
@@ -142,6 +104,7 @@ impl<'a> Default for AffiliatedData<'a> {
             plot: None,
             results: None,
             attr: HashMap::new(),
+            post_affiliated: 0,
         }
     }
 }
@@ -168,27 +131,32 @@ impl<'a> Parser<'a> {
     ///
     /// NB: it looks like this function parses objects ignoring parser granularity settings
     ///
-    /// "FIXME: currently CAPTION's values are not parsed into objects and can only contain
-    /// raw strings for now for the following reasons:
-    ///
-    /// - Original algorithm does not take into account granularity, and it is probably a bug.
-    ///
-    /// - It is unclear what should be the type of the field that stores the
-    /// object since it can contain many objects.
-    ///
-    /// - It is unclear who should be the "parent" of these objects.  parse-objects function says
-    /// that: "Eventually, if both ACC and PARENT are nil, the common parent is the list of
-    /// objects itself." - It is hard to encode this into a type system, since in all other
-    /// cases, apart from affiliated keywords, objects parents are nodes of syntax trees
-    /// (ACC or PARENT)
-    pub fn collect_affiliated_keywords(&self, limit: usize) -> (usize, Option<AffiliatedData>) {
+    /// CAPTION's values, the secondary one included, are parsed into objects
+    /// only when granularity is `Object`, they are raw strings otherwise.
+    /// Original algorithm does not take granularity into account, which is
+    /// probably a bug. Parsed objects have no parent, like in
+    /// `org-element--parse-objects` called without ACC nor PARENT: they are
+    /// a secondary string, located in the buffer.
+    pub fn collect_affiliated_keywords(&self, limit: usize) -> (usize, Option<AffiliatedData<'a>>) {
         if !self.cursor.borrow().is_bol() {
             return (self.cursor.borrow().pos(), None);
         }
         let origin = self.cursor.borrow().pos();
-        let _restrict = |that| SyntaxT::Keyword.can_contain(that);
+        // Parses the text between `start` and `end`, blanks around excluded
+        let caption = |start: usize, end: usize| {
+            let raw = &self.input[start..end];
+            let start = start + (raw.len() - raw.trim_start().len());
+            let end = start + raw.trim().len();
+            if self.granularity == ParseGranularity::Object {
+                StringOrObject::Parsed(
+                    self.parse_objects(start, end, |that| SyntaxT::Keyword.can_contain(that)),
+                )
+            } else {
+                StringOrObject::Raw(Cow::from(&self.input[start..end]))
+            }
+        };
 
-        let mut output: AffiliatedData = Default::default();
+        let mut output: AffiliatedData<'a> = Default::default();
 
         loop {
             let maybe_affiliated = capturing_at!(REGEX_AFFILIATED, self);
@@ -221,10 +189,15 @@ impl<'a> Parser<'a> {
             };
 
             match matched.0 {
-                "CAPTION" => output.caption.push(DualVal {
-                    value: StringOrObject::Raw(value),
-                    secondary: secondary_value.map(StringOrObject::Raw),
-                }),
+                "CAPTION" => {
+                    let line = self.cursor.borrow().pos();
+                    output.caption.push(DualVal {
+                        value: caption(value_begin, value_end),
+                        secondary: captures
+                            .name("SECONDARY")
+                            .map(|sec| caption(line + sec.start(), line + sec.end())),
+                    })
+                }
 
                 "RESULTS" => {
                     output.results = Some(DualVal {
@@ -258,18 +231,19 @@ impl<'a> Parser<'a> {
             return (origin, None);
         }
 
+        output.post_affiliated = self.cursor.borrow().pos();
         return (origin, Some(output));
     }
 }
 
 mod test {
-    use super::REGEX_AFFILIATED;
     use crate::affiliated::DualVal;
     use crate::cursor::{is_multiline_regex, Cursor};
     use crate::data::RepeaterType::CatchUp;
-    use crate::data::StringOrObject;
+    use crate::data::{StringOrObject, Syntax, SyntaxT};
     use crate::parser::ParseGranularity;
     use crate::parser::Parser;
+    use crate::regexps::REGEX_AFFILIATED;
     use regex::Match;
     use std::borrow::Cow;
     use std::collections::HashMap;
@@ -370,6 +344,29 @@ mod test {
         );
         assert_eq!(test_attrs, collected.attr);
 
+        assert_eq!(1, collected.caption.len());
+        fn plain_text<'a>(caption: &StringOrObject<'a>) -> Vec<(&'a str, usize)> {
+            match caption {
+                StringOrObject::Parsed(objects) => objects
+                    .iter()
+                    .map(|o| match o.data {
+                        Syntax::PlainText(text) => (text, o.location.start),
+                        _ => panic!("expected plain text"),
+                    })
+                    .collect(),
+                StringOrObject::Raw(_) => panic!("expected parsed caption"),
+            }
+        }
+        assert_eq!(
+            vec![("org-rs", 16)],
+            plain_text(&collected.caption[0].value)
+        );
+        let secondary = collected.caption[0].secondary.as_ref().unwrap();
+        assert_eq!(vec![("GIT", 10)], plain_text(secondary));
+
+        // Captions are raw strings unless objects are parsed
+        let p = Parser::new(text.as_str(), ParseGranularity::Element);
+        let collected = p.collect_affiliated_keywords(text.len()).1.unwrap();
         let mut test_caption: Vec<DualVal<StringOrObject>> = vec![];
         test_caption.push(DualVal {
             value: StringOrObject::Raw(Cow::from("org-rs")),
@@ -378,4 +375,53 @@ mod test {
 
         assert_eq!(test_caption, collected.caption);
     }
+
+    #[test]
+    fn parsed_caption() {
+        let text = "#+CAPTION[a [[link]]]:  A /word/ here \n| a |\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let table = &section.children.borrow()[0];
+        let caption = &table.caption()[0];
+        let types = |caption: &StringOrObject| match caption {
+            StringOrObject::Parsed(objects) => objects
+                .iter()
+                .map(|o| (SyntaxT::from(&o.data), o.location.start, o.location.end))
+                .collect::<Vec<_>>(),
+            StringOrObject::Raw(_) => panic!("expected parsed caption"),
+        };
+        assert_eq!(
+            vec![
+                (SyntaxT::PlainText, 24, 26),
+                (SyntaxT::Italic, 26, 33),
+                (SyntaxT::PlainText, 33, 37),
+            ],
+            types(&caption.value)
+        );
+        match &caption.value {
+            StringOrObject::Parsed(objects) => match objects[1].children.borrow()[0].data {
+                Syntax::PlainText(text) => assert_eq!("word", text),
+                _ => panic!("expected plain text"),
+            },
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            vec![(SyntaxT::PlainText, 10, 12), (SyntaxT::Link, 12, 20)],
+            types(caption.secondary.as_ref().unwrap())
+        );
+    }
+
+    #[test]
+    fn post_affiliated() {
+        let text = "#+NAME: p\n#+CAPTION: c\nParagraph\n\nPlain\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let paragraphs = section.children.borrow();
+        assert_eq!(0, paragraphs[0].location.start);
+        assert_eq!(23, paragraphs[0].post_affiliated());
+        assert_eq!(34, paragraphs[1].location.start);
+        assert_eq!(34, paragraphs[1].post_affiliated());
+    }
 }
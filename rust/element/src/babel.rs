@@ -14,40 +14,234 @@
 //    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::affiliated::AffiliatedData;
-use crate::data::SyntaxNode;
+use crate::data::{Interval, Syntax, SyntaxNode};
 use crate::parser::Parser;
-use regex::Regex;
-
-lazy_static! {
-    pub static ref REGEX_BABEL_CALL: Regex = Regex::new(r"\+CALL:").unwrap();
-}
 
 #[derive(Debug)]
 pub struct BabelCallData<'a> {
     /// Name of code block being called (string).
-    pub call: &'a str,
+    call: &'a str,
 
     /// Header arguments applied to the named code block (string or nil).
-    pub inside_header: Option<&'a str>,
+    inside_header: Option<&'a str>,
 
     /// Arguments passed to the code block (string or nil).
-    pub arguments: Option<&'a str>,
+    arguments: Option<&'a str>,
 
     /// Header arguments applied to the calling instance (string or nil).
-    pub end_header: Option<&'a str>,
+    end_header: Option<&'a str>,
 
     /// Raw call, as Org syntax (string).
-    pub value: &'a str,
+    value: &'a str,
+}
+
+impl<'a> BabelCallData<'a> {
+    /// Name of code block being called
+    pub fn call(&self) -> &'a str {
+        self.call
+    }
+
+    /// Header arguments applied to the named code block,
+    /// without brackets
+    pub fn inside_header(&self) -> Option<&'a str> {
+        self.inside_header
+    }
+
+    /// Arguments passed to the code block, without parentheses
+    pub fn arguments(&self) -> Option<&'a str> {
+        self.arguments
+    }
+
+    /// Header arguments applied to the calling instance,
+    /// as written, brackets included
+    pub fn end_header(&self) -> Option<&'a str> {
+        self.end_header
+    }
+
+    /// Raw call, i.e. everything after `#+CALL:`
+    pub fn value(&self) -> &'a str {
+        self.value
+    }
+}
+
+/// Parses paired brackets at the beginning of `text`.
+/// `open` is the opening bracket, either `[`, `(` or `{`, other
+/// brackets are ignored. Returns the contents, without the brackets, and the
+/// position following the closing bracket. Returns None if `text` does
+/// not start with `open` or brackets are unbalanced.
+///
+/// elisp: `org-element--parse-paired-brackets`
+pub(crate) fn parse_paired_brackets(text: &str, open: char) -> Option<(&str, usize)> {
+    let close = match open {
+        '[' => ']',
+        '(' => ')',
+        '{' => '}',
+        _ => return None,
+    };
+    if !text.starts_with(open) {
+        return None;
+    }
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some((&text[1..i], i + 1));
+            }
+        }
+    }
+    None
+}
+
+fn non_blank(s: &str) -> Option<&str> {
+    if s.trim().is_empty() {
+        None
+    } else {
+        Some(s)
+    }
 }
 
 impl<'a> Parser<'a> {
-    // TODO implement babel_call_parser
+    /// Parse a babel call.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which car is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and cdr is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose car is `babel-call' and cdr is a plist
+    /// containing `:call', `:inside-header', `:arguments',
+    /// `:end-header', `:begin', `:end', `:value', `:post-blank' and
+    /// `:post-affiliated' as keywords."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L1753
+    /// (defun org-element-babel-call-parser (limit affiliated)
+    ///
+    /// Line is expected to look like
+    /// `#+CALL: name[inside-header](arguments)[end-header]`,
+    /// all parts but the name being optional.
     pub fn babel_call_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let post_affiliated = c.pos();
+        let before_blank = c.goto_next_line();
+        let line = self.input[post_affiliated..before_blank].trim_end();
+
+        // (search-forward ":" before-blank t)
+        // (skip-chars-forward " \t")
+        let value = match line.find(':') {
+            Some(colon) => line[colon + 1..].trim_start_matches(|c| c == ' ' || c == '\t'),
+            None => "",
+        };
+
+        let call_end = value
+            .find(|c| "[]()".contains(c))
+            .unwrap_or_else(|| value.len());
+        let call = value[..call_end].trim();
+        let mut rest = &value[call_end..];
+
+        let inside_header = parse_paired_brackets(rest, '[').map(|(header, next)| {
+            rest = &rest[next..];
+            header
+        });
+        let arguments = parse_paired_brackets(rest, '(').and_then(|(arguments, next)| {
+            rest = &rest[next..];
+            non_blank(arguments)
+        });
+        let end_header = non_blank(rest.trim());
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::BabelCall(Box::new(BabelCallData {
+                call,
+                inside_header,
+                arguments,
+                end_header,
+                value,
+            })),
+            Interval { start, end },
+            None,
+            post_blank,
+            affiliated,
+        )
+    }
+}
+
+mod test {
+    use super::{parse_paired_brackets, BabelCallData};
+    use crate::data::{Interval, Syntax, SyntaxNode};
+    use crate::parser::{ParseGranularity, Parser};
+
+    fn babel_call<'a, 'b>(node: &'b SyntaxNode<'a>) -> &'b BabelCallData<'a> {
+        match &node.data {
+            Syntax::BabelCall(b) => b,
+            _ => panic!("expected babel call"),
+        }
+    }
+
+    #[test]
+    fn babel_call_accessors() {
+        let line = "#+CALL: square[:eval yes](x=4)[:results silent]";
+        let data = BabelCallData {
+            call: &line[8..14],
+            inside_header: Some(&line[15..24]),
+            arguments: Some(&line[26..29]),
+            end_header: Some(&line[30..47]),
+            value: &line[8..],
+        };
+        assert_eq!("square", data.call());
+        assert_eq!(Some(":eval yes"), data.inside_header());
+        assert_eq!(Some("x=4"), data.arguments());
+        assert_eq!(Some("[:results silent]"), data.end_header());
+        assert_eq!("square[:eval yes](x=4)[:results silent]", data.value());
+        assert_eq!(line[26..].as_ptr(), data.arguments().unwrap().as_ptr());
+    }
+
+    #[test]
+    fn paired_brackets() {
+        assert_eq!(
+            Some(("a [b] c", 9)),
+            parse_paired_brackets("[a [b] c] d", '[')
+        );
+        assert_eq!(Some(("x, (y)", 8)), parse_paired_brackets("(x, (y))", '('));
+        assert_eq!(None, parse_paired_brackets("[unbalanced", '['));
+        assert_eq!(None, parse_paired_brackets("a[b]", '['));
+        assert_eq!(Some(("a {b}", 7)), parse_paired_brackets("{a {b}}c", '{'));
+    }
+
+    #[test]
+    fn babel_call_parser() {
+        let text = "#+CALL: square[:eval yes](x=4)[:results silent]  \n\n#+call: name\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.babel_call_parser(text.len(), 0, None);
+        assert_eq!(Interval { start: 0, end: 51 }, node.location);
+        assert_eq!(1, node.post_blank);
+        let call = babel_call(&node);
+        assert_eq!("square", call.call());
+        assert_eq!(Some(":eval yes"), call.inside_header());
+        assert_eq!(Some("x=4"), call.arguments());
+        assert_eq!(Some("[:results silent]"), call.end_header());
+        assert_eq!("square[:eval yes](x=4)[:results silent]", call.value());
+
+        parser.cursor.borrow_mut().set(51);
+        let node = parser.babel_call_parser(text.len(), 51, None);
+        assert_eq!(text.len(), node.location.end);
+        let call = babel_call(&node);
+        assert_eq!("name", call.call());
+        assert_eq!(None, call.inside_header());
+        assert_eq!(None, call.arguments());
+        assert_eq!(None, call.end_header());
+
+        let text = "#+CALL: f()";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.babel_call_parser(text.len(), 0, None);
+        assert_eq!("f", babel_call(&node).call());
+        assert_eq!(None, babel_call(&node).arguments());
     }
 }
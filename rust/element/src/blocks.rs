@@ -14,224 +14,1199 @@
 //    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::affiliated::AffiliatedData;
+use crate::babel::parse_paired_brackets;
 use crate::data::LineNumberingMode;
-use crate::data::SyntaxNode;
+use crate::data::{InlineSrcBlockData, Interval, Syntax, SyntaxNode};
 use crate::parser::Parser;
+use crate::regexps::{
+    REGEX_BLOCK_BEGIN_LINE, REGEX_CODE_LINE_TO_ESCAPE, REGEX_DYNAMIC_BLOCK_BEGIN,
+    REGEX_DYNAMIC_BLOCK_END, REGEX_ESCAPED_CODE_LINE, REGEX_EXAMPLE_BLOCK_BEGIN,
+    REGEX_EXPORT_BLOCK_BEGIN, REGEX_INLINE_SRC_BLOCK, REGEX_SRC_BLOCK_BEGIN, REGEX_SRC_BLOCK_END,
+    REGEX_SWITCH_KEEP_LABELS, REGEX_SWITCH_LABEL_FORMAT, REGEX_SWITCH_NUMBER_LINES,
+    REGEX_SWITCH_PRESERVE_INDENT, REGEX_SWITCH_REMOVE_LABELS,
+};
 use regex::Regex;
-
-lazy_static! {
-    /// Used to identify the  Inline Comments, Blocks, Babel Calls, Dynamic Blocks and Keywords.
-    pub static ref REGEX_STARTS_WITH_HASHTAG: Regex = Regex::new(r"[ \t]*#").unwrap();
-
-    /// Used to identify Comments. Used together with REGEX_STARTS_WITH_HASHTAG
-    pub static ref REGEX_COLON_OR_EOL: Regex = Regex::new(r"(?: |$)").unwrap();
-
-    /// Used to identify center, comment, example, export, quote, source, verse
-    /// and special blocks. Used together with REGEX_STARTS_WITH_HASHTAG
-    pub static ref REGEX_BLOCK_BEGIN: Regex = Regex::new(r"\+BEGIN_(\S+)").unwrap();
-
-    pub static ref REGEX_DYNAMIC_BLOCK: Regex = Regex::new(r"\+BEGIN:? ").unwrap();
-
-}
+use std::borrow::Cow;
 
 /// Greater element
 #[derive(Debug)]
 pub struct DynamicBlockData<'a> {
     /// Block's parameters (string).
-    arguments: &'a str,
+    pub arguments: Option<&'a str>,
 
     /// Block's name (string).
-    block_name: &'a str,
-
-    /// Drawer's name (string).
-    drawer_name: &'a str,
+    pub block_name: &'a str,
 }
 
 #[derive(Debug)]
 pub struct CommentBlockData<'a> {
     /// Comments, without block's boundaries (string).
-    value: &'a str,
+    /// Commas protecting lines from being parsed are removed.
+    pub value: Cow<'a, str>,
 }
 
 #[derive(Debug)]
 pub struct ExampleBlockData<'a> {
     /// Format string used to write labels in current block,
     /// if different from org_coderef_label_format (string or nil).
-    label_fmt: Option<&'a str>,
-
-    ///Language of the code in the block, if specified (string or nil).
-    language: Option<&'a str>,
+    pub label_fmt: Option<&'a str>,
 
     /// Non_nil if code lines should be numbered.
     /// A `new` value starts numbering from 1 wheareas
     /// `continued` resume numbering from previous numbered block
     /// (symbol new, continued or nil).
-    number_lines: Option<LineNumberingMode>,
-
-    /// Block's options located on the block's opening line (string)
-    options: &'a str,
-
-    /// Optional header arguments (string or nil)
-    parameters: Option<&'a str>,
+    pub number_lines: Option<LineNumberingMode>,
 
     /// Non_nil when indentation within the block mustn't be modified
     /// upon export (boolean).
-    preserve_indent: bool,
+    pub preserve_indent: bool,
 
     /// Non_nil if labels should be kept visible upon export (boolean).
-    retain_labels: bool,
+    pub retain_labels: bool,
 
     /// Optional switches for code block export (string or nil).
-    switches: Option<&'a str>,
+    pub switches: Option<&'a str>,
 
     /// Non_nil if links to labels contained in the block should
     /// display the label instead of the line number (boolean).
-    use_labels: bool,
+    pub use_labels: bool,
 
     /// Contents (string).
-    value: &'a str,
+    /// Commas protecting lines from being parsed are removed.
+    pub value: Cow<'a, str>,
 }
 
 #[derive(Debug)]
 pub struct ExportBlockData<'a> {
-    ///Related back_end's name (string).
-    type_s: &'a str,
+    ///Related back_end's name (string or nil).
+    /// It is written as it appears, org upcases it.
+    pub type_s: Option<&'a str>,
 
     ///Contents (string)
-    value: &'a str,
+    /// Commas protecting lines from being parsed are removed.
+    pub value: Cow<'a, str>,
 }
 
 #[derive(Debug)]
 pub struct SpecialBlockData<'a> {
     /// Block's name (string).
-    type_s: &'a str,
+    pub type_s: &'a str,
     /// Raw contents in block (string).
-    raw_value: &'a str,
+    pub raw_value: &'a str,
 }
 
 #[derive(Debug)]
 pub struct SrcBlockData<'a> {
     /// Format string used to write labels in current block,
     /// if different from org_coderef_label_format (string or nil).
-    label_fmt: Option<&'a str>,
+    pub label_fmt: Option<&'a str>,
 
     /// Language of the code in the block, if specified (string or nil).
-    language: Option<&'a str>,
+    pub language: Option<&'a str>,
 
     /// Non_nil if code lines should be numbered.
     /// A `new` value starts numbering from 1 wheareas
     /// `continued` resume numbering from previous
     /// numbered block (symbol new, continued or nil).
-    number_lines: Option<LineNumberingMode>,
+    pub number_lines: Option<LineNumberingMode>,
 
     /// Optional header arguments (string or nil).
-    parameters: Option<&'a str>,
+    pub parameters: Option<&'a str>,
 
     /// Non_nil when indentation within the block
     /// mustn't be modified upon export (boolean).
-    preserve_indent: bool,
+    pub preserve_indent: bool,
     ///Non_nil if labels should be kept visible upon export (boolean).
-    retain_labels: bool,
+    pub retain_labels: bool,
 
     /// Optional switches for code block export (string or nil).
-    switches: Option<&'a str>,
+    pub switches: Option<&'a str>,
 
     /// Non_nil if links to labels contained in the block
     /// should display the label instead of the line number (boolean).
+    pub use_labels: bool,
+
+    /// Source code (string).
+    /// Commas protecting lines from being parsed are removed.
+    pub value: Cow<'a, str>,
+}
+
+/// Returns `s` with commas protecting lines inside of blocks removed
+/// i.e., ",* Not a headline" becomes "* Not a headline"
+/// and ",#+KEYWORD:" becomes "#+KEYWORD:"
+///
+/// elisp: `org-unescape-code-in-string`
+pub fn unescape_code_in_string(s: &str) -> Cow<str> {
+    if !REGEX_ESCAPED_CODE_LINE.is_match(s) {
+        return Cow::Borrowed(s);
+    }
+    let mut result = String::with_capacity(s.len());
+    let mut last = 0;
+    for cap in REGEX_ESCAPED_CODE_LINE.captures_iter(s) {
+        let comma = cap.get(1).unwrap();
+        result.push_str(&s[last..comma.start()]);
+        last = comma.end();
+    }
+    result.push_str(&s[last..]);
+    Cow::Owned(result)
+}
+
+/// Returns `s` with commas protecting lines that would otherwise be
+/// parsed as headlines or keywords, the reverse of
+/// `unescape_code_in_string`
+///
+/// elisp: `org-escape-code-in-string`
+pub fn escape_code_in_string(s: &str) -> Cow<str> {
+    REGEX_CODE_LINE_TO_ESCAPE.replace_all(s, "$1,$2")
+}
+
+/// Normalizes contents of an example or a verse block for interpretation.
+///
+/// Leading blank lines are removed, as well as trailing whitespace, and
+/// the result ends with exactly one newline unless it is empty.
+/// When `preserve_indent` is false, indentation common to all non-blank
+/// lines is removed too. Indentation is counted in characters, so a tab
+/// and a space are both one level deep.
+///
+/// elisp: `(org-element-normalize-string (org-remove-indentation contents))`
+pub fn normalize_block_contents(contents: &str, preserve_indent: bool) -> String {
+    let lines: Vec<&str> = contents
+        .lines()
+        .skip_while(|l| l.trim().is_empty())
+        .collect();
+    let lines = match lines.iter().rposition(|l| !l.trim().is_empty()) {
+        Some(last) => &lines[..=last],
+        None => return String::new(),
+    };
+
+    let indent = if preserve_indent {
+        0
+    } else {
+        lines
+            .iter()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.len() - l.trim_start_matches(|c| c == ' ' || c == '\t').len())
+            .min()
+            .unwrap_or(0)
+    };
+
+    let mut result = String::with_capacity(contents.len());
+    for line in lines {
+        // blank lines may be shorter than the common indentation
+        if !line.trim().is_empty() {
+            result.push_str(&line[indent..]);
+        }
+        result.push('\n');
+    }
+    let trimmed = result.trim_end().len();
+    result.truncate(trimmed);
+    result.push('\n');
+    result
+}
+
+/// Properties of example and source blocks set by switches
+/// on their opening line
+struct Switches<'a> {
+    label_fmt: Option<&'a str>,
+    number_lines: Option<LineNumberingMode>,
+    preserve_indent: bool,
+    retain_labels: bool,
     use_labels: bool,
+}
+
+impl<'a> Switches<'a> {
+    /// Switches analysis, shared by `org-element-example-block-parser`
+    /// and `org-element-src-block-parser`
+    fn analyse(switches: Option<&'a str>) -> Switches<'a> {
+        let number_lines = switches
+            .and_then(|sw| REGEX_SWITCH_NUMBER_LINES.captures(sw))
+            .map(|c| {
+                let offset = c
+                    .get(2)
+                    .and_then(|n| n.as_str().parse::<usize>().ok())
+                    .map_or(0, |n| n.saturating_sub(1));
+                match c.get(1).unwrap().as_str() {
+                    "-" => LineNumberingMode::New(offset),
+                    _ => LineNumberingMode::Continued(offset),
+                }
+            });
+        let preserve_indent =
+            switches.map_or(false, |sw| REGEX_SWITCH_PRESERVE_INDENT.is_match(sw));
+        let label_fmt = switches
+            .and_then(|sw| REGEX_SWITCH_LABEL_FORMAT.captures(sw))
+            .map(|c| c.get(1).unwrap().as_str());
+        let keep_labels = switches.map_or(false, |sw| REGEX_SWITCH_KEEP_LABELS.is_match(sw));
+        // Should labels be retained in (or stripped from) src blocks?
+        let retain_labels = switches.map_or(true, |sw| !REGEX_SWITCH_REMOVE_LABELS.is_match(sw))
+            || (number_lines.is_some() && keep_labels);
+        // What should code-references use - labels or line-numbers?
+        let use_labels = switches.is_none() || (retain_labels && !keep_labels);
+
+        Switches {
+            label_fmt,
+            number_lines,
+            preserve_indent,
+            retain_labels,
+            use_labels,
+        }
+    }
+}
 
-    ///Source code (string).
-    value: &'a str,
+/// Returns `None` if `s` is empty or contains only whitespace,
+/// `s` without leading and trailing whitespace otherwise.
+/// elisp: `(and (org-string-nw-p s) (org-trim s))`
+fn trim_non_blank(s: &str) -> Option<&str> {
+    let trimmed = s.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed)
+    }
 }
 
 impl<'a> Parser<'a> {
-    // TODO implement center_block_parser
+    /// Parse a center block.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `center-block' and CDR is a plist
+    /// containing `:begin', `:end', `:contents-begin', `:contents-end',
+    /// `:post-blank' and `:post-affiliated' keywords.
+    ///
+    /// Assume point is at the beginning of the block."
+    /// (defun org-element-center-block-parser (limit affiliated)
     pub fn center_block_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        match self.block_end_line("CENTER", limit) {
+            // Incomplete block: parse it as a paragraph.
+            None => self.paragraph_parser(limit, start, affiliated),
+            Some(line) => self.greater_block(Syntax::CenterBlock, line, limit, start, affiliated),
+        }
     }
 
-    // TODO implement comment_block_parser
+    /// Parse a comment block.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `comment-block' and CDR is a plist
+    /// containing `:begin', `:end', `:value', `:post-blank' and
+    /// `:post-affiliated' keywords.
+    ///
+    /// Assume point is at comment block beginning."
+    /// (defun org-element-comment-block-parser (limit affiliated)
     pub fn comment_block_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let contents_end = match self.block_end_line("COMMENT", limit) {
+            // Incomplete block: parse it as a paragraph.
+            None => return self.paragraph_parser(limit, start, affiliated),
+            Some(line) => line,
+        };
+        let value = unescape_code_in_string(self.block_value(contents_end));
+        self.cursor.borrow_mut().set(contents_end);
+        self.cursor.borrow_mut().goto_next_line();
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::CommentBlock(Box::new(CommentBlockData { value })),
+            Interval { start, end },
+            None,
+            post_blank,
+            affiliated,
+        )
     }
 
-    // TODO implement comment_block_parser
+    /// Parse an example block.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `example-block' and CDR is a plist
+    /// containing `:begin', `:end', `:number-lines', `:preserve-indent',
+    /// `:retain-labels', `:use-labels', `:label-fmt', `:switches',
+    /// `:value', `:post-blank' and `:post-affiliated' keywords."
+    /// (defun org-element-example-block-parser (limit affiliated)
     pub fn example_block_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let contents_end = match self.block_end_line("EXAMPLE", limit) {
+            // Incomplete block: parse it as a paragraph.
+            None => return self.paragraph_parser(limit, start, affiliated),
+            Some(line) => line,
+        };
+
+        let cap = capturing_at!(REGEX_EXAMPLE_BLOCK_BEGIN, self)
+            .expect("cursor must be at the beginning of an example block");
+        let switches = cap.get(1).map(|m| m.as_str());
+        let switches_data = Switches::analyse(switches);
+
+        let value = unescape_code_in_string(self.block_value(contents_end));
+
+        self.cursor.borrow_mut().set(contents_end);
+        self.cursor.borrow_mut().goto_next_line();
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        let data = ExampleBlockData {
+            label_fmt: switches_data.label_fmt,
+            number_lines: switches_data.number_lines,
+            preserve_indent: switches_data.preserve_indent,
+            retain_labels: switches_data.retain_labels,
+            switches: switches.and_then(trim_non_blank),
+            use_labels: switches_data.use_labels,
+            value,
+        };
+
+        SyntaxNode::new(
+            Syntax::ExampleBlock(Box::new(data)),
+            Interval { start, end },
+            None,
+            post_blank,
+            affiliated,
+        )
     }
 
-    // TODO implement export_block_parser
+    /// Parse an export block.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `export-block' and CDR is a plist
+    /// containing `:begin', `:end', `:type', `:value', `:post-blank' and
+    /// `:post-affiliated' keywords.
+    ///
+    /// Assume point is at export-block beginning."
+    /// (defun org-element-export-block-parser (limit affiliated)
     pub fn export_block_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let contents_end = match self.block_end_line("EXPORT", limit) {
+            // Incomplete block: parse it as a paragraph.
+            None => return self.paragraph_parser(limit, start, affiliated),
+            Some(line) => line,
+        };
+        let type_s = capturing_at!(REGEX_EXPORT_BLOCK_BEGIN, self)
+            .and_then(|cap| cap.get(1))
+            .map(|m| m.as_str());
+        let value = unescape_code_in_string(self.block_value(contents_end));
+        self.cursor.borrow_mut().set(contents_end);
+        self.cursor.borrow_mut().goto_next_line();
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::ExportBlock(Box::new(ExportBlockData { type_s, value })),
+            Interval { start, end },
+            None,
+            post_blank,
+            affiliated,
+        )
     }
 
-    // TODO implement quote_block_parser
+    /// Parse a quote block.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `quote-block' and CDR is a plist
+    /// containing `:begin', `:end', `:contents-begin', `:contents-end',
+    /// `:post-blank' and `:post-affiliated' keywords.
+    ///
+    /// Assume point is at the beginning of the block."
+    /// (defun org-element-quote-block-parser (limit affiliated)
     pub fn quote_block_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        match self.block_end_line("QUOTE", limit) {
+            // Incomplete block: parse it as a paragraph.
+            None => self.paragraph_parser(limit, start, affiliated),
+            Some(line) => self.greater_block(Syntax::QuoteBlock, line, limit, start, affiliated),
+        }
     }
 
-    // TODO implement src_block_parser
+    /// Parse a source block.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `src-block' and CDR is a plist
+    /// containing `:language', `:switches', `:parameters', `:begin',
+    /// `:end', `:number-lines', `:retain-labels', `:use-labels',
+    /// `:label-fmt', `:preserve-indent', `:value', `:post-blank' and
+    /// `:post-affiliated' keywords.
+    ///
+    /// Assume point is at the beginning of the block."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L2389
+    /// (defun org-element-src-block-parser (limit affiliated)
+    ///
+    /// When `Parser::src_languages` is set, a block whose language
+    /// is not registered produces a diagnostic.
     pub fn src_block_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let post_affiliated = self.cursor.borrow().pos();
+        let contents_end = {
+            let mut c = self.cursor.borrow_mut();
+            let found = c.re_search_forward(&*REGEX_SRC_BLOCK_END, Some(limit));
+            let contents_end = found.map(|_| c.line_beginning_position(None));
+            c.set(post_affiliated);
+            contents_end
+        };
+
+        let contents_end = match contents_end {
+            // Incomplete block: parse it as a paragraph.
+            None => return self.paragraph_parser(limit, start, affiliated),
+            Some(end) => end,
+        };
+
+        let cap = capturing_at!(REGEX_SRC_BLOCK_BEGIN, self)
+            .expect("cursor must be at the beginning of a source block");
+        let language = cap.get(1).map(|m| m.as_str());
+        let switches = cap.get(2).map(|m| m.as_str());
+        let parameters = cap.get(3).and_then(|m| trim_non_blank(m.as_str()));
+
+        let switches_data = Switches::analyse(switches);
+
+        // Retrieve code.
+        let value = unescape_code_in_string(self.block_value(contents_end));
+
+        let end_line = self.cursor.borrow_mut().line_end_position(None);
+        if let (Some(registry), Some(lang)) = (&self.src_languages, language) {
+            if !registry.contains(lang) {
+                self.report(
+                    post_affiliated,
+                    end_line,
+                    format!("Unregistered source block language: {}", lang),
+                );
+            }
+        }
+
+        self.cursor.borrow_mut().set(contents_end);
+        self.cursor.borrow_mut().goto_next_line();
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        let data = SrcBlockData {
+            label_fmt: switches_data.label_fmt,
+            language,
+            number_lines: switches_data.number_lines,
+            parameters,
+            preserve_indent: switches_data.preserve_indent,
+            retain_labels: switches_data.retain_labels,
+            switches: switches.and_then(trim_non_blank),
+            use_labels: switches_data.use_labels,
+            value,
+        };
+
+        SyntaxNode::new(
+            Syntax::SrcBlock(Box::new(data)),
+            Interval { start, end },
+            None,
+            post_blank,
+            affiliated,
+        )
     }
 
-    // TODO implement verse_block_parser
+    /// Parse a verse block.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `verse-block' and CDR is a plist
+    /// containing `:begin', `:end', `:contents-begin', `:contents-end',
+    /// `:post-blank' and `:post-affiliated' keywords.
+    ///
+    /// Assume point is at beginning of the block."
+    /// (defun org-element-verse-block-parser (limit affiliated)
+    ///
+    /// Empty blocks have no contents.
     pub fn verse_block_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let contents_end = match self.block_end_line("VERSE", limit) {
+            // Incomplete block: parse it as a paragraph.
+            None => return self.paragraph_parser(limit, start, affiliated),
+            Some(line) => line,
+        };
+
+        let mut c = self.cursor.borrow_mut();
+        let contents_begin = c.goto_next_line();
+        let contents = if contents_begin < contents_end {
+            Some(Interval {
+                start: contents_begin,
+                end: contents_end,
+            })
+        } else {
+            None
+        };
+        c.set(contents_end);
+        c.goto_next_line();
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::VerseBlock,
+            Interval { start, end },
+            contents,
+            post_blank,
+            affiliated,
+        )
     }
 
-    // TODO implement special_block_parser
+    /// Parse a special block.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `special-block' and CDR is a plist
+    /// containing `:type', `:begin', `:end', `:contents-begin',
+    /// `:contents-end', `:post-blank' and `:post-affiliated' keywords.
+    ///
+    /// Assume point is at the beginning of the block."
+    /// (defun org-element-special-block-parser (limit affiliated)
+    ///
+    /// Raw value is the text between the opening and closing lines.
     pub fn special_block_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
+    ) -> SyntaxNode<'a> {
+        let type_s = capturing_at!(REGEX_BLOCK_BEGIN_LINE, self)
+            .expect("cursor must be at the beginning of a block")
+            .get(1)
+            .unwrap()
+            .as_str();
+        let contents_end = match self.block_end_line(type_s, limit) {
+            // Incomplete block: parse it as a paragraph.
+            None => return self.paragraph_parser(limit, start, affiliated),
+            Some(line) => line,
+        };
+        let raw_value = self.block_value(contents_end);
+        let data = SpecialBlockData { type_s, raw_value };
+        self.greater_block(
+            Syntax::SpecialBlock(Box::new(data)),
+            contents_end,
+            limit,
+            start,
+            affiliated,
+        )
+    }
+
+    /// Returns the text between the opening line of the block starting
+    /// at cursor position and `contents_end`, the beginning of its
+    /// closing line. Cursor doesn't move.
+    fn block_value(&self, contents_end: usize) -> &'a str {
+        let value_begin = self
+            .cursor
+            .borrow_mut()
+            .line_beginning_position(Some(2))
+            .min(contents_end);
+        &self.input[value_begin..contents_end]
+    }
+
+    /// Returns the block starting at cursor position, which closing
+    /// line begins at `contents_end`, as a greater element of `data`
+    fn greater_block(
+        &self,
+        data: Syntax<'a>,
+        contents_end: usize,
+        limit: usize,
+        start: usize,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        // Empty blocks have no contents.
+        let contents_begin = c.goto_next_line();
+        let contents = if contents_begin < contents_end {
+            Some(Interval {
+                start: contents_begin,
+                end: contents_end,
+            })
+        } else {
+            None
+        };
+        c.set(contents_end);
+        c.goto_next_line();
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            data,
+            Interval { start, end },
+            contents,
+            post_blank,
+            affiliated,
+        )
+    }
+
+    /// Returns the beginning of the line closing the block of type
+    /// `block_type` starting at cursor position, if it is found before
+    /// `limit`. Cursor doesn't move.
+    /// Elements are parsed within their container, the section or
+    /// the headline, so a block never goes past the next headline.
+    /// elisp: (re-search-forward (format "^[ \t]*#\\+END_%s[ \t]*$" type) limit t)
+    fn block_end_line(&self, block_type: &str, limit: usize) -> Option<usize> {
+        let end = format!(r"(?im)^[ \t]*#\+END_{}[ \t]*$", regex::escape(block_type));
+        let mut c = self.cursor.borrow_mut();
+        let pos = c.pos();
+        let found = c.re_search_forward(&Regex::new(&end).unwrap(), Some(limit));
+        let line = found.map(|_| c.line_beginning_position(None));
+        c.set(pos);
+        line
     }
 
-    // TODO implement dynamic_block_parser
+    /// Parse a dynamic block.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `dynamic-block' and CDR is a plist
+    /// containing `:block-name', `:begin', `:end', `:contents-begin',
+    /// `:contents-end', `:arguments', `:post-blank' and
+    /// `:post-affiliated' keywords.
+    ///
+    /// Assume point is at beginning of dynamic block."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L742
+    /// (defun org-element-dynamic-block-parser (limit affiliated)
     pub fn dynamic_block_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let post_affiliated = self.cursor.borrow().pos();
+        let block_end_line = {
+            let mut c = self.cursor.borrow_mut();
+            let found = c.re_search_forward(&*REGEX_DYNAMIC_BLOCK_END, Some(limit));
+            let block_end_line = found.map(|_| c.line_beginning_position(None));
+            c.set(post_affiliated);
+            block_end_line
+        };
+        let cap = capturing_at!(REGEX_DYNAMIC_BLOCK_BEGIN, self);
+
+        let (block_end_line, cap) = match (block_end_line, cap) {
+            (Some(block_end_line), Some(cap)) => (block_end_line, cap),
+            // Incomplete block: parse it as a paragraph.
+            _ => return self.paragraph_parser(limit, start, affiliated),
+        };
+        let block_name = cap.get(1).unwrap().as_str();
+        let arguments = cap.get(2).and_then(|m| trim_non_blank(m.as_str()));
+
+        let mut c = self.cursor.borrow_mut();
+        // Empty blocks have no contents.
+        let contents_begin = c.goto_next_line();
+        let contents = if contents_begin < block_end_line {
+            Some(Interval {
+                start: contents_begin,
+                end: block_end_line,
+            })
+        } else {
+            None
+        };
+        c.set(block_end_line);
+        c.goto_next_line();
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::DynamicBlock(Box::new(DynamicBlockData {
+                arguments,
+                block_name,
+            })),
+            Interval { start, end },
+            contents,
+            post_blank,
+            affiliated,
+        )
+    }
+
+    /// Parse inline source block at point, if any.
+    ///
+    /// When at an inline source block, return a list whose car is
+    /// `inline-src-block' and cdr a plist with `:begin', `:end',
+    /// `:language', `:value', `:parameters' and `:post-blank' as keywords.
+    /// Otherwise, return nil.
+    ///
+    /// Assume point is at the beginning of the inline src block."
+    /// (defun org-element-inline-src-block-parser ()
+    ///
+    /// Header arguments and body are both enclosed in brackets, which
+    /// must be balanced inside, so `src_c{int main() {}}` works.
+    /// Both can span over several lines. Body is never parsed for objects.
+    pub fn inline_src_block_parser(&self) -> Option<SyntaxNode<'a>> {
+        let mut c = self.cursor.borrow_mut();
+        let text = c.data();
+        let begin = c.pos();
+        // \<src_
+        let at_word_start = text[..begin]
+            .chars()
+            .next_back()
+            .map_or(true, |ch| !ch.is_alphanumeric());
+        if !at_word_start {
+            return None;
+        }
+        let cap = REGEX_INLINE_SRC_BLOCK.captures(&text[begin..])?;
+        let language = cap.get(1).unwrap();
+        let mut pos = begin + language.end();
+
+        let parameters = match parse_paired_brackets(&text[pos..], '[') {
+            Some((parameters, len)) => {
+                pos += len;
+                Some(parameters.trim()).filter(|p| !p.is_empty())
+            }
+            None => None,
+        };
+        let (value, len) = parse_paired_brackets(&text[pos..], '{')?;
+        c.set(pos + len);
+        let post_blank = c.skip_chars_forward(" \t", None);
+
+        Some(SyntaxNode::new(
+            Syntax::InlineSrcBlock(Box::new(InlineSrcBlockData {
+                language: language.as_str(),
+                parameters,
+                value,
+            })),
+            Interval {
+                start: begin,
+                end: c.pos(),
+            },
+            None,
+            post_blank,
+            None,
+        ))
+    }
+}
+
+mod test {
+    use crate::data::{Interval, LineNumberingMode, Syntax, SyntaxNode, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+    use std::collections::HashSet;
+
+    #[test]
+    fn normalize_block_contents() {
+        let contents = "\n  \n    (defun foo ()\n      nil)\n\n  bar\n\n  \n";
+        assert_eq!(
+            "  (defun foo ()\n    nil)\n\nbar\n",
+            super::normalize_block_contents(contents, false)
+        );
+        assert_eq!(
+            "    (defun foo ()\n      nil)\n\n  bar\n",
+            super::normalize_block_contents(contents, true)
+        );
+        assert_eq!("", super::normalize_block_contents("\n \n", false));
+    }
+
+    #[test]
+    fn escape_code_in_string() {
+        let code = "* a\n  #+KEY: b\nd * e\n";
+        let escaped = super::escape_code_in_string(code);
+        assert_eq!(",* a\n  ,#+KEY: b\nd * e\n", escaped);
+        assert_eq!(code, super::unescape_code_in_string(&escaped));
+        assert_eq!(",,* c", super::escape_code_in_string(",* c"));
+    }
+
+    #[test]
+    fn dynamic_block() {
+        let text = "#+BEGIN: clocktable :maxlevel 2\n| a |\n#+END:\n\nText";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.dynamic_block_parser(text.len(), 0, None);
+        assert_eq!(Interval { start: 0, end: 46 }, node.location);
+        assert_eq!(Some(Interval { start: 32, end: 38 }), node.content_location);
+        assert_eq!(1, node.post_blank);
+        match &node.data {
+            Syntax::DynamicBlock(b) => {
+                assert_eq!("clocktable", b.block_name);
+                assert_eq!(Some(":maxlevel 2"), b.arguments);
+            }
+            _ => panic!("expected dynamic block"),
+        }
+
+        // Empty blocks have no contents
+        let text = "#+begin: empty\n#+end:";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.dynamic_block_parser(text.len(), 0, None);
+        assert_eq!(text.len(), node.location.end);
+        assert_eq!(None, node.content_location);
+        match &node.data {
+            Syntax::DynamicBlock(b) => assert_eq!(None, b.arguments),
+            _ => panic!("expected dynamic block"),
+        }
+    }
+
+    #[test]
+    fn src_block() {
+        let text =
+            "#+BEGIN_SRC rust -n -r :tangle yes\nfn main() {}\n,* escaped\n#+END_SRC\n\nText";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.src_block_parser(text.len(), 0, None);
+        assert_eq!(0, node.location.start);
+        assert_eq!(text.len() - 4, node.location.end);
+        assert_eq!(1, node.post_blank);
+        match &node.data {
+            Syntax::SrcBlock(d) => {
+                assert_eq!(Some("rust"), d.language);
+                assert_eq!(Some("-n -r"), d.switches);
+                assert_eq!(Some(":tangle yes"), d.parameters);
+                assert_eq!("fn main() {}\n* escaped\n", d.value);
+                assert!(d.number_lines.is_some());
+                assert!(!d.retain_labels);
+            }
+            _ => panic!("expected src block"),
+        }
+    }
+
+    #[test]
+    fn src_block_body() {
+        // Lines starting like a terminator don't end the block, and
+        // indentation common to the whole body is kept.
+        let text =
+            "#+begin_src sh -i :results none\n    ls\n    #+END_SRCX\n      pwd\n#+end_src\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.src_block_parser(text.len(), 0, None);
+        assert_eq!(text.len(), node.location.end);
+        match &node.data {
+            Syntax::SrcBlock(d) => {
+                assert_eq!(Some("sh"), d.language);
+                assert_eq!(Some("-i"), d.switches);
+                assert!(d.preserve_indent);
+                assert_eq!(Some(":results none"), d.parameters);
+                assert_eq!("    ls\n    #+END_SRCX\n      pwd\n", d.value);
+            }
+            _ => panic!("expected src block"),
+        }
+    }
+
+    #[test]
+    fn src_block_without_language() {
+        let text = "#+BEGIN_SRC\n(+ 1 2)\n#+END_SRC\n#+begin_src  \nx\n#+end_src\n#+BEGIN_EXAMPLE -n\n,* a\n#+END_EXAMPLE\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = root.children.borrow()[0].clone();
+        let blocks = section.children.borrow();
+        assert_eq!(3, blocks.len());
+
+        match &blocks[0].data {
+            Syntax::SrcBlock(d) => {
+                assert_eq!(None, d.language);
+                assert_eq!(None, d.parameters);
+                assert_eq!("(+ 1 2)\n", d.value);
+            }
+            _ => panic!("expected src block"),
+        }
+        match &blocks[1].data {
+            Syntax::SrcBlock(d) => {
+                assert_eq!(None, d.language);
+                assert_eq!(None, d.parameters);
+            }
+            _ => panic!("expected src block"),
+        }
+        match &blocks[2].data {
+            Syntax::ExampleBlock(d) => {
+                assert_eq!(Some("-n"), d.switches);
+                assert_eq!(Some(LineNumberingMode::New(0)), d.number_lines);
+                assert_eq!("* a\n", d.value);
+            }
+            _ => panic!("expected example block"),
+        }
+        assert_eq!(Interval { start: 56, end: 94 }, blocks[2].location);
+    }
+
+    #[test]
+    fn src_block_switches() {
+        let src_block = |line: &str| {
+            let text = format!("{}\nx\n#+END_SRC\n", line);
+            let parser = Parser::new(&text, ParseGranularity::Object);
+            match parser.src_block_parser(text.len(), 0, None).data {
+                Syntax::SrcBlock(d) => (
+                    d.number_lines,
+                    d.retain_labels,
+                    d.use_labels,
+                    d.label_fmt.map(String::from),
+                ),
+                _ => panic!("expected src block"),
+            }
+        };
+
+        let (number_lines, retain_labels, use_labels, label_fmt) =
+            src_block(r#"#+BEGIN_SRC emacs-lisp -n 5 -r -l "(%s)""#);
+        assert_eq!(Some(LineNumberingMode::New(4)), number_lines);
+        assert_eq!(5, number_lines.unwrap().start());
+        assert!(!number_lines.unwrap().is_continued());
+        assert!(!retain_labels);
+        assert!(!use_labels);
+        assert_eq!(Some("(%s)".to_string()), label_fmt);
+
+        let (number_lines, retain_labels, use_labels, label_fmt) =
+            src_block("#+BEGIN_SRC emacs-lisp +n");
+        assert_eq!(Some(LineNumberingMode::Continued(0)), number_lines);
+        assert!(number_lines.unwrap().is_continued());
+        assert!(retain_labels);
+        assert!(use_labels);
+        assert_eq!(None, label_fmt);
+
+        let (number_lines, retain_labels, _, _) = src_block("#+BEGIN_SRC emacs-lisp -n10 -r -k");
+        assert_eq!(Some(10), number_lines.map(LineNumberingMode::start));
+        assert!(retain_labels);
+
+        assert_eq!(None, src_block("#+BEGIN_SRC emacs-lisp").0);
+    }
+
+    #[test]
+    fn src_block_unregistered_language() {
+        let text = "#+begin_src pyton\nprint(1)\n#+end_src\n";
+        let mut parser = Parser::new(text, ParseGranularity::Object);
+        let mut registry = HashSet::new();
+        registry.insert("python".to_string());
+        parser.src_languages = Some(registry);
+
+        let node = parser.src_block_parser(text.len(), 0, None);
+        assert_eq!(text.len(), node.location.end);
+        match &node.data {
+            Syntax::SrcBlock(d) => {
+                assert_eq!(Some("pyton"), d.language);
+                assert_eq!("print(1)\n", d.value);
+            }
+            _ => panic!("expected src block"),
+        }
+
+        let diagnostics = parser.diagnostics.borrow();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(0, diagnostics[0].location.start);
+        assert_eq!(17, diagnostics[0].location.end);
+        assert!(diagnostics[0].message.contains("pyton"));
+    }
+
+    #[test]
+    fn src_block_registered_language() {
+        let text = "#+BEGIN_SRC python\nprint(1)\n#+END_SRC";
+        let mut parser = Parser::new(text, ParseGranularity::Object);
+        let mut registry = HashSet::new();
+        registry.insert("python".to_string());
+        parser.src_languages = Some(registry);
+        parser.src_block_parser(text.len(), 0, None);
+        assert!(parser.diagnostics.borrow().is_empty());
+    }
+
+    #[test]
+    fn block_terminators() {
+        for end in &["#+end_src", "#+End_Src  ", "  #+END_SRC\t"] {
+            let text = format!("#+begin_src sh\nls\n{}\nText", end);
+            let parser = Parser::new(&text, ParseGranularity::Object);
+            let node = parser.src_block_parser(text.len(), 0, None);
+            assert_eq!(text.len() - 4, node.location.end, "{}", end);
+            match &node.data {
+                Syntax::SrcBlock(d) => assert_eq!("ls\n", d.value),
+                _ => panic!("expected src block"),
+            }
+        }
+
+        for end in &["#+end:", "#+End", "  #+END: \t"] {
+            let text = format!("#+begin: table\n| a |\n{}\nText", end);
+            let parser = Parser::new(&text, ParseGranularity::Object);
+            let node = parser.dynamic_block_parser(text.len(), 0, None);
+            assert_eq!(text.len() - 4, node.location.end, "{}", end);
+            assert_eq!(Some(Interval { start: 15, end: 21 }), node.content_location);
+        }
+    }
+
+    #[test]
+    fn inline_src_block() {
+        fn inline_src<'a>(node: &SyntaxNode<'a>) -> (&'a str, Option<&'a str>, &'a str) {
+            match &node.data {
+                Syntax::InlineSrcBlock(b) => (b.language, b.parameters, b.value),
+                _ => panic!("expected inline source block"),
+            }
+        }
+        let in_paragraph = |that| SyntaxT::Paragraph.can_contain(that);
+
+        let objects = Parser::parse_secondary_string("run src_python{print(1)} now", in_paragraph);
+        assert_eq!(3, objects.len());
+        assert_eq!(Interval { start: 4, end: 25 }, objects[1].location);
+        assert_eq!(1, objects[1].post_blank);
+        assert_eq!(("python", None, "print(1)"), inline_src(&objects[1]));
+        assert!(objects[1].children.borrow().is_empty());
+
+        let text = "src_sh[:results raw]{ls *.org*}";
+        let objects = Parser::parse_secondary_string(text, in_paragraph);
+        assert_eq!(1, objects.len());
+        assert_eq!(text.len(), objects[0].location.end);
+        assert_eq!(
+            ("sh", Some(":results raw"), "ls *.org*"),
+            inline_src(&objects[0])
+        );
+
+        // Balanced braces
+        let objects =
+            Parser::parse_secondary_string("src_c[ ]{int f() { return 1; }}", in_paragraph);
+        assert_eq!(
+            ("c", None, "int f() { return 1; }"),
+            inline_src(&objects[0])
+        );
+
+        for text in &["src_sh{unclosed", "xsrc_sh{ls}", "src_ {ls}", "src_sh"] {
+            let objects = Parser::parse_secondary_string(text, in_paragraph);
+            assert!(
+                objects
+                    .iter()
+                    .all(|o| SyntaxT::from(&o.data) != SyntaxT::InlineSrcBlock),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn greater_blocks() {
+        let text = "#+BEGIN_QUOTE\nQuoted.\n#+END_QUOTE\n\nText";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.quote_block_parser(text.len(), 0, None);
+        assert_eq!(SyntaxT::QuoteBlock, SyntaxT::from(&node.data));
+        assert_eq!(Interval { start: 0, end: 35 }, node.location);
+        assert_eq!(Some(Interval { start: 14, end: 22 }), node.content_location);
+        assert_eq!(1, node.post_blank);
+
+        // Empty blocks have no contents
+        let text = "#+begin_center\n#+end_center\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.center_block_parser(text.len(), 0, None);
+        assert_eq!(SyntaxT::CenterBlock, SyntaxT::from(&node.data));
+        assert_eq!(text.len(), node.location.end);
+        assert_eq!(None, node.content_location);
+
+        let text = "#+begin_note\nA note.\n#+end_note\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.special_block_parser(text.len(), 0, None);
+        assert_eq!(Some(Interval { start: 13, end: 21 }), node.content_location);
+        match &node.data {
+            Syntax::SpecialBlock(d) => {
+                assert_eq!("note", d.type_s);
+                assert_eq!("A note.\n", d.raw_value);
+            }
+            _ => panic!("expected special block"),
+        }
+
+        // Contents are parsed into elements
+        let text = "#+BEGIN_QUOTE\nText.\n\n- item\n#+END_QUOTE\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let quote = &section.children.borrow()[0];
+        let types: Vec<_> = quote
+            .children
+            .borrow()
+            .iter()
+            .map(|c| SyntaxT::from(&c.data))
+            .collect();
+        assert_eq!(vec![SyntaxT::Paragraph, SyntaxT::PlainList], types);
+    }
+
+    #[test]
+    fn comment_and_export_blocks() {
+        let text = "#+BEGIN_COMMENT\n,* not a headline\n#+END_COMMENT\nText";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.comment_block_parser(text.len(), 0, None);
+        assert_eq!(text.len() - 4, node.location.end);
+        assert_eq!(None, node.content_location);
+        match &node.data {
+            Syntax::CommentBlock(d) => assert_eq!("* not a headline\n", d.value),
+            _ => panic!("expected comment block"),
+        }
+
+        for (begin, backend) in &[
+            ("#+BEGIN_EXPORT html", Some("html")),
+            ("#+begin_export latex  ", Some("latex")),
+            ("#+BEGIN_EXPORT", None),
+        ] {
+            let text = format!("{}\n,#+TITLE: x\n#+END_EXPORT\n", begin);
+            let parser = Parser::new(&text, ParseGranularity::Element);
+            let node = parser.export_block_parser(text.len(), 0, None);
+            assert_eq!(text.len(), node.location.end, "{}", begin);
+            match &node.data {
+                Syntax::ExportBlock(d) => {
+                    assert_eq!(*backend, d.type_s);
+                    assert_eq!("#+TITLE: x\n", d.value);
+                }
+                _ => panic!("expected export block"),
+            }
+        }
+    }
+
+    #[test]
+    fn unterminated_blocks() {
+        for block in &[
+            "#+BEGIN_SRC sh",
+            "#+BEGIN_QUOTE",
+            "#+begin_note",
+            "#+BEGIN: dyn",
+            ":LOGBOOK:",
+        ] {
+            let text = format!("{}\ntext\n* Headline\n#+END_SRC\n", block);
+            let parser = Parser::new(&text, ParseGranularity::Element);
+            let root = parser.parse_buffer();
+            let types: Vec<_> = root
+                .children
+                .borrow()
+                .iter()
+                .map(|node| SyntaxT::from(&node.data))
+                .collect();
+            assert_eq!(
+                vec![SyntaxT::Section, SyntaxT::Headline],
+                types,
+                "{}",
+                block
+            );
+
+            let section = root.children.borrow()[0].clone();
+            assert_eq!(
+                Interval {
+                    start: 0,
+                    end: block.len() + 6
+                },
+                section.location
+            );
+            let paragraph = section.children.borrow()[0].clone();
+            assert_eq!(SyntaxT::Paragraph, SyntaxT::from(&paragraph.data));
+            assert_eq!(section.location, paragraph.location);
+        }
     }
 }
@@ -0,0 +1,198 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Citations
+//! https://orgmode.org/manual/Citations.html
+//!
+//! [cite/STYLE:GLOBAL-PREFIX;REFERENCES;GLOBAL-SUFFIX]
+//!
+//! STYLE, GLOBAL-PREFIX and GLOBAL-SUFFIX are optional. REFERENCES are
+//! separated by semicolons, each one follows the pattern
+//! PREFIX @KEY SUFFIX, where PREFIX and SUFFIX are optional.
+
+use crate::babel::parse_paired_brackets;
+use crate::data::{Interval, Syntax, SyntaxNode};
+use crate::parser::Parser;
+use crate::regexps::{REGEX_CITATION_KEY, REGEX_CITATION_PREFIX};
+
+/// Object
+#[derive(Debug)]
+pub struct CitationData<'a> {
+    /// Citation style, the part after `cite/`, if any (string or nil).
+    pub style: Option<&'a str>,
+
+    /// Text before the first reference, common to all of them, if any.
+    pub prefix: Option<&'a str>,
+
+    /// Text after the last reference, common to all of them, if any.
+    pub suffix: Option<&'a str>,
+
+    /// Cited references, in order of appearance.
+    pub references: Vec<CitationReference<'a>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CitationReference<'a> {
+    /// Citation key, without `@` (string).
+    pub key: &'a str,
+
+    /// Text before the key, if any.
+    pub prefix: Option<&'a str>,
+
+    /// Text after the key, if any.
+    pub suffix: Option<&'a str>,
+}
+
+/// Returns `s` trimmed, or None if it is blank
+fn non_blank(s: &str) -> Option<&str> {
+    Some(s.trim()).filter(|s| !s.is_empty())
+}
+
+impl<'a> Parser<'a> {
+    /// Parse citation object at point, if any.
+    ///
+    /// When at a citation object, return a list whose car is `citation'
+    /// and cdr is a plist with `:style', `:prefix', `:suffix', `:begin',
+    /// `:end', `:contents-begin', `:contents-end', and `:post-blank'
+    /// keywords.  Otherwise, return nil.
+    ///
+    /// Assume point is at the beginning of the citation."
+    /// (defun org-element-citation-parser ()
+    ///
+    /// References are kept in `CitationData` instead of being child
+    /// nodes. Prefixes and suffixes are trimmed and not parsed for objects.
+    pub fn citation_parser(&self) -> Option<SyntaxNode<'a>> {
+        let mut c = self.cursor.borrow_mut();
+        let text = c.data();
+        let begin = c.pos();
+        let cap = REGEX_CITATION_PREFIX.captures(&text[begin..])?;
+        let style = cap.get(1).map(|m| m.as_str());
+        let (_, len) = parse_paired_brackets(&text[begin..], '[')?;
+        let closing = begin + len;
+        let contents = &text[begin + cap.get(0).unwrap().end()..closing - 1];
+
+        let parts: Vec<&str> = contents.split(';').collect();
+        let has_key = |part: &str| REGEX_CITATION_KEY.is_match(part);
+        let first = parts.iter().position(|part| has_key(part))?;
+        let last = parts.iter().rposition(|part| has_key(part)).unwrap();
+        // Only the first and the last parts can be global
+        if first > 1 || last + 2 < parts.len() {
+            return None;
+        }
+        let references = parts[first..=last]
+            .iter()
+            .map(|part| {
+                let key = REGEX_CITATION_KEY.captures(part)?;
+                let whole = key.get(0).unwrap();
+                Some(CitationReference {
+                    key: key.get(1).unwrap().as_str(),
+                    prefix: non_blank(&part[..whole.start()]),
+                    suffix: non_blank(&part[whole.end()..]),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        c.set(closing);
+        let post_blank = c.skip_chars_forward(" \t", None);
+        Some(SyntaxNode::new(
+            Syntax::Citation(Box::new(CitationData {
+                style,
+                prefix: parts[..first].first().and_then(|p| non_blank(p)),
+                suffix: parts[last + 1..].first().and_then(|p| non_blank(p)),
+                references,
+            })),
+            Interval {
+                start: begin,
+                end: c.pos(),
+            },
+            None,
+            post_blank,
+            None,
+        ))
+    }
+}
+
+mod test {
+    use super::{CitationData, CitationReference};
+    use crate::data::{Interval, Syntax, SyntaxNode, SyntaxT};
+    use crate::parser::Parser;
+
+    fn in_paragraph(that: SyntaxT) -> bool {
+        SyntaxT::Paragraph.can_contain(that)
+    }
+
+    fn citation<'a, 'b>(node: &'b SyntaxNode<'a>) -> &'b CitationData<'a> {
+        match &node.data {
+            Syntax::Citation(c) => c,
+            _ => panic!("expected citation"),
+        }
+    }
+
+    #[test]
+    fn citation_parser() {
+        let text = "As shown [cite/t/b:see ;@doe2020 p. 7;@roe, ch. 2;and others] before.";
+        let objects = Parser::parse_secondary_string(text, in_paragraph);
+        assert_eq!(3, objects.len());
+        assert_eq!(Interval { start: 9, end: 62 }, objects[1].location);
+        assert_eq!(1, objects[1].post_blank);
+
+        let c = citation(&objects[1]);
+        assert_eq!(Some("t/b"), c.style);
+        assert_eq!(Some("see"), c.prefix);
+        assert_eq!(Some("and others"), c.suffix);
+        assert_eq!(
+            vec![
+                CitationReference {
+                    key: "doe2020",
+                    prefix: None,
+                    suffix: Some("p. 7"),
+                },
+                CitationReference {
+                    key: "roe",
+                    prefix: None,
+                    suffix: Some(", ch. 2"),
+                },
+            ],
+            c.references
+        );
+
+        let objects = Parser::parse_secondary_string("[cite:@a;@b; also @c]", in_paragraph);
+        let c = citation(&objects[0]);
+        assert_eq!((None, None, None), (c.style, c.prefix, c.suffix));
+        let keys: Vec<_> = c.references.iter().map(|r| r.key).collect();
+        assert_eq!(vec!["a", "b", "c"], keys);
+        assert_eq!(Some("also"), c.references[2].prefix);
+    }
+
+    #[test]
+    fn not_a_citation() {
+        for text in &[
+            "[cite:no keys]",
+            "[cite:@a",
+            "[cite:one;two;@a]",
+            "[cite:@a;one;two]",
+            "[cite @a]",
+        ] {
+            let objects = Parser::parse_secondary_string(text, in_paragraph);
+            assert!(
+                objects
+                    .iter()
+                    .all(|o| SyntaxT::from(&o.data) != SyntaxT::Citation),
+                "{}",
+                text
+            );
+        }
+    }
+}
@@ -16,14 +16,15 @@
 // Parts of the cursor code are shamelessly copied from xi-rope
 // https://github.com/xi-editor/xi-editor/tree/master/rust/rope
 
-use memchr::{memchr, memrchr};
+use memchr::{memchr, memchr_iter, memrchr};
 use regex::{Captures, Match, Regex};
 
-use crate::headline::{REGEX_HEADLINE_MULTILINE, REGEX_HEADLINE_SHORT};
-
-lazy_static! {
-    pub static ref REGEX_EMPTY_LINE: Regex = Regex::new(r"^[ \t]*$").unwrap();
-}
+use crate::data::Interval;
+use crate::headline::{headline_title, TodoKeywords};
+use crate::regexps::{
+    LineKind, DISPATCH_LINE_KINDS, DISPATCH_REGEX_SET, REGEX_BLOCK_BEGIN_LINE,
+    REGEX_HEADLINE_MULTILINE, REGEX_HEADLINE_SHORT,
+};
 
 pub trait Metric {
     fn is_boundary(s: &str, offset: usize) -> bool;
@@ -71,8 +72,11 @@ impl Metric for LinesMetric {
     }
 
     fn prev(s: &str, offset: usize) -> Option<usize> {
-        debug_assert!(offset > 0, "caller is responsible for validating input");
-        memrchr(b'\n', &s.as_bytes()[..offset - 1]).map(|pos| pos + 1)
+        if offset == 0 {
+            None
+        } else {
+            memrchr(b'\n', &s.as_bytes()[..offset - 1]).map(|pos| pos + 1)
+        }
     }
 
     fn next(s: &str, offset: usize) -> Option<usize> {
@@ -80,6 +84,11 @@ impl Metric for LinesMetric {
     }
 }
 
+/// Line number of `pos` in `s`, see `Cursor::current_line_number`
+pub(crate) fn line_number(s: &str, pos: usize) -> usize {
+    memchr_iter(b'\n', &s.as_bytes()[..pos]).count() + 1
+}
+
 pub struct Cursor<'a> {
     data: &'a str,
     pos: usize,
@@ -90,6 +99,11 @@ impl<'a> Cursor<'a> {
         Cursor { data, pos }
     }
 
+    /// Creates a cursor at the beginning of `data`
+    pub fn at_start(data: &'a str) -> Cursor<'a> {
+        Cursor::new(data, 0)
+    }
+
     pub fn set(&mut self, pos: usize) {
         self.pos = pos;
     }
@@ -98,6 +112,11 @@ impl<'a> Cursor<'a> {
         self.pos
     }
 
+    /// Text the cursor moves over
+    pub fn data(&self) -> &'a str {
+        self.data
+    }
+
     /// Get next codepoint after cursor position, and advance cursor.
     pub fn get_next_char(&mut self) -> Option<char> {
         let pos = self.pos;
@@ -157,6 +176,24 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    /// Returns the line number of the cursor position, the first line
+    /// being 1, the way Emacs counts lines: position right after
+    /// a newline, including the end of data ending with one, is on
+    /// the next line.
+    /// elisp: `line-number-at-pos`
+    pub fn current_line_number(&self) -> usize {
+        line_number(self.data, self.pos)
+    }
+
+    /// Returns the offset of the `n`th `M` boundary counting from
+    /// the beginning of the data, which is the 0th one, e.g. the
+    /// beginning of the `n`th line, counting from 0, for `LinesMetric`.
+    /// Returns None if there are less than `n` boundaries.
+    /// Cursor position is not changed
+    pub fn nth<M: Metric>(&self, n: usize) -> Option<usize> {
+        (0..n).try_fold(0, |offset, _| M::next(self.data, offset))
+    }
+
     /// Skip over space, tabs and newline characters
     /// Cursor position is set before next non-whitespace char
     pub fn skip_whitespace(&mut self) -> usize {
@@ -176,7 +213,7 @@ impl<'a> Cursor<'a> {
     /// If cursor is already at the beginning of the line - nothing happens
     /// Returns the position of the cursor
     pub fn goto_line_begin(&mut self) -> usize {
-        if self.pos() != 0 && self.at_or_prev::<LinesMetric>().is_none() {
+        if self.at_or_prev::<LinesMetric>().is_none() {
             self.set(0);
         }
         self.pos()
@@ -256,36 +293,54 @@ impl<'a> Cursor<'a> {
     /// Return the character position of the last character on the current line.
     /// With argument N not nil or 1, move forward N - 1 lines first.
     /// If scan reaches end of buffer, return that position.
+    /// with N < 1 cursor will move to previous lines, stopping at the first one
+    ///
+    /// The position returned is the one of the newline ending the line,
+    /// or the end of the buffer on the last line.
     ///
     /// Corresponds to `line-end-position` in elisp
     /// This function does not move the cursor (does save-excursion)
     pub fn line_end_position(&mut self, n: Option<i32>) -> usize {
         let pos = self.pos();
-        match n {
-            None | Some(1) => {
+        let n = n.unwrap_or(1);
+        // (forward-line (1- n))
+        if n > 1 {
+            for _ in 1..n {
                 self.goto_next_line();
             }
-
-            Some(x) => {
-                if x > 1 {
-                    for _p in 0..x {
-                        self.goto_next_line();
-                    }
-                } else if self.pos() != 0 {
-                    for p in 0..=x.abs() {
-                        if self.prev::<LinesMetric>().is_none() {
-                            break;
-                        }
-                    }
-                }
+        } else {
+            for _ in n..1 {
+                self.goto_prev_line();
             }
         }
 
-        let result = self.prev::<BaseMetric>().unwrap_or(0);
+        let result = memchr(b'\n', &self.data.as_bytes()[self.pos..])
+            .map_or(self.data.len(), |p| self.pos + p);
         self.set(pos);
         return result;
     }
 
+    /// Return number of lines between `start` and `end`. This is
+    /// usually the number of newlines between them, but can be one more
+    /// if `start` is not equal to `end` and the greater of them is not
+    /// at the start of a line.
+    ///
+    /// Corresponds to `count-lines` in elisp
+    pub fn count_lines(&self, start: usize, end: usize) -> usize {
+        let (start, end) = if start > end {
+            (end, start)
+        } else {
+            (start, end)
+        };
+        let region = &self.data.as_bytes()[start..end];
+        let newlines = region.iter().filter(|&&b| b == b'\n').count();
+        if region.is_empty() || region[region.len() - 1] == b'\n' {
+            newlines
+        } else {
+            newlines + 1
+        }
+    }
+
     pub fn char_after(&mut self, offset: usize) -> Option<char> {
         let pos = self.pos();
         self.set(offset);
@@ -294,7 +349,7 @@ impl<'a> Cursor<'a> {
         return result;
     }
 
-    /// Checks if current line matches a given regex
+    /// Checks if text at cursor matches a given regex
     /// This function determines whether the text in
     /// the current buffer directly following cursor matches
     /// the regular expression regexp.
@@ -304,6 +359,12 @@ impl<'a> Cursor<'a> {
     /// The result is true if so, false otherwise.
     /// This function does not move cursor
     /// Use `capturing_at` if you need capture groups.
+    ///
+    /// Unless the regex is multiline, only the rest of the current line is
+    /// searched. The regex engine does not backtrack, so the work done is
+    /// linear in the length of that line. At the end of data, that text
+    /// is empty: only a regex matching the empty string succeeds, as
+    /// `^[ \t]*$` does, with an empty match.
     pub fn looking_at(&self, re: &Regex) -> Option<Match<'a>> {
         let end = if !is_multiline_regex(re.as_str()) {
             LinesMetric::next(self.data, self.pos)
@@ -313,6 +374,7 @@ impl<'a> Cursor<'a> {
             self.data.len()
         };
         re.find(&self.data[self.pos..end])
+            .filter(|m| m.start() == 0)
     }
 
     /// Acts exactly as `looking_at` but returns Captures
@@ -328,6 +390,7 @@ impl<'a> Cursor<'a> {
         };
 
         re.captures(&self.data[self.pos..end])
+            .filter(|cap| cap.get(0).unwrap().start() == 0)
     }
 
     /// Possibly moves cursor to the beginning of the next headline
@@ -335,7 +398,10 @@ impl<'a> Cursor<'a> {
     /// If next headline is found returns it's start position
     pub fn next_headline(&mut self) -> Option<(usize)> {
         // make sure we don't match current headline
-        self.next::<LinesMetric>();
+        if self.next::<LinesMetric>().is_none() {
+            self.pos = self.data.len();
+            return None;
+        }
         let beg = self.pos();
         match REGEX_HEADLINE_MULTILINE.find(&self.data[beg..]) {
             Some(p) => {
@@ -346,7 +412,93 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    /// Moves cursor to the beginning of the first headline whose title
+    /// matches `query` and returns the interval of the headline's line,
+    /// newline excluded. Headlines are searched in document order from
+    /// the beginning of the buffer, regardless of the cursor position.
+    ///
+    /// Title is compared without TODO keyword, one of `keywords`,
+    /// priority cookie and tags.
+    /// Unless `fuzzy` is set it has to be equal to `query`. Otherwise
+    /// characters of `query` have to appear in the title in the same order,
+    /// not necessarily next to each other, case is ignored.
+    /// If no headline matches cursor doesn't move and None is returned.
+    /// Similar to `org-goto` completion
+    pub fn find_headline_by_title(
+        &mut self,
+        query: &str,
+        fuzzy: bool,
+        keywords: &TodoKeywords,
+    ) -> Option<Interval> {
+        let matches = |title: &str| {
+            if !fuzzy {
+                return title == query;
+            }
+            let mut title = title.chars().flat_map(char::to_lowercase);
+            query
+                .chars()
+                .flat_map(char::to_lowercase)
+                .all(|q| title.any(|t| t == q))
+        };
+        let found = REGEX_HEADLINE_MULTILINE
+            .find_iter(self.data)
+            .map(|m| {
+                let end = memchr(b'\n', &self.data.as_bytes()[m.start()..])
+                    .map_or(self.data.len(), |p| m.start() + p);
+                Interval {
+                    start: m.start(),
+                    end,
+                }
+            })
+            .find(|line| matches(headline_title(&self.data[line.start..line.end], keywords)))?;
+        self.pos = found.start;
+        Some(found)
+    }
+
+    /// Moves cursor to the beginning of the next line opening a block,
+    /// i.e. `#+BEGIN_NAME`. When `name` is given, only blocks with that
+    /// name are considered, case is ignored.
+    /// Lines closing blocks and dynamic blocks (`#+BEGIN:`) never match.
+    /// If no block is found cursor doesn't move and None is returned.
+    /// corresponds to `org-next-block`
+    pub fn goto_next_block(&mut self, name: Option<&str>) -> Option<usize> {
+        let mut line = LinesMetric::next(self.data, self.pos)?;
+        loop {
+            if self.is_block_begin(line, name) {
+                self.pos = line;
+                return Some(line);
+            }
+            line = LinesMetric::next(self.data, line)?;
+        }
+    }
+
+    /// Same as `goto_next_block` but moves to the previous block,
+    /// corresponds to `org-previous-block`
+    pub fn goto_prev_block(&mut self, name: Option<&str>) -> Option<usize> {
+        let mut line = memrchr(b'\n', &self.data.as_bytes()[..self.pos]).map(|p| p + 1)?;
+        while line > 0 {
+            line = memrchr(b'\n', &self.data.as_bytes()[..line - 1]).map_or(0, |p| p + 1);
+            if self.is_block_begin(line, name) {
+                self.pos = line;
+                return Some(line);
+            }
+        }
+        None
+    }
+
+    /// Checks if a line starting at `line` opens a block named `name`, or any block
+    fn is_block_begin(&self, line: usize, name: Option<&str>) -> bool {
+        let end =
+            memchr(b'\n', &self.data.as_bytes()[line..]).map_or(self.data.len(), |p| line + p);
+        match REGEX_BLOCK_BEGIN_LINE.captures(&self.data[line..end]) {
+            Some(cap) => name.map_or(true, |n| cap[1].eq_ignore_ascii_case(n)),
+            None => false,
+        }
+    }
+
     /// Return true if cursor is on a headline.
+    /// Headlines start at column 0, indented stars never make one,
+    /// whatever the position of the cursor on the line.
     /// corresponds to `org-at-heading-p`
     pub fn on_headline(&mut self) -> bool {
         let pos = self.pos();
@@ -356,6 +508,82 @@ impl<'a> Cursor<'a> {
         return result;
     }
 
+    /// Classifies the line the cursor is on by the element it can start.
+    /// The whole line is considered regardless of the column.
+    /// Patterns are tried in `DISPATCH_REGEX_SET` priority order
+    /// and the first one that matches wins, `LineKind::Text` otherwise.
+    /// Does not move the cursor
+    pub fn current_line_kind(&self) -> LineKind {
+        let beg = memrchr(b'\n', &self.data.as_bytes()[..self.pos]).map_or(0, |p| p + 1);
+        let end = memchr(b'\n', &self.data.as_bytes()[self.pos..])
+            .map_or(self.data.len(), |p| self.pos + p);
+        DISPATCH_REGEX_SET
+            .matches(&self.data[beg..end])
+            .iter()
+            .next()
+            .map_or(LineKind::Text, |i| DISPATCH_LINE_KINDS[i])
+    }
+
+    /// Returns the text of the line `n` lines away from the current one,
+    /// without the newline. Negative `n` looks at previous lines, 0 at the
+    /// current one. Returns None if there is no such line. Text following
+    /// the last newline counts as a line, even when it is empty, like
+    /// in Emacs. Does not move the cursor
+    pub fn peek_line(&self, n: i32) -> Option<&'a str> {
+        let bytes = self.data.as_bytes();
+        let mut beg = memrchr(b'\n', &bytes[..self.pos]).map_or(0, |p| p + 1);
+        if n > 0 {
+            for _ in 0..n {
+                beg += memchr(b'\n', &bytes[beg..])? + 1;
+            }
+        } else {
+            for _ in n..0 {
+                if beg == 0 {
+                    return None;
+                }
+                beg = memrchr(b'\n', &bytes[..beg - 1]).map_or(0, |p| p + 1);
+            }
+        }
+        let end = memchr(b'\n', &bytes[beg..]).map_or(self.data.len(), |p| beg + p);
+        Some(&self.data[beg..end])
+    }
+
+    /// Returns the line beginning at or after point, along with the
+    /// position of its beginning, and moves point past its newline.
+    /// Text of the line excludes the newline, as well as a carriage
+    /// return before it. The last line is returned even when it does not
+    /// end with a newline, but, unlike in `peek_line`, a newline ending
+    /// the data is not followed by another, empty, line.
+    pub fn next_line_text(&mut self) -> Option<(usize, &'a str)> {
+        let bytes = self.data.as_bytes();
+        let beg = if self.is_bol() {
+            self.pos
+        } else {
+            self.pos + memchr(b'\n', &bytes[self.pos..])? + 1
+        };
+        if beg >= self.data.len() {
+            return None;
+        }
+        let end = memchr(b'\n', &bytes[beg..]).map_or(self.data.len(), |p| beg + p);
+        self.pos = (end + 1).min(self.data.len());
+        Some((beg, without_cr(&self.data[beg..end])))
+    }
+
+    /// Returns the line before the one containing point, along with the
+    /// position of its beginning, and moves point there. Text of the line
+    /// is the same as for `next_line_text`. At the end of data ending
+    /// with a newline, the previous line is the last one.
+    pub fn prev_line_text(&mut self) -> Option<(usize, &'a str)> {
+        let bytes = self.data.as_bytes();
+        let current = memrchr(b'\n', &bytes[..self.pos]).map_or(0, |p| p + 1);
+        if current == 0 {
+            return None;
+        }
+        let beg = memrchr(b'\n', &bytes[..current - 1]).map_or(0, |p| p + 1);
+        self.pos = beg;
+        Some((beg, without_cr(&self.data[beg..current - 1])))
+    }
+
     pub fn is_bol(&self) -> bool {
         if self.pos == 0 {
             true
@@ -370,8 +598,8 @@ impl<'a> Cursor<'a> {
     /// None then search to end of the buffer. If count is specified,
     /// find the countth occurence. If countth occurence is not found
     /// None is returned. If count is not provided then 1 is used as
-    /// count. Note that searching backward is not supported like it
-    /// is in the elisp equivalent.
+    /// count. Use `search_backward` to search backward, instead of
+    /// a negative count as in the elisp equivalent.
     pub fn search_forward(
         &mut self,
         str: &str,
@@ -414,6 +642,81 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    /// Search backward from point for str. Sets point to the beginning
+    /// of the occurence found and returns point. bound is a position in
+    /// the buffer. The match found must not begin before that position.
+    /// If None then search to the beginning of the buffer. If count is
+    /// specified, find the countth occurence, counting backward. Each
+    /// occurence ends before the beginning of the previous one, the way
+    /// successive searches do. If countth occurence is not found None is
+    /// returned.
+    pub fn search_backward(
+        &mut self,
+        str: &str,
+        bound: Option<usize>,
+        count: Option<usize>,
+    ) -> Option<usize> {
+        let count = count.unwrap_or(1);
+        let bound = bound.unwrap_or(0);
+
+        let pos = self.pos();
+        if bound > pos {
+            return None;
+        }
+
+        let (start, _) = self.data[..pos]
+            .rmatch_indices(str)
+            .take_while(|(start, _)| *start >= bound)
+            .nth(count.checked_sub(1)?)?;
+        self.set(start);
+        Some(start)
+    }
+
+    /// Acts exactly as `search_forward` but ignores case,
+    /// like elisp does with `case-fold-search` set to t.
+    ///
+    /// Comparison is done char by char using simple case folding, so
+    /// a single char only matches a single char (ß does not match SS).
+    /// Folded chars can have different length in bytes than their
+    /// counterparts, therefore buffer is never searched through a folded
+    /// copy: returned offsets always point into the original data.
+    pub fn search_forward_ci(
+        &mut self,
+        str: &str,
+        bound: Option<usize>,
+        count: Option<usize>,
+    ) -> Option<usize> {
+        let count = count.unwrap_or(1);
+        let bound = bound.unwrap_or_else(|| self.data.len());
+
+        let pos = self.pos();
+        if bound < pos {
+            return None;
+        }
+
+        let mut i = 1;
+        let mut start = pos;
+        while start < self.data.len() {
+            if let Some(end) = match_folded(&self.data[start..], str).map(|len| start + len) {
+                if end > bound {
+                    return None;
+                }
+                if count == i {
+                    self.set(end);
+                    return Some(end);
+                }
+                i += 1;
+                start = end.max(start + 1);
+            } else {
+                start += 1;
+            }
+            while !self.data.is_char_boundary(start) {
+                start += 1;
+            }
+        }
+        None
+    }
+
     /// (re-search-forward REGEXP &optional BOUND NOERROR COUNT)
     ///
     /// Search forward from point for regular expression REGEXP.
@@ -439,34 +742,120 @@ impl<'a> Cursor<'a> {
         }
     }
 
+    /// (re-search-backward REGEXP &optional BOUND NOERROR COUNT)
+    ///
+    /// Search backward from point for regular expression REGEXP.
+    /// Set point to the beginning of the occurrence found, and return
+    /// the occurrence. The optional second argument BOUND is a buffer
+    /// position that bounds the search. The match found must not begin
+    /// before that position. A value of nil means search to the
+    /// beginning of the buffer.
+    ///
+    /// The match found is the one beginning closest to point, text
+    /// after point is not searched, so the match ends at point at the
+    /// latest. Point doesn't move if there is none.
+    pub fn re_search_backward(&mut self, re: &Regex, bound: Option<usize>) -> Option<Interval> {
+        let text = &self.data[..self.pos];
+        let mut start = bound.unwrap_or(0);
+        let mut found = None;
+        while start < self.pos {
+            let m = match re.find_at(text, start) {
+                Some(m) if m.start() < self.pos => m,
+                _ => break,
+            };
+            found = Some(Interval {
+                start: m.start(),
+                end: m.end(),
+            });
+            // Matches may overlap, try again right after this one's beginning
+            start = m.start()
+                + self.data[m.start()..]
+                    .chars()
+                    .next()
+                    .map_or(1, char::len_utf8);
+        }
+        if let Some(interval) = found {
+            self.set(interval.start);
+        }
+        found
+    }
+
+    /// Check if point is inside a match of `re`.
+    /// Only the current line is checked, but `bound_lines` extra lines
+    /// before and after the current one can be included into the search.
+    /// A match that ends exactly at point counts as containing it.
+    ///
+    /// Returns the interval of the match containing point, if any.
+    /// This function does not move the cursor (does save-excursion)
+    ///
+    /// Corresponds to `org-in-regexp` in org.el
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org.el#L20814
+    pub fn in_regexp(&mut self, re: &Regex, bound_lines: usize) -> Option<Interval> {
+        let pos = self.pos();
+        let n = bound_lines as i32;
+        let eol = self.line_end_position(Some(n + 1));
+        let begin = self.line_beginning_position(Some(1 - n));
+
+        re.find_iter(&self.data[begin..eol])
+            .take_while(|m| begin + m.start() <= pos)
+            .find(|m| begin + m.end() >= pos)
+            .map(|m| Interval {
+                start: begin + m.start(),
+                end: begin + m.end(),
+            })
+    }
+
     /// Moves point forward, stopping before a char not in str, or at position limit.
+    /// Moves point forward over chars of `str`, stopping before `limit`,
+    /// a byte position, would be crossed. Returns the number of chars
+    /// skipped.
     pub fn skip_chars_forward(&mut self, str: &str, limit: Option<usize>) -> usize {
-        let pos = self.pos();
         let limit = match limit {
             Some(lim) => lim,
             _ => self.data.len(),
         };
 
-        if pos >= limit {
-            return 0;
+        let mut count = 0;
+        while self.pos < limit {
+            let pos = self.pos;
+            match self.get_next_char() {
+                Some(c) if str.contains(c) && self.pos <= limit => count += 1,
+                _ => {
+                    self.pos = pos;
+                    break;
+                }
+            }
         }
+        count
+    }
 
+    /// Moves point backward, stopping after a char not in str, or at position limit.
+    /// `limit` is an absolute position, cursor never moves before it.
+    /// Returns the number of characters skipped.
+    ///
+    /// Corresponds to `skip-chars-backward` in elisp
+    pub fn skip_chars_backward(&mut self, str: &str, limit: Option<usize>) -> usize {
+        let limit = limit.unwrap_or(0);
         let mut count = 0;
-        while let Some(c) = self.get_next_char() {
-            if !str.contains(c) {
-                self.get_prev_char();
-                return count;
-            }
-            if count + pos > limit {
-                self.get_prev_char();
-                return count;
+        while self.pos > limit {
+            let pos = self.pos;
+            match self.get_prev_char() {
+                Some(c) if str.contains(c) && self.pos >= limit => count += 1,
+                _ => {
+                    self.pos = pos;
+                    break;
+                }
             }
-            count += 1;
         }
         count
     }
 }
 
+/// Returns `line` without the carriage return ending it, if any
+fn without_cr(line: &str) -> &str {
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
 /// Given the inital byte of a UTF-8 codepoint, returns the number of
 /// bytes required to represent the codepoint.
 /// RFC reference : https://tools.ietf.org/html/rfc3629#section-4
@@ -479,6 +868,29 @@ pub fn len_utf8_from_first_byte(b: u8) -> usize {
     }
 }
 
+/// Simple case folding of a single char. Chars whose lowercase
+/// form is longer than one char are left as they are.
+fn fold_char(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+/// If `haystack` starts with `needle` ignoring case returns
+/// the length in bytes of the matched part of `haystack`.
+fn match_folded(haystack: &str, needle: &str) -> Option<usize> {
+    let mut hay = haystack.char_indices();
+    for n in needle.chars() {
+        match hay.next() {
+            Some((_, h)) if fold_char(h) == fold_char(n) => {}
+            _ => return None,
+        }
+    }
+    Some(hay.next().map_or(haystack.len(), |(i, _)| i))
+}
+
 /// Checks if a regular expression can match multiple lines.
 pub fn is_multiline_regex(regex: &str) -> bool {
     // regex characters that match line breaks
@@ -493,11 +905,11 @@ mod test {
     use super::Cursor;
     use super::LinesMetric;
     use super::Metric;
-    use super::REGEX_EMPTY_LINE;
 
-    use crate::data::Syntax;
-    use crate::headline::REGEX_HEADLINE_SHORT;
+    use crate::data::{Interval, Syntax};
+    use crate::headline::TodoKeywords;
     use crate::parser::Parser;
+    use crate::regexps::{LineKind, REGEX_EMPTY_LINE, REGEX_HEADLINE_SHORT};
 
     use crate::cursor::BaseMetric;
     use regex::Match;
@@ -555,6 +967,48 @@ mod test {
         assert!(cursor.looking_at(&*REGEX_EMPTY_LINE).is_none());
     }
 
+    #[test]
+    fn line_text() {
+        let mut cursor = Cursor::new("a\r\nb\n", 0);
+        assert_eq!(Some((0, "a")), cursor.next_line_text());
+        assert_eq!(3, cursor.pos());
+        assert_eq!(Some((3, "b")), cursor.next_line_text());
+        assert_eq!(5, cursor.pos());
+        assert_eq!(None, cursor.next_line_text());
+        assert_eq!(Some((3, "b")), cursor.prev_line_text());
+        assert_eq!(Some((0, "a")), cursor.prev_line_text());
+        assert_eq!(None, cursor.prev_line_text());
+        assert_eq!(0, cursor.pos());
+
+        // From the middle of a line, and without a final newline
+        let mut cursor = Cursor::new("αβ\n\nγ", 2);
+        assert_eq!(Some((5, "")), cursor.next_line_text());
+        assert_eq!(Some((6, "γ")), cursor.next_line_text());
+        assert_eq!(8, cursor.pos());
+        assert_eq!(None, cursor.next_line_text());
+        assert_eq!(Some((5, "")), cursor.prev_line_text());
+        assert_eq!(Some((0, "αβ")), cursor.prev_line_text());
+    }
+
+    #[test]
+    fn looking_at_buffer_end() {
+        let matches_empty = Regex::new(r"^([ \t]*)$").unwrap();
+        let multiline = Regex::new(r"(?m)^([ \t]*)$").unwrap();
+        let non_matching = Regex::new(r"^(\S+)").unwrap();
+        for text in &["ab", "ab\n", ""] {
+            let cursor = Cursor::new(text, text.len());
+            for re in &[&matches_empty, &multiline] {
+                let m = cursor.looking_at(re).unwrap();
+                assert_eq!((0, 0), (m.start(), m.end()));
+                let cap = cursor.capturing_at(re).unwrap();
+                assert_eq!("", &cap[1]);
+            }
+            assert!(cursor.looking_at(&non_matching).is_none());
+            assert!(cursor.capturing_at(&non_matching).is_none());
+            assert_eq!(text.len(), cursor.pos());
+        }
+    }
+
     #[test]
     fn on_headline() {
         let rope = "Some text\n**** headline\n";
@@ -640,6 +1094,33 @@ mod test {
         assert_eq!(cursor.get_next_char().unwrap(), 'F');
     }
 
+    #[test]
+    fn first_line_edges() {
+        let rope = "\nab\ncd";
+        for pos in 0..2 {
+            let mut cursor = Cursor::new(&rope, pos);
+            assert_eq!(pos, cursor.goto_line_begin());
+            assert_eq!(0, cursor.goto_prev_line());
+            assert_eq!(0, cursor.goto_line_begin());
+            assert_eq!(None, cursor.prev::<LinesMetric>());
+            assert_eq!(0, cursor.pos());
+        }
+
+        // Beginning of data is not a line boundary
+        let mut cursor = Cursor::new(&rope, 1);
+        assert_eq!(None, cursor.prev::<LinesMetric>());
+        let mut cursor = Cursor::new(&"a", 1);
+        assert_eq!(0, cursor.goto_line_begin());
+        assert_eq!(0, cursor.goto_prev_line());
+
+        // Moving back past the first line stops there
+        let mut cursor = Cursor::new(&rope, 5);
+        assert_eq!(0, cursor.line_beginning_position(Some(-4)));
+        assert_eq!(0, cursor.line_end_position(Some(-4)));
+        cursor.set(1);
+        assert_eq!(0, cursor.line_beginning_position(Some(-1)));
+    }
+
     #[test]
     fn line_begin_pos() {
         let rope = "One\nTwo\nThi\nFo4\nFiv\nSix\n7en";
@@ -666,7 +1147,7 @@ mod test {
         assert_eq!(cursor.line_end_position(Some(1)), 15);
         assert_eq!(cursor.line_end_position(Some(2)), 19);
         assert_eq!(cursor.line_end_position(Some(3)), 23);
-        assert_eq!(cursor.line_end_position(Some(4)), 26);
+        assert_eq!(cursor.line_end_position(Some(4)), 27);
 
         //Moving backward
         assert_eq!(cursor.line_end_position(Some(0)), 11);
@@ -675,6 +1156,125 @@ mod test {
         assert_eq!(cursor.line_end_position(Some(-3)), 3);
     }
 
+    #[test]
+    fn line_end_pos_table() {
+        // Results for N from -3 to 4
+        let text = "a\nbcd\n\nefghi\nj";
+        let table = [
+            (0, [1, 1, 1, 1, 1, 5, 6, 12]),
+            (1, [1, 1, 1, 1, 1, 5, 6, 12]),
+            (3, [1, 1, 1, 1, 5, 6, 12, 14]),
+            (6, [1, 1, 1, 5, 6, 12, 14, 14]),
+            (9, [1, 1, 5, 6, 12, 14, 14, 14]),
+            (14, [1, 5, 6, 12, 14, 14, 14, 14]),
+        ];
+        for (pos, expected) in table.iter() {
+            let mut cursor = Cursor::new(&text, *pos);
+            let actual: Vec<_> = (-3..=4)
+                .map(|n| cursor.line_end_position(Some(n)))
+                .collect();
+            assert_eq!(expected.to_vec(), actual, "from {}", pos);
+            assert_eq!(*pos, cursor.pos());
+        }
+
+        // Text following the last newline is an empty line
+        let text = "a\nbcd\n\nefghi\nj\n";
+        let table = [
+            (13, [1, 5, 6, 12, 14, 15, 15, 15]),
+            (15, [5, 6, 12, 14, 15, 15, 15, 15]),
+        ];
+        for (pos, expected) in table.iter() {
+            let mut cursor = Cursor::new(&text, *pos);
+            let actual: Vec<_> = (-3..=4)
+                .map(|n| cursor.line_end_position(Some(n)))
+                .collect();
+            assert_eq!(expected.to_vec(), actual, "from {}", pos);
+        }
+        assert_eq!(15, Cursor::new(&text, 15).line_end_position(None));
+    }
+
+    #[test]
+    fn skip_chars_backward() {
+        let text = "ab  \t\u{e9} x";
+        let mut cursor = Cursor::new(&text, text.len());
+        assert_eq!(0, cursor.skip_chars_backward(" ", None));
+        cursor.set(8);
+        assert_eq!(1, cursor.skip_chars_backward(" ", None));
+        assert_eq!(7, cursor.pos());
+        assert_eq!(0, cursor.skip_chars_backward(" ", None));
+        assert_eq!(4, cursor.skip_chars_backward("\u{e9}\t ", None));
+        assert_eq!(2, cursor.pos());
+        assert_eq!(2, cursor.skip_chars_backward("ab", None));
+        assert_eq!(0, cursor.pos());
+        assert_eq!(0, cursor.skip_chars_backward("ab", None));
+
+        // Limit is a position the cursor never goes before
+        cursor.set(7);
+        assert_eq!(0, cursor.skip_chars_backward("\u{e9}\t ", Some(7)));
+        assert_eq!(7, cursor.pos());
+        assert_eq!(1, cursor.skip_chars_backward("\u{e9}\t ", Some(5)));
+        assert_eq!(5, cursor.pos());
+        // Limit in the middle of a character
+        cursor.set(7);
+        assert_eq!(0, cursor.skip_chars_backward("\u{e9}\t ", Some(6)));
+        assert_eq!(7, cursor.pos());
+        cursor.set(5);
+        assert_eq!(1, cursor.skip_chars_backward("\u{e9}\t ", Some(4)));
+        assert_eq!(4, cursor.pos());
+        assert_eq!(0, cursor.skip_chars_backward(" ", Some(10)));
+        assert_eq!(4, cursor.pos());
+    }
+
+    #[test]
+    fn search_forward_ci() {
+        let text = "#+BEGIN_SRC rust\ncode\n#+End_Src\n";
+        let mut cursor = Cursor::new(&text, 0);
+        assert_eq!(None, cursor.search_forward("#+begin_src", None, None));
+        assert_eq!(
+            Some(11),
+            cursor.search_forward_ci("#+begin_src", None, None)
+        );
+        assert_eq!(Some(31), cursor.search_forward_ci("#+end_src", None, None));
+        assert_eq!(31, cursor.pos());
+
+        cursor.set(0);
+        assert_eq!(None, cursor.search_forward_ci("#+end_src", Some(30), None));
+        assert_eq!(Some(31), cursor.search_forward_ci("SRC", None, Some(2)));
+        assert_eq!(None, cursor.search_forward_ci("src", None, Some(2)));
+
+        // offsets point into the original data
+        let text = "Ärger und ärger";
+        let mut cursor = Cursor::new(&text, 0);
+        assert_eq!(Some(6), cursor.search_forward_ci("ärger", None, None));
+        assert_eq!(Some(17), cursor.search_forward_ci("ÄRGER", None, None));
+        // only simple folding, one char matches one char
+        let text = "Straße";
+        let mut cursor = Cursor::new(&text, 0);
+        assert_eq!(None, cursor.search_forward_ci("STRASSE", None, None));
+        assert_eq!(Some(7), cursor.search_forward_ci("STRAßE", None, None));
+    }
+
+    #[test]
+    fn goto_block() {
+        let text = "#+BEGIN_QUOTE\nq\n#+END_QUOTE\n#+begin_src rust\n#+END_SRC\n#+BEGIN: dyn\n#+END:\n#+BEGIN_SRC sh\n#+END_SRC\n";
+        let mut cursor = Cursor::new(&text, 0);
+        assert_eq!(Some(28), cursor.goto_next_block(None));
+        assert_eq!(Some(75), cursor.goto_next_block(None));
+        assert_eq!(None, cursor.goto_next_block(None));
+        assert_eq!(75, cursor.pos());
+
+        assert_eq!(Some(28), cursor.goto_prev_block(Some("SRC")));
+        assert_eq!(None, cursor.goto_prev_block(Some("src")));
+        assert_eq!(Some(0), cursor.goto_prev_block(None));
+        assert_eq!(None, cursor.goto_prev_block(None));
+        assert_eq!(0, cursor.pos());
+
+        assert_eq!(Some(28), cursor.goto_next_block(Some("src")));
+        assert_eq!(None, cursor.goto_next_block(Some("quote")));
+        cursor.set(20);
+        assert_eq!(Some(0), cursor.goto_prev_block(Some("quote")));
+    }
+
     #[test]
     fn is_bol() {
         let rope = "One\nTwo\nThi\nFo4\nFiv\nSix\n7en";
@@ -713,6 +1313,27 @@ mod test {
         assert_eq!(cursor.search_forward("aba", Some(10000), Some(6)), None);
     }
 
+    #[test]
+    fn search_backward() {
+        let str = "onetwothreefouronetwothreeonetwothreeonetwothreefouroneabababa";
+        let mut cursor = Cursor::new(&str, str.len());
+        assert_eq!(cursor.search_backward("one", None, Some(2)), Some(37));
+        assert_eq!(cursor.search_backward("one", None, None), Some(26));
+        cursor.set(str.len());
+        assert_eq!(cursor.search_backward("threeone", Some(50), None), None); // there is no match after 50th pos
+        assert_eq!(cursor.search_backward("threeone", Some(0), Some(10)), None); // there is not a 10th match so return None
+        assert_eq!(cursor.search_backward("two", None, Some(4)), Some(3));
+        assert_eq!(cursor.pos(), 3);
+        assert_eq!(cursor.search_backward("one", Some(10), None), None); // bound is after current pos
+        assert_eq!(cursor.pos(), 3);
+        cursor.set(str.len());
+        assert_eq!(cursor.search_backward("aba", Some(0), Some(2)), Some(55));
+        cursor.set(str.len());
+        assert_eq!(cursor.search_backward("aba", Some(0), Some(3)), None);
+        cursor.set(60);
+        assert_eq!(cursor.search_backward("aba", None, None), Some(57)); // match must end before point
+    }
+
     #[test]
     fn skip_chars_forward() {
         let str = "  k\t **hello";
@@ -721,7 +1342,68 @@ mod test {
         assert_eq!(cursor.pos(), 2);
         assert_eq!(cursor.skip_chars_forward(" k\t", None), 3);
         cursor.set(0);
-        assert_eq!(cursor.skip_chars_forward("* k\t", Some(2)), 3);
+        assert_eq!(cursor.skip_chars_forward("* k\t", Some(2)), 2);
+        assert_eq!(cursor.pos(), 2);
+
+        // Limit is a byte position, whatever the width of chars
+        let str = "ααα bbb";
+        let mut cursor = Cursor::new(&str, 0);
+        assert_eq!(cursor.skip_chars_forward("α ", Some(4)), 2);
+        assert_eq!(cursor.pos(), 4);
+        cursor.set(0);
+        assert_eq!(cursor.skip_chars_forward("α ", Some(5)), 2);
+        assert_eq!(cursor.pos(), 4);
+        assert_eq!(cursor.skip_chars_forward("α b", Some(9)), 4);
+        assert_eq!(cursor.pos(), 9);
+        assert_eq!(cursor.skip_chars_forward("α b", Some(4)), 0);
+        assert_eq!(cursor.pos(), 9);
+    }
+
+    #[test]
+    fn count_lines() {
+        let text = "One\nTwo\n\nFour";
+        let cursor = Cursor::new(&text, 0);
+        assert_eq!(0, cursor.count_lines(0, 0));
+        assert_eq!(1, cursor.count_lines(0, 2));
+        assert_eq!(1, cursor.count_lines(0, 4));
+        assert_eq!(2, cursor.count_lines(0, 5));
+        assert_eq!(3, cursor.count_lines(0, 9));
+        assert_eq!(4, cursor.count_lines(0, text.len()));
+        assert_eq!(4, cursor.count_lines(text.len(), 0));
+    }
+
+    #[test]
+    fn in_regexp() {
+        let re = Regex::new(r"\[\[[^\]]+\](?:\[[^\]]+\])?\]").unwrap();
+        let text = "First line\nSee [[https://orgmode.org][site]] here\nLast";
+        let mut cursor = Cursor::new(&text, 20);
+
+        let expected = Some(Interval { start: 15, end: 44 });
+        assert_eq!(expected, cursor.in_regexp(&re, 0));
+        assert_eq!(20, cursor.pos());
+        cursor.set(15);
+        assert_eq!(expected, cursor.in_regexp(&re, 0));
+        cursor.set(44);
+        assert_eq!(expected, cursor.in_regexp(&re, 0));
+
+        cursor.set(13);
+        assert_eq!(None, cursor.in_regexp(&re, 0));
+        cursor.set(46);
+        assert_eq!(None, cursor.in_regexp(&re, 0));
+        cursor.set(3);
+        assert_eq!(None, cursor.in_regexp(&re, 1));
+    }
+
+    #[test]
+    fn in_regexp_bound_lines() {
+        let re = Regex::new(r"\[\[[^\]]+\]\]").unwrap();
+        let text = "[[multi\nline]] link";
+        let mut cursor = Cursor::new(&text, 10);
+        assert_eq!(None, cursor.in_regexp(&re, 0));
+        assert_eq!(
+            Some(Interval { start: 0, end: 14 }),
+            cursor.in_regexp(&re, 1)
+        );
     }
 
     #[test]
@@ -738,4 +1420,171 @@ mod test {
         assert_eq!(None, cursor.re_search_forward(&re, Some(24)));
         assert_eq!(25, cursor.pos());
     }
+
+    #[test]
+    fn re_search_backward() {
+        let text = "One\nTwo\nThi\nFo4\nFiv\nSix\n7en 89";
+        let mut cursor = Cursor::new(&text, 24);
+
+        let re = Regex::new(r"\d+").unwrap();
+        assert_eq!(
+            Some(Interval { start: 14, end: 15 }),
+            cursor.re_search_backward(&re, None)
+        );
+        assert_eq!(14, cursor.pos());
+        assert_eq!(None, cursor.re_search_backward(&re, None));
+        assert_eq!(14, cursor.pos());
+
+        // Match beginning closest to point wins, as in Emacs
+        cursor.set(text.len());
+        assert_eq!(
+            Some(Interval { start: 29, end: 30 }),
+            cursor.re_search_backward(&re, None)
+        );
+        // Matches are cut at point
+        cursor.set(29);
+        assert_eq!(
+            Some(Interval { start: 28, end: 29 }),
+            cursor.re_search_backward(&re, Some(20))
+        );
+        assert_eq!(28, cursor.pos());
+        cursor.set(29);
+        assert_eq!(None, cursor.re_search_backward(&re, Some(29)));
+        assert_eq!(29, cursor.pos());
+    }
+
+    #[test]
+    fn peek_line() {
+        let text = "first\nsecond\nthird\nfourth\n";
+        let cursor = Cursor::new(text, 15);
+        assert_eq!(Some("third"), cursor.peek_line(0));
+        assert_eq!(Some("second"), cursor.peek_line(-1));
+        assert_eq!(Some("first"), cursor.peek_line(-2));
+        assert_eq!(None, cursor.peek_line(-3));
+        assert_eq!(Some("fourth"), cursor.peek_line(1));
+        assert_eq!(Some(""), cursor.peek_line(2));
+        assert_eq!(None, cursor.peek_line(3));
+        assert_eq!(15, cursor.pos());
+
+        // At the newline, cursor is still on the line it ends
+        let cursor = Cursor::new(text, 5);
+        assert_eq!(Some("first"), cursor.peek_line(0));
+        assert_eq!(Some("second"), cursor.peek_line(1));
+        assert_eq!(Some("first"), Cursor::new("first", 0).peek_line(0));
+        assert_eq!(None, Cursor::new("", 0).peek_line(-1));
+    }
+
+    #[test]
+    fn current_line_kind() {
+        let text = "* Head\n  - item\n#+BEGIN_SRC\ncode\n#+END_SRC\n\n| a |";
+        let mut cursor = Cursor::new(&text, 0);
+        assert_eq!(LineKind::Headline, cursor.current_line_kind());
+        cursor.set(10);
+        assert_eq!(LineKind::Item, cursor.current_line_kind());
+        assert_eq!(10, cursor.pos());
+        cursor.set(16);
+        assert_eq!(LineKind::BlockBegin, cursor.current_line_kind());
+        cursor.set(28);
+        assert_eq!(LineKind::Text, cursor.current_line_kind());
+        cursor.set(33);
+        assert_eq!(LineKind::BlockEnd, cursor.current_line_kind());
+        cursor.set(43);
+        assert_eq!(LineKind::Empty, cursor.current_line_kind());
+        cursor.set(text.len());
+        assert_eq!(LineKind::TableRow, cursor.current_line_kind());
+    }
+
+    #[test]
+    fn find_headline_by_title() {
+        let text = "Intro\n* TODO [#A] Project plan :work:\n** Plan\n* DONE Pack bags\n";
+        let mut cursor = Cursor::new(&text, 40);
+        let keywords = TodoKeywords::default();
+        assert_eq!(
+            Some(Interval { start: 6, end: 37 }),
+            cursor.find_headline_by_title("Project plan", false, &keywords)
+        );
+        assert_eq!(6, cursor.pos());
+        assert_eq!(
+            Some(Interval { start: 38, end: 45 }),
+            cursor.find_headline_by_title("Plan", false, &keywords)
+        );
+        assert_eq!(
+            None,
+            cursor.find_headline_by_title("TODO Project plan", false, &keywords)
+        );
+        assert_eq!(38, cursor.pos());
+
+        // First match in document order wins
+        assert_eq!(
+            Some(Interval { start: 6, end: 37 }),
+            cursor.find_headline_by_title("pln", true, &keywords)
+        );
+        assert_eq!(
+            Some(Interval { start: 46, end: 62 }),
+            cursor.find_headline_by_title("PkBg", true, &keywords)
+        );
+        assert_eq!(None, cursor.find_headline_by_title("work", true, &keywords));
+        assert_eq!(46, cursor.pos());
+
+        // Custom keywords
+        let text = "* WAIT Foo\n* Foo\n";
+        let mut cursor = Cursor::new(&text, 0);
+        let keywords = TodoKeywords::from_sequence("TODO WAIT | DONE");
+        assert_eq!(
+            Some(Interval { start: 0, end: 10 }),
+            cursor.find_headline_by_title("Foo", false, &keywords)
+        );
+        let keywords = TodoKeywords::default();
+        assert_eq!(
+            Some(Interval { start: 11, end: 16 }),
+            cursor.find_headline_by_title("Foo", false, &keywords)
+        );
+    }
+
+    #[test]
+    fn at_start() {
+        let mut cursor = Cursor::at_start("* Headline\nText");
+        assert_eq!(0, cursor.pos());
+        assert!(cursor.on_headline());
+        assert_eq!(11, cursor.goto_next_line());
+
+        assert_eq!(0, Cursor::at_start("").pos());
+    }
+
+    #[test]
+    fn nth() {
+        let cursor = Cursor::new("one\ntwo\n\nfour", 5);
+        assert_eq!(Some(0), cursor.nth::<LinesMetric>(0));
+        assert_eq!(Some(4), cursor.nth::<LinesMetric>(1));
+        assert_eq!(Some(8), cursor.nth::<LinesMetric>(2));
+        assert_eq!(Some(9), cursor.nth::<LinesMetric>(3));
+        assert_eq!(None, cursor.nth::<LinesMetric>(4));
+        assert_eq!(5, cursor.pos());
+
+        let cursor = Cursor::at_start("aéb");
+        assert_eq!(Some(0), cursor.nth::<BaseMetric>(0));
+        assert_eq!(Some(1), cursor.nth::<BaseMetric>(1));
+        assert_eq!(Some(3), cursor.nth::<BaseMetric>(2));
+        assert_eq!(Some(4), cursor.nth::<BaseMetric>(3));
+        assert_eq!(None, cursor.nth::<BaseMetric>(4));
+    }
+
+    #[test]
+    fn current_line_number() {
+        let text = "one\ntwo\n";
+        let mut cursor = Cursor::at_start(text);
+        assert_eq!(1, cursor.current_line_number());
+        cursor.set(3);
+        assert_eq!(1, cursor.current_line_number());
+        cursor.set(4);
+        assert_eq!(2, cursor.current_line_number());
+        cursor.set(text.len());
+        assert_eq!(3, cursor.current_line_number());
+
+        let text = "one\ntwo";
+        let cursor = Cursor::new(text, text.len());
+        assert_eq!(2, cursor.current_line_number());
+
+        assert_eq!(1, Cursor::at_start("").current_line_number());
+    }
 }
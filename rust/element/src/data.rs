@@ -16,7 +16,7 @@
 // https://orgmode.org/worg/dev/org-element-api.html
 // API page lists LineBreak as element, when both org-syntax page and source code list is as object
 
-use crate::affiliated::AffiliatedData;
+use crate::affiliated::{AffiliatedData, DualVal};
 use crate::babel::BabelCallData;
 use crate::blocks::CommentBlockData;
 use crate::blocks::DynamicBlockData;
@@ -24,6 +24,7 @@ use crate::blocks::ExampleBlockData;
 use crate::blocks::ExportBlockData;
 use crate::blocks::SpecialBlockData;
 use crate::blocks::SrcBlockData;
+use crate::citation::CitationData;
 use crate::data::Syntax::BabelCall;
 use crate::drawer::DrawerData;
 use crate::headline::{HeadlineData, InlineTaskData, NodePropertyData};
@@ -82,8 +83,8 @@ pub struct SyntaxNode<'a> {
     /// of the following one at the same level, if any.
     pub post_blank: usize,
 
-    // TODO affiliated keywords stub
-    pub affiliated: Option<()>,
+    /// Affiliated keywords attached to the element, if any
+    pub affiliated: Option<AffiliatedData<'a>>,
 }
 
 impl<'a> SyntaxNode<'a> {
@@ -98,6 +99,173 @@ impl<'a> SyntaxNode<'a> {
             affiliated: None,
         }
     }
+
+    /// Creates a node without parent and children
+    pub fn new(
+        data: Syntax<'a>,
+        location: Interval,
+        content_location: Option<Interval>,
+        post_blank: usize,
+        affiliated: Option<AffiliatedData<'a>>,
+    ) -> SyntaxNode<'a> {
+        SyntaxNode {
+            parent: RefCell::new(None),
+            children: RefCell::new(vec![]),
+            data,
+            location,
+            content_location,
+            post_blank,
+            affiliated,
+        }
+    }
+
+    /// Returns node's value exactly as it is written in the source,
+    /// for nodes that have one, e.g. headline's title with emphasis
+    /// markers intact or keyword's value.
+    /// Returned slice borrows from the parsed input, parsed
+    /// representation, if any, is kept in node's data.
+    /// Corresponds to `:raw-value` property, or `:value` for keywords.
+    pub fn raw_value(&self) -> Option<&'a str> {
+        match &self.data {
+            Syntax::Headline(h) => Some(h.raw_value),
+            Syntax::InlineTask(t) => Some(t.raw_value),
+            Syntax::Keyword(k) => Some(k.value()),
+            Syntax::RadioTarget(r) => Some(r.raw_value),
+            Syntax::SpecialBlock(b) => Some(b.raw_value),
+            Syntax::Timestamp(t) => Some(t.raw_value),
+            _ => None,
+        }
+    }
+
+    /// Beginning of the element proper, after its affiliated keywords.
+    /// Same as the beginning of the node when it has none.
+    /// Corresponds to `:post-affiliated` property.
+    pub fn post_affiliated(&self) -> usize {
+        self.affiliated
+            .as_ref()
+            .map_or(self.location.start, |a| a.post_affiliated)
+    }
+
+    /// Returns the value of `#+NAME:` affiliated keyword, if any
+    pub fn name(&self) -> Option<&str> {
+        self.affiliated
+            .as_ref()?
+            .name
+            .as_ref()
+            .map(|name| name.as_ref())
+    }
+
+    /// Returns the values of `#+CAPTION:` affiliated keywords,
+    /// in order of appearance. Each one can have a secondary value,
+    /// the short caption, written in brackets.
+    pub fn caption(&self) -> &[DualVal<StringOrObject<'a>>] {
+        self.affiliated.as_ref().map_or(&[], |a| &a.caption[..])
+    }
+
+    /// Hash of the results from `#+RESULTS[HASH]:`, for results of
+    /// source blocks evaluated with `:cache yes`, see
+    /// `tangle::src_block_hash`
+    pub fn results_hash(&self) -> Option<&str> {
+        let results = self.affiliated.as_ref()?.results.as_ref()?;
+        results.secondary.as_ref().map(|hash| hash.as_ref())
+    }
+
+    /// Structural equality: both nodes have the same type, properties,
+    /// post blank and children, recursively, while their positions in
+    /// `src_a` and `src_b`, the texts they were respectively parsed from,
+    /// may differ. Text of childless nodes is compared word by word,
+    /// so the indentation is not taken into account.
+    /// Affiliated keywords are not compared.
+    pub fn struct_eq(&self, other: &SyntaxNode, src_a: &str, src_b: &str) -> bool {
+        let ty = SyntaxT::from(&self.data);
+        if ty != SyntaxT::from(&other.data)
+            || self.post_blank != other.post_blank
+            || !data_eq(&self.data, &other.data, src_a, src_b)
+        {
+            return false;
+        }
+        let children = self.children.borrow();
+        let other_children = other.children.borrow();
+        if children.is_empty() && other_children.is_empty() {
+            let words = |node: &SyntaxNode, src: &'_ str| {
+                src[node.location.start..node.location.end]
+                    .split_whitespace()
+                    .map(|w| w.to_string())
+                    .collect::<Vec<_>>()
+            };
+            return words(self, src_a) == words(other, src_b);
+        }
+        nodes_eq(&children, &other_children, src_a, src_b)
+    }
+}
+
+/// Compares node lists, see `SyntaxNode::struct_eq`.
+fn nodes_eq(a: &[Handle], b: &[Handle], src_a: &str, src_b: &str) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b.iter())
+            .all(|(a, b)| a.struct_eq(b, src_a, src_b))
+}
+
+/// Compares node properties, see `SyntaxNode::struct_eq`.
+/// Properties are compared through their debug representation,
+/// the ones holding nodes, i.e. headline titles, keyword values and
+/// item tags, recursively, plain text word by word, and list structures
+/// regardless of their position.
+fn data_eq(a: &Syntax, b: &Syntax, src_a: &str, src_b: &str) -> bool {
+    match (a, b) {
+        (Syntax::Headline(a), Syntax::Headline(b)) => {
+            let props = |h: &HeadlineData| {
+                format!(
+                    "{:?}",
+                    (
+                        (h.archivedp, &h.closed, h.commentedp, &h.deadline),
+                        (h.footnote_section_p, h.level, h.pre_blank, h.priority),
+                        (&h.properties, h.quotedp, h.raw_value, &h.scheduled),
+                        (&h.tags, h.todo_keyword, h.todo_type),
+                    )
+                )
+            };
+            props(a) == props(b) && nodes_eq(&a.title, &b.title, src_a, src_b)
+        }
+        (Syntax::Keyword(a), Syntax::Keyword(b)) => {
+            (a.key(), a.value()) == (b.key(), b.value())
+                && match (&a.parsed_value, &b.parsed_value) {
+                    (Some(a), Some(b)) => nodes_eq(a, b, src_a, src_b),
+                    (a, b) => a.is_none() && b.is_none(),
+                }
+        }
+        (Syntax::PlainList(a), Syntax::PlainList(b)) => {
+            (a.type_s, a.tight) == (b.type_s, b.tight) && list_struct_eq(&a.structure, &b.structure)
+        }
+        (Syntax::Item(a), Syntax::Item(b)) => {
+            (a.bullet, a.checkbox, a.counter) == (b.bullet, b.checkbox, b.counter)
+                && (a.pre_blank, a.raw_tag) == (b.pre_blank, b.raw_tag)
+                && match (&a.tag, &b.tag) {
+                    (Some(a), Some(b)) => nodes_eq(a, b, src_a, src_b),
+                    (a, b) => a.is_none() && b.is_none(),
+                }
+                && list_struct_eq(&a.structure, &b.structure)
+        }
+        (Syntax::PlainText(a), Syntax::PlainText(b)) => {
+            a.split_whitespace().eq(b.split_whitespace())
+        }
+        (a, b) => format!("{:?}", a) == format!("{:?}", b),
+    }
+}
+
+/// Compares list structures, see `SyntaxNode::struct_eq`.
+/// Positions are taken relatively to the beginning of the list.
+fn list_struct_eq(a: &ListStruct, b: &ListStruct) -> bool {
+    let base = |s: &ListStruct| s.items.first().map_or(0, |item| item.begin);
+    let (base_a, base_b) = (base(a), base(b));
+    a.items.len() == b.items.len()
+        && a.items.iter().zip(b.items.iter()).all(|(a, b)| {
+            (a.indent, a.bullet, a.counter, a.checkbox, a.tag)
+                == (b.indent, b.bullet, b.counter, b.checkbox, b.tag)
+                && (a.begin - base_a, a.end - base_a) == (b.begin - base_b, b.end - base_b)
+                && a.parent.map(|p| p - base_a) == b.parent.map(|p| p - base_b)
+        })
 }
 
 /// Complete list of syntax entities
@@ -117,7 +285,7 @@ pub enum Syntax<'a> {
     Clock(Box<ClockData<'a>>),
 
     /// Element
-    Comment(Box<CommentData<'a>>),
+    Comment(Box<CommentData>),
 
     /// Element
     CommentBlock(Box<CommentBlockData<'a>>),
@@ -184,7 +352,7 @@ pub enum Syntax<'a> {
     Paragraph,
 
     /// Greater element
-    PlainList(Box<PlainListData>),
+    PlainList(Box<PlainListData<'a>>),
 
     /// Element
     Planning(Box<PlanningData<'a>>),
@@ -214,7 +382,10 @@ pub enum Syntax<'a> {
     VerseBlock,
 
     /// Recursive object
-    Bold,
+    Bold(Box<EmphasisData>),
+
+    /// Object
+    Citation(Box<CitationData<'a>>),
 
     /// Object.
     Code(Box<CodeData<'a>>),
@@ -235,7 +406,7 @@ pub enum Syntax<'a> {
     InlineSrcBlock(Box<InlineSrcBlockData<'a>>),
 
     /// Recursive object.
-    Italic,
+    Italic(Box<EmphasisData>),
 
     LineBreak,
 
@@ -255,7 +426,7 @@ pub enum Syntax<'a> {
     StatisticsCookie(Box<StatisticsCookieData<'a>>),
 
     /// Recursive object.
-    StrikeThrough,
+    StrikeThrough(Box<EmphasisData>),
 
     /// Recursive object.
     Subscript(Box<SubscriptData>),
@@ -273,7 +444,7 @@ pub enum Syntax<'a> {
     Timestamp(Box<TimestampData<'a>>),
 
     /// Recursive object.
-    Underline,
+    Underline(Box<EmphasisData>),
 
     /// Object
     Verbatim(Box<VerbatimData<'a>>),
@@ -349,6 +520,7 @@ impl SyntaxT {
         use SyntaxT::*;
         match self {
             Bold              => true,  // Recursive object
+            Citation          => true,  // Object
             Code              => true,  // Object.
             Entity            => true,  // Object
             ExportSnippet     => true,  // Object
@@ -393,8 +565,11 @@ impl SyntaxT {
         }
     }
 
+    /// Elements and objects which contents are parsed into objects.
+    /// Contents of any other type, e.g. verbatim, code or source blocks,
+    /// are a verbatim context: they are kept as literal text.
     #[rustfmt::skip]
-    fn is_object_container(self) -> bool {
+    pub fn is_object_container(self) -> bool {
         use SyntaxT::*;
         match self {
             Paragraph         => true,  // Element containing objects.
@@ -505,7 +680,7 @@ impl SyntaxT {
             //             latex-fragment link macro radio-target strike-through
             //             subscript superscript target timestamp underline verbatim)
             TableCell => match that {
-                Bold | Code | Entity | ExportSnippet | FootnoteReference | Italic
+                Bold | Citation | Code | Entity | ExportSnippet | FootnoteReference | Italic
                 | LatexFragment | Link | Macro | RadioTarget | StrikeThrough | Subscript
                 | Superscript | Target | Timestamp | Underline | Verbatim => true,
                 _ => false,
@@ -525,26 +700,32 @@ impl SyntaxT {
 /// Some elements can contain objects directly in their value fields
 pub enum StringOrObject<'a> {
     Raw(Cow<'a, str>),
-    Parsed(SyntaxNode<'a>),
+    /// Secondary string, i.e. a list of objects
+    Parsed(Vec<Handle<'a>>),
 }
 
 impl<'a> Debug for StringOrObject<'a> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             StringOrObject::Raw(raw) => write!(f, "Raw: {:?}", raw),
-            StringOrObject::Parsed(p) => unimplemented!(),
+            StringOrObject::Parsed(p) => write!(f, "Parsed: {:?}", p),
         }
     }
 }
 
+/// Parsed values are equal when they hold objects of the same types
+/// at the same locations.
 impl<'a> PartialEq for StringOrObject<'a> {
     fn eq(&self, other: &StringOrObject) -> bool {
-        match self {
-            StringOrObject::Raw(raw) => match other {
-                StringOrObject::Parsed(..) => false,
-                StringOrObject::Raw(rhs) => raw.eq(rhs),
-            },
-            StringOrObject::Parsed(p) => unimplemented!(),
+        match (self, other) {
+            (StringOrObject::Raw(raw), StringOrObject::Raw(rhs)) => raw.eq(rhs),
+            (StringOrObject::Parsed(p), StringOrObject::Parsed(rhs)) => {
+                p.len() == rhs.len()
+                    && p.iter().zip(rhs).all(|(a, b)| {
+                        SyntaxT::from(&a.data) == SyntaxT::from(&b.data) && a.location == b.location
+                    })
+            }
+            _ => false,
         }
     }
 }
@@ -552,16 +733,16 @@ impl<'a> PartialEq for StringOrObject<'a> {
 #[derive(Debug)]
 pub struct ClockData<'a> {
     /// Clock duration for a closed clock, or nil (string or nil).
-    duration: &'a str,
+    pub duration: Option<&'a str>,
 
     /// Status of current clock (symbol closed or running).
-    status: ClockStatus,
+    pub status: ClockStatus,
 
     /// Timestamp associated to clock keyword (timestamp object).
-    value: TimestampData<'a>,
+    pub value: Option<TimestampData<'a>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ClockStatus {
     Running,
     Closed,
@@ -570,28 +751,56 @@ pub enum ClockStatus {
 #[derive(Debug)]
 pub struct DiarySexpData<'a> {
     /// Full Sexp (string).
-    value: &'a str,
+    pub value: &'a str,
 }
 
-#[derive(Debug)]
+/// Line numbering requested by `-n` and `+n` switches of a block,
+/// e.g. `-n 10`. Variants hold the offset added to line numbers,
+/// that is one less than the number given to the switch, or 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LineNumberingMode {
-    New,
-    Continued,
+    /// `-n`: numbering starts anew
+    New(usize),
+    /// `+n`: numbering resumes from previous numbered block
+    Continued(usize),
 }
 
-#[derive(Debug)]
+impl LineNumberingMode {
+    /// Offset added to line numbers
+    pub fn offset(self) -> usize {
+        match self {
+            LineNumberingMode::New(offset) | LineNumberingMode::Continued(offset) => offset,
+        }
+    }
+
+    /// Whether numbering resumes from previous numbered block
+    pub fn is_continued(self) -> bool {
+        match self {
+            LineNumberingMode::New(_) => false,
+            LineNumberingMode::Continued(_) => true,
+        }
+    }
+
+    /// Number of the first line of a block numbered anew,
+    /// or the number added to the previous block's last one.
+    pub fn start(self) -> usize {
+        self.offset() + 1
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct PlanningData<'a> {
     /// Timestamp associated to closed keyword, if any
     /// (timestamp object or nil).
-    closed: Option<TimestampData<'a>>,
+    pub closed: Option<TimestampData<'a>>,
 
     /// Timestamp associated to deadline keyword, if any
     /// (timestamp object or nil).
-    deadline: Option<TimestampData<'a>>,
+    pub deadline: Option<TimestampData<'a>>,
 
     /// Timestamp associated to scheduled keyword, if any
     /// (timestamp object or nil).
-    scheduled: Option<TimestampData<'a>>,
+    pub scheduled: Option<TimestampData<'a>>,
 }
 
 // ===== Objects Data ======
@@ -599,36 +808,51 @@ pub struct PlanningData<'a> {
 #[derive(Debug)]
 pub struct CodeData<'a> {
     /// Contents (string).
-    value: &'a str,
+    pub value: &'a str,
+    /// Markers and their context.
+    pub emphasis: EmphasisData,
+}
+
+/// Marker of an emphasis object along with the characters around it
+/// that made it valid, see `emphasis.rs`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmphasisData {
+    /// Marker character, one of `*`, `/`, `_`, `+`, `=` and `~`.
+    pub marker: char,
+    /// Character before the opening marker, None at the beginning
+    /// of the text.
+    pub pre: Option<char>,
+    /// Character after the closing marker, None at the end of the text.
+    pub post: Option<char>,
 }
 
 #[derive(Debug)]
 pub struct EntityData<'a> {
     /// Entity's ASCII representation (string).
-    ascii: &'a str,
+    pub ascii: &'a str,
 
     /// Entity's HTML representation (string).
-    html: &'a str,
+    pub html: &'a str,
 
     /// Entity's LaTeX representation (string).
-    latex: &'a str,
+    pub latex: &'a str,
 
     /// Non-nil if entity's LaTeX representation should be
     /// in math mode (boolean).
-    latex_math_p: bool,
+    pub latex_math_p: bool,
 
     /// Entity's Latin-1 encoding representation (string).
-    latin1: &'a str,
+    pub latin1: &'a str,
 
     /// Entity's name, without backslash nor brackets (string).
-    name: &'a str,
+    pub name: &'a str,
 
     /// Non-nil if entity is written with optional
     /// brackets in original buffer (boolean).
-    use_brackets_p: bool,
+    pub use_brackets_p: bool,
 
     /// Entity's UTF-8 encoding representation (string).
-    utf_8: &'a str,
+    pub utf_8: &'a str,
 }
 
 #[derive(Debug)]
@@ -644,11 +868,17 @@ pub struct ExportSnippetData<'a> {
 #[derive(Debug)]
 pub struct FootnoteReferenceData<'a> {
     /// Footnote's label, if any (string or nil).
-    label: Option<&'a str>,
+    pub label: Option<&'a str>,
 
     /// Determine whether reference has its
     /// definition inline, or not (symbol inline, standard).
-    type_s: &'a str,
+    pub type_s: FootnoteReferenceType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FootnoteReferenceType {
+    Inline,
+    Standard,
 }
 
 #[derive(Debug)]
@@ -672,16 +902,16 @@ pub struct InlineBabelCallData<'a> {
 #[derive(Debug)]
 pub struct InlineSrcBlockData<'a> {
     ///Language of the code in the block (string).
-    language: &'a str,
+    pub language: &'a str,
 
     ///Optional header arguments (string or nil).
-    parameters: Option<&'a str>,
+    pub parameters: Option<&'a str>,
 
     ///Source code (string).
-    value: &'a str,
+    pub value: &'a str,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LinkFormat {
     Plain,
     Angle,
@@ -693,29 +923,29 @@ pub struct LinkData<'a> {
     /// Name of application requested to open the link
     /// in Emacs (string or nil).
     /// It only applies to "file" type links.
-    application: Option<&'a str>,
+    pub application: Option<&'a str>,
 
     /// Format for link syntax (symbol plain, angle, bracket).
-    format: LinkFormat,
+    pub format: LinkFormat,
 
     /// Identifier for link's destination.
     /// It is usually the link part with type,
     /// if specified, removed (string).
-    path: &'a str,
+    pub path: &'a str,
 
     ///Uninterpreted link part (string).
-    raw_link: &'a str,
+    pub raw_link: &'a str,
 
     /// Additional information for file location (string or nil).
     /// It only applies to "file" type links.
-    search_option: Option<&'a str>,
+    pub search_option: Option<&'a str>,
 
     /// Link type
-    link_type: LinkType,
+    pub link_type: LinkType<'a>,
 }
 
-#[derive(Debug)]
-pub enum LinkType {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkType<'a> {
     /// Line in some source code,
     Coderef,
 
@@ -733,6 +963,9 @@ pub enum LinkType {
 
     /// Radio-target.
     Radio,
+
+    /// Any other type from `ORG_LINK_TYPES`, e.g. "https".
+    Other(&'a str),
 }
 
 #[derive(Debug)]
@@ -756,20 +989,40 @@ pub struct RadioTargetData<'a> {
 #[derive(Debug)]
 pub struct StatisticsCookieData<'a> {
     /// Full cookie (string).
-    value: &'a str,
+    pub value: &'a str,
+}
+
+impl<'a> StatisticsCookieData<'a> {
+    /// Returns numerator and denominator of a `[done/total]` cookie.
+    /// Missing numbers, as in `[/]`, are 0.
+    pub fn fraction(&self) -> Option<(usize, usize)> {
+        let inner = &self.value[1..self.value.len() - 1];
+        let slash = inner.find('/')?;
+        let number = |s: &str| s.parse().unwrap_or(0);
+        Some((number(&inner[..slash]), number(&inner[slash + 1..])))
+    }
+
+    /// Returns the percentage of a `[percent%]` cookie.
+    /// A missing number, as in `[%]`, is 0.
+    pub fn percent(&self) -> Option<usize> {
+        let inner = &self.value[1..self.value.len() - 1];
+        inner
+            .strip_suffix('%')
+            .map(|number| number.parse().unwrap_or(0))
+    }
 }
 
 #[derive(Debug)]
 pub struct SubscriptData {
     /// Non_nil if contents are enclosed in curly brackets (t, nil).
-    use_brackets_p: bool,
+    pub use_brackets_p: bool,
 }
 
 /// Recursive object.
 #[derive(Debug)]
 pub struct SuperscriptData {
     /// Non_nil if contents are enclosed in curly brackets (t, nil).
-    use_brackets_p: bool,
+    pub use_brackets_p: bool,
 }
 
 #[derive(Debug)]
@@ -778,82 +1031,86 @@ pub struct TargetData<'a> {
     value: &'a str,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TimestampData<'a> {
     /// Day part from timestamp end.
     /// If no ending date is defined, it defaults to start day part (integer).
-    day_end: usize,
+    pub day_end: usize,
 
     /// Day part from timestamp start (integer).
-    day_start: usize,
+    pub day_start: usize,
 
     /// Hour part from timestamp end.
     /// If no ending date is defined, it defaults to start hour part,
     /// if any (integer or nil).
-    hour_end: Option<usize>,
+    pub hour_end: Option<usize>,
 
     /// Hour part from timestamp start, if specified (integer or nil).
-    hour_start: Option<usize>,
+    pub hour_start: Option<usize>,
 
     /// Minute part from timestamp end.
     /// If no ending date is defined, it defaults to start minute part,
     /// if any (integer or nil).
-    minute_end: Option<usize>,
+    pub minute_end: Option<usize>,
 
     /// Minute part from timestamp start, if specified (integer or nil).
-    minute_start: Option<usize>,
+    pub minute_start: Option<usize>,
 
     /// Month part from timestamp end.
     /// If no ending date is defined, it defaults to start month part
     /// (integer).
-    month_end: usize,
+    pub month_end: usize,
 
     /// Month part from timestamp start (integer).
-    month_start: usize,
+    pub month_start: usize,
 
     /// Raw timestamp (string).
-    raw_value: &'a str,
+    pub raw_value: &'a str,
+
+    /// Sexp of a diary timestamp, including parentheses (string or nil).
+    /// Date parts of a diary timestamp are all set to 0.
+    pub diary_sexp: Option<&'a str>,
 
     // TODO maybe the following three fields can be combined into one
     /// Type of repeater, if any (symbol catch_up, restart, cumulate or nil)
-    repeater_type: Option<RepeaterType>,
+    pub repeater_type: Option<RepeaterType>,
 
     /// Unit of shift, if a repeater is defined
     /// (symbol year, month, week, day, hour or nil).
-    repeater_unit: Option<TimeUnit>,
+    pub repeater_unit: Option<TimeUnit>,
 
     /// Value of shift, if a repeater is defined (integer or nil).
-    repeater_value: Option<usize>,
+    pub repeater_value: Option<usize>,
 
     /// Type of timestamp:
     /// (symbol active, active_range, diary, inactive, inactive_range).
-    type_s: TimestampType,
+    pub type_s: TimestampType,
 
     /// Type of warning, if any (symbol all, first or nil)
-    warning_type: Option<WarningType>,
+    pub warning_type: Option<WarningType>,
 
     /// Unit of delay, if one is defined
     /// (symbol year, month, week, day, hour or nil).
-    warning_unit: Option<TimeUnit>,
+    pub warning_unit: Option<TimeUnit>,
 
     /// Value of delay, if one is defined (integer or nil).
-    warning_value: Option<usize>,
+    pub warning_value: Option<usize>,
 
     /// Year part from timestamp end.
     /// If no ending date is defined, it defaults to start year part (integer)
-    year_end: usize,
+    pub year_end: usize,
 
     /// Year part from timestamp start (integer).
-    year_start: usize,
+    pub year_start: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum WarningType {
     All,
     First,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TimestampType {
     Active,
     ActiveRange,
@@ -862,14 +1119,14 @@ pub enum TimestampType {
     InactiveRange,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RepeaterType {
     CatchUp,
     Restart,
     Cumulate,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TimeUnit {
     Year,
     Month,
@@ -881,12 +1138,15 @@ pub enum TimeUnit {
 #[derive(Debug)]
 pub struct VerbatimData<'a> {
     ///Contents (string).
-    value: &'a str,
+    pub value: &'a str,
+    /// Markers and their context.
+    pub emphasis: EmphasisData,
 }
 
 mod test {
 
     use crate::data::SyntaxT;
+    use crate::parser::{ParseGranularity, Parser};
 
     #[test]
     fn can_contain() {
@@ -905,4 +1165,27 @@ mod test {
         assert!(closure_test(br, |that| bold.can_contain(that)));
         assert!(!closure_test(verse, |that| bold.can_contain(that)));
     }
+
+    #[test]
+    fn struct_eq() {
+        let a = "Intro\n* TODO Title *b*\nSome /text/\n\n#+TITLE: x\n";
+        let b = "   Intro\n* TODO Title *b*\n  Some /text/\n\n  #+TITLE: x\n";
+        let parse = |src| Parser::new(src, ParseGranularity::Object).parse_buffer();
+        let tree_a = parse(a);
+        assert!(tree_a.struct_eq(&parse(b), a, b));
+
+        let c = "Intro\n* TODO Title *c*\nSome /text/\n\n#+TITLE: x\n";
+        assert!(!tree_a.struct_eq(&parse(c), a, c));
+        let d = "Intro\n* TODO Title *b*\nSome /text/\n#+TITLE: x\n";
+        assert!(!tree_a.struct_eq(&parse(d), a, d));
+
+        // Lists are compared regardless of their position
+        let a = "Intro\n- a\n  - [X] b\n- c :: d\n";
+        let b = "   Intro\n- a\n  - [X] b\n- c :: d\n";
+        assert!(parse(a).struct_eq(&parse(b), a, b));
+        let c = "Intro\n- a\n  - [ ] b\n- c :: d\n";
+        assert!(!parse(a).struct_eq(&parse(c), a, c));
+        let d = "Intro\n- a\n- [X] b\n- c :: d\n";
+        assert!(!parse(a).struct_eq(&parse(d), a, d));
+    }
 }
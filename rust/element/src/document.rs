@@ -0,0 +1,105 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Owned Org documents
+
+use crate::data::SyntaxNode;
+use crate::parser::{Diagnostic, ParseGranularity, Parser};
+
+#[cfg(feature = "std")]
+use std::{fs, io, path::Path};
+
+/// Text of an Org document, owned.
+/// Syntax trees borrow from the document, so they are built on demand
+/// with `parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrgDocument {
+    text: String,
+}
+
+impl OrgDocument {
+    pub fn new(text: String) -> OrgDocument {
+        OrgDocument { text }
+    }
+
+    /// Reads the file at `path`. Line endings are normalized to `\n`,
+    /// use `from_path_with` to keep them as they are.
+    ///
+    /// Fails only when the file can not be read or is not valid UTF-8,
+    /// problems found in the contents are reported by `parse`.
+    #[cfg(feature = "std")]
+    pub fn from_path(path: &Path) -> io::Result<OrgDocument> {
+        OrgDocument::from_path_with(path, true)
+    }
+
+    /// Reads the file at `path`, replacing `\r\n` and lone `\r` line
+    /// endings with `\n` when `normalize_line_endings` is set.
+    /// The parser only recognizes `\n` as a line ending.
+    #[cfg(feature = "std")]
+    pub fn from_path_with(path: &Path, normalize_line_endings: bool) -> io::Result<OrgDocument> {
+        let text = fs::read_to_string(path)?;
+        if normalize_line_endings && text.contains('\r') {
+            Ok(OrgDocument::new(
+                text.replace("\r\n", "\n").replace('\r', "\n"),
+            ))
+        } else {
+            Ok(OrgDocument::new(text))
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Parses the whole document, returns the root of the syntax tree
+    /// along with diagnostics collected while parsing.
+    pub fn parse(&self, granularity: ParseGranularity) -> (SyntaxNode<'_>, Vec<Diagnostic>) {
+        let parser = Parser::new(&self.text, granularity);
+        let root = parser.parse_buffer();
+        (root, parser.diagnostics.into_inner())
+    }
+}
+
+mod test {
+    use super::OrgDocument;
+    use crate::data::Syntax;
+    use crate::parser::ParseGranularity;
+    use std::fs;
+    use std::io::ErrorKind;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn from_path() {
+        let path = std::env::temp_dir().join(format!("org-rs-{}.org", std::process::id()));
+        fs::write(&path, "* First\r\nBody\r\n* Second\r\n").unwrap();
+
+        let document = OrgDocument::from_path(&path).unwrap();
+        assert_eq!("* First\nBody\n* Second\n", document.text());
+        let (root, diagnostics) = document.parse(ParseGranularity::Element);
+        assert!(diagnostics.is_empty());
+        let headline = &root.children.borrow()[0];
+        match &headline.data {
+            Syntax::Headline(h) => assert_eq!("First", h.raw_value),
+            _ => panic!("expected headline"),
+        }
+
+        let raw = OrgDocument::from_path_with(&path, false).unwrap();
+        assert_eq!("* First\r\nBody\r\n* Second\r\n", raw.text());
+
+        fs::remove_file(&path).unwrap();
+        let error = OrgDocument::from_path(&path).unwrap_err();
+        assert_eq!(ErrorKind::NotFound, error.kind());
+    }
+}
@@ -14,18 +14,9 @@
 //    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::affiliated::AffiliatedData;
-use crate::data::SyntaxNode;
+use crate::data::{Interval, Syntax, SyntaxNode};
 use crate::parser::Parser;
-use regex::Regex;
-
-lazy_static! {
-
-
-    /// Matches first or last line of a drawer
-    /// Group 1 contains drawer's name or \"END\"
-    pub static ref REGEX_DRAWER: Regex = Regex::new(r"^[ \t]*:((?:\w|[-_])+):[ \t]*$").unwrap();
-
-}
+use crate::regexps::{REGEX_DRAWER, REGEX_DRAWER_END};
 
 #[derive(Debug)]
 pub struct DrawerData<'a> {
@@ -34,13 +25,178 @@ pub struct DrawerData<'a> {
 }
 
 impl<'a> Parser<'a> {
-    // TODO implement drawer_parser
+    /// Parse a drawer.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `drawer' and CDR is a plist containing
+    /// `:drawer-name', `:begin', `:end', `:contents-begin',
+    /// `:contents-end', `:post-blank' and `:post-affiliated' keywords.
+    ///
+    /// Assume point is at beginning of drawer."
+    /// (defun org-element-drawer-parser (limit affiliated)
+    ///
+    /// A drawer without its `:END:` line is parsed as a paragraph.
+    /// Like contents of any greater element, contents of the drawer
+    /// are parsed into elements, lists included, see `parse_contents`.
     pub fn drawer_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let post_affiliated = c.pos();
+        let drawer_end_line = match c.re_search_forward(&*REGEX_DRAWER_END, Some(limit)) {
+            Some(_) => c.goto_line_begin(),
+            None => {
+                // Incomplete drawer: parse it as a paragraph.
+                c.set(post_affiliated);
+                drop(c);
+                return self.paragraph_parser(limit, start, affiliated);
+            }
+        };
+        c.set(post_affiliated);
+        let drawer_name = c
+            .capturing_at(&*REGEX_DRAWER)
+            .map_or("", |cap| cap.get(1).unwrap().as_str());
+
+        // Empty drawers have no contents.
+        let contents_begin = c.goto_next_line();
+        let content_location = if contents_begin < drawer_end_line {
+            Some(Interval {
+                start: contents_begin,
+                end: drawer_end_line,
+            })
+        } else {
+            None
+        };
+        c.set(drawer_end_line);
+        c.goto_next_line();
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::Drawer(Box::new(DrawerData { drawer_name })),
+            Interval { start, end },
+            content_location,
+            post_blank,
+            affiliated,
+        )
+    }
+}
+
+mod test {
+    use crate::data::{Interval, Syntax, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+
+    #[test]
+    fn drawer_parser() {
+        let text = ":LOGBOOK:\nNote\n:END:\n\nText";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let drawer = parser.drawer_parser(text.len(), 0, None);
+        assert_eq!(Interval { start: 0, end: 22 }, drawer.location);
+        assert_eq!(
+            Some(Interval { start: 10, end: 15 }),
+            drawer.content_location
+        );
+        assert_eq!(1, drawer.post_blank);
+        match &drawer.data {
+            Syntax::Drawer(d) => assert_eq!("LOGBOOK", d.drawer_name),
+            _ => panic!("expected drawer"),
+        }
+
+        // Terminator is case-insensitive and tolerates whitespace
+        for end in &[":end:", "  :End:\t ", ":END:  "] {
+            let text = format!(":notes:\nNote\n{}\nText", end);
+            let parser = Parser::new(&text, ParseGranularity::Element);
+            let drawer = parser.drawer_parser(text.len(), 0, None);
+            assert_eq!(SyntaxT::Drawer, SyntaxT::from(&drawer.data), "{}", end);
+            assert_eq!(text.len() - 4, drawer.location.end);
+        }
+
+        // Empty drawers have no contents
+        let text = ":EMPTY:\n:END:";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let drawer = parser.drawer_parser(text.len(), 0, None);
+        assert_eq!(text.len(), drawer.location.end);
+        assert_eq!(None, drawer.content_location);
+    }
+
+    #[test]
+    fn drawer_contents() {
+        let text = "* Headline\n:NOTES:\nSome text.\n\n- first\n- second\n:END:\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let headline = &root.children.borrow()[0];
+        let section = &headline.children.borrow()[0];
+        let drawer = &section.children.borrow()[0];
+        assert_eq!(SyntaxT::Drawer, SyntaxT::from(&drawer.data));
+
+        let children = drawer.children.borrow();
+        let types: Vec<_> = children.iter().map(|c| SyntaxT::from(&c.data)).collect();
+        assert_eq!(vec![SyntaxT::Paragraph, SyntaxT::PlainList], types);
+        assert_eq!(Interval { start: 19, end: 31 }, children[0].location);
+        let items = children[1].children.borrow();
+        let types: Vec<_> = items.iter().map(|c| SyntaxT::from(&c.data)).collect();
+        assert_eq!(vec![SyntaxT::Item, SyntaxT::Item], types);
+        assert_eq!(Interval { start: 39, end: 48 }, items[1].location);
+
+        // Property drawers only hold node properties
+        let text = "* Headline\n:PROPERTIES:\n:ID: 1\n:END:\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let headline = &root.children.borrow()[0];
+        let section = &headline.children.borrow()[0];
+        let drawer = &section.children.borrow()[0];
+        assert_eq!(SyntaxT::PropertyDrawer, SyntaxT::from(&drawer.data));
+        let types: Vec<_> = drawer
+            .children
+            .borrow()
+            .iter()
+            .map(|c| SyntaxT::from(&c.data))
+            .collect();
+        assert_eq!(vec![SyntaxT::NodeProperty], types);
+    }
+
+    #[test]
+    fn drawer_with_blank_lines() {
+        let text = ":LOGBOOK:\n\nNote\n\n\n:END:\nText";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let drawer = parser.drawer_parser(text.len(), 0, None);
+        assert_eq!(SyntaxT::Drawer, SyntaxT::from(&drawer.data));
+        assert_eq!(Interval { start: 0, end: 24 }, drawer.location);
+        assert_eq!(
+            Some(Interval { start: 10, end: 18 }),
+            drawer.content_location
+        );
+        assert_eq!(0, drawer.post_blank);
+    }
+
+    #[test]
+    fn unterminated_drawer() {
+        // No `:END:` before the end of the buffer
+        let text = ":LOGBOOK:\nNote\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.drawer_parser(text.len(), 0, None);
+        assert_eq!(SyntaxT::Paragraph, SyntaxT::from(&node.data));
+
+        // `:END:` of the next section doesn't close the drawer
+        let text = "* A\n:LOGBOOK:\nNote\n* B\n:END:\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let headline = &root.children.borrow()[0];
+        let section = &headline.children.borrow()[0];
+        let types: Vec<_> = section
+            .children
+            .borrow()
+            .iter()
+            .map(|c| SyntaxT::from(&c.data))
+            .collect();
+        assert_eq!(vec![SyntaxT::Paragraph], types);
+        assert_eq!(Interval { start: 4, end: 19 }, section.location);
     }
 }
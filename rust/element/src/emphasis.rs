@@ -0,0 +1,341 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Emphasis Markers
+//! https://orgmode.org/worg/dev/org-syntax.html#Emphasis_Markers
+//!
+//! Text markup follows the pattern:
+//!
+//! PRE MARKER CONTENTS MARKER POST
+//!
+//! PRE is a whitespace character, (, {, ', ", - or the beginning of a line.
+//!
+//! MARKER is a character among *, =, /, +, _ and ~.
+//!
+//! CONTENTS is a string following the pattern BORDER BODY BORDER.
+//! BORDER can be any non-whitespace character and BODY can contain
+//! any character, but at most one new line. CONTENTS can also be
+//! a single BORDER character.
+//!
+//! POST is a whitespace character, -, ., ,, :, !, ?, ;, ', ", ), }, [ or the end of a line.
+//!
+//! Markers *, /, _ and + produce bold, italic, underline and strike-through
+//! objects, which can contain other objects. Markers = and ~ produce
+//! verbatim and code objects, contents of which are never parsed.
+//!
+//! Org has no escape character in plain text, yet a backslash before
+//! a marker is not a valid PRE, so `\*not bold\*` is never emphasized.
+//! Such text ends up in a plain text object as written, backslashes
+//! included: interpreting is left to the exporters, e.g., as entities.
+
+use crate::data::{CodeData, EmphasisData, Interval, Syntax, SyntaxNode, VerbatimData};
+use crate::parser::Parser;
+
+fn is_pre(c: char) -> bool {
+    c.is_whitespace() || "-('\"{".contains(c)
+}
+
+fn is_post(c: char) -> bool {
+    c.is_whitespace() || "-.,:!?;'\")}[".contains(c)
+}
+
+/// Looks for emphasis delimited by `marker` starting at `begin`.
+/// Returns the position of the closing marker,
+/// contents span between the two markers.
+///
+/// Matches the same text as `org-emph-re` and `org-verbatim-re` do
+/// (see `REGEX_EMPH_BOLD`). Search never goes past the end of the line
+/// following the opening marker, so each attempt is bounded by the
+/// length of two lines.
+pub fn match_emphasis(text: &str, begin: usize, marker: char) -> Option<usize> {
+    scan_emphasis(text, begin, marker).ok()
+}
+
+/// Same as `match_emphasis`, but when `begin` holds a valid opening
+/// marker that is never closed, returns the end of the line holding it.
+/// Any other opening marker of the same kind found later on that line
+/// cannot be closed either, since its search would cover a part of
+/// the same text.
+fn scan_emphasis(text: &str, begin: usize, marker: char) -> Result<usize, Option<usize>> {
+    if !text[begin..].starts_with(marker) {
+        return Err(None);
+    }
+    if let Some(prev) = text[..begin].chars().next_back() {
+        if !is_pre(prev) {
+            return Err(None);
+        }
+    }
+
+    let contents_begin = begin + marker.len_utf8();
+    let mut chars = text[contents_begin..].char_indices();
+    let first = match chars.next() {
+        Some((_, first)) if !first.is_whitespace() => first,
+        _ => return Err(None),
+    };
+
+    let mut last = first;
+    let mut line_end = None;
+    for (offset, c) in chars {
+        let closing = contents_begin + offset;
+        if c == marker && !last.is_whitespace() {
+            let after = text[closing + marker.len_utf8()..].chars().next();
+            if after.map_or(true, is_post) {
+                return Ok(closing);
+            }
+        }
+        if c == '\n' {
+            if line_end.is_some() {
+                return Err(line_end);
+            }
+            line_end = Some(closing);
+        }
+        last = c;
+    }
+    Err(line_end.or_else(|| Some(text.len())))
+}
+
+/// Opening marker known to be unclosed, see `scan_emphasis`.
+/// Parser keeps the last one found for each kind of marker.
+#[derive(Debug, Clone, Copy)]
+pub struct UnclosedEmphasis {
+    marker: char,
+    begin: usize,
+    line_end: usize,
+    /// End of the text searched, which is narrowed while parsing objects
+    text_end: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Common part of the emphasis parsers.
+    /// Returns object's location, contents location, post blank
+    /// and the markers along with their context.
+    fn emphasis_at(&self, marker: char) -> Option<(Interval, Interval, usize, EmphasisData)> {
+        let mut c = self.cursor.borrow_mut();
+        let begin = c.pos();
+        let text_end = c.data().len();
+
+        // Without this, a long line full of opening markers would
+        // be scanned to its end once per marker.
+        let mut unclosed = self.unclosed_emphasis.borrow_mut();
+        let known = unclosed.iter().position(|u| u.marker == marker);
+        if let Some(u) = known.map(|i| unclosed[i]) {
+            if u.text_end == text_end && u.begin < begin && begin < u.line_end {
+                return None;
+            }
+        }
+        let contents_end = match scan_emphasis(c.data(), begin, marker) {
+            Ok(contents_end) => contents_end,
+            Err(Some(line_end)) => {
+                let u = UnclosedEmphasis {
+                    marker,
+                    begin,
+                    line_end,
+                    text_end,
+                };
+                match known {
+                    Some(i) => unclosed[i] = u,
+                    None => unclosed.push(u),
+                }
+                return None;
+            }
+            Err(None) => return None,
+        };
+        let emphasis = EmphasisData {
+            marker,
+            pre: c.data()[..begin].chars().next_back(),
+            post: c.data()[contents_end + marker.len_utf8()..].chars().next(),
+        };
+        c.set(contents_end + marker.len_utf8());
+        let post_blank = c.skip_chars_forward(" \t", None);
+        let end = c.pos();
+
+        let location = Interval { start: begin, end };
+        let contents = Interval {
+            start: begin + marker.len_utf8(),
+            end: contents_end,
+        };
+        Some((location, contents, post_blank, emphasis))
+    }
+
+    fn recursive_emphasis(
+        &self,
+        marker: char,
+        data: fn(Box<EmphasisData>) -> Syntax<'a>,
+    ) -> Option<SyntaxNode<'a>> {
+        let (location, contents, post_blank, emphasis) = self.emphasis_at(marker)?;
+        Some(SyntaxNode::new(
+            data(Box::new(emphasis)),
+            location,
+            Some(contents),
+            post_blank,
+            None,
+        ))
+    }
+
+    /// Parse bold object at point, if any.
+    ///
+    /// When at a bold object, return a list whose car is `bold' and cdr
+    /// is a plist with `:begin', `:end', `:contents-begin' and
+    /// `:contents-end' and `:post-blank' keywords.  Otherwise, return
+    /// nil.
+    ///
+    /// Assume point is at the first star marker."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L2723
+    /// (defun org-element-bold-parser ()
+    pub fn bold_parser(&self) -> Option<SyntaxNode<'a>> {
+        self.recursive_emphasis('*', Syntax::Bold)
+    }
+
+    /// Parse italic object at point, if any.
+    /// Assume point is at the first slash marker.
+    /// (defun org-element-italic-parser ()
+    pub fn italic_parser(&self) -> Option<SyntaxNode<'a>> {
+        self.recursive_emphasis('/', Syntax::Italic)
+    }
+
+    /// Parse underline object at point, if any.
+    /// Assume point is at the first underscore marker.
+    /// (defun org-element-underline-parser ()
+    pub fn underline_parser(&self) -> Option<SyntaxNode<'a>> {
+        self.recursive_emphasis('_', Syntax::Underline)
+    }
+
+    /// Parse strike-through object at point, if any.
+    /// Assume point is at the first plus sign marker.
+    /// (defun org-element-strike-through-parser ()
+    pub fn strike_through_parser(&self) -> Option<SyntaxNode<'a>> {
+        self.recursive_emphasis('+', Syntax::StrikeThrough)
+    }
+
+    /// Parse verbatim object at point, if any.
+    ///
+    /// When at a verbatim object, return a list whose car is `verbatim'
+    /// and cdr is a plist with `:value', `:begin', `:end' and
+    /// `:post-blank' keywords.  Otherwise, return nil.
+    ///
+    /// Assume point is at the first equal sign marker."
+    /// (defun org-element-verbatim-parser ()
+    pub fn verbatim_parser(&self) -> Option<SyntaxNode<'a>> {
+        let (location, contents, post_blank, emphasis) = self.emphasis_at('=')?;
+        let value = &self.input[contents.start..contents.end];
+        Some(SyntaxNode::new(
+            Syntax::Verbatim(Box::new(VerbatimData { value, emphasis })),
+            location,
+            None,
+            post_blank,
+            None,
+        ))
+    }
+
+    /// Parse code object at point, if any.
+    /// Assume point is at the first tilde marker.
+    /// (defun org-element-code-parser ()
+    pub fn code_parser(&self) -> Option<SyntaxNode<'a>> {
+        let (location, contents, post_blank, emphasis) = self.emphasis_at('~')?;
+        let value = &self.input[contents.start..contents.end];
+        Some(SyntaxNode::new(
+            Syntax::Code(Box::new(CodeData { value, emphasis })),
+            location,
+            None,
+            post_blank,
+            None,
+        ))
+    }
+}
+
+mod test {
+    use super::match_emphasis;
+    use crate::data::{EmphasisData, Interval, Syntax, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+
+    fn in_paragraph(that: SyntaxT) -> bool {
+        SyntaxT::Paragraph.can_contain(that)
+    }
+
+    #[test]
+    fn emphasis_boundaries() {
+        assert_eq!(Some(5), match_emphasis("*bold* text", 0, '*'));
+        assert_eq!(Some(3), match_emphasis("(/a/)", 1, '/'));
+        assert_eq!(Some(7), match_emphasis("_two\nup_", 0, '_'));
+        assert_eq!(None, match_emphasis("a*bold*", 1, '*'));
+        assert_eq!(None, match_emphasis("* bold*", 0, '*'));
+        assert_eq!(None, match_emphasis("*bold *", 0, '*'));
+        assert_eq!(None, match_emphasis("*bold*x", 0, '*'));
+        assert_eq!(None, match_emphasis("*a\nb\nc*", 0, '*'));
+        assert_eq!(None, match_emphasis("**", 0, '*'));
+    }
+
+    #[test]
+    fn escaped_markers() {
+        assert_eq!(None, match_emphasis("\\*not bold\\*", 1, '*'));
+        // An underscore after a backslash starts a subscript
+        // instead, see `script.rs`.
+        for text in &["\\*not bold\\*", "a \\+not struck+", "\\/a/ \\=b="] {
+            let objects = Parser::parse_secondary_string(text, in_paragraph);
+            assert_eq!(1, objects.len());
+            match &objects[0].data {
+                Syntax::PlainText(plain) => assert_eq!(text, plain),
+                _ => panic!("expected plain text"),
+            }
+        }
+    }
+
+    #[test]
+    fn bold_and_verbatim() {
+        let text = "*bold*  =x*y=";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let bold = parser.bold_parser().unwrap();
+        assert_eq!(Interval { start: 0, end: 8 }, bold.location);
+        assert_eq!(Some(Interval { start: 1, end: 5 }), bold.content_location);
+        assert_eq!(2, bold.post_blank);
+
+        parser.cursor.borrow_mut().set(8);
+        let verbatim = parser.verbatim_parser().unwrap();
+        match &verbatim.data {
+            Syntax::Verbatim(v) => assert_eq!("x*y", v.value),
+            _ => panic!("expected verbatim"),
+        }
+        assert_eq!(None, verbatim.content_location);
+        assert!(parser.code_parser().is_none());
+    }
+
+    #[test]
+    fn emphasis_context() {
+        let text = "(*bold*, =x=";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        parser.cursor.borrow_mut().set(1);
+        let bold = parser.bold_parser().unwrap();
+        let expected = EmphasisData {
+            marker: '*',
+            pre: Some('('),
+            post: Some(','),
+        };
+        match &bold.data {
+            Syntax::Bold(emphasis) => assert_eq!(expected, **emphasis),
+            _ => panic!("expected bold"),
+        }
+
+        parser.cursor.borrow_mut().set(9);
+        let expected = EmphasisData {
+            marker: '=',
+            pre: Some(' '),
+            post: None,
+        };
+        match &parser.verbatim_parser().unwrap().data {
+            Syntax::Verbatim(v) => assert_eq!(expected, v.emphasis),
+            _ => panic!("expected verbatim"),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Entities
+//! https://orgmode.org/worg/dev/org-syntax.html#Entities_and_LaTeX_Fragments
+//!
+//! \NAME POST or \NAME{}, where NAME is a known entity and POST is
+//! the end of line or a non-alphabetic character.
+
+use crate::data::{EntityData, Interval, Syntax, SyntaxNode};
+use crate::parser::Parser;
+use crate::regexps::REGEX_ENTITY;
+
+/// Entity definition, fields are the ones of `EntityData`
+#[derive(Debug)]
+pub struct Entity {
+    pub name: &'static str,
+    pub latex: &'static str,
+    pub latex_math_p: bool,
+    pub html: &'static str,
+    pub ascii: &'static str,
+    pub latin1: &'static str,
+    pub utf_8: &'static str,
+}
+
+macro_rules! entities {
+    ($(($name:expr, $latex:expr, $math:expr, $html:expr, $ascii:expr, $latin1:expr, $utf8:expr)),* $(,)?) => {
+        &[$(Entity {
+            name: $name,
+            latex: $latex,
+            latex_math_p: $math,
+            html: $html,
+            ascii: $ascii,
+            latin1: $latin1,
+            utf_8: $utf8,
+        }),*]
+    };
+}
+
+/// Known entities, a subset of `org-entities`: Greek letters and
+/// the most common symbols.
+/// elisp: `org-entities`
+#[rustfmt::skip]
+pub const ORG_ENTITIES: &[Entity] = entities![
+    // Greek
+    ("alpha", "\\alpha", true, "&alpha;", "alpha", "alpha", "α"),
+    ("beta", "\\beta", true, "&beta;", "beta", "beta", "β"),
+    ("gamma", "\\gamma", true, "&gamma;", "gamma", "gamma", "γ"),
+    ("Gamma", "\\Gamma", true, "&Gamma;", "Gamma", "Gamma", "Γ"),
+    ("delta", "\\delta", true, "&delta;", "delta", "delta", "δ"),
+    ("Delta", "\\Delta", true, "&Delta;", "Delta", "Delta", "Δ"),
+    ("epsilon", "\\epsilon", true, "&epsilon;", "epsilon", "epsilon", "ε"),
+    ("zeta", "\\zeta", true, "&zeta;", "zeta", "zeta", "ζ"),
+    ("eta", "\\eta", true, "&eta;", "eta", "eta", "η"),
+    ("theta", "\\theta", true, "&theta;", "theta", "theta", "θ"),
+    ("Theta", "\\Theta", true, "&Theta;", "Theta", "Theta", "Θ"),
+    ("iota", "\\iota", true, "&iota;", "iota", "iota", "ι"),
+    ("kappa", "\\kappa", true, "&kappa;", "kappa", "kappa", "κ"),
+    ("lambda", "\\lambda", true, "&lambda;", "lambda", "lambda", "λ"),
+    ("Lambda", "\\Lambda", true, "&Lambda;", "Lambda", "Lambda", "Λ"),
+    ("mu", "\\mu", true, "&mu;", "mu", "mu", "μ"),
+    ("nu", "\\nu", true, "&nu;", "nu", "nu", "ν"),
+    ("xi", "\\xi", true, "&xi;", "xi", "xi", "ξ"),
+    ("Xi", "\\Xi", true, "&Xi;", "Xi", "Xi", "Ξ"),
+    ("omicron", "\\textit{o}", false, "&omicron;", "omicron", "omicron", "ο"),
+    ("pi", "\\pi", true, "&pi;", "pi", "pi", "π"),
+    ("Pi", "\\Pi", true, "&Pi;", "Pi", "Pi", "Π"),
+    ("rho", "\\rho", true, "&rho;", "rho", "rho", "ρ"),
+    ("sigma", "\\sigma", true, "&sigma;", "sigma", "sigma", "σ"),
+    ("Sigma", "\\Sigma", true, "&Sigma;", "Sigma", "Sigma", "Σ"),
+    ("tau", "\\tau", true, "&tau;", "tau", "tau", "τ"),
+    ("upsilon", "\\upsilon", true, "&upsilon;", "upsilon", "upsilon", "υ"),
+    ("Upsilon", "\\Upsilon", true, "&Upsilon;", "Upsilon", "Upsilon", "Υ"),
+    ("phi", "\\phi", true, "&phi;", "phi", "phi", "φ"),
+    ("Phi", "\\Phi", true, "&Phi;", "Phi", "Phi", "Φ"),
+    ("chi", "\\chi", true, "&chi;", "chi", "chi", "χ"),
+    ("psi", "\\psi", true, "&psi;", "psi", "psi", "ψ"),
+    ("Psi", "\\Psi", true, "&Psi;", "Psi", "Psi", "Ψ"),
+    ("omega", "\\omega", true, "&omega;", "omega", "omega", "ω"),
+    ("Omega", "\\Omega", true, "&Omega;", "Omega", "Omega", "Ω"),
+    // Symbols
+    ("nbsp", "~", false, "&nbsp;", " ", "\u{a0}", "\u{a0}"),
+    ("copy", "\\textcopyright{}", false, "&copy;", "(c)", "©", "©"),
+    ("reg", "\\textregistered{}", false, "&reg;", "(r)", "®", "®"),
+    ("trade", "\\texttrademark{}", false, "&trade;", "TM", "TM", "™"),
+    ("deg", "\\textdegree{}", false, "&deg;", "degree", "°", "°"),
+    ("pm", "\\textpm{}", false, "&plusmn;", "+-", "±", "±"),
+    ("times", "\\texttimes{}", false, "&times;", "*", "×", "×"),
+    ("div", "\\textdiv{}", false, "&divide;", "/", "÷", "÷"),
+    ("sup1", "\\textsuperscript{1}", false, "&sup1;", "^1", "¹", "¹"),
+    ("sup2", "\\textsuperscript{2}", false, "&sup2;", "^2", "²", "²"),
+    ("sup3", "\\textsuperscript{3}", false, "&sup3;", "^3", "³", "³"),
+    ("frac12", "\\textonehalf{}", false, "&frac12;", "1/2", "½", "½"),
+    ("frac14", "\\textonequarter{}", false, "&frac14;", "1/4", "¼", "¼"),
+    ("frac34", "\\textthreequarters{}", false, "&frac34;", "3/4", "¾", "¾"),
+    ("there4", "\\therefore", true, "&there4;", "[therefore]", "[therefore]", "∴"),
+    ("hellip", "\\dots{}", false, "&hellip;", "...", "...", "…"),
+    ("mdash", "---", false, "&mdash;", "--", "--", "—"),
+    ("ndash", "--", false, "&ndash;", "-", "-", "–"),
+    ("euro", "\\texteuro{}", false, "&euro;", "EUR", "EUR", "€"),
+    ("rarr", "\\rightarrow", true, "&rarr;", "->", "->", "→"),
+    ("to", "\\to", true, "&rarr;", "->", "->", "→"),
+    ("larr", "\\leftarrow", true, "&larr;", "<-", "<-", "←"),
+    ("infin", "\\infty", true, "&infin;", "[infinity]", "[infinity]", "∞"),
+    ("infty", "\\infty", true, "&infin;", "[infinity]", "[infinity]", "∞"),
+    ("ne", "\\ne", true, "&ne;", "[not equal to]", "[not equal to]", "≠"),
+    ("le", "\\le", true, "&le;", "<=", "<=", "≤"),
+    ("ge", "\\ge", true, "&ge;", ">=", ">=", "≥"),
+    ("sum", "\\sum", true, "&sum;", "[sum]", "[sum]", "∑"),
+];
+
+/// Returns the entity named `name`, if any
+/// elisp: `org-entity-get`
+pub fn entity_get(name: &str) -> Option<&'static Entity> {
+    ORG_ENTITIES.iter().find(|e| e.name == name)
+}
+
+impl<'a> Parser<'a> {
+    /// Parse entity at point, if any.
+    ///
+    /// When at an entity, return a list whose car is `entity' and cdr
+    /// a plist with `:begin', `:end', `:latex', `:latex-math-p',
+    /// `:html', `:latin1', `:utf-8', `:ascii', `:use-brackets-p' and
+    /// `:post-blank' as keywords.  Otherwise, return nil.
+    ///
+    /// Assume point is at the beginning of the entity."
+    /// (defun org-element-entity-parser ()
+    ///
+    /// Spaces entities, e.g. `\_ `, are not supported.
+    pub fn entity_parser(&self) -> Option<SyntaxNode<'a>> {
+        let mut c = self.cursor.borrow_mut();
+        let begin = c.pos();
+        let cap = REGEX_ENTITY.captures(&c.data()[begin..])?;
+        let name = cap.get(1).unwrap();
+        let entity = entity_get(name.as_str())?;
+        let use_brackets_p = &cap[2] == "{}";
+        let name_end = begin + name.end();
+        c.set(if use_brackets_p {
+            name_end + 2
+        } else {
+            name_end
+        });
+        let post_blank = c.skip_chars_forward(" \t", None);
+        Some(SyntaxNode::new(
+            Syntax::Entity(Box::new(EntityData {
+                ascii: entity.ascii,
+                html: entity.html,
+                latex: entity.latex,
+                latex_math_p: entity.latex_math_p,
+                latin1: entity.latin1,
+                name: entity.name,
+                use_brackets_p,
+                utf_8: entity.utf_8,
+            })),
+            Interval {
+                start: begin,
+                end: c.pos(),
+            },
+            None,
+            post_blank,
+            None,
+        ))
+    }
+}
+
+mod test {
+    use crate::data::{Interval, Syntax, SyntaxT};
+    use crate::parser::Parser;
+
+    #[test]
+    fn entity_parser() {
+        let text = "\\alpha-rays, \\deg{}C \\alphabet";
+        let objects = Parser::parse_secondary_string(text, |t| SyntaxT::Paragraph.can_contain(t));
+        let types: Vec<_> = objects.iter().map(|o| SyntaxT::from(&o.data)).collect();
+        assert_eq!(
+            vec![
+                SyntaxT::Entity,
+                SyntaxT::PlainText,
+                SyntaxT::Entity,
+                SyntaxT::PlainText,
+                SyntaxT::LatexFragment
+            ],
+            types
+        );
+        assert_eq!(Interval { start: 0, end: 6 }, objects[0].location);
+        assert_eq!(Interval { start: 13, end: 19 }, objects[2].location);
+        match (&objects[0].data, &objects[2].data) {
+            (Syntax::Entity(alpha), Syntax::Entity(deg)) => {
+                assert_eq!(
+                    ("alpha", "α", false),
+                    (alpha.name, alpha.utf_8, alpha.use_brackets_p)
+                );
+                assert_eq!(
+                    ("deg", "&deg;", true),
+                    (deg.name, deg.html, deg.use_brackets_p)
+                );
+            }
+            _ => panic!("expected entities"),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Paragraph filling
+//!
+//! Filling rearranges words of a paragraph so that its lines are not
+//! longer than the fill column. Lines of a list item are aligned with
+//! the text following the bullet, like `org-fill-paragraph` does.
+
+use crate::data::{Interval, Syntax, SyntaxNode};
+use crate::parser::{ParseGranularity, Parser};
+use crate::regexps::REGEX_ITEM;
+
+const TAB_WIDTH: usize = 8;
+
+/// Returns the column at the end of `s`, tabs are expanded to `TAB_WIDTH`
+fn column_width(s: &str) -> usize {
+    s.chars().fold(0, |col, c| match c {
+        '\t' => (col / TAB_WIDTH + 1) * TAB_WIDTH,
+        _ => col + 1,
+    })
+}
+
+/// Returns the part of `line` preceding the item's text
+/// (indentation and bullet included) if the line starts a list item.
+fn item_prefix(line: &str) -> Option<&str> {
+    REGEX_ITEM.find(line).map(|m| &line[..m.end()])
+}
+
+/// Fills every paragraph of `region` so that its lines are not longer
+/// than `fill_column` columns, unless a line holds a single longer word.
+///
+/// Only paragraphs are filled, lines of any other element, e.g. tables,
+/// headlines, keywords or blocks, are kept as they are, so are blank
+/// lines. Text of each list item is filled on its own: continuation
+/// lines are indented to the item's body column, i.e., aligned with
+/// the text after the bullet. Other paragraphs keep the indentation
+/// of their first line.
+///
+/// elisp: `org-fill-paragraph`
+pub fn fill_region(region: &str, fill_column: usize) -> String {
+    fn paragraphs(node: &SyntaxNode, acc: &mut Vec<Interval>) {
+        match (&node.data, node.content_location) {
+            (Syntax::Paragraph, Some(contents)) => acc.push(contents),
+            _ => {
+                for child in node.children.borrow().iter() {
+                    paragraphs(child, acc);
+                }
+            }
+        }
+    }
+
+    let root = Parser::new(region, ParseGranularity::Element).parse_buffer();
+    let mut contents = vec![];
+    paragraphs(&root, &mut contents);
+
+    let mut result = String::with_capacity(region.len());
+    let mut last = 0;
+    for contents in contents {
+        let line_start = region[..contents.start].rfind('\n').map_or(0, |p| p + 1);
+        let end = line_start
+            + region[line_start..contents.end]
+                .trim_end_matches('\n')
+                .len();
+        result.push_str(&region[last..line_start]);
+        fill_lines(
+            &region[line_start..end],
+            contents.start - line_start,
+            fill_column,
+            &mut result,
+        );
+        last = end;
+    }
+    result.push_str(&region[last..]);
+    result
+}
+
+/// Fills the lines of a paragraph, `text`, whose contents begin at
+/// `offset`, past the bullet of an item, if any
+fn fill_lines(text: &str, offset: usize, fill_column: usize, result: &mut String) {
+    let words = text[offset..].trim_start();
+    let prefix = &text[..text.len() - words.len()];
+    let indent = match item_prefix(text) {
+        Some(item) if offset > 0 => column_width(item),
+        _ => column_width(prefix),
+    };
+
+    result.push_str(prefix);
+    let mut column = column_width(prefix);
+    let mut line_is_empty = true;
+    for word in words.split_whitespace() {
+        let width = word.chars().count();
+        if !line_is_empty && column + 1 + width > fill_column {
+            result.push('\n');
+            result.extend(std::iter::repeat(' ').take(indent));
+            column = indent;
+            line_is_empty = true;
+        }
+        if !line_is_empty {
+            result.push(' ');
+            column += 1;
+        }
+        result.push_str(word);
+        column += width;
+        line_is_empty = false;
+    }
+}
+
+mod test {
+    use super::fill_region;
+
+    #[test]
+    fn fill_paragraph() {
+        let text = "  Some words that\ndo not fit on a single line.\n";
+        assert_eq!(
+            "  Some words that do\n  not fit on a\n  single line.\n",
+            fill_region(text, 20)
+        );
+        assert_eq!("", fill_region("", 20));
+        assert_eq!("nothing\n\nto fill", fill_region("nothing\n\nto fill", 20));
+    }
+
+    #[test]
+    fn fill_list_items() {
+        let text = "- This is a long list item that should wrap\n  10. [X] nested item text\n";
+        assert_eq!(
+            concat!(
+                "- This is a long\n",
+                "  list item that\n",
+                "  should wrap\n",
+                "  10. [X] nested\n",
+                "      item text\n"
+            ),
+            fill_region(text, 16)
+        );
+
+        let text = "+\ta tab after the bullet";
+        assert_eq!("+\ta tab after\n        the bullet", fill_region(text, 20));
+    }
+
+    #[test]
+    fn fill_paragraphs_only() {
+        let text = concat!(
+            "* A headline that is longer than the fill column\n",
+            "#+TITLE: A keyword that is longer than the fill column\n",
+            "| a table row longer than the fill column |\n",
+            "#+BEGIN_QUOTE\n",
+            "Quoted text that is longer than the fill column\n",
+            "#+END_QUOTE\n",
+            "#+BEGIN_EXAMPLE\n",
+            "Example text that is longer than the fill column\n",
+            "#+END_EXAMPLE\n",
+            "- [X] An item that is longer than the fill column\n",
+            "  Continued.\n",
+            "Text.\n"
+        );
+        assert_eq!(
+            text.replace(
+                "Quoted text that is longer than the fill column",
+                "Quoted text that is\nlonger than the fill\ncolumn"
+            )
+            .replace(
+                "- [X] An item that is longer than the fill column\n  Continued.",
+                "- [X] An item that\n  is longer than the\n  fill column\n  Continued."
+            ),
+            fill_region(text, 20)
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Footnotes collection
+//!
+//! Pairs footnote references with their definitions and numbers them
+//! the way exporters do: in order of first reference. References found
+//! in a definition are numbered right after the reference pointing to it.
+
+use std::collections::HashMap;
+
+use crate::data::{FootnoteReferenceType, Handle, Interval, Syntax, SyntaxNode};
+use crate::parser::Diagnostic;
+
+#[derive(Debug)]
+pub struct Footnote<'a> {
+    /// Footnote's label, `None` for anonymous footnotes
+    pub label: Option<&'a str>,
+
+    /// Export number, starting from 1
+    pub number: usize,
+
+    /// Contents of the definition
+    pub definition: &'a str,
+
+    /// Locations of the references, in order of appearance
+    pub references: Vec<Interval>,
+}
+
+#[derive(Debug, Default)]
+pub struct FootnoteTable<'a> {
+    /// Referenced footnotes, ordered by number
+    pub footnotes: Vec<Footnote<'a>>,
+
+    /// Undefined references and unused definitions
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> FootnoteTable<'a> {
+    /// Returns footnote with the given label, if it is referenced
+    pub fn get(&self, label: &str) -> Option<&Footnote<'a>> {
+        self.footnotes.iter().find(|f| f.label == Some(label))
+    }
+}
+
+/// Secondary strings are visited before the contents
+fn children<'a>(node: &SyntaxNode<'a>) -> Vec<Handle<'a>> {
+    let mut children = match &node.data {
+        Syntax::Headline(h) => h.title.clone(),
+        Syntax::Keyword(k) => k.parsed_value.clone().unwrap_or_default(),
+        _ => vec![],
+    };
+    children.extend(node.children.borrow().iter().cloned());
+    children
+}
+
+fn contents<'a>(node: &SyntaxNode<'a>, src: &'a str) -> &'a str {
+    node.content_location
+        .map_or("", |c| src[c.start..c.end].trim_end())
+}
+
+struct Collector<'a, 't> {
+    src: &'a str,
+    /// Footnote definitions and labelled inline references by label
+    definitions: &'t HashMap<&'a str, Handle<'a>>,
+    /// Indices into `table.footnotes` by label
+    numbered: HashMap<&'a str, usize>,
+    table: FootnoteTable<'a>,
+}
+
+impl<'a, 't> Collector<'a, 't> {
+    fn visit(&mut self, node: &SyntaxNode<'a>) {
+        match &node.data {
+            // Definitions are visited when first referenced
+            Syntax::FootnoteDefinition(_) => return,
+            Syntax::FootnoteReference(r) => {
+                self.reference(node, r.label);
+                // Contents of a labelled inline reference are
+                // visited along with the definition.
+                if r.label.is_some() {
+                    return;
+                }
+            }
+            _ => (),
+        }
+        for child in children(node) {
+            self.visit(&child);
+        }
+    }
+
+    fn reference(&mut self, node: &SyntaxNode<'a>, label: Option<&'a str>) {
+        let label = match label {
+            Some(label) => label,
+            None => {
+                let number = self.table.footnotes.len() + 1;
+                self.table.footnotes.push(Footnote {
+                    label: None,
+                    number,
+                    definition: contents(node, self.src),
+                    references: vec![node.location],
+                });
+                return;
+            }
+        };
+        if let Some(&index) = self.numbered.get(label) {
+            self.table.footnotes[index].references.push(node.location);
+            return;
+        }
+        let definition = match self.definitions.get(label) {
+            Some(definition) => definition.clone(),
+            None => {
+                self.table.diagnostics.push(Diagnostic {
+                    location: node.location,
+                    message: format!("Undefined footnote reference: {}", label),
+                });
+                return;
+            }
+        };
+        self.numbered.insert(label, self.table.footnotes.len());
+        self.table.footnotes.push(Footnote {
+            label: Some(label),
+            number: self.table.footnotes.len() + 1,
+            definition: contents(&definition, self.src),
+            references: vec![node.location],
+        });
+        for child in children(&definition) {
+            self.visit(&child);
+        }
+    }
+}
+
+fn collect_definitions<'a>(node: &Handle<'a>, definitions: &mut Vec<(&'a str, Handle<'a>)>) {
+    match &node.data {
+        Syntax::FootnoteDefinition(d) => definitions.push((d.label, node.clone())),
+        Syntax::FootnoteReference(r) if r.type_s == FootnoteReferenceType::Inline => {
+            if let Some(label) = r.label {
+                definitions.push((label, node.clone()));
+            }
+        }
+        _ => (),
+    }
+    for child in children(node) {
+        collect_definitions(&child, definitions);
+    }
+}
+
+/// Collects footnotes of the tree starting at `root`, obtained by parsing `src`.
+///
+/// Definitions are looked for in the whole document, in footnote
+/// definitions as well as in labelled inline references.
+/// Each labelled footnote gets a single number, anonymous inline
+/// footnotes get one each. References without a definition and
+/// definitions never referenced are reported as diagnostics.
+///
+/// elisp: `org-export-collect-footnote-definitions`
+pub fn collect_footnotes<'a>(root: &SyntaxNode<'a>, src: &'a str) -> FootnoteTable<'a> {
+    let mut found = vec![];
+    for child in children(root) {
+        collect_definitions(&child, &mut found);
+    }
+    let mut definitions = HashMap::new();
+    for (label, node) in &found {
+        definitions.entry(*label).or_insert_with(|| node.clone());
+    }
+
+    let mut collector = Collector {
+        src,
+        definitions: &definitions,
+        numbered: HashMap::new(),
+        table: FootnoteTable::default(),
+    };
+    collector.visit(root);
+
+    let mut table = collector.table;
+    for (label, node) in &found {
+        if !collector.numbered.contains_key(label) {
+            table.diagnostics.push(Diagnostic {
+                location: node.location,
+                message: format!("Unused footnote definition: {}", label),
+            });
+        }
+    }
+    table
+}
+
+mod test {
+    use super::collect_footnotes;
+    use crate::data::Interval;
+    use crate::parser::{ParseGranularity, Parser};
+
+    #[test]
+    fn collect_footnotes_numbering() {
+        let src = concat!(
+            "Text with a note[fn:b] and an anonymous one[fn::inline *text*].\n",
+            "Another[fn:a] then again[fn:b], dangling[fn:missing] and [fn:c:inline c].\n",
+            "\n",
+            "* Footnotes\n",
+            "[fn:a] Definition of A, see [fn:d]\n",
+            "\n",
+            "[fn:b] Definition of B\n",
+            "\n",
+            "[fn:d] Definition of D\n",
+            "[fn:unused]\n",
+            "Never referenced\n"
+        );
+        let parser = Parser::new(src, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let table = collect_footnotes(&root, src);
+
+        let numbers: Vec<_> = table
+            .footnotes
+            .iter()
+            .map(|f| (f.label, f.number, f.definition))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some("b"), 1, "Definition of B"),
+                (None, 2, "inline *text*"),
+                (Some("a"), 3, "Definition of A, see [fn:d]"),
+                (Some("d"), 4, "Definition of D"),
+                (Some("c"), 5, "inline c"),
+            ],
+            numbers
+        );
+        let b = table.get("b").unwrap();
+        assert_eq!(
+            vec![
+                Interval { start: 16, end: 23 },
+                Interval { start: 88, end: 94 }
+            ],
+            b.references
+        );
+
+        let messages: Vec<_> = table
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            vec![
+                "Undefined footnote reference: missing",
+                "Unused footnote definition: unused"
+            ],
+            messages
+        );
+        assert_eq!(
+            Interval {
+                start: 104,
+                end: 117
+            },
+            table.diagnostics[0].location
+        );
+    }
+}
@@ -0,0 +1,672 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Table formulas
+//! https://orgmode.org/manual/The-Spreadsheet.html
+//!
+//! Only a small arithmetic subset of the spreadsheet is supported:
+//!
+//! - column formulas `$3=...` and field formulas `@2$3=...`,
+//! - numbers, `+`, `-`, `*`, `/` and parentheses,
+//! - field references `$1`, `@2$3`, `@>$<`, `@-1`, `$+1`,
+//! - `vsum` and `vmean` over ranges such as `@2..@>>` or `@2$1..@3$2`.
+//!
+//! Rows are counted without table rules, as in Org. A column formula
+//! applies to every row below the first rule, or to every row if the
+//! table has none. Field formulas override column formulas and are
+//! computed after them. Formats following `;` are ignored.
+
+use crate::data::{Interval, Syntax, SyntaxNode};
+use crate::parser::Diagnostic;
+use crate::table::TableData;
+
+const ERROR: &str = "#ERROR";
+
+#[derive(Debug)]
+pub(crate) enum Line {
+    Rule,
+    Row(Vec<String>),
+}
+
+/// Table contents: rows of cells, rules excluded
+struct Grid {
+    rows: Vec<Vec<String>>,
+    columns: usize,
+}
+
+impl Grid {
+    fn value(&self, row: usize, column: usize) -> Option<f64> {
+        let cell = self.rows.get(row - 1)?.get(column - 1)?;
+        if cell.is_empty() {
+            Some(0.0)
+        } else {
+            cell.parse().ok()
+        }
+    }
+}
+
+/// Reference to a row or a column, as written in a formula
+#[derive(Debug, Clone, Copy)]
+enum Index {
+    Absolute(usize),
+    Relative(isize),
+    /// `<`, `<<`... 0 is the first
+    First(usize),
+    /// `>`, `>>`... 0 is the last
+    Last(usize),
+}
+
+impl Index {
+    fn resolve(self, current: usize, count: usize) -> Option<usize> {
+        let index = match self {
+            Index::Absolute(n) => n as isize,
+            Index::Relative(n) => current as isize + n,
+            Index::First(n) => 1 + n as isize,
+            Index::Last(n) => count as isize - n as isize,
+        };
+        if index >= 1 && index <= count as isize {
+            Some(index as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reference {
+    row: Option<Index>,
+    column: Option<Index>,
+}
+
+impl Reference {
+    fn resolve(self, grid: &Grid, row: usize, column: usize) -> Option<(usize, usize)> {
+        let r = match self.row {
+            Some(index) => index.resolve(row, grid.rows.len())?,
+            None => row,
+        };
+        let c = match self.column {
+            Some(index) => index.resolve(column, grid.columns)?,
+            None => column,
+        };
+        Some((r, c))
+    }
+}
+
+/// Evaluates a formula right hand side for the field at `row`, `column`.
+/// Returns `None` on any error: syntax, unknown function, invalid
+/// reference, non numeric field or division by zero.
+struct Evaluator<'g> {
+    grid: &'g Grid,
+    row: usize,
+    column: usize,
+    text: &'g [u8],
+    pos: usize,
+}
+
+impl<'g> Evaluator<'g> {
+    fn peek(&self) -> Option<u8> {
+        self.text.get(self.pos).cloned()
+    }
+
+    fn skip_blanks(&mut self) {
+        while self.peek().map_or(false, |b| b == b' ' || b == b'\t') {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_blanks();
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn evaluate(mut self) -> Option<f64> {
+        let value = self.expression()?;
+        self.skip_blanks();
+        if self.pos == self.text.len() {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat(b'+') {
+                value += self.term()?;
+            } else if self.eat(b'-') {
+                value -= self.term()?;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.factor()?;
+        loop {
+            if self.eat(b'*') {
+                value *= self.factor()?;
+            } else if self.eat(b'/') {
+                let divisor = self.factor()?;
+                if divisor == 0.0 {
+                    return None;
+                }
+                value /= divisor;
+            } else {
+                return Some(value);
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Option<f64> {
+        self.skip_blanks();
+        match self.peek()? {
+            b'-' => {
+                self.pos += 1;
+                self.factor().map(|v| -v)
+            }
+            b'(' => {
+                self.pos += 1;
+                let value = self.expression()?;
+                if self.eat(b')') {
+                    Some(value)
+                } else {
+                    None
+                }
+            }
+            b'@' | b'$' => {
+                let reference = self.reference()?;
+                let (row, column) = reference.resolve(self.grid, self.row, self.column)?;
+                self.grid.value(row, column)
+            }
+            b if b.is_ascii_digit() || b == b'.' => self.number(),
+            b if b.is_ascii_alphabetic() => self.function(),
+            _ => None,
+        }
+    }
+
+    fn number(&mut self) -> Option<f64> {
+        let start = self.pos;
+        while self
+            .peek()
+            .map_or(false, |b| b.is_ascii_digit() || b == b'.')
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn function(&mut self) -> Option<f64> {
+        let start = self.pos;
+        while self.peek().map_or(false, |b| b.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let name = std::str::from_utf8(&self.text[start..self.pos]).ok()?;
+        if !self.eat(b'(') {
+            return None;
+        }
+        let values = self.range()?;
+        if !self.eat(b')') {
+            return None;
+        }
+        let sum: f64 = values.iter().sum();
+        match name {
+            "vsum" => Some(sum),
+            "vmean" if !values.is_empty() => Some(sum / values.len() as f64),
+            _ => None,
+        }
+    }
+
+    /// Values of the fields in a rectangular range `REF..REF`
+    fn range(&mut self) -> Option<Vec<f64>> {
+        self.skip_blanks();
+        let from = self.reference()?;
+        if !self.text[self.pos..].starts_with(b"..") {
+            return None;
+        }
+        self.pos += 2;
+        let to = self.reference()?;
+
+        let (r1, c1) = from.resolve(self.grid, self.row, self.column)?;
+        let (r2, c2) = to.resolve(self.grid, self.row, self.column)?;
+        let mut values = vec![];
+        for row in r1.min(r2)..=r1.max(r2) {
+            for column in c1.min(c2)..=c1.max(c2) {
+                values.push(self.grid.value(row, column)?);
+            }
+        }
+        Some(values)
+    }
+
+    fn reference(&mut self) -> Option<Reference> {
+        let row = if self.peek() == Some(b'@') {
+            self.pos += 1;
+            Some(self.index()?)
+        } else {
+            None
+        };
+        let column = if self.peek() == Some(b'$') {
+            self.pos += 1;
+            Some(self.index()?)
+        } else {
+            None
+        };
+        if row.is_none() && column.is_none() {
+            return None;
+        }
+        Some(Reference { row, column })
+    }
+
+    fn index(&mut self) -> Option<Index> {
+        let repeated = |e: &mut Self, b: u8| {
+            let start = e.pos;
+            while e.peek() == Some(b) {
+                e.pos += 1;
+            }
+            e.pos - start - 1
+        };
+        match self.peek()? {
+            b'<' => Some(Index::First(repeated(self, b'<'))),
+            b'>' => Some(Index::Last(repeated(self, b'>'))),
+            sign @ b'+' | sign @ b'-' => {
+                self.pos += 1;
+                let n = self.digits()? as isize;
+                Some(Index::Relative(if sign == b'-' { -n } else { n }))
+            }
+            _ => self.digits().map(Index::Absolute),
+        }
+    }
+
+    fn digits(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().map_or(false, |b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.text[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+}
+
+fn evaluate(grid: &Grid, row: usize, column: usize, rhs: &str) -> String {
+    let evaluator = Evaluator {
+        grid,
+        row,
+        column,
+        text: rhs.as_bytes(),
+        pos: 0,
+    };
+    match evaluator.evaluate() {
+        Some(value) if value.is_finite() => format_number(value),
+        _ => ERROR.to_string(),
+    }
+}
+
+/// Formats a result without trailing zeros, rounded to 10 decimals
+fn format_number(value: f64) -> String {
+    let formatted = format!("{:.10}", value);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    match formatted {
+        "-0" => "0".to_string(),
+        _ => formatted.to_string(),
+    }
+}
+
+/// Parses a formula target: `$N` or `@N$N` (with `<`/`>` indices)
+fn target(lhs: &str) -> Option<Reference> {
+    let mut evaluator = Evaluator {
+        grid: &Grid {
+            rows: vec![],
+            columns: 0,
+        },
+        row: 0,
+        column: 0,
+        text: lhs.trim().as_bytes(),
+        pos: 0,
+    };
+    let reference = evaluator.reference()?;
+    if evaluator.pos != evaluator.text.len() || reference.column.is_none() {
+        return None;
+    }
+    Some(reference)
+}
+
+pub(crate) fn parse_lines(contents: &str) -> Vec<Line> {
+    contents
+        .lines()
+        .map(|line| {
+            let line = line.trim();
+            if line.starts_with("|-") {
+                return Line::Rule;
+            }
+            let line = line.trim_start_matches('|');
+            let line = line.strip_suffix('|').unwrap_or(line);
+            Line::Row(
+                line.split('|')
+                    .map(|cell| cell.trim().to_string())
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// Writes the table back, aligned like `org-table-align` does:
+/// columns mostly made of numbers are aligned to the right.
+pub(crate) fn align(lines: &[Line], indent: &str, columns: usize, out: &mut String) {
+    let cells = || {
+        lines.iter().filter_map(|line| match line {
+            Line::Row(cells) => Some(cells),
+            Line::Rule => None,
+        })
+    };
+    let mut widths = vec![1; columns];
+    let mut numeric = vec![false; columns];
+    for column in 0..columns {
+        let (mut numbers, mut non_empty) = (0, 0);
+        for cell in cells().filter_map(|cells| cells.get(column)) {
+            widths[column] = widths[column].max(cell.chars().count());
+            if !cell.is_empty() {
+                non_empty += 1;
+                if cell.parse::<f64>().is_ok() {
+                    numbers += 1;
+                }
+            }
+        }
+        numeric[column] = non_empty > 0 && numbers * 2 > non_empty;
+    }
+
+    for line in lines {
+        out.push_str(indent);
+        match line {
+            Line::Rule => {
+                let dashes: Vec<String> = widths.iter().map(|w| "-".repeat(w + 2)).collect();
+                out.push('|');
+                out.push_str(&dashes.join("+"));
+                out.push('|');
+            }
+            Line::Row(cells) => {
+                out.push('|');
+                for column in 0..columns {
+                    let cell = cells.get(column).map_or("", |c| c.as_str());
+                    let width = widths[column];
+                    if numeric[column] {
+                        out.push_str(&format!(" {:>width$} |", cell, width = width));
+                    } else {
+                        out.push_str(&format!(" {:<width$} |", cell, width = width));
+                    }
+                }
+            }
+        }
+        out.push('\n');
+    }
+}
+
+/// Recomputes every formula of `table`, a node obtained by parsing
+/// `src`, and returns `src` with the table filled with the results.
+/// Fields whose formula cannot be computed, e.g., because of a reference
+/// out of the table, are set to `#ERROR`. The table is realigned.
+/// Returns `src` unchanged if `table` is not a table with formulas.
+///
+/// Formulas whose target is not a field of the table, e.g. `@9$1=1`
+/// in a table of two rows, have nowhere to write `#ERROR` to. They
+/// are left out and returned as diagnostics, located on their
+/// `#+TBLFM:` line.
+///
+/// elisp: `org-table-recalculate`
+pub fn recalculate_table(table: &SyntaxNode, src: &str) -> (String, Vec<Diagnostic>) {
+    let (data, contents) = match (&table.data, table.content_location) {
+        (Syntax::Table(data), Some(contents)) if !data.tblfm.is_empty() => (data, contents),
+        _ => return (src.to_string(), vec![]),
+    };
+    let mut diagnostics = vec![];
+    let mut report = |formula: &str, message: &str| {
+        let formula = formula.trim();
+        let start = src[contents.end..table.location.end]
+            .find(formula)
+            .map_or(contents.end, |offset| contents.end + offset);
+        diagnostics.push(Diagnostic {
+            location: Interval {
+                start,
+                end: start + formula.len(),
+            },
+            message: format!("{}: {}", message, formula),
+        });
+    };
+    let text = &src[contents.start..contents.end];
+    let mut lines = parse_lines(text);
+    let mut grid = Grid {
+        rows: lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Row(cells) => Some(cells.clone()),
+                Line::Rule => None,
+            })
+            .collect(),
+        columns: 0,
+    };
+    grid.columns = grid.rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    // Column formulas skip header rows, above the first rule following
+    // a row, rules bordering the top of the table do not count.
+    let mut first_body_row = 1;
+    let mut rows_above = 0;
+    for line in lines.iter() {
+        match line {
+            Line::Row(_) => rows_above += 1,
+            Line::Rule if rows_above > 0 => {
+                first_body_row = rows_above + 1;
+                break;
+            }
+            Line::Rule => {}
+        }
+    }
+
+    let (columns, fields, invalid) = formulas(data);
+    for formula in invalid {
+        report(formula, "Invalid formula target");
+    }
+    for (reference, rhs, formula) in &columns {
+        let column = match reference.column.and_then(|c| c.resolve(0, grid.columns)) {
+            Some(column) => column,
+            None => {
+                report(formula, "Formula target out of table");
+                continue;
+            }
+        };
+        for row in first_body_row..=grid.rows.len() {
+            let overridden = fields.iter().any(|(field, _, _)| {
+                field
+                    .resolve(&grid, row, column)
+                    .map_or(false, |(r, c)| r == row && c == column)
+            });
+            if !overridden {
+                let value = evaluate(&grid, row, column, rhs);
+                set(&mut grid, row, column, value);
+            }
+        }
+    }
+    for (reference, rhs, formula) in &fields {
+        match reference.resolve(&grid, 0, 0) {
+            Some((row, column)) => {
+                let value = evaluate(&grid, row, column, rhs);
+                set(&mut grid, row, column, value);
+            }
+            None => report(formula, "Formula target out of table"),
+        }
+    }
+
+    let mut rows = grid.rows.into_iter();
+    for line in lines.iter_mut() {
+        if let Line::Row(cells) = line {
+            *cells = rows.next().unwrap();
+        }
+    }
+    let indent_len = text.len() - text.trim_start_matches(|c| c == ' ' || c == '\t').len();
+    let mut result = String::with_capacity(src.len());
+    result.push_str(&src[..contents.start]);
+    align(&lines, &text[..indent_len], grid.columns, &mut result);
+    if !text.ends_with('\n') {
+        result.pop();
+    }
+    result.push_str(&src[contents.end..]);
+    (result, diagnostics)
+}
+
+fn set(grid: &mut Grid, row: usize, column: usize, value: String) {
+    let cells = &mut grid.rows[row - 1];
+    if cells.len() < column {
+        cells.resize(column, String::new());
+    }
+    cells[column - 1] = value;
+}
+
+/// Formula with its target, its right-hand side and its whole text
+type Formula<'a> = (Reference, &'a str, &'a str);
+
+/// Splits `#+TBLFM:` lines into column formulas and field formulas,
+/// along with formulas whose target cannot be parsed
+fn formulas<'a>(data: &TableData<'a>) -> (Vec<Formula<'a>>, Vec<Formula<'a>>, Vec<&'a str>) {
+    let mut columns = vec![];
+    let mut fields = vec![];
+    let mut invalid = vec![];
+    for formula in data.tblfm.iter().flat_map(|line| line.split("::")) {
+        let mut parts = formula.splitn(2, '=');
+        let (lhs, rhs) = match (parts.next(), parts.next()) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => continue,
+        };
+        let rhs = rhs.split(';').next().unwrap().trim();
+        match target(lhs) {
+            Some(reference) if reference.row.is_none() => columns.push((reference, rhs, formula)),
+            Some(reference) => fields.push((reference, rhs, formula)),
+            None => invalid.push(formula),
+        }
+    }
+    (columns, fields, invalid)
+}
+
+mod test {
+    use super::recalculate_table;
+    use crate::data::Interval;
+    use crate::parser::{Diagnostic, ParseGranularity, Parser};
+
+    fn recalculate_with_diagnostics(src: &str) -> (String, Vec<Diagnostic>) {
+        let parser = Parser::new(src, ParseGranularity::Element);
+        let table = parser.table_parser(src.len(), 0, None);
+        recalculate_table(&table, src)
+    }
+
+    fn recalculate(src: &str) -> String {
+        let (result, diagnostics) = recalculate_with_diagnostics(src);
+        assert_eq!(Vec::<Diagnostic>::new(), diagnostics);
+        result
+    }
+
+    #[test]
+    fn column_formula() {
+        let src = concat!(
+            "| a | b | sum |\n",
+            "|---+---+-----|\n",
+            "| 1 | 2 |     |\n",
+            "| 3 | 4.5 |     |\n",
+            "#+TBLFM: $3=$1+$2\n"
+        );
+        assert_eq!(
+            concat!(
+                "| a |   b | sum |\n",
+                "|---+-----+-----|\n",
+                "| 1 |   2 |   3 |\n",
+                "| 3 | 4.5 | 7.5 |\n",
+                "#+TBLFM: $3=$1+$2\n"
+            ),
+            recalculate(src)
+        );
+    }
+
+    #[test]
+    fn column_formula_bordered_table() {
+        let src = concat!(
+            "|---+-----|\n",
+            "| a | sum |\n",
+            "|---+-----|\n",
+            "| 1 |     |\n",
+            "| 3 |     |\n",
+            "|---+-----|\n",
+            "#+TBLFM: $2=$1*2\n"
+        );
+        assert_eq!(
+            src.replace("| 1 |     |", "| 1 |   2 |")
+                .replace("| 3 |     |", "| 3 |   6 |"),
+            recalculate(src)
+        );
+    }
+
+    #[test]
+    fn field_formula_vsum() {
+        let src = concat!(
+            "  | x | y |\n",
+            "  |---+---|\n",
+            "  | 1 | 4 |\n",
+            "  | 2 | 5 |\n",
+            "  | 6 | 0 |\n",
+            "  |---+---|\n",
+            "  |   |   |\n",
+            "#+TBLFM: @>$1=vsum(@2..@>>)::@>$2=vmean(@2$1..@>>$2) * (2 - 1)\n",
+            "after"
+        );
+        assert_eq!(src.replace("|   |   |", "| 9 | 3 |"), recalculate(src));
+    }
+
+    #[test]
+    fn formula_errors() {
+        let src = "| 1 |   |\n| a |   |\n#+TBLFM: $2=$1*2+$5::@1$1=1/0";
+        assert_eq!(
+            "| #ERROR | #ERROR |\n| a      | #ERROR |\n#+TBLFM: $2=$1*2+$5::@1$1=1/0",
+            recalculate(src)
+        );
+
+        // Targets out of the table are reported, other formulas still apply
+        let src = "| 1 |   |\n#+TBLFM: @9$1=1::$2=$1+1::$7=2::x=1\n";
+        let (result, diagnostics) = recalculate_with_diagnostics(src);
+        assert_eq!("| 1 | 2 |\n#+TBLFM: @9$1=1::$2=$1+1::$7=2::x=1\n", result);
+        let reported: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (&src[d.location.start..d.location.end], d.message.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("x=1", "Invalid formula target: x=1"),
+                ("$7=2", "Formula target out of table: $7=2"),
+                ("@9$1=1", "Formula target out of table: @9$1=1"),
+            ],
+            reported
+        );
+        assert_eq!(Interval { start: 19, end: 25 }, diagnostics[2].location);
+        let src = "| 1 | 2 |\n| 3 | 4 |\n#+TBLFM: $2=@-1$1";
+        assert_eq!(
+            "| 1 | #ERROR |\n| 3 | 1      |\n#+TBLFM: $2=@-1$1",
+            recalculate(src)
+        );
+    }
+}
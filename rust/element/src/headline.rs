@@ -87,86 +87,110 @@
 //!    (headline))))
 //!
 
-use crate::data::{SyntaxNode, TimestampData};
-use crate::parser::Parser;
-use regex::Regex;
+use crate::data::{Handle, Interval, PlanningData, Syntax, SyntaxNode, SyntaxT, TimestampData};
+use crate::parser::{ParseGranularity, Parser};
+use crate::regexps::{
+    REGEX_DRAWER_END, REGEX_HEADLINE_PRIORITY, REGEX_HEADLINE_TAGS, REGEX_NODE_PROPERTY,
+    REGEX_PLANNING_LINE, REGEX_PROPERTY_DRAWER,
+};
+use crate::settings::BufferSettings;
+use std::borrow::Cow;
+use std::rc::Rc;
 
-const ORG_CLOSED_STRING: &str = "CLOSED";
-const ORG_DEADLINE_STRING: &str = "DEADLINE";
-const ORG_SCHEDULED_STRING: &str = "SCHEDULED";
+pub const ORG_CLOSED_STRING: &str = "CLOSED";
+pub const ORG_DEADLINE_STRING: &str = "DEADLINE";
+pub const ORG_SCHEDULED_STRING: &str = "SCHEDULED";
 
-lazy_static! {
-    pub static ref REGEX_HEADLINE_SHORT: Regex = Regex::new(r"^\*+\s").unwrap();
+/// Entries with this keyword in front of their title are considered commented
+/// elisp: `org-comment-string`
+pub const ORG_COMMENT_STRING: &str = "COMMENT";
 
-    // TODO document why is it needed and what are the consequences of using multiline regex
-    pub static ref REGEX_HEADLINE_MULTILINE: Regex = Regex::new(r"(?m)^\*+\s").unwrap();
+/// The tag that marks a subtree as archived
+/// elisp: `org-archive-tag`
+pub const ORG_ARCHIVE_TAG: &str = "ARCHIVE";
 
-    /// Matches a line with planning info.
-    /// Matched keyword is in group 1
-    pub static ref REGEX_PLANNING_LINE: Regex = Regex::new(
-        &format!(r"^[ \t]*((?:{}|{}|{}):)",
-            ORG_CLOSED_STRING, ORG_DEADLINE_STRING, ORG_SCHEDULED_STRING ))
-        .unwrap();
-
-    /// Matches an entire property drawer
-    /// Requires multiline match
-    /// correspond to org-property-drawer-re in org.el
-    pub static ref REGEX_PROPERTY_DRAWER: Regex = Regex::new(
-        r"^[ \t]*:PROPERTIES:[ \t]*\n(?:[ \t]*:\S+:(?: .*)?[ \t]*\n)*?[ \t]*:END:[ \t]*")
-            .unwrap();
-
-    pub static ref REGEX_CLOCK_LINE: Regex = Regex::new(r"^[ \t]*CLOCK:").unwrap();
-
-}
+/// Headline with this title holds footnote definitions
+/// elisp: `org-footnote-section`
+pub const ORG_FOOTNOTE_SECTION: &str = "Footnotes";
 
 #[derive(Debug)]
 pub struct HeadlineData<'a> {
     /// Non_nil if the headline has an archive tag (boolean).
-    archivedp: bool,
+    pub archivedp: bool,
 
     /// Headline's CLOSED reference, if any (timestamp object or nil)
-    closed: Option<TimestampData<'a>>,
+    pub closed: Option<TimestampData<'a>>,
 
     /// Non_nil if the headline has a comment keyword (boolean).
-    commentedp: bool,
+    pub commentedp: bool,
 
     /// Headline's DEADLINE reference, if any (timestamp object or nil).
-    deadline: Option<TimestampData<'a>>,
+    pub deadline: Option<TimestampData<'a>>,
 
     /// Non_nil if the headline is a footnote section (boolean).
-    footnote_section_p: bool,
+    pub footnote_section_p: bool,
 
     /// Reduced level of the headline (integer).
-    level: usize,
+    pub level: usize,
 
     /// Number of blank lines between the headline
     /// and the first non_blank line of its contents (integer).
-    pre_blank: usize,
+    pub pre_blank: usize,
 
     /// Headline's priority, as a character (integer).
-    priority: usize,
+    pub priority: Option<char>,
+
+    /// Properties set in the property drawer of the headline,
+    /// in order of appearance.
+    pub properties: Vec<NodePropertyData<'a>>,
 
     /// Non_nil if the headline contains a quote keyword (boolean).
-    quotedp: bool,
+    pub quotedp: bool,
 
     /// Raw headline's text, without the stars and the tags (string).
-    raw_value: &'a str,
+    /// As in Org, TODO keyword, priority cookie and COMMENT keyword are
+    /// left out too, while the rest of the title is kept verbatim,
+    /// emphasis markers and statistics cookies included. Blanks around
+    /// it are trimmed.
+    pub raw_value: &'a str,
 
     /// Headline's SCHEDULED reference, if any (timestamp object or nil).
-    scheduled: Option<TimestampData<'a>>,
+    pub scheduled: Option<TimestampData<'a>>,
 
-    /// Headline's tags, if any, without
-    /// the archive tag. (list of strings).
-    tags: Vec<Tag<'a>>,
+    /// Headline's tags, if any (list of strings).
+    pub tags: Vec<Tag<'a>>,
 
     /// Parsed headline's text, without the stars
     /// and the tags (secondary string).
-    title: &'a str,
+    /// When secondary strings are not parsed holds a single plain text object
+    pub title: Vec<Handle<'a>>,
 
     /// Headline's TODO keyword without quote and comment
     /// strings, if any (string or nil).
-    /// also used instead of todo-type
-    todo_keyword: TodoKeyword,
+    pub todo_keyword: Option<&'a str>,
+
+    /// Type of headline's TODO keyword, if any (symbol done, todo).
+    pub todo_type: Option<TodoType>,
+}
+
+impl<'a> HeadlineData<'a> {
+    /// Timestamp following SCHEDULED keyword in the planning line
+    /// of the headline, if any
+    pub fn scheduled(&self) -> Option<&TimestampData<'a>> {
+        self.scheduled.as_ref()
+    }
+
+    /// Timestamp following DEADLINE keyword in the planning line
+    /// of the headline, if any
+    pub fn deadline(&self) -> Option<&TimestampData<'a>> {
+        self.deadline.as_ref()
+    }
+
+    /// Timestamp following CLOSED keyword in the planning line
+    /// of the headline, if any
+    pub fn closed(&self) -> Option<&TimestampData<'a>> {
+        self.closed.as_ref()
+    }
 }
 
 #[derive(Debug)]
@@ -184,7 +208,7 @@ pub struct InlineTaskData<'a> {
     priority: usize,
 
     /// Raw inlinetask's text, without the stars and the tags (string).
-    raw_value: &'a str,
+    pub raw_value: &'a str,
 
     /// Inlinetask's SCHEDULED reference, if any (timestamp object or nil).
     scheduled: Option<TimestampData<'a>>,
@@ -197,8 +221,10 @@ pub struct InlineTaskData<'a> {
     title: &'a str,
 
     /// Inlinetask's TODO keyword, if any (string or nil).
+    todo_keyword: Option<&'a str>,
+
     /// Type of inlinetask's TODO keyword, if any (symbol done, todo).
-    todo_keyword: Option<TodoKeyword>,
+    todo_type: Option<TodoType>,
 }
 
 // A planning is an element with the following pattern:
@@ -213,39 +239,1017 @@ pub struct InlineTaskData<'a> {
 //
 // In particular, no blank line is allowed between PLANNING and HEADLINE.
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct NodePropertyData<'a> {
-    key: &'a str,
-    value: &'a str,
+    /// Property's name, as written (string).
+    pub key: &'a str,
+
+    /// Property's value, empty if there is none (string).
+    pub value: &'a str,
 }
 
-#[derive(Debug)]
-pub struct Tag<'a>(&'a str);
+impl<'a> NodePropertyData<'a> {
+    /// Property's name, without the `+` of a value appended
+    /// to the one of a previous property, e.g. `:VAR+: b`
+    pub fn name(&self) -> &'a str {
+        if self.is_append() {
+            &self.key[..self.key.len() - 1]
+        } else {
+            self.key
+        }
+    }
 
-#[derive(Debug)]
-pub enum TodoKeyword {
-    TODO,
-    DONE,
+    /// Whether the value is appended to the previous ones
+    pub fn is_append(&self) -> bool {
+        self.key.len() > 1 && self.key.ends_with('+')
+    }
+}
+
+impl<'a> HeadlineData<'a> {
+    /// Returns the value of property `key` set in the property drawer
+    /// of the headline, if any. Keys are compared ignoring case.
+    ///
+    /// Values of `KEY+` properties are appended to the one of `KEY`,
+    /// separated with a space, in order of appearance: `:VAR: a` then
+    /// `:VAR+: b` make `a b`.
+    /// elisp: `org--property-local-values`
+    pub fn get_property(&self, key: &str) -> Option<Cow<'a, str>> {
+        let mut values = self
+            .properties
+            .iter()
+            .filter(|property| property.name().eq_ignore_ascii_case(key))
+            .map(|property| property.value);
+        let first = values.next()?;
+        Some(values.fold(Cow::from(first), |acc, value| {
+            Cow::from(format!("{} {}", acc, value))
+        }))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Tag<'a>(pub &'a str);
+
+/// Type of TODO keyword
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TodoType {
+    Todo,
+    Done,
+}
+
+/// TODO keywords recognized in headlines, in two sets: keywords
+/// of not yet done states and keywords of done states.
+/// elisp: `org-todo-keywords`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TodoKeywords {
+    pub todo: Vec<String>,
+    pub done: Vec<String>,
+}
+
+impl Default for TodoKeywords {
+    fn default() -> Self {
+        TodoKeywords::from_sequence("TODO | DONE")
+    }
+}
+
+impl TodoKeywords {
+    /// Reads a sequence of keywords as written after `#+TODO:`,
+    /// e.g. `TODO WAITING(w@) | DONE CANCELLED(c)`. Keywords before
+    /// the bar are of `todo` type and keywords after it are of `done`
+    /// type. Without a bar, the last keyword is the only `done` one.
+    /// Fast access keys in parentheses are ignored.
+    pub fn from_sequence(sequence: &str) -> TodoKeywords {
+        let keywords = |part: &str| -> Vec<String> {
+            part.split_whitespace()
+                .map(|word| word.split('(').next().unwrap().to_string())
+                .filter(|word| !word.is_empty())
+                .collect()
+        };
+        match sequence.find('|') {
+            Some(bar) => TodoKeywords {
+                todo: keywords(&sequence[..bar]),
+                done: keywords(&sequence[bar + 1..]),
+            },
+            None => {
+                let mut todo = keywords(sequence);
+                let done = todo.pop().into_iter().collect();
+                TodoKeywords { todo, done }
+            }
+        }
+    }
+
+    /// Returns type of `keyword`, or None if it is not a TODO keyword.
+    /// Keywords are case sensitive.
+    pub fn todo_type(&self, keyword: &str) -> Option<TodoType> {
+        if self.todo.iter().any(|k| k == keyword) {
+            Some(TodoType::Todo)
+        } else if self.done.iter().any(|k| k == keyword) {
+            Some(TodoType::Done)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the keyword `s` starts with, if any, along with its type
+    fn find_at_start<'s>(&self, s: &'s str) -> Option<(&'s str, TodoType)> {
+        let todo = self.todo.iter().map(|k| (k, TodoType::Todo));
+        let done = self.done.iter().map(|k| (k, TodoType::Done));
+        todo.chain(done)
+            .find(|(k, _)| starts_with_word(s, k))
+            .map(|(k, todo_type)| (&s[..k.len()], todo_type))
+    }
+}
+
+/// Number of bytes taken by spaces and tabs at the beginning of `s`
+fn skip_blanks(s: &str) -> usize {
+    s.len() - s.trim_start_matches(|c| c == ' ' || c == '\t').len()
+}
+
+/// Checks if `s` starts with `word` followed by a space or the end of a line
+fn starts_with_word(s: &str, word: &str) -> bool {
+    s.starts_with(word) && s[word.len()..].chars().next().map_or(true, |c| c == ' ')
+}
+
+/// Returns node properties associated to the headline which line ends
+/// at `line_end`, read from the property drawer following it, possibly
+/// after a planning line.
+///
+/// elisp: `org-element--get-node-properties`
+fn node_properties(input: &str, line_end: usize) -> Vec<NodePropertyData> {
+    let next_line = |pos: usize| input[pos..].find('\n').map_or(input.len(), |p| pos + p + 1);
+    let mut pos = next_line(line_end);
+    if REGEX_PLANNING_LINE.is_match(&input[pos..]) {
+        pos = next_line(pos);
+    }
+    let drawer_end = match REGEX_PROPERTY_DRAWER.find(&input[pos..]) {
+        Some(m) if m.start() == 0 => pos + m.end(),
+        _ => return vec![],
+    };
+
+    let mut properties = vec![];
+    pos = next_line(pos);
+    while pos < drawer_end {
+        let end = next_line(pos).min(drawer_end);
+        let line = input[pos..end].trim_end_matches('\n');
+        if REGEX_DRAWER_END.is_match(line) {
+            break;
+        }
+        if let Some(cap) = REGEX_NODE_PROPERTY.captures(line) {
+            properties.push(NodePropertyData {
+                key: cap.get(1).unwrap().as_str(),
+                value: cap.get(2).map_or("", |m| m.as_str()),
+            });
+        }
+        pos = next_line(pos);
+    }
+    properties
+}
+
+/// Returns headlines of the tree under `root`, in document order.
+/// Headlines tagged with the archive tag of `settings` are left out,
+/// along with their whole subtree, like agenda views skip them.
+///
+/// elisp: `org-agenda-skip-archived-trees`
+pub fn headlines_excluding_archived<'a>(
+    root: &SyntaxNode<'a>,
+    settings: &BufferSettings,
+) -> Vec<Handle<'a>> {
+    fn walk<'a>(node: &SyntaxNode<'a>, archive_tag: &str, acc: &mut Vec<Handle<'a>>) {
+        for child in node.children.borrow().iter() {
+            if let Syntax::Headline(h) = &child.data {
+                if h.tags.iter().any(|t| t.0 == archive_tag) {
+                    continue;
+                }
+                acc.push(child.clone());
+                walk(child, archive_tag, acc);
+            }
+        }
+    }
+    let mut acc = vec![];
+    walk(root, settings.archive_tag(), &mut acc);
+    acc
+}
+
+/// Returns the title of the headline starting `line`, as headline
+/// parser's `raw_value` does: stars, TODO keyword, priority cookie,
+/// COMMENT keyword and tags are left out, blanks around are trimmed.
+pub(crate) fn headline_title<'l>(line: &'l str, keywords: &TodoKeywords) -> &'l str {
+    let mut pos = line.bytes().take_while(|&b| b == b'*').count();
+    pos += skip_blanks(&line[pos..]);
+    if let Some((k, _)) = keywords.find_at_start(&line[pos..]) {
+        pos += k.len();
+        pos += skip_blanks(&line[pos..]);
+    }
+    if let Some(m) = REGEX_HEADLINE_PRIORITY.find(&line[pos..]) {
+        pos += m.end();
+    }
+    if starts_with_word(&line[pos..], ORG_COMMENT_STRING) {
+        pos += ORG_COMMENT_STRING.len();
+    }
+    let end = REGEX_HEADLINE_TAGS
+        .find(&line[pos..])
+        .map_or(line.len(), |m| pos + m.start());
+    line[pos..end].trim()
 }
 
 impl<'a> Parser<'a> {
-    // TODO implement headline_parser
+    /// Parse a headline.
+    ///
+    /// Return a list whose CAR is `headline' and CDR is a plist
+    /// containing `:raw-value', `:title', `:begin', `:end',
+    /// `:pre-blank', `:contents-begin' and `:contents-end', `:level',
+    /// `:priority', `:tags', `:todo-keyword',`:todo-type', `:scheduled',
+    /// `:deadline', `:closed', `:archivedp', `:commentedp'
+    /// `:footnote-section-p', `:post-blank' and `:post-affiliated'
+    /// keywords.
+    ///
+    /// The plist also contains any property set in the property drawer,
+    /// with its name in upper cases and colons added at the
+    /// beginning (e.g., `:CUSTOM_ID').
+    ///
+    /// LIMIT is a buffer position bounding the search.
+    ///
+    /// When RAW-SECONDARY-P is non-nil, headline's title will not be
+    /// parsed as a secondary string, but as a plain string instead.
+    ///
+    /// Assume point is at beginning of the headline."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L961
+    /// (defun org-element-headline-parser (limit &optional raw-secondary-p)
+    ///
+    /// Headline ends where its subtree does, so no limit is needed.
+    /// Title is parsed into objects when granularity is `Object`.
     pub fn headline_parser(&self) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let begin = c.pos();
+        debug_assert!(
+            c.is_bol() && self.input[begin..].starts_with('*'),
+            "headlines start at column 0"
+        );
+        let line_end = self.input[begin..]
+            .find('\n')
+            .map_or(self.input.len(), |p| begin + p);
+        let line = &self.input[begin..line_end];
+
+        let level = line.bytes().take_while(|&b| b == b'*').count();
+        let mut pos = begin + level;
+        pos += skip_blanks(&self.input[pos..line_end]);
+
+        let todo = self.todo_keywords.find_at_start(&self.input[pos..line_end]);
+        if let Some((k, _)) = todo {
+            pos += k.len();
+            pos += skip_blanks(&self.input[pos..line_end]);
+        }
+        let todo_keyword = todo.map(|(k, _)| k);
+        let todo_type = todo.map(|(_, todo_type)| todo_type);
+
+        let priority = REGEX_HEADLINE_PRIORITY
+            .captures(&self.input[pos..line_end])
+            .map(|cap| {
+                pos += cap.get(0).unwrap().end();
+                cap[1].chars().next().unwrap()
+            });
+
+        let commentedp = starts_with_word(&self.input[pos..line_end], ORG_COMMENT_STRING);
+        if commentedp {
+            pos += ORG_COMMENT_STRING.len();
+        }
+
+        let title_start = pos;
+        let (title_end, tags) = match REGEX_HEADLINE_TAGS.captures(&self.input[pos..line_end]) {
+            Some(cap) => {
+                let tags = cap
+                    .get(1)
+                    .unwrap()
+                    .as_str()
+                    .split(':')
+                    .filter(|t| !t.is_empty())
+                    .map(Tag)
+                    .collect();
+                (pos + cap.get(0).unwrap().start(), tags)
+            }
+            None => (line_end, vec![]),
+        };
+        let title = &self.input[title_start..title_end];
+        let raw_value = title.trim();
+        let archivedp = tags.iter().any(|t: &Tag| t.0 == ORG_ARCHIVE_TAG);
+        let footnote_section_p = raw_value == ORG_FOOTNOTE_SECTION;
+
+        // (org-end-of-subtree t t)
+        let end = loop {
+            match c.next_headline() {
+                Some(next)
+                    if self.input[next..]
+                        .bytes()
+                        .take_while(|&b| b == b'*')
+                        .count()
+                        <= level =>
+                {
+                    break next
+                }
+                Some(_) => continue,
+                None => break self.input.len(),
+            }
+        };
+
+        c.set(begin);
+        c.goto_next_line();
+        c.skip_chars_forward(" \r\t\n", Some(end));
+        let contents_begin = if c.pos() < end {
+            Some(c.goto_line_begin())
+        } else {
+            None
+        };
+        let contents_end = contents_begin.map(|_| {
+            // (goto-char end) (skip-chars-backward " \r\t\n") (line-beginning-position 2)
+            let last = self.input[..end]
+                .trim_end_matches(|ch| " \r\t\n".contains(ch))
+                .len();
+            self.input[last..end]
+                .find('\n')
+                .map_or(end, |p| last + p + 1)
+        });
+        // Planning and property drawer are part of the contents,
+        // so blank lines are only counted right after the headline.
+        let pre_blank = contents_begin.map_or(0, |cb| c.count_lines(begin, cb) - 1);
+        let post_blank = match contents_end {
+            Some(contents_end) => c.count_lines(contents_end, end),
+            None => c.count_lines(begin, end) - 1,
+        };
+        drop(c);
+
+        let raw_title_start = title_start + (title.len() - title.trim_start().len());
+        let raw_title_end = raw_title_start + raw_value.len();
+        let title = if self.granularity == ParseGranularity::Object {
+            self.parse_objects(raw_title_start, raw_title_end, |that| {
+                SyntaxT::Headline.can_contain(that)
+            })
+        } else {
+            vec![Rc::new(SyntaxNode::new(
+                Syntax::PlainText(raw_value),
+                Interval {
+                    start: raw_title_start,
+                    end: raw_title_end,
+                },
+                None,
+                0,
+                None,
+            ))]
+        };
+
+        // Planning line, if any, has to follow the headline line.
+        let planning_begin = (line_end + 1).min(self.input.len());
+        let planning = if REGEX_PLANNING_LINE.is_match(&self.input[planning_begin..]) {
+            let planning_end = self.input[planning_begin..]
+                .find('\n')
+                .map_or(self.input.len(), |p| planning_begin + p);
+            self.planning_data(planning_begin, planning_end)
+        } else {
+            PlanningData::default()
+        };
+
+        let data = HeadlineData {
+            archivedp,
+            closed: planning.closed,
+            commentedp,
+            deadline: planning.deadline,
+            footnote_section_p,
+            level,
+            pre_blank,
+            priority,
+            properties: node_properties(self.input, line_end),
+            quotedp: false,
+            raw_value,
+            scheduled: planning.scheduled,
+            tags,
+            title,
+            todo_keyword,
+            todo_type,
+        };
+
+        SyntaxNode::new(
+            Syntax::Headline(Box::new(data)),
+            Interval { start: begin, end },
+            contents_begin.map(|start| Interval {
+                start,
+                end: contents_end.unwrap(),
+            }),
+            post_blank,
+            None,
+        )
     }
 
-    // TODO implement inlinetask_parser
+    /// Parse an inline task.
+    /// (defun org-element-inlinetask-parser (limit &optional raw-secondary-p)
+    ///
+    /// Inline tasks are not supported: as with `org-inlinetask` not
+    /// loaded, they are parsed as headlines, which `current_element`
+    /// always recognizes first.
     pub fn inlinetask_parser(&self, limit: usize, raw_secondary_p: bool) -> SyntaxNode<'a> {
-        unimplemented!()
+        self.headline_parser()
     }
 
-    // TODO implement property_drawer_parser
+    /// Parse a property drawer.
+    ///
+    /// LIMIT bounds the search.
+    ///
+    /// Return a list whose car is `property-drawer' and cdr is a plist
+    /// containing `:begin', `:end', `:contents-begin', `:contents-end',
+    /// `:post-blank' and `:post-affiliated' keywords.
+    ///
+    /// Assume point is at the beginning of the property drawer."
+    /// (defun org-element-property-drawer-parser (limit)
     pub fn property_drawer_parser(&self, limit: usize) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let begin = c.pos();
+        let contents_begin = c.line_beginning_position(Some(2));
+        c.set(contents_begin);
+        c.re_search_forward(&*REGEX_DRAWER_END, Some(limit));
+        let drawer_end_line = c.goto_line_begin();
+        let content_location = if drawer_end_line > contents_begin {
+            Some(Interval {
+                start: contents_begin,
+                end: drawer_end_line,
+            })
+        } else {
+            None
+        };
+        c.goto_next_line();
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::PropertyDrawer,
+            Interval { start: begin, end },
+            content_location,
+            post_blank,
+            None,
+        )
     }
 
-    // TODO implement node_property_parser
+    /// Parse a node-property at point.
+    ///
+    /// LIMIT bounds the search.
+    ///
+    /// Return a list whose car is `node-property' and cdr is a plist
+    /// containing `:key', `:value', `:begin', `:end', `:post-blank' and
+    /// `:post-affiliated' keywords."
+    /// (defun org-element-node-property-parser (limit)
     pub fn node_property_parser(&self, limit: usize) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let begin = c.pos();
+        let (key, value) = match c.capturing_at(&*REGEX_NODE_PROPERTY) {
+            Some(cap) => (
+                cap.get(1).unwrap().as_str(),
+                cap.get(2).map_or("", |m| m.as_str()),
+            ),
+            None => ("", ""),
+        };
+        let end = c.goto_next_line().min(limit);
+
+        SyntaxNode::new(
+            Syntax::NodeProperty(Box::new(NodePropertyData { key, value })),
+            Interval { start: begin, end },
+            None,
+            0,
+            None,
+        )
+    }
+}
+
+mod test {
+    use super::{headlines_excluding_archived, HeadlineData, Tag, TodoKeywords, TodoType};
+    use crate::cursor::Cursor;
+    use crate::data::{Interval, Syntax, SyntaxNode, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+    use crate::regexps::{REGEX_HEADLINE_MULTILINE, REGEX_HEADLINE_SHORT};
+    use crate::settings::BufferSettings;
+
+    fn headline<'a, 'b>(node: &'b SyntaxNode<'a>) -> &'b HeadlineData<'a> {
+        match &node.data {
+            Syntax::Headline(h) => h,
+            _ => panic!("expected headline"),
+        }
+    }
+
+    #[test]
+    fn headline_parser() {
+        let text = "** TODO [#A] A *bold* title :tag:work:\nBody\n\n* Next";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        assert_eq!(Interval { start: 0, end: 45 }, node.location);
+        assert_eq!(Some(Interval { start: 39, end: 44 }), node.content_location);
+        assert_eq!(1, node.post_blank);
+
+        let h = headline(&node);
+        assert_eq!(2, h.level);
+        assert_eq!(Some("TODO"), h.todo_keyword);
+        assert_eq!(Some(TodoType::Todo), h.todo_type);
+        assert_eq!(Some('A'), h.priority);
+        assert_eq!(vec![Tag("tag"), Tag("work")], h.tags);
+        assert!(!h.commentedp);
+        assert!(!h.archivedp);
+    }
+
+    #[test]
+    fn headline_without_title() {
+        let text = "*** \nBody\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        assert_eq!(Interval { start: 0, end: 10 }, node.location);
+        let h = headline(&node);
+        assert_eq!(3, h.level);
+        assert_eq!("", h.raw_value);
+        assert!(h.title.is_empty());
+        assert!(h.tags.is_empty());
+
+        let text = "* TODO [#B]";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        let h = headline(&node);
+        assert_eq!(Some("TODO"), h.todo_keyword);
+        assert_eq!(Some('B'), h.priority);
+        assert_eq!("", h.raw_value);
+    }
+
+    #[test]
+    fn title_with_colons() {
+        let text = "* Meeting 10:30 at :room: 3 :work:";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        let h = headline(&node);
+        assert_eq!("Meeting 10:30 at :room: 3", h.raw_value);
+        assert_eq!(vec![Tag("work")], h.tags);
+
+        let text = "* Ratio 1:2:";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let h_node = parser.headline_parser();
+        let h = headline(&h_node);
+        assert_eq!("Ratio 1:2:", h.raw_value);
+        assert!(h.tags.is_empty());
+    }
+
+    #[test]
+    fn headline_planning() {
+        let text = "* TODO Task\nDEADLINE: <2024-01-20 Sat> SCHEDULED: <2024-01-15 Mon +1w>\n\
+                    Body\n* Plain\nBody SCHEDULED: <2024-01-15 Mon>\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let children = root.children.borrow();
+
+        let h = headline(&children[0]);
+        let scheduled = h.scheduled().unwrap();
+        assert_eq!("<2024-01-15 Mon +1w>", scheduled.raw_value);
+        assert_eq!(Some(1), scheduled.repeater_value);
+        assert_eq!(20, h.deadline().unwrap().day_start);
+        assert!(h.closed().is_none());
+
+        let section = &children[0].children.borrow()[0];
+        let planning = &section.children.borrow()[0];
+        match &planning.data {
+            Syntax::Planning(p) => {
+                assert_eq!(15, p.scheduled.as_ref().unwrap().day_start);
+                assert_eq!(20, p.deadline.as_ref().unwrap().day_start);
+            }
+            _ => panic!("expected planning"),
+        }
+        assert_eq!(Interval { start: 12, end: 71 }, planning.location);
+
+        let h = headline(&children[1]);
+        assert!(h.scheduled().is_none());
+        assert!(h.deadline().is_none());
+        assert!(h.closed().is_none());
+    }
+
+    #[test]
+    fn headline_pre_blank() {
+        let text = "* Headline\n\nParagraph\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        assert_eq!(1, headline(&node).pre_blank);
+        assert_eq!(12, node.content_location.unwrap().start);
+
+        let text = "* Headline\n:PROPERTIES:\n:ID: 1\n:END:\n\nParagraph\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        assert_eq!(0, headline(&node).pre_blank);
+        assert_eq!(11, node.content_location.unwrap().start);
+
+        let text = "* Headline\n\n\n** Child";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        assert_eq!(2, headline(&node).pre_blank);
+
+        let text = "* Empty\n\n* Next";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        assert_eq!(0, headline(&node).pre_blank);
+        assert_eq!(None, node.content_location);
+        assert_eq!(1, node.post_blank);
+    }
+
+    #[test]
+    fn headline_raw_value() {
+        let text = "* A *bold* title :tag:";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        assert_eq!(Some("A *bold* title"), node.raw_value());
+
+        let title = &headline(&node).title;
+        assert_eq!(3, title.len());
+        match title[0].data {
+            Syntax::PlainText(t) => assert_eq!("A ", t),
+            _ => panic!("expected plain text"),
+        }
+        match title[1].data {
+            Syntax::Bold(_) => {}
+            _ => panic!("expected bold"),
+        }
+        assert_eq!(Interval { start: 4, end: 11 }, title[1].location);
+        match title[1].children.borrow()[0].data {
+            Syntax::PlainText(t) => assert_eq!("bold", t),
+            _ => panic!("expected plain text"),
+        }
+        match title[2].data {
+            Syntax::PlainText(t) => assert_eq!("title", t),
+            _ => panic!("expected plain text"),
+        }
+
+        // Title is not parsed with coarser granularity
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.headline_parser();
+        let title = &headline(&node).title;
+        assert_eq!(1, title.len());
+        match title[0].data {
+            Syntax::PlainText(t) => assert_eq!("A *bold* title", t),
+            _ => panic!("expected plain text"),
+        }
+    }
+
+    #[test]
+    fn headline_tab_separator() {
+        let text = "*\tHeadline\n**  \tTitle\n";
+        let mut cursor = Cursor::new(text, 0);
+        let m = cursor.looking_at(&*REGEX_HEADLINE_SHORT).unwrap();
+        assert_eq!(2, m.end());
+        assert_eq!("Headline", &text[m.end()..10]);
+
+        cursor.goto_next_line();
+        let m = cursor.looking_at(&*REGEX_HEADLINE_SHORT).unwrap();
+        assert_eq!(5, m.end());
+        assert!(cursor.on_headline());
+    }
+
+    #[test]
+    fn indented_headline() {
+        let text = "   *** not a headline\n* Headline\n";
+        let mut cursor = Cursor::new(text, 0);
+        for pos in 0..7 {
+            cursor.set(pos);
+            assert!(!cursor.on_headline(), "at {}", pos);
+        }
+        cursor.set(0);
+        assert!(cursor.looking_at(&*REGEX_HEADLINE_SHORT).is_none());
+        assert_eq!(Some(22), cursor.next_headline());
+
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let children = root.children.borrow();
+        assert_eq!(SyntaxT::Section, SyntaxT::from(&children[0].data));
+        let paragraph = &children[0].children.borrow()[0];
+        assert_eq!(SyntaxT::Paragraph, SyntaxT::from(&paragraph.data));
+        assert_eq!(Interval { start: 0, end: 22 }, paragraph.location);
+        assert_eq!(SyntaxT::Headline, SyntaxT::from(&children[1].data));
+        assert_eq!(22, children[1].location.start);
+    }
+
+    #[test]
+    fn headline_requires_separator() {
+        let mut cursor = Cursor::new("***\nText", 0);
+        assert!(cursor.looking_at(&*REGEX_HEADLINE_SHORT).is_none());
+        assert!(!cursor.on_headline());
+        assert!(REGEX_HEADLINE_MULTILINE.find("***\nText").is_none());
+        assert_eq!(None, cursor.next_headline());
+
+        let mut cursor = Cursor::new("Text\n*\tHeadline", 0);
+        assert_eq!(Some(5), cursor.next_headline());
+        assert_eq!(None, cursor.next_headline());
+        assert_eq!(15, cursor.pos());
+    }
+
+    #[test]
+    fn headline_sections() {
+        let kinds = |node: &SyntaxNode| -> Vec<(SyntaxT, usize, usize)> {
+            node.children
+                .borrow()
+                .iter()
+                .map(|child| {
+                    (
+                        SyntaxT::from(&child.data),
+                        child.location.start,
+                        child.location.end,
+                    )
+                })
+                .collect()
+        };
+
+        let text = "* One\n\nBody\n\n** Two\nSub body\n*** Three\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let children = root.children.borrow();
+        assert_eq!(1, children.len());
+        let one = &children[0];
+        assert_eq!(
+            vec![(SyntaxT::Section, 7, 13), (SyntaxT::Headline, 13, 39)],
+            kinds(one)
+        );
+        let section = &one.children.borrow()[0];
+        assert_eq!(
+            Some(Interval { start: 7, end: 12 }),
+            section.content_location
+        );
+        assert_eq!(1, section.post_blank);
+
+        let two = &one.children.borrow()[1];
+        assert_eq!(
+            vec![(SyntaxT::Section, 20, 29), (SyntaxT::Headline, 29, 39)],
+            kinds(two)
+        );
+        let three = &two.children.borrow()[1];
+        assert!(three.children.borrow().is_empty());
+
+        // No section when a sub-headline follows immediately
+        let text = "* One\n** Two\ntext\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let one = &root.children.borrow()[0];
+        assert_eq!(vec![(SyntaxT::Headline, 6, 18)], kinds(one));
+        let two = &one.children.borrow()[0];
+        assert_eq!(vec![(SyntaxT::Section, 13, 18)], kinds(two));
+    }
+
+    #[test]
+    fn todo_type() {
+        let text = "* TODO a\n* DONE b\n* WAITING c\n* CANCELLED d\n* Plain e\n* TODOS f\n";
+        let types = |parser: &Parser| -> Vec<(Option<String>, Option<TodoType>, String)> {
+            let root = parser.parse_buffer();
+            let types = root
+                .children
+                .borrow()
+                .iter()
+                .map(|node| {
+                    let h = headline(node);
+                    (
+                        h.todo_keyword.map(String::from),
+                        h.todo_type,
+                        h.raw_value.to_string(),
+                    )
+                })
+                .collect();
+            types
+        };
+
+        let parser = Parser::new(text, ParseGranularity::Element);
+        assert_eq!(
+            vec![
+                (Some("TODO".into()), Some(TodoType::Todo), "a".into()),
+                (Some("DONE".into()), Some(TodoType::Done), "b".into()),
+                (None, None, "WAITING c".into()),
+                (None, None, "CANCELLED d".into()),
+                (None, None, "Plain e".into()),
+                (None, None, "TODOS f".into()),
+            ],
+            types(&parser)
+        );
+
+        let mut parser = Parser::new(text, ParseGranularity::Element);
+        parser.todo_keywords = TodoKeywords::from_sequence("TODO WAITING(w@) | DONE(d) CANCELLED");
+        let types = types(&parser);
+        assert_eq!(
+            vec![
+                Some(TodoType::Todo),
+                Some(TodoType::Done),
+                Some(TodoType::Todo),
+                Some(TodoType::Done),
+                None,
+                None
+            ],
+            types.iter().map(|t| t.1).collect::<Vec<_>>()
+        );
+        assert_eq!("c", types[2].2);
+
+        let keywords = TodoKeywords::from_sequence("NEXT LATER FINISHED");
+        assert_eq!(vec!["NEXT", "LATER"], keywords.todo);
+        assert_eq!(vec!["FINISHED"], keywords.done);
+        assert_eq!(Some(TodoType::Done), keywords.todo_type("FINISHED"));
+        assert_eq!(None, keywords.todo_type("next"));
+    }
+
+    #[test]
+    fn raw_value_like_org() {
+        let raw_value = |text: &str| {
+            let parser = Parser::new(text, ParseGranularity::Element);
+            let node = parser.headline_parser();
+            let h = headline(&node);
+            (h.raw_value.to_string(), h.commentedp)
+        };
+        assert_eq!(
+            ("Fix *the* [2/3] bug".to_string(), true),
+            raw_value("* TODO [#A] COMMENT Fix *the* [2/3] bug   :a:b:\n")
+        );
+        assert_eq!(
+            ("[50%] /done/ ~now~".to_string(), false),
+            raw_value("** DONE [50%] /done/ ~now~")
+        );
+        // COMMENT must be a word of its own, and is case sensitive
+        assert_eq!(
+            ("COMMENTARY on x".to_string(), false),
+            raw_value("* COMMENTARY on x")
+        );
+        assert_eq!(
+            ("comment lower".to_string(), false),
+            raw_value("* comment lower")
+        );
+        // Tags are only recognized at the end of the line
+        assert_eq!(
+            ("Title with :colon: inside".to_string(), false),
+            raw_value("* Title with :colon: inside :tag:")
+        );
+        assert_eq!((String::new(), true), raw_value("* TODO COMMENT"));
+    }
+
+    #[test]
+    fn commented_and_archived() {
+        let flags = |text: &str| {
+            let parser = Parser::new(text, ParseGranularity::Element);
+            let node = parser.headline_parser();
+            let h = headline(&node);
+            (h.commentedp, h.archivedp, h.raw_value.to_string())
+        };
+        assert_eq!((true, false, "foo".to_string()), flags("* COMMENT foo"));
+        assert_eq!(
+            (true, false, "bar".to_string()),
+            flags("* TODO COMMENT bar\n")
+        );
+        assert_eq!((true, false, String::new()), flags("* COMMENT\nBody"));
+        assert_eq!((false, true, "foo".to_string()), flags("* foo :ARCHIVE:"));
+        assert_eq!(
+            (true, true, "old".to_string()),
+            flags("** DONE COMMENT old :x:ARCHIVE:")
+        );
+        // Only the exact tag archives a headline
+        assert_eq!((false, false, "foo".to_string()), flags("* foo :archive:"));
+        assert_eq!((false, false, "Comment".to_string()), flags("* Comment"));
+        assert_eq!(
+            (false, false, "COMMENT:x".to_string()),
+            flags("* COMMENT:x")
+        );
+    }
+
+    #[test]
+    fn skip_archived_trees() {
+        let text = concat!(
+            "#+ARCHIVE: %s_done::\n",
+            "* Active\n",
+            "** Old :ARCHIVE:\n",
+            "*** Older\n",
+            "** Current\n",
+            "* Done :x:OLD:\n",
+            "** Done child\n",
+        );
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let titles = |settings: &BufferSettings| -> Vec<&str> {
+            headlines_excluding_archived(&root, settings)
+                .iter()
+                .map(|node| headline(node).raw_value)
+                .collect()
+        };
+
+        let mut settings = BufferSettings::collect(text);
+        assert_eq!(Some("%s_done::"), settings.archive_location.as_deref());
+        assert_eq!(
+            vec!["Active", "Current", "Done", "Done child"],
+            titles(&settings)
+        );
+
+        settings.archive_tag = Some("OLD".to_string());
+        assert_eq!(vec!["Active", "Old", "Older", "Current"], titles(&settings));
+    }
+
+    #[test]
+    fn title_statistics_cookie() {
+        let text = "* TODO Tasks [1/3] and [50%] :work:\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.headline_parser();
+        let h = headline(&node);
+        assert_eq!("Tasks [1/3] and [50%]", h.raw_value);
+        let types: Vec<_> = h.title.iter().map(|o| SyntaxT::from(&o.data)).collect();
+        assert_eq!(
+            vec![
+                SyntaxT::PlainText,
+                SyntaxT::StatisticsCookie,
+                SyntaxT::PlainText,
+                SyntaxT::StatisticsCookie
+            ],
+            types
+        );
+        assert_eq!(Interval { start: 13, end: 19 }, h.title[1].location);
+        match &h.title[1].data {
+            Syntax::StatisticsCookie(cookie) => {
+                assert_eq!("[1/3]", cookie.value);
+                assert_eq!(Some((1, 3)), cookie.fraction());
+                assert_eq!(None, cookie.percent());
+            }
+            _ => panic!("expected statistics cookie"),
+        }
+        match &h.title[3].data {
+            Syntax::StatisticsCookie(cookie) => {
+                assert_eq!(None, cookie.fraction());
+                assert_eq!(Some(50), cookie.percent());
+            }
+            _ => panic!("expected statistics cookie"),
+        }
+
+        let objects =
+            Parser::parse_secondary_string("[/] [%] [1/x]", |t| SyntaxT::Headline.can_contain(t));
+        let types: Vec<_> = objects.iter().map(|o| SyntaxT::from(&o.data)).collect();
+        assert_eq!(
+            vec![
+                SyntaxT::StatisticsCookie,
+                SyntaxT::StatisticsCookie,
+                SyntaxT::PlainText
+            ],
+            types
+        );
+        match &objects[0].data {
+            Syntax::StatisticsCookie(cookie) => assert_eq!(Some((0, 0)), cookie.fraction()),
+            _ => panic!("expected statistics cookie"),
+        }
+    }
+
+    #[test]
+    fn appended_properties() {
+        let text = "* H\n:PROPERTIES:\n:VAR: a\n:var+: b c\n:OTHER+: d\n:END:\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let node = root.children.borrow()[0].clone();
+        let h = headline(&node);
+        assert_eq!(Some("a b c"), h.get_property("var").as_deref());
+        assert_eq!(Some("d"), h.get_property("OTHER").as_deref());
+        assert_eq!(None, h.get_property("VAR+"));
+
+        // Each line is a node property of its own
+        let section = node.children.borrow()[0].clone();
+        let drawer = section.children.borrow()[0].clone();
+        let properties: Vec<_> = drawer
+            .children
+            .borrow()
+            .iter()
+            .map(|p| match &p.data {
+                Syntax::NodeProperty(p) => (p.key, p.name(), p.is_append(), p.value),
+                _ => panic!("expected node property"),
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("VAR", "VAR", false, "a"),
+                ("var+", "var", true, "b c"),
+                ("OTHER+", "OTHER", true, "d"),
+            ],
+            properties
+        );
+    }
+
+    #[test]
+    fn property_drawers() {
+        fn first<'a>(node: &SyntaxNode<'a>) -> std::rc::Rc<SyntaxNode<'a>> {
+            node.children.borrow()[0].clone()
+        }
+
+        // Empty property drawer
+        let text = "* H\n:PROPERTIES:\n:END:\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let drawer = first(&first(&first(&root)));
+        assert_eq!(SyntaxT::PropertyDrawer, SyntaxT::from(&drawer.data));
+        assert_eq!(Interval { start: 4, end: 23 }, drawer.location);
+        assert_eq!(None, drawer.content_location);
+        assert!(drawer.children.borrow().is_empty());
+
+        // Property with an empty value, in the zeroth section
+        let text = ":PROPERTIES:\n:EMPTY:\n:my-key_2: v\n:END:\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let drawer = first(&first(&root));
+        assert_eq!(SyntaxT::PropertyDrawer, SyntaxT::from(&drawer.data));
+        let properties: Vec<_> = drawer
+            .children
+            .borrow()
+            .iter()
+            .map(|p| match &p.data {
+                Syntax::NodeProperty(p) => (p.key, p.value),
+                _ => panic!("expected node property"),
+            })
+            .collect();
+        assert_eq!(vec![("EMPTY", ""), ("my-key_2", "v")], properties);
     }
 }
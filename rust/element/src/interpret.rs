@@ -0,0 +1,210 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Interpretation of parsed nodes back into Org syntax,
+//! like `org-element-interpret-data` does.
+//!
+//! Nodes without an interpreter of their own are written exactly as
+//! they appear in the source.
+
+use crate::blocks::{escape_code_in_string, normalize_block_contents, ExampleBlockData};
+use crate::data::{EmphasisData, Syntax, SyntaxNode};
+
+/// Returns Org text of `node`, obtained by parsing `src`.
+/// Affiliated keywords are written as they appear in the source.
+/// elisp: `org-element-interpret-data`
+pub fn interpret<'a>(node: &SyntaxNode<'a>, src: &'a str) -> String {
+    let affiliated = &src[node.location.start..node.post_affiliated()];
+    let blank_lines = "\n".repeat(node.post_blank);
+    match &node.data {
+        Syntax::ExampleBlock(example) => format!(
+            "{}{}{}",
+            affiliated,
+            example_block_interpreter(example),
+            blank_lines
+        ),
+        Syntax::VerseBlock => format!(
+            "{}{}{}",
+            affiliated,
+            verse_block_interpreter(&interpret_contents(node, src)),
+            blank_lines
+        ),
+        Syntax::Bold(emphasis)
+        | Syntax::Italic(emphasis)
+        | Syntax::StrikeThrough(emphasis)
+        | Syntax::Underline(emphasis) => format!(
+            "{}{}",
+            emphasis_interpreter(emphasis, &interpret_contents(node, src)),
+            " ".repeat(node.post_blank)
+        ),
+        Syntax::Code(code) => format!(
+            "{}{}",
+            emphasis_interpreter(&code.emphasis, code.value),
+            " ".repeat(node.post_blank)
+        ),
+        Syntax::Verbatim(verbatim) => format!(
+            "{}{}",
+            emphasis_interpreter(&verbatim.emphasis, verbatim.value),
+            " ".repeat(node.post_blank)
+        ),
+        _ => src[node.location.start..node.location.end].to_string(),
+    }
+}
+
+/// Returns Org text of the children of `node`. Contents left out by
+/// a parser with `lazy_contents` set are taken as written.
+fn interpret_contents<'a>(node: &SyntaxNode<'a>, src: &'a str) -> String {
+    let children = node.children.borrow();
+    match node.content_location {
+        Some(contents) if children.is_empty() => src[contents.start..contents.end].to_string(),
+        _ => children.iter().map(|child| interpret(child, src)).collect(),
+    }
+}
+
+/// Interprets an example block.
+/// (defun org-element-example-block-interpreter (example-block _)
+///
+/// Value is normalized, see `normalize_block_contents`, and lines
+/// that would be parsed as headlines or keywords are protected again
+/// with a comma.
+fn example_block_interpreter(example: &ExampleBlockData) -> String {
+    let value = normalize_block_contents(&example.value, example.preserve_indent);
+    format!(
+        "#+begin_example{}\n{}#+end_example\n",
+        example
+            .switches
+            .map_or(String::new(), |s| format!(" {}", s)),
+        escape_code_in_string(&value)
+    )
+}
+
+/// Interprets a verse block.
+/// CONTENTS is verse block contents."
+/// (defun org-element-verse-block-interpreter (_ contents)
+///
+/// Lines are never refilled, contrary to paragraphs, so hard line
+/// breaks and indentation within the block are kept. Blank lines
+/// around them are removed, see `normalize_block_contents`.
+fn verse_block_interpreter(contents: &str) -> String {
+    format!(
+        "#+begin_verse\n{}#+end_verse\n",
+        normalize_block_contents(contents, true)
+    )
+}
+
+/// Interprets an emphasis object, i.e., bold, italic, underline,
+/// strike-through, code or verbatim one.
+/// CONTENTS is the contents of the object, or its value."
+/// (defun org-element-bold-interpreter (_ contents)
+///
+/// The bounding context, recorded in EMPHASIS, is not part of the
+/// object and is written by the interpreter of its container.
+fn emphasis_interpreter(emphasis: &EmphasisData, contents: &str) -> String {
+    format!("{}{}{}", emphasis.marker, contents, emphasis.marker)
+}
+
+mod test {
+    use super::interpret;
+    use crate::data::SyntaxT;
+    use crate::parser::{ParseGranularity, Parser};
+
+    #[test]
+    fn verse_block_round_trip() {
+        let text = "#+begin_verse\n  Great clouds overhead\n\n    Tiny *black* birds\\\\\n  rise and fall\n#+end_verse\n\nText\n";
+        for granularity in vec![ParseGranularity::Element, ParseGranularity::Object] {
+            let parser = Parser::new(text, granularity);
+            let root = parser.parse_buffer();
+            let section = root.children.borrow()[0].clone();
+            let verse = section.children.borrow()[0].clone();
+            assert_eq!(SyntaxT::VerseBlock, SyntaxT::from(&verse.data));
+            assert_eq!(&text[..text.len() - 5], interpret(&verse, text));
+        }
+
+        // Markers are normalized
+        let text = "#+NAME: haiku\n#+BEGIN_VERSE  \n a\n #+END_VERSE";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let section = root.children.borrow()[0].clone();
+        let verse = section.children.borrow()[0].clone();
+        assert_eq!(
+            "#+NAME: haiku\n#+begin_verse\n a\n#+end_verse\n",
+            interpret(&verse, text)
+        );
+    }
+
+    #[test]
+    fn verse_block_blank_lines() {
+        let text = "#+begin_verse\n\n   indented\n\n  line  \n\n#+end_verse\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let section = root.children.borrow()[0].clone();
+        let verse = section.children.borrow()[0].clone();
+        assert_eq!(
+            "#+begin_verse\n   indented\n\n  line\n#+end_verse\n",
+            interpret(&verse, text)
+        );
+    }
+
+    #[test]
+    fn example_block_interpreter() {
+        let text = concat!(
+            "#+BEGIN_EXAMPLE -n\n",
+            "\n",
+            "    ,* not a headline\n",
+            "      nested\n",
+            "    ,#+KEY: not a keyword\n",
+            "\n",
+            "#+END_EXAMPLE\n",
+            "\n",
+            "#+begin_example -i\n",
+            "  kept\n",
+            "#+end_example\n",
+        );
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let section = root.children.borrow()[0].clone();
+        let blocks = section.children.borrow();
+        assert_eq!(SyntaxT::ExampleBlock, SyntaxT::from(&blocks[0].data));
+        assert_eq!(
+            concat!(
+                "#+begin_example -n\n",
+                ",* not a headline\n",
+                "  nested\n",
+                ",#+KEY: not a keyword\n",
+                "#+end_example\n",
+                "\n",
+            ),
+            interpret(&blocks[0], text)
+        );
+        assert_eq!(
+            "#+begin_example -i\n  kept\n#+end_example\n",
+            interpret(&blocks[1], text)
+        );
+    }
+
+    #[test]
+    fn emphasis_round_trip() {
+        let text = "(*bold /and/ italic*, =x= ~y~  _u_ +s+)\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let section = root.children.borrow()[0].clone();
+        let paragraph = section.children.borrow()[0].clone();
+        let objects = paragraph.children.borrow();
+        assert_eq!(SyntaxT::Bold, SyntaxT::from(&objects[1].data));
+        assert_eq!("*bold /and/ italic*", interpret(&objects[1], text));
+        let interpreted: String = objects.iter().map(|o| interpret(o, text)).collect();
+        assert_eq!(text, interpreted);
+    }
+}
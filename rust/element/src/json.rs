@@ -0,0 +1,182 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Serialization of syntax trees to JSON
+//!
+//! Every node becomes an object holding its type, its bounds, its
+//! post blank and its children, in order. Values that tell nodes of
+//! the same type apart are added when the node has them: `name`
+//! from affiliated keywords, `raw_value` and the text of plain text
+//! objects as `value`. Output is indented, so that trees written to
+//! files can be compared line by line.
+
+use crate::data::{Syntax, SyntaxNode, SyntaxT};
+use std::fmt::Write;
+
+/// Returns `node` and its descendants as a JSON object.
+pub fn to_json(node: &SyntaxNode) -> String {
+    let mut out = String::new();
+    write_node(&mut out, node, 0);
+    out.push('\n');
+    out
+}
+
+fn write_node(out: &mut String, node: &SyntaxNode, depth: usize) {
+    let indent = "  ".repeat(depth + 1);
+    let mut fields: Vec<(&str, String)> = vec![
+        ("type", string(&format!("{:?}", SyntaxT::from(&node.data)))),
+        ("begin", node.location.start.to_string()),
+        ("end", node.location.end.to_string()),
+    ];
+    if let Some(contents) = node.content_location {
+        fields.push(("contents_begin", contents.start.to_string()));
+        fields.push(("contents_end", contents.end.to_string()));
+    }
+    fields.push(("post_blank", node.post_blank.to_string()));
+    if let Some(name) = node.name() {
+        fields.push(("name", string(name)));
+    }
+    if let Some(raw_value) = node.raw_value() {
+        fields.push(("raw_value", string(raw_value)));
+    }
+    if let Syntax::PlainText(text) = node.data {
+        fields.push(("value", string(text)));
+    }
+
+    out.push_str("{\n");
+    for (key, value) in fields {
+        let _ = writeln!(out, "{}\"{}\": {},", indent, key, value);
+    }
+    let children = node.children.borrow();
+    if children.is_empty() {
+        let _ = write!(out, "{}\"children\": []", indent);
+    } else {
+        let _ = writeln!(out, "{}\"children\": [", indent);
+        for (i, child) in children.iter().enumerate() {
+            out.push_str(&indent);
+            out.push_str("  ");
+            write_node(out, child, depth + 2);
+            out.push_str(if i + 1 < children.len() { ",\n" } else { "\n" });
+        }
+        let _ = write!(out, "{}]", indent);
+    }
+    let _ = write!(out, "\n{}}}", "  ".repeat(depth));
+}
+
+/// Returns `s` as a JSON string, quoted and escaped
+fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+mod test {
+    use super::{string, to_json};
+    use crate::parser::{ParseGranularity, Parser};
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn escaped_strings() {
+        assert_eq!(
+            r#""a \"b\" \\ c\n\t\u0001""#,
+            string("a \"b\" \\ c\n\t\u{1}")
+        );
+    }
+
+    #[test]
+    fn paragraph_json() {
+        let text = "Some *text*\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let section = root.children.borrow()[0].clone();
+        let paragraph = section.children.borrow()[0].clone();
+        let bold = paragraph.children.borrow()[1].clone();
+        let expected = r#"{
+  "type": "Bold",
+  "begin": 5,
+  "end": 11,
+  "contents_begin": 6,
+  "contents_end": 10,
+  "post_blank": 0,
+  "children": [
+    {
+      "type": "PlainText",
+      "begin": 6,
+      "end": 10,
+      "post_blank": 0,
+      "value": "text",
+      "children": []
+    }
+  ]
+}
+"#;
+        assert_eq!(expected, to_json(&bold));
+    }
+
+    /// Parses every fixture in `tests/fixtures` and compares its tree
+    /// with the JSON snapshot next to it. Set `ORG_RS_UPDATE_SNAPSHOTS`
+    /// to write the snapshots instead, e.g. after adding a fixture.
+    #[test]
+    fn fixture_snapshots() {
+        let update = env::var_os("ORG_RS_UPDATE_SNAPSHOTS").is_some();
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let mut fixtures: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "org"))
+            .collect();
+        fixtures.sort();
+        assert!(!fixtures.is_empty());
+
+        for fixture in fixtures {
+            let text = fs::read_to_string(&fixture).unwrap();
+            let parser = Parser::new(&text, ParseGranularity::Object);
+            let json = to_json(&parser.parse_buffer());
+            let snapshot = fixture.with_extension("json");
+            if update {
+                fs::write(&snapshot, &json).unwrap();
+                continue;
+            }
+            let expected = fs::read_to_string(&snapshot).unwrap_or_else(|_| {
+                panic!(
+                    "missing snapshot {}, set ORG_RS_UPDATE_SNAPSHOTS to write it",
+                    snapshot.display()
+                )
+            });
+            assert!(
+                expected == json,
+                "tree of {} differs from its snapshot, set ORG_RS_UPDATE_SNAPSHOTS \
+                 to update it",
+                fixture.display()
+            );
+        }
+    }
+}
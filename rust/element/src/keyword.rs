@@ -15,26 +15,40 @@
 //
 
 use crate::affiliated::AffiliatedData;
-use crate::data::SyntaxNode;
 use crate::data::SyntaxT;
-use crate::parser::Parser;
-use regex::{Match, Regex};
-use std::borrow::Cow;
+use crate::data::{Handle, Interval, Syntax, SyntaxNode};
+use crate::parser::{ParseGranularity, Parser};
+use crate::regexps::REGEX_KEYWORD_LINE;
 
-lazy_static! {
-    pub static ref REGEX_KEYWORD: Regex = Regex::new(r"\+\S+:").unwrap();
-}
+/// Keywords which value can contain objects
+/// elisp: `org-element-document-properties`
+pub const ORG_ELEMENT_DOCUMENT_PROPERTIES: [&str; 3] = ["AUTHOR", "DATE", "TITLE"];
 
 #[derive(Debug)]
 pub struct KeywordData<'a> {
     /// Keyword's name (string).
+    /// Kept as written, keys are case-insensitive.
     key: &'a str,
     /// Keyword's value (string).
     value: &'a str,
+    /// Keyword's value parsed into objects. Only set for keywords from
+    /// `ORG_ELEMENT_DOCUMENT_PROPERTIES` when objects are parsed.
+    pub parsed_value: Option<Vec<Handle<'a>>>,
+}
+
+impl<'a> KeywordData<'a> {
+    /// Keyword's name, as written
+    pub fn key(&self) -> &'a str {
+        self.key
+    }
+
+    /// Keyword's value, without surrounding blanks
+    pub fn value(&self) -> &'a str {
+        self.value
+    }
 }
 
 impl<'a> Parser<'a> {
-    // TODO implement keyword_parser
     /// Parse a keyword at point.
     ///
     /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
@@ -45,17 +59,103 @@ impl<'a> Parser<'a> {
     /// Return a list whose CAR is `keyword' and CDR is a plist
     /// containing `:key', `:value', `:begin', `:end', `:post-blank' and
     /// `:post-affiliated' keywords."
-
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L2129
+    /// (defun org-element-keyword-parser (limit affiliated)
     pub fn keyword_parser(
         &self,
         limit: usize,
         start: usize,
-        maybe_aff: Option<AffiliatedData>,
+        maybe_aff: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        // (save-excursion
-        //   ;; An orphaned affiliated keyword is considered as a regular
-        //   ;; keyword.  In this case AFFILIATED is nil, so we take care of
-        //   ;; this corner case.
-        unimplemented!()
+        // An orphaned affiliated keyword is considered as a regular
+        // keyword. In this case `maybe_aff` is None and start is
+        // the beginning of the keyword itself.
+        let cap = capturing_at!(REGEX_KEYWORD_LINE, self).unwrap();
+        let key = cap.get(1).unwrap().as_str();
+        let value_match = cap.get(2).unwrap();
+        let value = value_match.as_str().trim_end();
+
+        let parsed_value = if self.granularity == ParseGranularity::Object
+            && ORG_ELEMENT_DOCUMENT_PROPERTIES
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(key))
+        {
+            let pos = self.cursor.borrow().pos();
+            let value_start = pos + value_match.start();
+            Some(
+                self.parse_objects(value_start, value_start + value.len(), |that| {
+                    SyntaxT::Keyword.can_contain(that)
+                }),
+            )
+        } else {
+            None
+        };
+
+        self.cursor.borrow_mut().goto_next_line();
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::Keyword(Box::new(KeywordData {
+                key,
+                value,
+                parsed_value,
+            })),
+            Interval { start, end },
+            None,
+            post_blank,
+            maybe_aff,
+        )
+    }
+}
+
+mod test {
+    use crate::data::{Interval, Syntax};
+    use crate::parser::{ParseGranularity, Parser};
+
+    #[test]
+    fn keyword_parser() {
+        let text = "#+TITLE: The *best* title  \n\n#+foo: bar";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.keyword_parser(text.len(), 0, None);
+        assert_eq!(Interval { start: 0, end: 29 }, node.location);
+        assert_eq!(1, node.post_blank);
+        assert_eq!(Some("The *best* title"), node.raw_value());
+        match &node.data {
+            Syntax::Keyword(k) => {
+                assert_eq!("TITLE", k.key);
+                let parsed = k.parsed_value.as_ref().unwrap();
+                assert_eq!(3, parsed.len());
+                assert_eq!(Interval { start: 13, end: 20 }, parsed[1].location);
+            }
+            _ => panic!("expected keyword"),
+        }
+
+        parser.cursor.borrow_mut().set(29);
+        let node = parser.keyword_parser(text.len(), 29, None);
+        assert_eq!(text.len(), node.location.end);
+        match &node.data {
+            Syntax::Keyword(k) => {
+                assert_eq!("foo", k.key);
+                assert_eq!("bar", k.value);
+                assert!(k.parsed_value.is_none());
+            }
+            _ => panic!("expected keyword"),
+        }
+    }
+
+    #[test]
+    fn keyword_value() {
+        let text = "#+AUTHOR:   Jane Doe \n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.keyword_parser(text.len(), 0, None);
+        match &node.data {
+            Syntax::Keyword(k) => {
+                assert_eq!("AUTHOR", k.key());
+                assert_eq!("Jane Doe", k.value());
+                // Value borrows from the input
+                assert_eq!(text[12..].as_ptr(), k.value().as_ptr());
+            }
+            _ => panic!("expected keyword"),
+        }
     }
 }
@@ -23,25 +23,18 @@ use crate::affiliated::AffiliatedData;
 /// NAME is constituted of alpha-numeric or asterisk characters.
 ///
 /// CONTENTS can contain anything but the “\end{NAME}” string.
-use crate::data::SyntaxNode;
+use crate::babel::parse_paired_brackets;
+use crate::data::{Interval, Syntax, SyntaxNode};
 use crate::parser::Parser;
+use crate::regexps::{REGEX_LATEX_BEGIN_ENVIRIONMENT, REGEX_LATEX_COMMAND};
 use regex::Regex;
 
-// TODO wirte latex regexes
-lazy_static! {
-
-
-    /// Regexp matching the beginning of a LaTeX environment.
-    /// The environment is captured by the first group.
-    pub static ref REGEX_LATEX_BEGIN_ENVIRIONMENT: Regex = Regex::new(r"^[ \t]*\\begin{([A-Za-z0-9*]+)}").unwrap();
-}
-
 /// Format string matching the ending of a LaTeX environment
 /// Unfortunately because of the way original elisp parser is written this
 /// regex can't be made static as it should match the opening part
 ///
 /// In ideal world this should be replaced by a proper parser
-pub static FMTSTR_LATEX_END_ENVIRONMENT: &str = r"\\end{%s}[ \t]*$";
+pub static FMTSTR_LATEX_END_ENVIRONMENT: &str = r"\\end\{%s\}[ \t]*$";
 
 #[derive(Debug)]
 pub struct LatexEnvironmentData<'a> {
@@ -58,17 +51,16 @@ pub struct LatexEnvironmentData<'a> {
     post_blank: usize,
 
     ///LaTeX code (string).
-    value: &'a str,
+    pub value: &'a str,
 }
 
 #[derive(Debug)]
 pub struct LatexFragmentData<'a> {
     ///LaTeX code (string).
-    value: &'a str,
+    pub value: &'a str,
 }
 
 impl<'a> Parser<'a> {
-    // TODO implement latext_environment_parser
     /// Parse a LaTeX environment.
     /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
     /// the buffer position at the beginning of the first affiliated
@@ -80,12 +72,197 @@ impl<'a> Parser<'a> {
     /// `:post-affiliated' keywords.
     ///
     /// Assume point is at the beginning of the latex environment."
+    /// (defun org-element-latex-environment-parser (limit affiliated)
     pub fn latex_environment_parser(
         &self,
         limit: usize,
         start: usize,
-        maybe_aff: Option<AffiliatedData>,
+        maybe_aff: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let code_begin = c.pos();
+        let name = match c.capturing_at(&*REGEX_LATEX_BEGIN_ENVIRIONMENT) {
+            Some(cap) => cap.get(1).unwrap().as_str(),
+            None => "",
+        };
+        let pattern = FMTSTR_LATEX_END_ENVIRONMENT.replace("%s", &regex::escape(name));
+        let end_environment = Regex::new(&format!("(?mi){}", pattern)).unwrap();
+        if c.re_search_forward(&end_environment, Some(limit)).is_none() {
+            // Incomplete latex environment: parse it as a paragraph.
+            c.set(code_begin);
+            drop(c);
+            return self.paragraph_parser(limit, start, maybe_aff);
+        }
+        let code_end = c.goto_next_line();
+        let value = &self.input[code_begin..code_end];
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        let data = LatexEnvironmentData {
+            begin: start,
+            end,
+            post_blank,
+            value,
+        };
+        SyntaxNode::new(
+            Syntax::LatexEnvironment(Box::new(data)),
+            Interval { start, end },
+            None,
+            post_blank,
+            maybe_aff,
+        )
+    }
+
+    /// Parse LaTeX fragment at point, if any.
+    ///
+    /// When at a LaTeX fragment, return a list whose car is
+    /// `latex-fragment' and cdr a plist with `:value', `:begin', `:end',
+    /// and `:post-blank' as keywords.  Otherwise, return nil.
+    ///
+    /// Assume point is at the beginning of the LaTeX fragment."
+    /// (defun org-element-latex-fragment-parser ()
+    ///
+    /// Command arguments, e.g. `\frac{a}{b^{2}}`, may nest brackets
+    /// and braces as long as they are balanced and fit on one line.
+    pub fn latex_fragment_parser(&self) -> Option<SyntaxNode<'a>> {
+        let mut c = self.cursor.borrow_mut();
+        let data = c.data();
+        let begin = c.pos();
+        let text = &data[begin..];
+        let after_fragment = if !text.starts_with('$') {
+            if text.starts_with("\\(") {
+                text.find("\\)").map(|i| begin + i + 2)
+            } else if text.starts_with("\\[") {
+                text.find("\\]").map(|i| begin + i + 2)
+            } else {
+                // Macro.
+                REGEX_LATEX_COMMAND
+                    .find(text)
+                    .map(|m| begin + command_arguments_end(&text[m.end()..]) + m.end())
+            }
+        } else if text.starts_with("$$") {
+            text[2..].find("$$").map(|i| begin + i + 4)
+        } else {
+            let before = data[..begin].chars().next_back();
+            let after = text[1..].chars().next();
+            if before == Some('$') || after.map_or(true, |ch| " \t\n,.;".contains(ch)) {
+                None
+            } else {
+                text[1..]
+                    .find('$')
+                    .filter(|&i| !" \t\n,.".contains(text[..i + 1].chars().next_back().unwrap()))
+                    .map(|i| begin + i + 2)
+                    .filter(|&end| {
+                        data[end..]
+                            .chars()
+                            .next()
+                            .map_or(true, |ch| ch.is_whitespace() || ch.is_ascii_punctuation())
+                    })
+            }
+        }?;
+        c.set(after_fragment);
+        let post_blank = c.skip_chars_forward(" \t", None);
+        Some(SyntaxNode::new(
+            Syntax::LatexFragment(Box::new(LatexFragmentData {
+                value: &data[begin..after_fragment],
+            })),
+            Interval {
+                start: begin,
+                end: c.pos(),
+            },
+            None,
+            post_blank,
+            None,
+        ))
+    }
+}
+
+/// Returns the length of the `[OPTIONS]` and `{ARGUMENT}` groups
+/// following a LaTeX command name. Each group must be balanced and
+/// must not span several lines.
+fn command_arguments_end(s: &str) -> usize {
+    let mut end = 0;
+    loop {
+        let rest = &s[end..];
+        let group = parse_paired_brackets(rest, '[')
+            .or_else(|| parse_paired_brackets(rest, '{'))
+            .map(|(_, len)| len);
+        match group {
+            Some(len) if !rest[..len].contains('\n') => end += len,
+            _ => return end,
+        }
+    }
+}
+
+mod test {
+    use crate::data::{Interval, Syntax, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+
+    fn fragments(text: &str) -> Vec<&str> {
+        Parser::parse_secondary_string(text, |t| SyntaxT::Paragraph.can_contain(t))
+            .iter()
+            .filter_map(|o| match &o.data {
+                Syntax::LatexFragment(d) => Some(d.value),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn latex_fragment_commands() {
+        assert_eq!(vec!["\\textbf{hi}"], fragments("a \\textbf{hi} b"));
+        assert_eq!(
+            vec!["\\section*[short]{Title}"],
+            fragments("\\section*[short]{Title} text")
+        );
+        assert_eq!(vec!["\\frac{a}{b^{2}}"], fragments("\\frac{a}{b^{2}}"));
+        assert_eq!(vec!["\\foo{}"], fragments("\\foo{}"));
+        assert_eq!(vec!["\\foo"], fragments("\\foo"));
+        // Unbalanced or multi-line arguments are left out.
+        assert_eq!(vec!["\\cmd"], fragments("\\cmd{open"));
+        assert_eq!(vec!["\\cmd"], fragments("\\cmd{a\nb}"));
+
+        let objects = Parser::parse_secondary_string("\\alpha \\textbf{hi}", |t| {
+            SyntaxT::Paragraph.can_contain(t)
+        });
+        assert_eq!(SyntaxT::Entity, SyntaxT::from(&objects[0].data));
+        assert_eq!(SyntaxT::LatexFragment, SyntaxT::from(&objects[1].data));
+        assert_eq!(1, objects[0].post_blank);
+    }
+
+    #[test]
+    fn latex_fragment_delimiters() {
+        assert_eq!(vec!["\\(x\\)", "\\[y\\]"], fragments("\\(x\\) and \\[y\\]"));
+        assert_eq!(vec!["$$a + b$$"], fragments("see $$a + b$$."));
+        assert_eq!(vec!["$x$"], fragments("so $x$, then"));
+        assert!(fragments("costs $5 or $ 6$").is_empty());
+        assert!(fragments("$a $b").is_empty());
+    }
+
+    #[test]
+    fn latex_environment() {
+        let text = "\\begin{equation*}\nx = 1\n\\END{equation*}  \n\nText";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.latex_environment_parser(text.len(), 0, None);
+        assert_eq!(Interval { start: 0, end: 43 }, node.location);
+        assert_eq!(1, node.post_blank);
+        match &node.data {
+            Syntax::LatexEnvironment(d) => {
+                assert_eq!("\\begin{equation*}\nx = 1\n\\END{equation*}  \n", d.value)
+            }
+            _ => panic!("expected latex environment"),
+        }
+
+        // Incomplete environment: parsed as a paragraph
+        let text = "\\begin{equation}\nx\n\\end{align}\n";
+        let root = Parser::new(text, ParseGranularity::Element).parse_buffer();
+        let section = &root.children.borrow()[0];
+        let types: Vec<_> = section
+            .children
+            .borrow()
+            .iter()
+            .map(|c| SyntaxT::from(&c.data))
+            .collect();
+        assert_eq!(vec![SyntaxT::Paragraph], types);
     }
 }
@@ -17,6 +17,8 @@
 // This should be eventually turned off, but for now this helps reduce the noice
 #![allow(dead_code)]
 #![allow(warnings)]
+// All regular expressions are defined in a single lazy_static! block
+#![recursion_limit = "256"]
 #[macro_use]
 extern crate lazy_static;
 #[macro_use]
@@ -31,15 +33,33 @@ mod parser;
 mod affiliated;
 mod babel;
 mod blocks;
+mod citation;
 mod cursor;
 mod data;
+pub mod document;
 mod drawer;
+mod emphasis;
+mod entities;
+mod fill;
 mod fixed_width;
+mod footnote;
+mod formula;
 mod headline;
+mod interpret;
+mod json;
 mod keyword;
 mod latex;
+mod link;
 mod list;
 mod markup;
+mod motion;
 mod paragraph;
 mod planning;
+mod plist;
+mod regexps;
+mod script;
+mod settings;
+mod sort;
 mod table;
+mod tangle;
+mod timestamp;
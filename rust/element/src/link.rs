@@ -0,0 +1,526 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Links
+//! https://orgmode.org/worg/dev/org-syntax.html#Links
+//!
+//! [[LINK]] or [[LINK][DESCRIPTION]], where LINK is TYPE:PATH, an
+//! absolute or relative file name, (CODEREF), #CUSTOM-ID or anything
+//! else, which is then a fuzzy link.
+//! <TYPE:PATH> is an angle link.
+//!
+//! For "file" type links, TYPE can be `file+APPLICATION` and PATH can
+//! end with `::SEARCH-OPTION`, e.g. [[file:notes.org::*Tasks]].
+
+use crate::data::{Interval, LinkData, LinkFormat, LinkType, Syntax, SyntaxNode};
+use crate::document::OrgDocument;
+use crate::headline::HeadlineData;
+use crate::parser::{ParseGranularity, Parser};
+use crate::regexps::{REGEX_LINK_ANGLE, REGEX_LINK_BRACKET};
+use crate::settings::BufferSettings;
+use regex::Regex;
+
+/// Known link types, link starting with one of them followed by
+/// a colon is a link of that type.
+/// elisp: `org-link-types`
+pub const ORG_LINK_TYPES: &[&str] = &[
+    "attachment",
+    "bbdb",
+    "doi",
+    "docview",
+    "elisp",
+    "eww",
+    "file",
+    "file+emacs",
+    "file+sys",
+    "ftp",
+    "gnus",
+    "help",
+    "http",
+    "https",
+    "id",
+    "info",
+    "irc",
+    "mailto",
+    "mhe",
+    "news",
+    "rmail",
+    "shell",
+];
+
+/// Splits `raw_link` into its type and path if it starts with
+/// a known link type followed by a colon.
+/// elisp: `org-link-types-re`
+fn explicit_type(raw_link: &str) -> Option<(&str, &str)> {
+    let colon = raw_link.find(':')?;
+    let link_type = &raw_link[..colon];
+    if ORG_LINK_TYPES.contains(&link_type) {
+        Some((link_type, &raw_link[colon + 1..]))
+    } else {
+        None
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Parse link at point, if any.
+    ///
+    /// When at a link, return a list whose car is `link' and cdr a plist
+    /// with `:type', `:path', `:format', `:raw-link', `:application',
+    /// `:search-option', `:begin', `:end', `:contents-begin',
+    /// `:contents-end' and `:post-blank' as keywords.  Otherwise, return
+    /// nil.
+    ///
+    /// Assume point is at the beginning of the link."
+    /// (defun org-element-link-parser ()
+    ///
+    /// Only bracket and angle links are recognized. Raw link is
+    /// the verbatim link part, it is neither unescaped nor are its
+    /// newlines replaced.
+    pub fn link_parser(&self) -> Option<SyntaxNode<'a>> {
+        let mut c = self.cursor.borrow_mut();
+        let text = c.data();
+        let begin = c.pos();
+        let rest = &text[begin..];
+
+        let (format, raw_link, link_type, path, link_end, contents) = if rest.starts_with("[[") {
+            // Type 1: Text targeted from a radio target.
+            // (not supported)
+            // Type 2: Standard link, i.e. [[https://orgmode.org][homepage]]
+            let cap = REGEX_LINK_BRACKET.captures(rest)?;
+            if cap.get(0).unwrap().start() != 0 {
+                return None;
+            }
+            let raw_link = cap.get(1).unwrap().as_str();
+            let contents = cap.get(2).map(|m| Interval {
+                start: begin + m.start(),
+                end: begin + m.end(),
+            });
+            let (link_type, path) = if raw_link.starts_with('/')
+                || raw_link.starts_with("./")
+                || raw_link.starts_with("../")
+                || raw_link.starts_with("~/")
+            {
+                // File type.
+                ("file", raw_link)
+            } else if let Some((link_type, path)) = explicit_type(raw_link) {
+                // Explicit type (http, irc, bbdb...).
+                (link_type, path)
+            } else if raw_link.len() > 1 && raw_link.starts_with('(') && raw_link.ends_with(')') {
+                // Code-ref type: PATH is the name of the reference.
+                ("coderef", &raw_link[1..raw_link.len() - 1])
+            } else if raw_link.starts_with('#') {
+                // Custom-id type: PATH is the name of the custom id.
+                ("custom-id", &raw_link[1..])
+            } else {
+                // Fuzzy type: Internal link either matches a target, an
+                // headline name or nothing.  PATH is the target or
+                // headline's name.
+                ("fuzzy", raw_link)
+            };
+            let end = begin + cap.get(0).unwrap().end();
+            (
+                LinkFormat::Bracket,
+                raw_link,
+                link_type,
+                path,
+                end,
+                contents,
+            )
+        } else if rest.starts_with('<') {
+            // Type 3: Angular link, e.g., <https://orgmode.org>
+            let cap = REGEX_LINK_ANGLE.captures(rest)?;
+            let link_type = cap.get(1).unwrap().as_str();
+            if cap.get(0).unwrap().start() != 0 || !ORG_LINK_TYPES.contains(&link_type) {
+                return None;
+            }
+            let whole = cap.get(0).unwrap();
+            let raw_link = &rest[1..whole.end() - 1];
+            let path = cap.get(2).unwrap().as_str();
+            (
+                LinkFormat::Angle,
+                raw_link,
+                link_type,
+                path,
+                begin + whole.end(),
+                None,
+            )
+        } else {
+            return None;
+        };
+
+        // Special "file"-type link processing.  Extract opening
+        // application and search option, if any.
+        let mut path = path;
+        let mut application = None;
+        let mut search_option = None;
+        let link_type = match link_type {
+            "coderef" => LinkType::Coderef,
+            "custom-id" => LinkType::CustomId,
+            "fuzzy" => LinkType::Fuzzy,
+            "id" => LinkType::Id,
+            t if t == "file" || t.starts_with("file+") => {
+                application = t.get(5..).filter(|app| !app.is_empty());
+                if let Some(pos) = path.find("::") {
+                    search_option = Some(&path[pos + 2..]);
+                    path = &path[..pos];
+                }
+                // Normalize URI: file:///path is file:/path
+                if path.starts_with("///") {
+                    path = &path[path.len() - path.trim_start_matches('/').len() - 1..];
+                }
+                LinkType::File
+            }
+            t => LinkType::Other(t),
+        };
+
+        c.set(link_end);
+        let post_blank = c.skip_chars_forward(" \t", None);
+        Some(SyntaxNode::new(
+            Syntax::Link(Box::new(LinkData {
+                application,
+                format,
+                path,
+                raw_link,
+                search_option,
+                link_type,
+            })),
+            Interval {
+                start: begin,
+                end: c.pos(),
+            },
+            contents,
+            post_blank,
+            None,
+        ))
+    }
+}
+
+/// Expands a link whose type is an abbreviation defined with
+/// `#+LINK:`, e.g. with `#+LINK: gh https://github.com/%s`,
+/// `[[gh:org/repo]]` expands to `https://github.com/org/repo`.
+///
+/// In the replacement, `%s` is replaced by `path` and `%h` by `path`
+/// percent-encoded. When there is neither, `path` is appended to it.
+/// Returns None when `link_type` is not an abbreviation. The first
+/// definition of an abbreviation wins.
+/// elisp: `org-link-expand-abbrev`
+pub fn expand_link_abbrev(
+    link_type: &str,
+    path: &str,
+    settings: &BufferSettings,
+) -> Option<String> {
+    let (_, replacement) = settings
+        .link_abbrevs
+        .iter()
+        .find(|(abbrev, _)| abbrev == link_type)?;
+    let expanded = if replacement.contains("%s") {
+        replacement.replace("%s", path)
+    } else if replacement.contains("%h") {
+        replacement.replace("%h", &hexify(path))
+    } else {
+        format!("{}{}", replacement, path)
+    };
+    Some(expanded)
+}
+
+/// Percent-encodes every byte of `s` that is not unreserved in URIs.
+/// elisp: `url-hexify-string`
+fn hexify(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            b => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Returns the location in `doc` that a link with `search_option`
+/// points to, if any. Search options are written after `::` in `file:`
+/// links, e.g. `[[file:notes.org::*Tasks]]`:
+///
+/// - `*Headline`: the headline with that title,
+/// - `#custom-id`: the headline with that `CUSTOM_ID` property,
+/// - `42`: the 42nd line, without its newline,
+/// - `/regexp/`: the first match of the regular expression.
+///
+/// Anything else is a fuzzy search: a `<<target>>`, then an element
+/// named with `#+NAME:`, then a headline with that title. When there
+/// is no `search_option`, `path_or_target` is such a fuzzy search, as
+/// it is for links like `[[Tasks]]`. Headlines and elements span their
+/// whole location and titles are compared with whitespace collapsed.
+/// elisp: `org-link-search`
+pub fn resolve_search_option(
+    doc: &OrgDocument,
+    path_or_target: &str,
+    search_option: Option<&str>,
+) -> Option<Interval> {
+    let text = doc.text();
+    let search = search_option.unwrap_or(path_or_target).trim();
+    if let Ok(line) = search.parse::<usize>() {
+        return line_location(text, line);
+    }
+    if search.len() > 1 && search.starts_with('/') && search.ends_with('/') {
+        let re = Regex::new(&search[1..search.len() - 1]).ok()?;
+        return re.find(text).map(|m| Interval {
+            start: m.start(),
+            end: m.end(),
+        });
+    }
+
+    let (root, _) = doc.parse(ParseGranularity::Element);
+    let headline = |matches: &dyn Fn(&HeadlineData) -> bool| {
+        find_node(&root, &|node| match &node.data {
+            Syntax::Headline(h) => matches(h),
+            _ => false,
+        })
+    };
+    let titled = |title: &str| {
+        let title = normalize(title);
+        headline(&|h| normalize(h.raw_value) == title)
+    };
+    if search.starts_with('#') {
+        let id = &search[1..];
+        return headline(&|h| h.get_property("CUSTOM_ID").as_deref() == Some(id));
+    }
+    if search.starts_with('*') {
+        return titled(&search[1..]);
+    }
+
+    let target = format!("<<{}>>", search);
+    let dedicated = text
+        .match_indices(&target)
+        .find(|&(start, _)| !text[..start].ends_with('<'))
+        .map(|(start, _)| Interval {
+            start,
+            end: start + target.len(),
+        });
+    dedicated
+        .or_else(|| find_node(&root, &|node| node.name() == Some(search)))
+        .or_else(|| titled(search))
+}
+
+/// Returns location of the first node of `node`'s tree accepted
+/// by `matches`, in order of appearance.
+fn find_node<'a>(
+    node: &SyntaxNode<'a>,
+    matches: &dyn Fn(&SyntaxNode<'a>) -> bool,
+) -> Option<Interval> {
+    if matches(node) {
+        return Some(node.location);
+    }
+    node.children
+        .borrow()
+        .iter()
+        .find_map(|child| find_node(child, matches))
+}
+
+/// Returns location of the `line`th line of `text`, counting from 1,
+/// its newline excluded.
+fn line_location(text: &str, line: usize) -> Option<Interval> {
+    let start = if line == 1 {
+        0
+    } else {
+        text.match_indices('\n').nth(line.checked_sub(2)?)?.0 + 1
+    };
+    if start == text.len() && line > 1 {
+        return None;
+    }
+    let end = text[start..]
+        .find('\n')
+        .map_or(text.len(), |eol| start + eol);
+    Some(Interval { start, end })
+}
+
+/// Collapses whitespace of `s`.
+/// elisp: `org-link--normalize-string`
+fn normalize(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+mod test {
+    use super::{expand_link_abbrev, resolve_search_option};
+    use crate::data::{Interval, LinkData, LinkFormat, LinkType, Syntax, SyntaxNode, SyntaxT};
+    use crate::document::OrgDocument;
+    use crate::parser::Parser;
+    use crate::settings::BufferSettings;
+
+    fn in_paragraph(that: SyntaxT) -> bool {
+        SyntaxT::Paragraph.can_contain(that)
+    }
+
+    fn link<'a, 'b>(node: &'b SyntaxNode<'a>) -> &'b LinkData<'a> {
+        match &node.data {
+            Syntax::Link(l) => l,
+            _ => panic!("expected link"),
+        }
+    }
+
+    #[test]
+    fn description_contents() {
+        let text = "[[https://orgmode.org][desc]] [[https://orgmode.org]]";
+        let objects = Parser::parse_secondary_string(text, in_paragraph);
+        assert_eq!(2, objects.len());
+        let contents = objects[0].content_location.unwrap();
+        assert_eq!(Interval { start: 23, end: 27 }, contents);
+        assert_eq!(None, objects[1].content_location);
+
+        // Description can be parsed on its own, on demand
+        let description = &text[contents.start..contents.end];
+        let parsed =
+            Parser::parse_secondary_string(description, |that| SyntaxT::Link.can_contain(that));
+        assert_eq!(1, parsed.len());
+        match parsed[0].data {
+            Syntax::PlainText(text) => assert_eq!("desc", text),
+            _ => panic!("expected plain text"),
+        }
+    }
+
+    #[test]
+    fn link_parser() {
+        let text = "See [[file:notes.org::*Tasks][my *tasks*]] now";
+        let objects = Parser::parse_secondary_string(text, in_paragraph);
+        assert_eq!(3, objects.len());
+        assert_eq!(Interval { start: 4, end: 43 }, objects[1].location);
+        assert_eq!(
+            Some(Interval { start: 30, end: 40 }),
+            objects[1].content_location
+        );
+        assert_eq!(2, objects[1].children.borrow().len());
+        let l = link(&objects[1]);
+        assert_eq!(LinkFormat::Bracket, l.format);
+        assert_eq!(LinkType::File, l.link_type);
+        assert_eq!("file:notes.org::*Tasks", l.raw_link);
+        assert_eq!("notes.org", l.path);
+        assert_eq!(Some("*Tasks"), l.search_option);
+        assert_eq!(None, l.application);
+
+        let objects = Parser::parse_secondary_string("[[file+sys:///tmp/a.pdf::42]]", in_paragraph);
+        let l = link(&objects[0]);
+        assert_eq!(LinkType::File, l.link_type);
+        assert_eq!(Some("sys"), l.application);
+        assert_eq!("/tmp/a.pdf", l.path);
+        assert_eq!(Some("42"), l.search_option);
+
+        let objects = Parser::parse_secondary_string("<https://orgmode.org>", in_paragraph);
+        let l = link(&objects[0]);
+        assert_eq!(LinkFormat::Angle, l.format);
+        assert_eq!(LinkType::Other("https"), l.link_type);
+        assert_eq!("https://orgmode.org", l.raw_link);
+        assert_eq!("//orgmode.org", l.path);
+        assert_eq!(None, l.search_option);
+    }
+
+    #[test]
+    fn link_types() {
+        let cases = [
+            ("[[./a.org]]", LinkType::File, "./a.org"),
+            ("[[(ref)]]", LinkType::Coderef, "ref"),
+            ("[[#intro]]", LinkType::CustomId, "intro"),
+            ("[[id:1234]]", LinkType::Id, "1234"),
+            ("[[Some heading]]", LinkType::Fuzzy, "Some heading"),
+            ("[[unknown:x]]", LinkType::Fuzzy, "unknown:x"),
+        ];
+        for (text, link_type, path) in cases.iter() {
+            let objects = Parser::parse_secondary_string(text, in_paragraph);
+            let l = link(&objects[0]);
+            assert_eq!((*link_type, *path), (l.link_type, l.path), "{}", text);
+        }
+
+        for text in &["<unknown:x>", "[[a]", "[not a link]"] {
+            let objects = Parser::parse_secondary_string(text, in_paragraph);
+            assert_eq!(
+                vec![SyntaxT::PlainText],
+                vec![SyntaxT::from(&objects[0].data)]
+            );
+        }
+    }
+
+    #[test]
+    fn search_options() {
+        let text = concat!(
+            "Intro <<here>>\n",
+            "* TODO Some   Tasks :work:\n",
+            "  :PROPERTIES:\n",
+            "  :CUSTOM_ID: tasks\n",
+            "  :END:\n",
+            "#+NAME: table\n",
+            "| a |\n",
+            "* Tasks\n",
+        );
+        let doc = OrgDocument::new(text.to_string());
+        let resolve = |search: &str| resolve_search_option(&doc, "notes.org", Some(search));
+
+        assert_eq!(Some(15), resolve("*Some Tasks").map(|i| i.start));
+        assert_eq!(Some(105), resolve("*Tasks").map(|i| i.start));
+        assert_eq!(None, resolve("*Missing"));
+        assert_eq!(Some(15), resolve("#tasks").map(|i| i.start));
+        assert_eq!(Some(Interval { start: 85, end: 98 }), resolve("6"));
+        assert_eq!(Some(Interval { start: 0, end: 14 }), resolve("1"));
+        assert_eq!(None, resolve("0"));
+        assert_eq!(None, resolve("42"));
+        assert_eq!(Some(Interval { start: 3, end: 8 }), resolve("/ro <</"));
+        assert_eq!(Some(Interval { start: 6, end: 14 }), resolve("here"));
+        assert_eq!(Some(85), resolve("table").map(|i| i.start));
+        assert_eq!(
+            Some(15),
+            resolve_search_option(&doc, "Some Tasks", None).map(|i| i.start)
+        );
+
+        let lines: String = (1..=50).map(|n| format!("line {}\n", n)).collect();
+        let doc = OrgDocument::new(lines.clone());
+        let line = resolve_search_option(&doc, "notes.org", Some("42")).unwrap();
+        assert_eq!(lines.find("line 42").unwrap(), line.start);
+        assert_eq!("line 42", &lines[line.start..line.end]);
+    }
+
+    #[test]
+    fn link_abbreviations() {
+        let text = concat!(
+            "#+LINK: gh https://github.com/%s\n",
+            "#+LINK: search   https://duckduckgo.com/?q=%h\n",
+            "#+LINK: wiki https://en.wikipedia.org/wiki/\n",
+            "#+LINK: gh https://ignored.org/%s\n",
+            "#+LINK: incomplete\n",
+            "See [[gh:org/repo]]\n",
+        );
+        let settings = BufferSettings::collect(text);
+        assert_eq!(4, settings.link_abbrevs.len());
+
+        let objects = Parser::parse_secondary_string("[[gh:org/repo]]", in_paragraph);
+        let l = link(&objects[0]);
+        assert_eq!(LinkType::Fuzzy, l.link_type);
+        let colon = l.raw_link.find(':').unwrap();
+        let (abbrev, path) = (&l.raw_link[..colon], &l.raw_link[colon + 1..]);
+        assert_eq!(
+            Some("https://github.com/org/repo".to_string()),
+            expand_link_abbrev(abbrev, path, &settings)
+        );
+
+        assert_eq!(
+            Some("https://duckduckgo.com/?q=a%20b%2Bc".to_string()),
+            expand_link_abbrev("search", "a b+c", &settings)
+        );
+        assert_eq!(
+            Some("https://en.wikipedia.org/wiki/Org-mode".to_string()),
+            expand_link_abbrev("wiki", "Org-mode", &settings)
+        );
+        assert_eq!(None, expand_link_abbrev("GH", "org/repo", &settings));
+        assert_eq!(None, expand_link_abbrev("incomplete", "x", &settings));
+    }
+}
@@ -63,94 +63,91 @@
 //!
 
 use crate::affiliated::AffiliatedData;
-use crate::data::SyntaxNode;
+use crate::cursor::Cursor;
+use crate::data::{Handle, Interval, Syntax, SyntaxNode, SyntaxT};
+use crate::paragraph::end_of_contents;
 use crate::parser::Parser;
+use crate::regexps::{
+    REGEX_ANY_BLOCK_BEGIN, REGEX_DRAWER, REGEX_DRAWER_END, REGEX_EMPTY_LINE, REGEX_ITEM,
+    REGEX_LIST_END, REGEX_LIST_FULL_ITEM,
+};
 use regex::Regex;
-use std::borrow::Cow;
-use std::cell::Cell;
 use std::rc::Rc;
 
-lazy_static! {
-
-//TODO implement all regexes
-// (defconst org-list-end-re "^[ \t]*\n[ \t]*\n"
-//   "Regex matching the end of a plain list.")
-//
-// (defconst org-list-full-item-re
-//   (concat "^[ \t]*\\(\\(?:[-+*]\\|\\(?:[0-9]+\\|[A-Za-z]\\)[.)]\\)\\(?:[ \t]+\\|$\\)\\)"
-// 	  "\\(?:\\[@\\(?:start:\\)?\\([0-9]+\\|[A-Za-z]\\)\\][ \t]*\\)?"
-// 	  "\\(?:\\(\\[[ X-]\\]\\)\\(?:[ \t]+\\|$\\)\\)?"
-// 	  "\\(?:\\(.*\\)[ \t]+::\\(?:[ \t]+\\|$\\)\\)?")
-//   "Matches a list item and puts everything into groups:
-// group 1: bullet
-// group 2: counter
-// group 3: checkbox
-// group 4: description tag")
-//
-// (defun org-item-re ()
-//   "Return the correct regular expression for plain lists."
-//   (let ((term (cond
-// 	       ((eq org-plain-list-ordered-item-terminator t) "[.)]")
-// 	       ((=  org-plain-list-ordered-item-terminator ?\)) ")")
-// 	       ((=  org-plain-list-ordered-item-terminator ?.) "\\.")
-// 	       (t "[.)]")))
-// 	(alpha (if org-list-allow-alphabetical "\\|[A-Za-z]" "")))
-//     (concat "\\([ \t]*\\([-+]\\|\\(\\([0-9]+" alpha "\\)" term
-// 	    "\\)\\)\\|[ \t]+\\*\\)\\([ \t]+\\|$\\)")))
-//
-// (defsubst org-item-beginning-re ()
-//   "Regexp matching the beginning of a plain list item."
-//   (concat "^" (org-item-re)))
-
-    pub static ref REGEX_ITEM : Regex = Regex::new(r"([ \t]*([-+]|(([0-9]+)[.)]))|[ \t]+\*)([ \t]|$)").unwrap();
-
+/// List structure
+/// Describes every item of a list, sub-lists included, in order of
+/// appearance. Shared between the plain list and its items, it provides
+/// item boundaries to list operations without looking at the buffer again.
+/// See `org-list-struct`
+#[derive(Debug, Default, PartialEq)]
+pub struct ListStruct<'a> {
+    pub items: Vec<ItemStruct<'a>>,
 }
 
-/// List structure
-/// This looks like an intermediate list representation, required both by
-/// plain list itself and items in the list.
-#[derive(Debug)]
-pub struct ListStruct {
-    // stub
+/// Structure entry of a single item, `org-list-struct` stores it as
+/// `(begin indent bullet counter checkbox tag end)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStruct<'a> {
+    /// Beginning of the item's first line
+    pub begin: usize,
+    /// Column of the bullet, tabs are expanded to 8 columns
+    pub indent: usize,
+    /// Bullet as written, separating blanks included, e.g. `"1. "`
+    pub bullet: &'a str,
+    /// Counter set with `[@COUNTER]`, if any
+    pub counter: Option<&'a str>,
+    pub checkbox: Option<CheckBox>,
+    /// Description tag, only unordered items can have one
+    pub tag: Option<&'a str>,
+    /// End of the item, sub-items included
+    pub end: usize,
+    /// Beginning of the item this one is a sub-item of, if any.
+    /// See `org-list-parents-alist`
+    pub parent: Option<usize>,
 }
 
 #[derive(Debug)]
-pub struct ItemData<'rope> {
+pub struct ItemData<'a> {
     /// Item's bullet (string).
-    bullet: Cow<'rope, str>,
+    pub bullet: &'a str,
     /// Item's check_box, if any (symbol on, off, trans, nil).
-    checkbox: Option<CheckBox>,
+    pub checkbox: Option<CheckBox>,
     /// Item's counter, if any. Literal counters become ordinals (integer).
-    counter: usize,
+    pub counter: Option<usize>,
     /// Number of newline characters between the beginning
     /// of the item and the beginning of the contents (0, 1 or 2).
-    pre_blank: usize,
+    pub pre_blank: usize,
     /// Uninterpreted item's tag, if any (string or nil).
-    raw_tag: Option<Cow<'rope, str>>,
+    pub raw_tag: Option<&'a str>,
     /// Parsed item's tag, if any (secondary string or nil).
-    tag: Option<Cow<'rope, str>>,
-    // TODO figure out what is list structure
-    // /// Full list's structure, as returned by org_list_struct (alist).
-    structure: ListStruct,
+    /// When secondary strings are not parsed holds a single plain text object
+    pub tag: Option<Vec<Handle<'a>>>,
+    /// Full list's structure, as returned by org_list_struct (alist).
+    pub structure: Rc<ListStruct<'a>>,
 }
 
 #[derive(Debug)]
-pub struct PlainListData {
+pub struct PlainListData<'a> {
     /// Full list's structure, as returned by org_list_struct (alist).
-    pub structure: Rc<ListStruct>,
+    pub structure: Rc<ListStruct<'a>>,
 
     ///List's type (symbol descriptive, ordered, unordered).
     pub type_s: ListKind,
+
+    /// Whether no item of the list is separated from the next one by
+    /// blank lines. Blank lines within an item, e.g. before a sub-list,
+    /// and after the last item do not make the list loose.
+    pub tight: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ListKind {
     Descriptive,
     Ordered,
     Unordered,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CheckBox {
     On,
     Off,
@@ -158,50 +155,708 @@ pub enum CheckBox {
 }
 
 impl<'a> Parser<'a> {
-    // TODO implement item_parser
-    //https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L1253
+    /// Parse an item.
+    ///
+    /// STRUCT is the structure of the plain list.
+    ///
+    /// Return a list whose CAR is `item' and CDR is a plist containing
+    /// `:bullet', `:begin', `:end', `:contents-begin', `:contents-end',
+    /// `:checkbox', `:counter', `:tag', `:structure', `:pre-blank',
+    /// `:post-blank' and `:post-affiliated' keywords.
+    ///
+    /// When optional argument RAW-SECONDARY-P is non-nil, item's tag, if
+    /// any, will not be parsed as a secondary string, but as a plain
+    /// string instead.
+    ///
+    /// Assume point is at the beginning of the item."
+    /// (defun org-element-item-parser (_ struct &optional raw-secondary-p)
+    ///
+    /// Structure is computed when missing.
     pub fn item_parser(
         &self,
-        structure: Option<Rc<ListStruct>>,
+        structure: Option<Rc<ListStruct<'a>>>,
         raw_secondary_p: bool,
     ) -> SyntaxNode<'a> {
-        //   let mut item_data = ItemData {
-        //       bullet: (),
-        //       checkbox: None,
-        //       counter: 0,
-        //       pre_blank: 0,
-        //       raw_tag: None,
-        //       tag: None,
-        //       structure: ListStruct {}
-        //   }
-
-        //    let mut node = SyntaxNode {
-        //        parent: Cell::new(None),
-        //        children: (),
-        //        data: item_data
-        //        location: Interval {},
-        //        content_location: None,
-        //        post_blank: 0,
-        //        affiliated: None
-        //    }
-        unimplemented!()
-    }
-
-    // TODO implement plain_list_parser
+        let begin = self.cursor.borrow_mut().goto_line_begin();
+        let structure = structure.unwrap_or_else(|| self.list_struct(self.input.len()));
+        let line_end = self.input[begin..]
+            .find('\n')
+            .map_or(self.input.len(), |p| begin + p);
+        let cap = REGEX_LIST_FULL_ITEM
+            .captures(&self.input[begin..line_end])
+            .unwrap();
+        let bullet = cap.get(1).unwrap().as_str();
+        let checkbox = cap.get(3).map(|m| match m.as_str() {
+            "[X]" => CheckBox::On,
+            "[-]" => CheckBox::Trans,
+            _ => CheckBox::Off,
+        });
+        // Literal counters become ordinals: A is 1.
+        let counter = cap.get(2).and_then(|m| match m.as_str().chars().next() {
+            Some(ch) if ch.is_ascii_alphabetic() => {
+                Some(ch.to_ascii_uppercase() as usize - 'A' as usize + 1)
+            }
+            _ => m.as_str().parse().ok(),
+        });
+
+        let item_end = structure
+            .items
+            .iter()
+            .find(|item| item.begin == begin)
+            .map_or(line_end, |item| item.end);
+        let mut c = self.cursor.borrow_mut();
+        c.set(item_end);
+        let end = if c.is_bol() {
+            item_end
+        } else {
+            c.line_beginning_position(Some(2))
+        };
+
+        // Ignore tags in ordered lists: they are just a part of
+        // item's body.
+        let ordered = bullet.contains(|ch| ch == '.' || ch == ')');
+        let tag = cap.get(4).filter(|_| !ordered);
+        let after_meta = match cap.get(4) {
+            Some(m) if ordered => m.start(),
+            _ => cap.get(0).unwrap().end(),
+        };
+        let mut pre_blank = 0;
+        c.set(begin + after_meta);
+        c.skip_chars_forward(" \r\t\n", Some(end));
+        let contents_begin = if c.pos() == end {
+            None
+        } else if c.line_beginning_position(None) == begin {
+            Some(c.pos())
+        } else {
+            let line_begin = c.goto_line_begin();
+            pre_blank = c.count_lines(line_begin, begin);
+            Some(line_begin)
+        };
+        let contents_end = contents_begin.map(|_| {
+            // (goto-char end) (skip-chars-backward " \r\t\n") (line-beginning-position 2)
+            let last = self.input[..end]
+                .trim_end_matches(|ch| " \r\t\n".contains(ch))
+                .len();
+            self.input[last..end]
+                .find('\n')
+                .map_or(end, |p| last + p + 1)
+        });
+        let post_blank = match contents_end {
+            Some(contents_end) => c.count_lines(contents_end, end),
+            None => c.count_lines(begin, end).saturating_sub(1),
+        };
+        drop(c);
+
+        let tag = tag.map(|m| {
+            let (start, end) = (begin + m.start(), begin + m.end());
+            if raw_secondary_p {
+                vec![Rc::new(SyntaxNode::new(
+                    Syntax::PlainText(m.as_str()),
+                    Interval { start, end },
+                    None,
+                    0,
+                    None,
+                ))]
+            } else {
+                self.parse_objects(start, end, |that| SyntaxT::Item.can_contain(that))
+            }
+        });
+
+        SyntaxNode::new(
+            Syntax::Item(Box::new(ItemData {
+                bullet,
+                checkbox,
+                counter,
+                pre_blank,
+                raw_tag: cap.get(4).filter(|_| !ordered).map(|m| m.as_str()),
+                tag,
+                structure,
+            })),
+            Interval { start: begin, end },
+            contents_begin.map(|start| Interval {
+                start,
+                end: contents_end.unwrap(),
+            }),
+            post_blank,
+            None,
+        )
+    }
+
+    /// Parse a plain list.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.  STRUCTURE is the structure of the plain list being
+    /// parsed.
+    ///
+    /// Return a list whose CAR is `plain-list' and CDR is a plist
+    /// containing `:type', `:begin', `:end', `:contents-begin' and
+    /// `:contents-end', `:structure', `:post-blank' and
+    /// `:post-affiliated' keywords.
+    ///
+    /// Assume point is at the beginning of the list."
+    /// (defun org-element-plain-list-parser (limit affiliated structure)
     pub fn plain_list_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
-        structure: Rc<ListStruct>,
+        affiliated: Option<AffiliatedData<'a>>,
+        structure: Rc<ListStruct<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let contents_begin = c.pos();
+        let first = structure
+            .items
+            .iter()
+            .find(|item| item.begin == contents_begin);
+        let type_s = if self.input[contents_begin..]
+            .trim_start_matches(|ch| ch == ' ' || ch == '\t')
+            .starts_with(|ch: char| ch.is_ascii_alphanumeric())
+        {
+            ListKind::Ordered
+        } else if first.map_or(false, |item| item.tag.is_some()) {
+            ListKind::Descriptive
+        } else {
+            ListKind::Unordered
+        };
+
+        // List ends after the last item at the indentation of the first one.
+        let mut siblings: Vec<&ItemStruct> = first.into_iter().collect();
+        if let Some(first) = first {
+            while let Some(item) = structure.items.iter().find(|item| {
+                item.begin == siblings[siblings.len() - 1].end && item.indent == first.indent
+            }) {
+                siblings.push(item);
+            }
+        }
+        let contents_end = siblings.last().map_or(contents_begin, |item| item.end);
+        let tight = siblings.iter().rev().skip(1).all(|item| {
+            end_of_contents(self.input, item.begin, item.end).min(item.end) == item.end
+        });
+        c.set(contents_end);
+        c.skip_chars_forward(" \r\t\n", Some(limit));
+        let end = if c.pos() >= limit {
+            limit
+        } else {
+            c.goto_line_begin()
+        };
+        let post_blank = c.count_lines(contents_end, end);
+
+        SyntaxNode::new(
+            Syntax::PlainList(Box::new(PlainListData {
+                structure,
+                type_s,
+                tight,
+            })),
+            Interval { start, end },
+            Some(Interval {
+                start: contents_begin,
+                end: contents_end,
+            }),
+            post_blank,
+            affiliated,
+        )
+    }
+
+    /// Return structure of list at point.  Internal function.  See
+    /// `org-list-struct' for details.
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L1377
+    /// (defun org-element--list-struct (limit)
+    ///
+    /// Cursor is expected at the beginning of the first item, it doesn't move.
+    /// Inline tasks are not supported, so they are not skipped.
+    pub fn list_struct(&self, limit: usize) -> Rc<ListStruct<'a>> {
+        let (data, pos) = {
+            let c = self.cursor.borrow();
+            (c.data(), c.pos())
+        };
+        let mut c = Cursor::new(data, pos);
+        let mut items: Vec<ItemStruct<'a>> = vec![];
+        let mut structure: Vec<ItemStruct<'a>> = vec![];
+
+        // (skip-chars-backward " \r\t\n") (line-beginning-position 2)
+        let end_before = |pos: usize| {
+            let last = data[..pos]
+                .trim_end_matches(|ch| " \r\t\n".contains(ch))
+                .len();
+            data[last..].find('\n').map_or(data.len(), |p| last + p + 1)
+        };
+
+        loop {
+            let pos = c.pos();
+            // At limit: end all items.
+            if pos >= limit || pos >= data.len() {
+                let end = end_before(pos);
+                for mut item in items.drain(..) {
+                    item.end = end;
+                    structure.push(item);
+                }
+                break;
+            }
+
+            // At list end: end all items.
+            if REGEX_LIST_END.is_match(&data[pos..]) {
+                for mut item in items.drain(..) {
+                    item.end = pos;
+                    structure.push(item);
+                }
+                break;
+            }
+
+            let line = c.peek_line(0).unwrap_or("");
+            let ind = indentation(line);
+            // At a new item: end previous sibling.
+            if REGEX_ITEM.is_match(line) {
+                while items
+                    .last()
+                    .map_or(false, |item| ind < self.sub_item_column(item))
+                {
+                    let mut item = items.pop().unwrap();
+                    item.end = pos;
+                    structure.push(item);
+                }
+                let cap = REGEX_LIST_FULL_ITEM.captures(line).unwrap();
+                let bullet = cap.get(1).unwrap().as_str();
+                items.push(ItemStruct {
+                    begin: pos,
+                    indent: ind,
+                    bullet,
+                    counter: cap.get(2).map(|m| m.as_str()),
+                    checkbox: cap.get(3).map(|m| match m.as_str() {
+                        "[X]" => CheckBox::On,
+                        "[-]" => CheckBox::Trans,
+                        _ => CheckBox::Off,
+                    }),
+                    // Description tag.
+                    tag: cap
+                        .get(4)
+                        .filter(|_| bullet.contains(|ch| "-+*".contains(ch)))
+                        .map(|m| m.as_str()),
+                    // Ending position, unknown so far.
+                    end: 0,
+                    parent: items.last().map(|item| item.begin),
+                });
+                c.goto_next_line();
+                continue;
+            }
+
+            // Skip empty lines.
+            if is_blank(line) {
+                c.goto_next_line();
+                continue;
+            }
+
+            // At some text line.  Check if it ends any previous item.
+            let end = end_before(pos);
+            while items.last().map_or(false, |item| ind <= item.indent) {
+                let mut item = items.pop().unwrap();
+                item.end = end;
+                structure.push(item);
+            }
+            if items.is_empty() {
+                break;
+            }
+
+            // Skip blocks (any type) and drawers contents.
+            let block = REGEX_ANY_BLOCK_BEGIN
+                .captures(line)
+                .map(|cap| cap[1].trim().to_owned());
+            if let Some(name) = block {
+                let end = format!(r"(?im)^[ \t]*#\+END{}[ \t]*$", regex::escape(&name));
+                c.re_search_forward(&Regex::new(&end).unwrap(), Some(limit));
+            } else if REGEX_DRAWER.is_match(line) {
+                c.re_search_forward(&*REGEX_DRAWER_END, Some(limit));
+            }
+            c.goto_next_line();
+        }
+
+        structure.sort_by_key(|item| item.begin);
+        Rc::new(ListStruct { items: structure })
+    }
+
+    /// Column a line starting with a bullet has to be indented to,
+    /// at least, to be a sub-item of `item`. That is the column
+    /// of item's contents, right after its bullet, unless
+    /// `list_indent_offset` is set.
+    fn sub_item_column(&self, item: &ItemStruct) -> usize {
+        match self.list_indent_offset {
+            Some(offset) => item.indent + offset.max(1),
+            None => item.bullet.chars().fold(item.indent, next_column),
+        }
+    }
+}
+
+/// Returns `src` with numbers of the items of `list`, a plain list
+/// node obtained by parsing `src`, made sequential. Sub-lists are
+/// renumbered as well. Every ordered list starts at 1, and an item with
+/// a numeric counter, e.g. `[@5]`, takes it as its number, following
+/// items counting from there. Delimiters of bullets are kept, and so is
+/// the indentation of continuation lines, even when numbers get wider.
+/// Unordered lists, and lists with alphabetical bullets, are left alone.
+///
+/// elisp: `org-list-struct-fix-bul`
+pub fn renumber_list(src: &str, list: &SyntaxNode) -> String {
+    let (structure, contents) = match (&list.data, list.content_location) {
+        (Syntax::PlainList(d), Some(contents)) => (&d.structure, contents),
+        _ => return src.to_string(),
+    };
+    let items: Vec<&ItemStruct> = structure
+        .items
+        .iter()
+        .filter(|item| item.begin >= contents.start && item.begin < contents.end)
+        .collect();
+    // Previous item of the same list, if any
+    let prev = |item: &ItemStruct| {
+        items
+            .iter()
+            .position(|p| p.end == item.begin && p.indent == item.indent)
+    };
+
+    // Number of each item, along with the span of the digits to replace
+    let mut numbers: Vec<Option<usize>> = vec![None; items.len()];
+    let mut edits: Vec<(usize, usize, usize)> = vec![];
+    for (i, item) in items.iter().enumerate() {
+        let bullet_begin = item.begin + src[item.begin..].len()
+            - src[item.begin..]
+                .trim_start_matches(|ch| ch == ' ' || ch == '\t')
+                .len();
+        let digits = item.bullet.bytes().take_while(u8::is_ascii_digit).count();
+        if digits == 0 {
+            continue;
+        }
+        let counter = item.counter.and_then(|counter| counter.parse().ok());
+        let number = match (counter, prev(item).and_then(|p| numbers[p])) {
+            (Some(counter), _) => counter,
+            (None, Some(previous)) => previous + 1,
+            (None, None) => 1,
+        };
+        numbers[i] = Some(number);
+        edits.push((bullet_begin, bullet_begin + digits, number));
     }
 
-    //(defun org-element--list-struct (limit)
-    ///  ;; Return structure of list at point.  Internal function.  See
-    ///  ;; `org-list-struct' for details.
-    pub fn list_struct(&self, limit: usize) -> Rc<ListStruct> {
-        unimplemented!()
+    let mut result = String::with_capacity(src.len());
+    let mut last = 0;
+    for (begin, end, number) in edits {
+        result.push_str(&src[last..begin]);
+        result.push_str(&number.to_string());
+        last = end;
+    }
+    result.push_str(&src[last..]);
+    result
+}
+
+fn is_blank(line: &str) -> bool {
+    REGEX_EMPTY_LINE.is_match(line)
+}
+
+/// Column of the first non-blank character of `line`
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|&ch| ch == ' ' || ch == '\t')
+        .fold(0, next_column)
+}
+
+/// Column after `ch` written at `col`, tabs are expanded to 8 columns
+fn next_column(col: usize, ch: char) -> usize {
+    match ch {
+        '\t' => (col / 8 + 1) * 8,
+        _ => col + 1,
+    }
+}
+
+mod test {
+    use super::{renumber_list, CheckBox, ItemStruct};
+    use crate::cursor::Cursor;
+    use crate::data::{Handle, Interval, Syntax, SyntaxNode, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+    use crate::regexps::REGEX_ITEM;
+
+    #[test]
+    fn item_tab_separator() {
+        let text = "-\titem\n1.\t\tfirst\n  *\tstar\n+ plus";
+        let mut cursor = Cursor::new(text, 0);
+        let m = cursor.looking_at(&*REGEX_ITEM).unwrap();
+        assert_eq!(2, m.end());
+
+        cursor.goto_next_line();
+        let m = cursor.looking_at(&*REGEX_ITEM).unwrap();
+        assert_eq!(4, m.end());
+        assert_eq!("1.\t\t", m.as_str());
+
+        cursor.goto_next_line();
+        let m = cursor.looking_at(&*REGEX_ITEM).unwrap();
+        assert_eq!("  *\t", m.as_str());
+
+        cursor.goto_next_line();
+        let m = cursor.looking_at(&*REGEX_ITEM).unwrap();
+        assert_eq!("+ ", m.as_str());
+    }
+
+    #[test]
+    fn item_bullet_at_eol() {
+        let cursor = Cursor::new("-\nnext", 0);
+        let m = cursor.looking_at(&*REGEX_ITEM).unwrap();
+        assert_eq!("-", m.as_str());
+
+        let cursor = Cursor::new("-text", 0);
+        assert!(cursor.looking_at(&*REGEX_ITEM).is_none());
+    }
+
+    #[test]
+    fn list_struct() {
+        let text = "- one\n  1. [X] sub\n  2) [@5] five\n\n- [-] term :: desc\n  text\n\nafter\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let structure = parser.list_struct(text.len());
+        assert_eq!(
+            vec![
+                ItemStruct {
+                    begin: 0,
+                    indent: 0,
+                    bullet: "- ",
+                    counter: None,
+                    checkbox: None,
+                    tag: None,
+                    end: 35,
+                    parent: None,
+                },
+                ItemStruct {
+                    begin: 6,
+                    indent: 2,
+                    bullet: "1. ",
+                    counter: None,
+                    checkbox: Some(CheckBox::On),
+                    tag: None,
+                    end: 19,
+                    parent: Some(0),
+                },
+                ItemStruct {
+                    begin: 19,
+                    indent: 2,
+                    bullet: "2) ",
+                    counter: Some("5"),
+                    checkbox: None,
+                    tag: None,
+                    end: 35,
+                    parent: Some(0),
+                },
+                ItemStruct {
+                    begin: 35,
+                    indent: 0,
+                    bullet: "- ",
+                    counter: None,
+                    checkbox: Some(CheckBox::Trans),
+                    tag: Some("term"),
+                    end: 61,
+                    parent: None,
+                },
+            ],
+            structure.items
+        );
+        assert_eq!(0, parser.cursor.borrow().pos());
+    }
+
+    #[test]
+    fn list_struct_end() {
+        // Block contents do not end the item, two blank lines end the list
+        let text = "- a\n  #+begin_src\nat col 0\n  #+end_src\n- b\n\n\npara";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let structure = parser.list_struct(text.len());
+        let bounds: Vec<_> = structure
+            .items
+            .iter()
+            .map(|item| (item.begin, item.end))
+            .collect();
+        assert_eq!(vec![(0, 39), (39, 43)], bounds);
+
+        let structure = parser.list_struct(39);
+        assert_eq!(1, structure.items.len());
+        assert_eq!(39, structure.items[0].end);
+    }
+
+    #[test]
+    fn list_struct_nesting() {
+        let text = "- a\n  - b\n - c\n";
+        let parents = |parser: &Parser| {
+            let structure = parser.list_struct(text.len());
+            structure
+                .items
+                .iter()
+                .map(|item| (item.begin, item.end, item.parent))
+                .collect::<Vec<_>>()
+        };
+
+        // Sub-items start at item's contents column
+        let mut parser = Parser::new(text, ParseGranularity::Element);
+        assert_eq!(
+            vec![(0, 10, None), (4, 10, Some(0)), (10, 15, None)],
+            parents(&parser)
+        );
+
+        // Any deeper bullet is a sub-item
+        parser.list_indent_offset = Some(1);
+        assert_eq!(
+            vec![(0, 15, None), (4, 10, Some(0)), (10, 15, Some(0))],
+            parents(&parser)
+        );
+
+        let text = "10. a\n   - b\n     - c\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let structure = parser.list_struct(text.len());
+        let parents: Vec<_> = structure.items.iter().map(|item| item.parent).collect();
+        assert_eq!(vec![None, None, Some(6)], parents);
+    }
+
+    #[test]
+    fn tight_and_loose_lists() {
+        let lists = |text: &str| {
+            let parser = Parser::new(text, ParseGranularity::Element);
+            let root = parser.parse_buffer();
+            let section = root.children.borrow()[0].clone();
+            let list = section.children.borrow()[0].clone();
+            let mut tight = vec![];
+            let mut stack = vec![list];
+            while let Some(node) = stack.pop() {
+                if let Syntax::PlainList(d) = &node.data {
+                    tight.push(d.tight);
+                }
+                stack.extend(node.children.borrow().iter().rev().cloned());
+            }
+            tight
+        };
+
+        assert_eq!(vec![true], lists("- a\n- b\n- c\n\nafter\n"));
+        assert_eq!(vec![false], lists("- a\n\n- b\n- c\n"));
+        // Blank line before a sub-list belongs to the item
+        assert_eq!(vec![true, true], lists("- a\n\n  - sub\n  - sub\n- b\n"));
+        // Blank line after a sub-list separates parent items only
+        assert_eq!(vec![false, true], lists("- a\n  - sub\n  - sub\n\n- b\n"));
+    }
+
+    #[test]
+    fn plain_list_tree() {
+        // Kind of lists, depth, bounds, and kind of item contents
+        fn tree(node: &Handle, depth: usize, out: &mut Vec<(String, usize, usize, usize)>) {
+            let kind = match &node.data {
+                Syntax::PlainList(d) => format!("{:?}", d.type_s),
+                data => format!("{:?}", SyntaxT::from(data)),
+            };
+            out.push((kind, depth, node.location.start, node.location.end));
+            for child in node.children.borrow().iter() {
+                tree(child, depth + 1, out);
+            }
+        }
+        let elements = |text: &str| {
+            let parser = Parser::new(text, ParseGranularity::Element);
+            let root = parser.parse_buffer();
+            let section = root.children.borrow()[0].clone();
+            let mut out = vec![];
+            for child in section.children.borrow().iter() {
+                tree(child, 0, &mut out);
+            }
+            out
+        };
+        let e = |kind: &str, depth, begin, end| (kind.to_string(), depth, begin, end);
+
+        let text = "1. first\n   - a\n   - b\n2) second\n   - term :: desc\n\nAfter\n";
+        assert_eq!(
+            vec![
+                e("Ordered", 0, 0, 52),
+                e("Item", 1, 0, 23),
+                e("Paragraph", 2, 3, 9),
+                e("Unordered", 2, 9, 23),
+                e("Item", 3, 9, 16),
+                e("Paragraph", 4, 14, 16),
+                e("Item", 3, 16, 23),
+                e("Paragraph", 4, 21, 23),
+                e("Item", 1, 23, 51),
+                e("Paragraph", 2, 26, 33),
+                e("Descriptive", 2, 33, 51),
+                e("Item", 3, 33, 51),
+                e("Paragraph", 4, 46, 51),
+                e("Paragraph", 0, 52, 58),
+            ],
+            elements(text)
+        );
+
+        // Blank line followed by a less indented paragraph ends the list,
+        // an indented paragraph continues the item
+        let text = "- x\n\n  more x\n- y\n\nAfter\n";
+        assert_eq!(
+            vec![
+                e("Unordered", 0, 0, 19),
+                e("Item", 1, 0, 14),
+                e("Paragraph", 2, 2, 5),
+                e("Paragraph", 2, 5, 14),
+                e("Item", 1, 14, 18),
+                e("Paragraph", 2, 16, 18),
+                e("Paragraph", 0, 19, 25),
+            ],
+            elements(text)
+        );
+    }
+
+    #[test]
+    fn item_parser() {
+        fn item(text: &str) -> SyntaxNode {
+            let parser = Parser::new(text, ParseGranularity::Element);
+            parser.item_parser(None, true)
+        }
+        fn data<'a>(
+            node: &SyntaxNode<'a>,
+        ) -> (String, Option<usize>, Option<CheckBox>, Option<&'a str>) {
+            match &node.data {
+                Syntax::Item(d) => (d.bullet.to_string(), d.counter, d.checkbox, d.raw_tag),
+                _ => panic!("expected item"),
+            }
+        }
+
+        let text = "- [ ] todo\n  continued\nafter";
+        let node = item(text);
+        assert_eq!(
+            (String::from("- "), None, Some(CheckBox::Off), None),
+            data(&node)
+        );
+        assert_eq!(Interval { start: 0, end: 23 }, node.location);
+        assert_eq!(Some(Interval { start: 6, end: 23 }), node.content_location);
+
+        let node = item("3. [@7] item\n   more\n");
+        assert_eq!((String::from("3. "), Some(7), None, None), data(&node));
+        assert_eq!(Some(Interval { start: 8, end: 21 }), node.content_location);
+
+        let node = item("- key :: value");
+        assert_eq!((String::from("- "), None, None, Some("key")), data(&node));
+        assert_eq!(Some(Interval { start: 9, end: 14 }), node.content_location);
+    }
+
+    #[test]
+    fn renumber() {
+        let renumber = |src: &str| {
+            let parser = Parser::new(src, ParseGranularity::Element);
+            let root = parser.parse_buffer();
+            let section = root.children.borrow()[0].clone();
+            let list = section.children.borrow()[0].clone();
+            renumber_list(src, &list)
+        };
+
+        assert_eq!(
+            "1. a\n2. b\n   1) sub\n   2) sub\n3. c\n\nText\n",
+            renumber("3. a\n3. b\n   4) sub\n   1) sub\n7. c\n\nText\n")
+        );
+        // Counters reset numbering
+        assert_eq!(
+            "1. a\n5. [@5] b\n6. c\n7. [@x] d\n",
+            renumber("1. a\n1. [@5] b\n1. c\n1. [@x] d\n")
+        );
+        assert_eq!(
+            "[@3] is not a counter\n",
+            renumber("[@3] is not a counter\n")
+        );
+        let unordered = "- a\n  1. sub\n  1. sub\n- b\n";
+        assert_eq!("- a\n  1. sub\n  2. sub\n- b\n", renumber(unordered));
+        assert_eq!(
+            "1. a\n2. b\n3. c\n4. d\n5. e\n6. f\n7. g\n8. h\n9. i\n10. j\n",
+            renumber("1. a\n1. b\n1. c\n1. d\n1. e\n1. f\n1. g\n1. h\n1. i\n1. j\n")
+        );
     }
 }
@@ -15,34 +15,29 @@
 //
 
 use crate::affiliated::AffiliatedData;
-use crate::data::SyntaxNode;
+use crate::cursor::Cursor;
+use crate::data::{
+    FootnoteReferenceData, FootnoteReferenceType, Interval, StatisticsCookieData, Syntax,
+    SyntaxNode,
+};
+use crate::paragraph::end_of_contents;
 use crate::parser::Parser;
-use regex::Regex;
-
-lazy_static! {
-    pub static ref REGEX_HORIZONTAL_RULE: Regex = Regex::new(r"[ \t]*-{5,}[ \t]*$").unwrap();
-
-    /// Regular expression matching the definition of a footnote.
-    /// Match group 1 contains definition's label
-    pub static ref REGEX_FOOTNOTE_DEFINITION: Regex = Regex::new(r"^\[fn:([-_[:word:]]+)\]").unwrap();
-
-
-    /// Fixed Width Areas
-    /// A “fixed-width line” start with a colon character and a whitespace or an end of line.
-    /// Fixed width areas can contain any number of consecutive fixed-width lines.
-    pub static ref REGEX_FIXED_WIDTH: Regex = Regex::new(r"[ \t]*:( |$)").unwrap();
-
-}
+use crate::regexps::{
+    REGEX_AFFILIATED, REGEX_COMMENT_LINE, REGEX_EMPTY_LINE, REGEX_FIXED_WIDTH,
+    REGEX_FOOTNOTE_DEFINITION, REGEX_FOOTNOTE_REFERENCE, REGEX_HEADLINE_SHORT,
+    REGEX_STATISTICS_COOKIE,
+};
 
 #[derive(Debug)]
-pub struct CommentData<'a> {
-    /// Comments, with pound signs (string).
-    value: &'a str,
+pub struct CommentData {
+    /// Comments, without pound signs and the space following them (string).
+    pub value: String,
 }
 
 #[derive(Debug)]
 pub struct FixedWidthData<'a> {
-    ///Contents, without colons prefix (string).
+    /// Contents, with colons prefix (string).
+    /// See `SyntaxNode::value` for contents without them.
     value: &'a str,
 }
 
@@ -50,52 +45,662 @@ pub struct FixedWidthData<'a> {
 #[derive(Debug)]
 pub struct FootnoteDefinitionData<'a> {
     /// Label used for references (string).
-    label: &'a str,
+    pub label: &'a str,
 
     /// Number of newline characters between the
     /// beginning of the footnoote and the beginning
     /// of the contents (0, 1 or 2).
-    pre_blank: u8,
+    pub pre_blank: u8,
+}
+
+/// Checks whether the line at cursor, assumed at its beginning, is a
+/// comment line. Unless `allow_indented`, the `#` has to be at column 0.
+pub(crate) fn is_comment_line(c: &Cursor, allow_indented: bool) -> bool {
+    match c.looking_at(&*REGEX_COMMENT_LINE) {
+        Some(m) => allow_indented || m.as_str().starts_with('#'),
+        None => false,
+    }
+}
+
+impl<'a> SyntaxNode<'a> {
+    /// Returns the value of a comment or a fixed-width element, obtained
+    /// by parsing `src`: its lines without their `#`, resp. `:`, prefix
+    /// and the space following it, joined with newlines.
+    /// Returns None for any other node.
+    ///
+    /// (replace-regexp-in-string "^[ \t]*: ?" "" ...)
+    pub fn value(&self, src: &'a str) -> Option<String> {
+        let prefix = match &self.data {
+            Syntax::Comment(_) => '#',
+            Syntax::FixedWidth(_) => ':',
+            _ => return None,
+        };
+        let begin = self.post_affiliated();
+        let end = end_of_contents(src, begin, self.location.end).min(self.location.end);
+        Some(strip_line_prefixes(&src[begin..end], prefix))
+    }
+}
+
+/// Returns lines of `text` without their `prefix`, along with the
+/// indentation before it and the space following it, joined with newlines
+fn strip_line_prefixes(text: &str, prefix: char) -> String {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| {
+            let line = line.trim_start_matches(|c| c == ' ' || c == '\t');
+            let line = line.strip_prefix(prefix).unwrap_or(line);
+            line.strip_prefix(' ').unwrap_or(line)
+        })
+        .collect();
+    lines.join("\n")
 }
 
 impl<'a> Parser<'a> {
-    // TODO implement comment_parser
+    /// Parse a comment.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `comment' and CDR is a plist
+    /// containing `:begin', `:end', `:value', `:post-blank',
+    /// `:post-affiliated' keywords.
+    ///
+    /// Assume point is at comment beginning."
+    /// (defun org-element-comment-parser (limit affiliated)
+    ///
+    /// Consecutive comment lines make a single comment.
     pub fn comment_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let post_affiliated = c.pos();
+        c.goto_next_line();
+        // Get comments ending.
+        while c.pos() < limit && is_comment_line(&c, self.allow_indented_comments) {
+            c.goto_next_line();
+        }
+        let com_end = c.pos().min(limit);
+        let value = strip_line_prefixes(&self.input[post_affiliated..com_end], '#');
+        c.set(com_end);
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::Comment(Box::new(CommentData { value })),
+            Interval { start, end },
+            None,
+            post_blank,
+            affiliated,
+        )
     }
 
-    // TODO implement horizontal_rule_parser
+    /// Parse an horizontal rule.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `horizontal-rule' and CDR is a plist
+    /// containing `:begin', `:end', `:post-blank' and `:post-affiliated'
+    /// keywords."
+    /// (defun org-element-horizontal-rule-parser (limit affiliated)
+    ///
+    /// A line of dashes between bars, e.g. `|-----|`, is a table rule
+    /// instead, it belongs to the table.
     pub fn horizontal_rule_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        self.cursor.borrow_mut().goto_next_line();
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::HorizontalRule,
+            Interval { start, end },
+            None,
+            post_blank,
+            affiliated,
+        )
     }
 
-    // TODO implement footnote_definition_parser
+    /// Parse a footnote definition.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `footnote-definition' and CDR is
+    /// a plist containing `:label', `:begin' `:end', `:contents-begin',
+    /// `:contents-end', `:pre-blank',`:post-blank' and
+    /// `:post-affiliated' keywords.
+    ///
+    /// Assume point is at the beginning of the footnote definition."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L849
+    /// (defun org-element-footnote-definition-parser (limit affiliated)
+    ///
+    /// Definition ends before the next headline, the next footnote
+    /// definition (and its affiliated keywords) or two consecutive
+    /// empty lines.
     pub fn footnote_definition_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let post_affiliated = c.pos();
+        let captures = c.capturing_at(&*REGEX_FOOTNOTE_DEFINITION).unwrap();
+        let label = captures.get(1).unwrap().as_str();
+        let label_end = post_affiliated + captures.get(0).unwrap().end();
+
+        // (re-search-forward org-element--footnote-separator limit t)
+        c.goto_next_line();
+        let end = loop {
+            let line = c.pos();
+            if line >= limit {
+                break limit;
+            }
+            if c.looking_at(&*REGEX_HEADLINE_SHORT).is_some() {
+                break line;
+            }
+            if c.looking_at(&*REGEX_FOOTNOTE_DEFINITION).is_some() {
+                // At a new footnote definition, make sure we end
+                // before any affiliated keyword above.
+                let mut end = line;
+                while end > post_affiliated {
+                    c.set(end);
+                    let prev = c.goto_prev_line();
+                    if prev <= post_affiliated || c.looking_at(&*REGEX_AFFILIATED).is_none() {
+                        break;
+                    }
+                    end = prev;
+                }
+                break end;
+            }
+            let next = c.goto_next_line();
+            if c.looking_at(&*REGEX_EMPTY_LINE).is_some()
+                && self.input[line..next].trim().is_empty()
+                && self.input[next..].contains('\n')
+            {
+                c.set(line);
+                c.skip_chars_forward(" \r\t\n", Some(limit));
+                if c.pos() >= limit {
+                    break limit;
+                }
+                break c.goto_line_begin();
+            }
+        };
+
+        c.set(label_end);
+        c.skip_chars_forward(" \r\t\n", Some(end));
+        let mut pre_blank = 0;
+        let contents_begin = if c.pos() >= end {
+            None
+        } else if c.line_beginning_position(None) == post_affiliated {
+            Some(c.pos())
+        } else {
+            let line = c.line_beginning_position(None);
+            pre_blank = c.count_lines(post_affiliated, line) as u8;
+            Some(line)
+        };
+        let contents_end = end_of_contents(self.input, label_end, end).min(end);
+        let post_blank = c.count_lines(contents_end, end);
+        c.set(end);
+
+        SyntaxNode::new(
+            Syntax::FootnoteDefinition(Box::new(FootnoteDefinitionData { label, pre_blank })),
+            Interval { start, end },
+            contents_begin.map(|contents_begin| Interval {
+                start: contents_begin,
+                end: contents_end,
+            }),
+            post_blank,
+            affiliated,
+        )
     }
 
-    // TODO implement fixed_width_parser
+    /// Parse footnote reference at point, if any.
+    ///
+    /// When at a footnote reference, return a list whose car is
+    /// `footnote-reference' and cdr a plist with `:label', `:type',
+    /// `:begin', `:end', `:contents-begin', `:contents-end' and
+    /// `:post-blank' as keywords.  Otherwise, return nil."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L2845
+    /// (defun org-element-footnote-reference-parser ()
+    ///
+    /// Only inline references have contents: the definition between
+    /// the label and the closing bracket, where brackets must be balanced.
+    /// They are parsed as objects along with the object containing the
+    /// reference, see `SyntaxT::can_contain`.
+    pub fn footnote_reference_parser(&self) -> Option<SyntaxNode<'a>> {
+        let mut c = self.cursor.borrow_mut();
+        let text = c.data();
+        let begin = c.pos();
+        let captures = REGEX_FOOTNOTE_REFERENCE.captures(&text[begin..])?;
+
+        // (scan-lists (point) 1 0)
+        let mut depth = 0;
+        let closing = text[begin..].char_indices().find_map(|(i, ch)| {
+            match ch {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                _ => (),
+            }
+            if depth == 0 {
+                Some(begin + i + 1)
+            } else {
+                None
+            }
+        })?;
+
+        let inline = captures.get(2).is_some();
+        let label = captures
+            .get(1)
+            .or_else(|| captures.get(3))
+            .map(|m| m.as_str());
+        let inner_begin = begin + captures.get(0).unwrap().end();
+        c.set(closing);
+        let post_blank = c.skip_chars_forward(" \t", None);
+        let end = c.pos();
+
+        let (type_s, contents) = if inline {
+            let contents = Interval {
+                start: inner_begin,
+                end: closing - 1,
+            };
+            (FootnoteReferenceType::Inline, Some(contents))
+        } else {
+            (FootnoteReferenceType::Standard, None)
+        };
+        Some(SyntaxNode::new(
+            Syntax::FootnoteReference(Box::new(FootnoteReferenceData { label, type_s })),
+            Interval { start: begin, end },
+            contents,
+            post_blank,
+            None,
+        ))
+    }
+
+    /// Parse a fixed-width section.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `fixed-width' and CDR is a plist
+    /// containing `:begin', `:end', `:value', `:post-blank' and
+    /// `:post-affiliated' keywords.
+    ///
+    /// Assume point is at the beginning of the fixed-width area."
+    /// (defun org-element-fixed-width-parser (limit affiliated)
+    ///
+    /// Consecutive fixed-width lines make a single element.
     pub fn fixed_width_parser(
         &self,
         limit: usize,
         start: usize,
-        maybe_aff: Option<AffiliatedData>,
+        maybe_aff: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let post_affiliated = c.pos();
+        while c.pos() < limit && c.looking_at(&*REGEX_FIXED_WIDTH).is_some() {
+            c.goto_next_line();
+        }
+        let end_area = c.pos().min(limit);
+        let value = self.input[post_affiliated..end_area].trim_end_matches('\n');
+        c.set(end_area);
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::FixedWidth(Box::new(FixedWidthData { value })),
+            Interval { start, end },
+            None,
+            post_blank,
+            maybe_aff,
+        )
+    }
+
+    /// Parse line break at point, if any.
+    ///
+    /// When at a line break, return a list whose car is `line-break',
+    /// and cdr a plist with `:begin', `:end' and `:post-blank' keywords.
+    /// Otherwise, return nil.
+    ///
+    /// Assume point is at the beginning of the line break."
+    /// (defun org-element-line-break-parser ()
+    ///
+    /// A line break is only valid at the end of a line: `\\` followed
+    /// by blanks up to the end of line, not preceded by another backslash.
+    pub fn line_break_parser(&self) -> Option<SyntaxNode<'a>> {
+        let c = self.cursor.borrow();
+        let text = c.data();
+        let begin = c.pos();
+        if !text[begin..].starts_with("\\\\") || text[..begin].ends_with('\\') {
+            return None;
+        }
+        // Stop at the first non-blank, not to scan the rest of
+        // a long line at each backslash.
+        let rest = &text[begin + 2..];
+        let eol = rest
+            .find(|c| c != ' ' && c != '\t')
+            .unwrap_or_else(|| rest.len());
+        if !rest[eol..].is_empty() && !rest[eol..].starts_with('\n') {
+            return None;
+        }
+        // (line-beginning-position 2)
+        let end = (begin + 2 + eol + 1).min(text.len());
+        Some(SyntaxNode::new(
+            Syntax::LineBreak,
+            Interval { start: begin, end },
+            None,
+            0,
+            None,
+        ))
+    }
+
+    /// Parse statistics cookie at point, if any.
+    ///
+    /// When at a statistics cookie, return a list whose car is
+    /// `statistics-cookie', and cdr a plist with `:begin', `:end',
+    /// `:value' and `:post-blank' keywords.  Otherwise, return nil.
+    ///
+    /// Assume point is at the beginning of the statistics-cookie."
+    /// (defun org-element-statistics-cookie-parser ()
+    pub fn statistics_cookie_parser(&self) -> Option<SyntaxNode<'a>> {
+        let mut c = self.cursor.borrow_mut();
+        let begin = c.pos();
+        let value = c.looking_at(&*REGEX_STATISTICS_COOKIE)?.as_str();
+        c.set(begin + value.len());
+        let post_blank = c.skip_chars_forward(" \t", None);
+        Some(SyntaxNode::new(
+            Syntax::StatisticsCookie(Box::new(StatisticsCookieData { value })),
+            Interval {
+                start: begin,
+                end: c.pos(),
+            },
+            None,
+            post_blank,
+            None,
+        ))
+    }
+}
+
+mod test {
+    use crate::data::{FootnoteReferenceType, Interval, Syntax, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+    use crate::table::TableRowType;
+
+    #[test]
+    fn comment_parser() {
+        let text = "# one\n  #\n  # two\n#not\n\nafter";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let comment = parser.comment_parser(text.len(), 0, None);
+        assert_eq!(Interval { start: 0, end: 18 }, comment.location);
+        assert_eq!(0, comment.post_blank);
+        match &comment.data {
+            Syntax::Comment(c) => assert_eq!("one\n\ntwo", c.value),
+            _ => panic!("expected comment"),
+        }
+
+        parser.cursor.borrow_mut().set(0);
+        let comment = parser.comment_parser(6, 0, None);
+        assert_eq!(Interval { start: 0, end: 6 }, comment.location);
+    }
+
+    #[test]
+    fn fixed_width_parser() {
+        let text = ": one\n:\n  :   indented\n:not\n\nafter";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let fixed = parser.fixed_width_parser(text.len(), 0, None);
+        assert_eq!(SyntaxT::FixedWidth, SyntaxT::from(&fixed.data));
+        assert_eq!(Interval { start: 0, end: 23 }, fixed.location);
+        assert_eq!(0, fixed.post_blank);
+        assert_eq!(Some("one\n\n  indented".to_string()), fixed.value(text));
+
+        let text = "#+NAME: out\n: a\n: b\n\n\nafter";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let fixed = section.children.borrow()[0].clone();
+        assert_eq!(Interval { start: 0, end: 22 }, fixed.location);
+        assert_eq!(2, fixed.post_blank);
+        assert_eq!(Some("a\nb".to_string()), fixed.value(text));
+    }
+
+    #[test]
+    fn comment_value() {
+        let text = "# one\n  #\n  # two\n#  three\n\nafter";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let comment = parser.comment_parser(text.len(), 0, None);
+        assert_eq!(Some("one\n\ntwo\n three".to_string()), comment.value(text));
+
+        let paragraph = parser.paragraph_parser(text.len(), 0, None);
+        assert_eq!(None, paragraph.value(text));
+    }
+
+    fn top_elements(text: &str, allow_indented_comments: bool) -> Vec<(SyntaxT, usize, usize)> {
+        let mut parser = Parser::new(text, ParseGranularity::Element);
+        parser.allow_indented_comments = allow_indented_comments;
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let elements = section
+            .children
+            .borrow()
+            .iter()
+            .map(|e| (SyntaxT::from(&e.data), e.location.start, e.location.end))
+            .collect();
+        elements
+    }
+
+    #[test]
+    fn indented_comments() {
+        let text = "  # foo
+";
+        assert_eq!(vec![(SyntaxT::Comment, 0, 8)], top_elements(text, true));
+        assert_eq!(vec![(SyntaxT::Paragraph, 0, 8)], top_elements(text, false));
+
+        let text = "# top
+  # foo
+text
+  # bar
+";
+        assert_eq!(
+            vec![
+                (SyntaxT::Comment, 0, 14),
+                (SyntaxT::Paragraph, 14, 19),
+                (SyntaxT::Comment, 19, 27),
+            ],
+            top_elements(text, true)
+        );
+        assert_eq!(
+            vec![(SyntaxT::Comment, 0, 6), (SyntaxT::Paragraph, 6, 27)],
+            top_elements(text, false)
+        );
+    }
+
+    #[test]
+    fn footnote_definition_parser() {
+        let text = "[fn:1] First\nline\n\n\n[fn:2]\n\nSecond\n#+NAME: n\n[fn:3] Third";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let first = parser.footnote_definition_parser(text.len(), 0, None);
+        assert_eq!(Interval { start: 0, end: 20 }, first.location);
+        assert_eq!(Some(Interval { start: 7, end: 18 }), first.content_location);
+        assert_eq!(2, first.post_blank);
+
+        parser.cursor.borrow_mut().set(20);
+        let second = parser.footnote_definition_parser(text.len(), 20, None);
+        assert_eq!(Interval { start: 20, end: 35 }, second.location);
+        assert_eq!(
+            Some(Interval { start: 28, end: 35 }),
+            second.content_location
+        );
+        match &second.data {
+            Syntax::FootnoteDefinition(d) => {
+                assert_eq!("2", d.label);
+                assert_eq!(2, d.pre_blank);
+            }
+            _ => panic!("expected footnote definition"),
+        }
+    }
+
+    #[test]
+    fn footnote_definition_termination() {
+        let definition = |text: &str| {
+            let parser = Parser::new(text, ParseGranularity::Element);
+            let root = parser.parse_buffer();
+            let section = &root.children.borrow()[0];
+            let first = section.children.borrow()[0].clone();
+            assert_eq!(SyntaxT::FootnoteDefinition, SyntaxT::from(&first.data));
+            let children: Vec<_> = first
+                .children
+                .borrow()
+                .iter()
+                .map(|e| (SyntaxT::from(&e.data), e.location.start, e.location.end))
+                .collect();
+            (first.location, first.content_location, children)
+        };
+
+        // By another definition
+        let (location, contents, children) = definition("[fn:1] One\n- item\n[fn:2] Two\n");
+        assert_eq!(Interval { start: 0, end: 18 }, location);
+        assert_eq!(Some(Interval { start: 7, end: 18 }), contents);
+        assert_eq!(
+            vec![(SyntaxT::Paragraph, 7, 11), (SyntaxT::PlainList, 11, 18)],
+            children
+        );
+
+        // By a headline
+        let (location, contents, children) = definition("[fn:1] One\ntwo\n* Headline\n");
+        assert_eq!(Interval { start: 0, end: 15 }, location);
+        assert_eq!(Some(Interval { start: 7, end: 15 }), contents);
+        assert_eq!(vec![(SyntaxT::Paragraph, 7, 15)], children);
+
+        // By two blank lines, a single one belongs to the definition
+        let (location, contents, children) = definition("[fn:1] One\n\ntwo\n\n\nafter\n");
+        assert_eq!(Interval { start: 0, end: 18 }, location);
+        assert_eq!(Some(Interval { start: 7, end: 16 }), contents);
+        assert_eq!(
+            vec![(SyntaxT::Paragraph, 7, 12), (SyntaxT::Paragraph, 12, 16)],
+            children
+        );
+    }
+
+    #[test]
+    fn footnote_reference_parser() {
+        let text = "[fn:a] [fn:b:some [nested] text]  [fn::anon] [fn:unclosed";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let standard = parser.footnote_reference_parser().unwrap();
+        assert_eq!(Interval { start: 0, end: 7 }, standard.location);
+        assert_eq!(None, standard.content_location);
+
+        parser.cursor.borrow_mut().set(7);
+        let inline = parser.footnote_reference_parser().unwrap();
+        assert_eq!(Interval { start: 7, end: 34 }, inline.location);
+        assert_eq!(
+            Some(Interval { start: 13, end: 31 }),
+            inline.content_location
+        );
+        assert_eq!(2, inline.post_blank);
+
+        parser.cursor.borrow_mut().set(34);
+        let anonymous = parser.footnote_reference_parser().unwrap();
+        match &anonymous.data {
+            Syntax::FootnoteReference(r) => {
+                assert_eq!(None, r.label);
+                assert_eq!(FootnoteReferenceType::Inline, r.type_s);
+            }
+            _ => panic!("expected footnote reference"),
+        }
+
+        parser.cursor.borrow_mut().set(45);
+        assert!(parser.footnote_reference_parser().is_none());
+    }
+
+    #[test]
+    fn inline_footnote_definition() {
+        let text = "[fn:1:see *this*] [fn:2]";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let objects = parser.parse_objects(0, text.len(), |t| SyntaxT::Paragraph.can_contain(t));
+        assert_eq!(2, objects.len());
+
+        let inline = &objects[0];
+        assert_eq!(Interval { start: 0, end: 18 }, inline.location);
+        let definition = inline.children.borrow();
+        let kinds: Vec<_> = definition.iter().map(|o| SyntaxT::from(&o.data)).collect();
+        assert_eq!(vec![SyntaxT::PlainText, SyntaxT::Bold], kinds);
+        assert_eq!(Interval { start: 10, end: 16 }, definition[1].location);
+        match &definition[1].children.borrow()[0].data {
+            Syntax::PlainText(text) => assert_eq!("this", *text),
+            _ => panic!("expected plain text in bold"),
+        }
+
+        // Standard references have no definition to parse
+        assert!(objects[1].children.borrow().is_empty());
+    }
+
+    #[test]
+    fn horizontal_rule_and_table_rule() {
+        let kinds = |text: &str| -> Vec<(SyntaxT, usize, usize)> {
+            let parser = Parser::new(text, ParseGranularity::Element);
+            let root = parser.parse_buffer();
+            let section = &root.children.borrow()[0];
+            let kinds = section
+                .children
+                .borrow()
+                .iter()
+                .map(|e| (SyntaxT::from(&e.data), e.location.start, e.location.end))
+                .collect();
+            kinds
+        };
+
+        assert_eq!(vec![(SyntaxT::HorizontalRule, 0, 6)], kinds("-----\n\n"));
+        assert_eq!(vec![(SyntaxT::HorizontalRule, 0, 10)], kinds("  ------- "));
+        assert_eq!(vec![(SyntaxT::Paragraph, 0, 5)], kinds("----\n"));
+        assert_eq!(vec![(SyntaxT::Paragraph, 0, 11)], kinds("----- text\n"));
+        assert_eq!(vec![(SyntaxT::Table, 0, 6)], kinds("|---|\n"));
+
+        let text = "Text\n-----\n| a |\n|---+---|\n| b |\n-----\n";
+        assert_eq!(
+            vec![
+                (SyntaxT::Paragraph, 0, 5),
+                (SyntaxT::HorizontalRule, 5, 11),
+                (SyntaxT::Table, 11, 33),
+                (SyntaxT::HorizontalRule, 33, 39),
+            ],
+            kinds(text)
+        );
+
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let table = &section.children.borrow()[2];
+        let rows: Vec<_> = table
+            .children
+            .borrow()
+            .iter()
+            .map(|row| match &row.data {
+                Syntax::TableRow(r) => r.table_row_type,
+                _ => panic!("expected table row"),
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                TableRowType::Standard,
+                TableRowType::Rule,
+                TableRowType::Standard
+            ],
+            rows
+        );
     }
 }
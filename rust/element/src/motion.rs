@@ -0,0 +1,326 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Structural motion, moving point from element to element
+
+use crate::data::{Handle, Syntax, SyntaxNode, SyntaxT};
+use crate::parser::Parser;
+use std::rc::Rc;
+
+impl<'a> Parser<'a> {
+    /// Determine closest element around point.
+    ///
+    /// Return value is the smallest element containing point. Objects
+    /// are never returned. When point is on the first line of a greater
+    /// element, e.g. on a headline, the greater element itself is
+    /// returned, otherwise the search goes on in its contents.
+    ///
+    /// (defun org-element-at-point ()
+    ///
+    /// Rust: the buffer is parsed once, on the first motion, by a parser
+    /// of its own configured like this one, so that diagnostics are not
+    /// reported again, and later changes to its configuration are not
+    /// taken into account. Contents are always parsed, even when
+    /// `lazy_contents` is set. The tree follows the granularity of the parser:
+    /// with `ParseGranularity::Headline` only headlines are found.
+    /// Tables are not entered, their rows are never returned.
+    pub fn element_at_point(&self) -> Option<Handle<'a>> {
+        let pos = self.cursor.borrow().pos();
+        element_path(&self.element_tree(), pos).pop()
+    }
+
+    /// Move forward by one element.
+    /// Move to the next element at the same level, when possible.
+    ///
+    /// (defun org-forward-element ()
+    ///
+    /// Rust: returns the new position of point, or `None`, leaving
+    /// point where it was, when it cannot move further down.
+    pub fn goto_next_element(&mut self) -> Option<usize> {
+        let pos = self.cursor.borrow().pos();
+        let root = self.element_tree();
+        let path = element_path(&root, pos);
+        let target = next_element(&root, &path);
+        self.cursor.borrow_mut().set(target.unwrap_or(pos));
+        target
+    }
+
+    /// Move backward by one element.
+    /// Move to the previous element at the same level, when possible.
+    ///
+    /// (defun org-backward-element ()
+    ///
+    /// Rust: returns the new position of point, or `None`, leaving
+    /// point where it was, when it cannot move further up.
+    pub fn goto_prev_element(&mut self) -> Option<usize> {
+        let pos = self.cursor.borrow().pos();
+        let root = self.element_tree();
+        let path = element_path(&root, pos);
+        let target = prev_element(&root, &path, pos, self.input);
+        self.cursor.borrow_mut().set(target.unwrap_or(pos));
+        target
+    }
+
+    /// Returns the tree of the whole input, parsing it on first use
+    fn element_tree(&self) -> Handle<'a> {
+        if let Some(root) = &*self.element_tree.borrow() {
+            return root.clone();
+        }
+        let mut parser = Parser::new(self.input, self.granularity);
+        parser.todo_keywords = self.todo_keywords.clone();
+        parser.list_indent_offset = self.list_indent_offset;
+        parser.allow_indented_comments = self.allow_indented_comments;
+        let root = Rc::new(parser.parse_buffer());
+        *self.element_tree.borrow_mut() = Some(root.clone());
+        root
+    }
+}
+
+/// Returns elements containing `pos`, from the outermost one to the
+/// element at point.
+fn element_path<'a>(root: &SyntaxNode<'a>, pos: usize) -> Vec<Handle<'a>> {
+    let mut path = vec![];
+    let mut children = root.children.borrow().clone();
+    while let Some(child) = children
+        .iter()
+        .find(|c| c.location.start <= pos && pos < c.location.end)
+        .cloned()
+    {
+        let t = SyntaxT::from(&child.data);
+        let enter = t != SyntaxT::Table
+            && t.is_greater_element()
+            && child
+                .content_location
+                .map_or(false, |c| c.start <= pos && pos < c.end);
+        children = child.children.borrow().clone();
+        path.push(child);
+        if !enter {
+            break;
+        }
+    }
+    path
+}
+
+/// Returns children of the parent of the last element of `path`
+fn siblings<'a>(root: &SyntaxNode<'a>, path: &[Handle<'a>]) -> Vec<Handle<'a>> {
+    match path.len() {
+        0 | 1 => root.children.borrow().clone(),
+        n => path[n - 2].children.borrow().clone(),
+    }
+}
+
+fn is_headline(node: &SyntaxNode) -> bool {
+    match node.data {
+        Syntax::Headline(_) => true,
+        _ => false,
+    }
+}
+
+/// elisp: `org-forward-element`
+fn next_element<'a>(root: &SyntaxNode<'a>, path: &[Handle<'a>]) -> Option<usize> {
+    let elem = path.last()?;
+    if is_headline(elem) {
+        // `org-forward-heading-same-level'
+        return siblings(root, path)
+            .iter()
+            .find(|s| is_headline(s) && s.location.start == elem.location.end)
+            .map(|s| s.location.start);
+    }
+    let end = elem.location.end;
+    match path.len().checked_sub(2).map(|i| &path[i]) {
+        Some(parent) if parent.content_location.map_or(false, |c| c.end <= end) => {
+            Some(parent.location.end)
+        }
+        _ => Some(end),
+    }
+}
+
+/// elisp: `org-backward-element`
+fn prev_element<'a>(
+    root: &SyntaxNode<'a>,
+    path: &[Handle<'a>],
+    pos: usize,
+    input: &str,
+) -> Option<usize> {
+    let elem = match path.last() {
+        Some(elem) => elem,
+        // Point is after the last element, on trailing blank lines
+        None => {
+            return path_before(root, pos, input)
+                .last()
+                .map(|e| e.location.start)
+        }
+    };
+    if is_headline(elem) {
+        if elem.location.start != pos {
+            return Some(elem.location.start);
+        }
+        // `org-backward-heading-same-level', when current headline has
+        // no sibling above, move to its parent.
+        let sibling = siblings(root, path)
+            .iter()
+            .find(|s| is_headline(s) && s.location.end == elem.location.start)
+            .map(|s| s.location.start);
+        return sibling.or_else(|| {
+            path.iter()
+                .rev()
+                .skip(1)
+                .find(|p| is_headline(p))
+                .map(|p| p.location.start)
+        });
+    }
+    if elem.location.start != pos {
+        return Some(elem.location.start);
+    }
+    prev_from(root, pos, input)
+}
+
+/// Moves to the beginning of the element before `beg`, or its
+/// outermost ancestor ending at `beg`
+fn prev_from(root: &SyntaxNode, beg: usize, input: &str) -> Option<usize> {
+    let path = path_before(root, beg, input);
+    let mut target = path.last()?.location.start;
+    for ancestor in path.iter().rev().skip(1) {
+        if ancestor.location.end > beg {
+            break;
+        }
+        target = ancestor.location.start;
+    }
+    Some(target)
+}
+
+/// Returns elements containing the last non blank character before
+/// `pos`, see `element_path`
+fn path_before<'a>(root: &SyntaxNode<'a>, pos: usize, input: &str) -> Vec<Handle<'a>> {
+    let before = input[..pos].trim_end_matches(|c| c == ' ' || c == '\t' || c == '\r' || c == '\n');
+    match before.len() {
+        0 => vec![],
+        n => element_path(root, n - 1),
+    }
+}
+
+mod test {
+    use crate::data::SyntaxT;
+    use crate::parser::{ParseGranularity, Parser};
+    use std::collections::HashSet;
+
+    const TEXT: &str = concat!(
+        "Para one\n",
+        "\n",
+        "- a\n",
+        "- b\n",
+        "\n",
+        "| x |\n",
+        "* H1\n",
+        "Text\n",
+        "* H2\n",
+        "** Sub\n",
+        "End\n"
+    );
+
+    fn next_from(parser: &mut Parser, pos: usize) -> Option<usize> {
+        parser.cursor.borrow_mut().set(pos);
+        parser.goto_next_element()
+    }
+
+    fn prev_from(parser: &mut Parser, pos: usize) -> Option<usize> {
+        parser.cursor.borrow_mut().set(pos);
+        parser.goto_prev_element()
+    }
+
+    #[test]
+    fn element_at_point() {
+        let parser = Parser::new(TEXT, ParseGranularity::Element);
+        let at = |pos: usize| {
+            parser.cursor.borrow_mut().set(pos);
+            let element = parser.element_at_point().unwrap();
+            assert_eq!(pos, parser.cursor.borrow().pos());
+            SyntaxT::from(&element.data)
+        };
+        assert_eq!(SyntaxT::Paragraph, at(3));
+        assert_eq!(SyntaxT::Item, at(10));
+        assert_eq!(SyntaxT::Paragraph, at(12));
+        assert_eq!(SyntaxT::Table, at(21));
+        assert_eq!(SyntaxT::Headline, at(27));
+        assert_eq!(SyntaxT::Paragraph, at(48));
+    }
+
+    #[test]
+    fn next_element() {
+        let mut parser = Parser::new(TEXT, ParseGranularity::Element);
+        // Within a section, over the list, item by item
+        assert_eq!(Some(10), next_from(&mut parser, 0));
+        assert_eq!(Some(14), next_from(&mut parser, 10));
+        assert_eq!(Some(19), next_from(&mut parser, 14));
+        // Last element of a section, to the next headline
+        assert_eq!(Some(25), next_from(&mut parser, 21));
+        assert_eq!(Some(35), next_from(&mut parser, 30));
+        // Headlines move to siblings only
+        assert_eq!(Some(35), next_from(&mut parser, 25));
+        assert_eq!(None, next_from(&mut parser, 35));
+        assert_eq!(35, parser.cursor.borrow().pos());
+    }
+
+    #[test]
+    fn prev_element() {
+        let mut parser = Parser::new(TEXT, ParseGranularity::Element);
+        // Beginning of current element first
+        assert_eq!(Some(19), prev_from(&mut parser, 22));
+        assert_eq!(Some(0), prev_from(&mut parser, 5));
+        // Previous element, out of the list it ends
+        assert_eq!(Some(10), prev_from(&mut parser, 19));
+        assert_eq!(Some(0), prev_from(&mut parser, 10));
+        assert_eq!(None, prev_from(&mut parser, 0));
+        // Across headline boundaries
+        assert_eq!(Some(25), prev_from(&mut parser, 30));
+        assert_eq!(Some(25), prev_from(&mut parser, 35));
+        assert_eq!(Some(40), prev_from(&mut parser, 47));
+        // Headline without sibling above moves to its parent, if any
+        assert_eq!(Some(35), prev_from(&mut parser, 40));
+        assert_eq!(None, prev_from(&mut parser, 25));
+    }
+
+    #[test]
+    fn motion_keeps_diagnostics() {
+        let text = "#+begin_src pyton\nprint(1)\n#+end_src\n\nText\n";
+        let mut parser = Parser::new(text, ParseGranularity::Element);
+        parser.src_languages = Some(HashSet::new());
+        parser.parse_buffer();
+        let diagnostics = parser.diagnostics.borrow().clone();
+        assert_eq!(1, diagnostics.len());
+
+        assert_eq!(Some(38), next_from(&mut parser, 0));
+        assert_eq!(Some(0), prev_from(&mut parser, 38));
+        parser.cursor.borrow_mut().set(40);
+        assert_eq!(
+            SyntaxT::Paragraph,
+            SyntaxT::from(&parser.element_at_point().unwrap().data)
+        );
+        assert_eq!(diagnostics, *parser.diagnostics.borrow());
+    }
+
+    #[test]
+    fn lazy_contents_motion() {
+        let text = "* H\nPara one\n\nPara two\n";
+        let mut parser = Parser::new(text, ParseGranularity::Element);
+        parser.lazy_contents = true;
+        parser.cursor.borrow_mut().set(15);
+        let element = parser.element_at_point().unwrap();
+        assert_eq!(SyntaxT::Paragraph, SyntaxT::from(&element.data));
+        assert_eq!(14, element.location.start);
+        assert_eq!(Some(14), next_from(&mut parser, 4));
+        assert_eq!(Some(4), prev_from(&mut parser, 14));
+    }
+}
@@ -13,36 +13,257 @@
 //    You should have received a copy of the GNU General Public License
 //    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::affiliated::AffiliatedData;
-use crate::data::SyntaxNode;
+use regex::Regex;
+
+use crate::affiliated::{AffiliatedData, ORG_ELEMENT_DUAL_KEYWORDS};
+use crate::cursor::Cursor;
+use crate::data::{Interval, Syntax, SyntaxNode};
+use crate::markup::is_comment_line;
 use crate::parser::Parser;
+use crate::regexps::{
+    REGEX_BLOCK_BEGIN_LINE, REGEX_COMMENT_LINE, REGEX_DRAWER, REGEX_DRAWER_END,
+    REGEX_KEYWORD_WITH_SECONDARY, REGEX_LATEX_BEGIN_ENVIRIONMENT, REGEX_PARAGRAPH_SEPARATE,
+};
 
-/// Parse a paragraph.
-///
-/// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
-/// the buffer position at the beginning of the first affiliated
-/// keyword and CDR is a plist of affiliated keywords along with
-/// their value.
+/// Returns the beginning of the line following the one containing
+/// the last non-blank character before `end`, i.e.
+/// (progn (skip-chars-backward " \r\t\n" start) (line-beginning-position 2))
+pub(crate) fn end_of_contents(input: &str, start: usize, end: usize) -> usize {
+    let trimmed = start
+        + input[start..end]
+            .trim_end_matches(|c| " \r\t\n".contains(c))
+            .len();
+    match input[trimmed..].find('\n') {
+        Some(eol) => trimmed + eol + 1,
+        None => input.len(),
+    }
+}
+
+/// Checks whether the line at cursor, matching `REGEX_PARAGRAPH_SEPARATE`,
+/// really ends a paragraph.
 ///
-/// Return a list whose CAR is `paragraph' and CDR is a plist
-/// containing `:begin', `:end', `:contents-begin' and
-/// `:contents-end', `:post-blank' and `:post-affiliated' keywords.
+/// A matching `org-element-paragraph-separate' is not
+/// necessarily the end of the paragraph.  In particular,
+/// drawers, blocks or LaTeX environments opening lines
+/// must be closed.  Moreover keywords with a secondary
+/// value must belong to "dual keywords".
 ///
-/// Assume point is at the beginning of the paragraph."
-/// (defun org-element-paragraph-parser (limit affiliated)
+/// Indented comment lines are not comments when `allow_indented_comments`
+/// is unset, they belong to the paragraph.
+fn ends_paragraph(c: &mut Cursor, limit: usize, allow_indented_comments: bool) -> bool {
+    let pos = c.pos();
+    if c.looking_at(&*REGEX_COMMENT_LINE).is_some() {
+        return is_comment_line(c, allow_indented_comments);
+    }
+    let closed = |c: &mut Cursor, re: &Regex| {
+        let found = c.re_search_forward(re, Some(limit)).is_some();
+        c.set(pos);
+        found
+    };
+    if c.looking_at(&*REGEX_DRAWER).is_some() {
+        return closed(c, &*REGEX_DRAWER_END);
+    }
+    if let Some(captures) = c.capturing_at(&*REGEX_BLOCK_BEGIN_LINE) {
+        let name = regex::escape(captures.get(1).unwrap().as_str());
+        let end = Regex::new(&format!(r"(?im)^[ \t]*#\+END_{}[ \t]*$", name)).unwrap();
+        return closed(c, &end);
+    }
+    if let Some(captures) = c.capturing_at(&*REGEX_LATEX_BEGIN_ENVIRIONMENT) {
+        let name = regex::escape(captures.get(1).unwrap().as_str());
+        let end = Regex::new(&format!(r"(?m)\\end\{{{}\}}[ \t]*$", name)).unwrap();
+        return closed(c, &end);
+    }
+    if let Some(captures) = c.capturing_at(&*REGEX_KEYWORD_WITH_SECONDARY) {
+        let keyword = captures.get(1).unwrap().as_str();
+        return ORG_ELEMENT_DUAL_KEYWORDS
+            .iter()
+            .any(|dual| dual.eq_ignore_ascii_case(keyword));
+    }
+    // Everything else is unambiguous.
+    true
+}
+
 impl<'a> Parser<'a> {
-    // TODO implement paragraph_parser
+    /// Parse a paragraph.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `paragraph' and CDR is a plist
+    /// containing `:begin', `:end', `:contents-begin' and
+    /// `:contents-end', `:post-blank' and `:post-affiliated' keywords.
+    ///
+    /// Assume point is at the beginning of the paragraph."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L2330
+    /// (defun org-element-paragraph-parser (limit affiliated)
+    ///
+    /// A paragraph ends before the first following line that starts
+    /// another element, see `REGEX_PARAGRAPH_SEPARATE`.
     pub fn paragraph_parser(
         &self,
         limit: usize,
         start: usize,
-        maybe_aff: Option<AffiliatedData>,
+        maybe_aff: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let contents_begin = c.pos();
+
+        // (end-of-line)
+        // (re-search-forward org-element-paragraph-separate limit 'move)
+        c.goto_next_line();
+        while c.pos() < limit
+            && !(c.looking_at(&*REGEX_PARAGRAPH_SEPARATE).is_some()
+                && ends_paragraph(&mut c, limit, self.allow_indented_comments))
+        {
+            c.goto_next_line();
+        }
+        let before_blank = c.pos().min(limit);
+        c.set(before_blank);
+        drop(c);
+
+        let contents_end = end_of_contents(self.input, contents_begin, before_blank);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::Paragraph,
+            Interval { start, end },
+            Some(Interval {
+                start: contents_begin,
+                end: contents_end,
+            }),
+            post_blank,
+            maybe_aff,
+        )
     }
 
-    // TODO implement section_parser
+    /// Parse a section.
+    ///
+    /// Return a list whose CAR is `section' and CDR is a plist
+    /// containing `:begin', `:end', `:contents-begin', `contents-end'
+    /// and `:post-blank' keywords."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L2382
+    /// (defun org-element-section-parser (_)
+    ///
+    /// Section ends at the next headline or at `limit`.
     pub fn section_parser(&self, limit: usize) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        // Beginning of section is the beginning of the first non-blank
+        // line after previous headline.
+        let begin = c.pos();
+        let end = c.next_headline().unwrap_or(limit).min(limit);
+        c.set(begin);
+
+        let pos_before_blank = end_of_contents(self.input, begin, end).min(end);
+        let post_blank = c.count_lines(pos_before_blank, end);
+
+        SyntaxNode::new(
+            Syntax::Section,
+            Interval { start: begin, end },
+            Some(Interval {
+                start: begin,
+                end: pos_before_blank,
+            }),
+            post_blank,
+            None,
+        )
+    }
+}
+
+mod test {
+    use crate::data::{Interval, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+
+    #[test]
+    fn paragraph_parser() {
+        let text = "First line\nsecond line\n\n\nNext paragraph\n* Headline";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let paragraph = parser.paragraph_parser(text.len(), 0, None);
+        assert_eq!(SyntaxT::Paragraph, SyntaxT::from(&paragraph.data));
+        assert_eq!(Interval { start: 0, end: 25 }, paragraph.location);
+        assert_eq!(
+            Some(Interval { start: 0, end: 23 }),
+            paragraph.content_location
+        );
+        assert_eq!(2, paragraph.post_blank);
+
+        parser.cursor.borrow_mut().set(25);
+        let paragraph = parser.paragraph_parser(text.len(), 25, None);
+        assert_eq!(Interval { start: 25, end: 40 }, paragraph.location);
+        assert_eq!(0, paragraph.post_blank);
+    }
+
+    fn paragraph_end(text: &str) -> usize {
+        let parser = Parser::new(text, ParseGranularity::Element);
+        parser.paragraph_parser(text.len(), 0, None).location.end
+    }
+
+    #[test]
+    fn paragraph_ends_at_element() {
+        assert_eq!(11, paragraph_end("Some text\n\n| a | b |\n"));
+        assert_eq!(10, paragraph_end("Some text\n  | a | b |\n"));
+        assert_eq!(10, paragraph_end("Some text\n* Headline\n"));
+        assert_eq!(10, paragraph_end("Some text\n- item\n"));
+        assert_eq!(10, paragraph_end("Some text\n#+NAME: x\n"));
+        assert_eq!(10, paragraph_end("Some text\n#+CAPTION[short]: long\n"));
+        assert_eq!(
+            10,
+            paragraph_end("Some text\n#+begin_quote\nq\n#+end_quote\n")
+        );
+        assert_eq!(10, paragraph_end("Some text\n:DRAWER:\nd\n:end:\n"));
+        assert_eq!(10, paragraph_end("Some text\n-----\n"));
+
+        // Lines that look like the beginning of an element but are not
+        let text = "a | b\n*bold* text\n#+BEGIN_QUOTE\n:DRAWER:\n#+KEY[x]: y\n#not keyword\n";
+        assert_eq!(text.len(), paragraph_end(text));
+    }
+
+    #[test]
+    fn section_parser() {
+        let text = "Some text\n\n* Headline\nbody";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let section = parser.section_parser(text.len());
+        assert_eq!(Interval { start: 0, end: 11 }, section.location);
+        assert_eq!(
+            Some(Interval { start: 0, end: 10 }),
+            section.content_location
+        );
+        assert_eq!(1, section.post_blank);
+
+        parser.cursor.borrow_mut().set(22);
+        let section = parser.section_parser(text.len());
+        assert_eq!(Interval { start: 22, end: 26 }, section.location);
+        assert_eq!(0, section.post_blank);
+    }
+
+    #[test]
+    fn paragraph_with_hash_lines() {
+        let text = concat!(
+            "#+not a keyword\n",
+            "Some text\n",
+            "#not a comment\n",
+            "#+ spaced: no\n",
+            "# comment\n",
+            "#+NAME: x\n",
+            "named\n"
+        );
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let elements: Vec<_> = section
+            .children
+            .borrow()
+            .iter()
+            .map(|e| (SyntaxT::from(&e.data), e.location.start, e.location.end))
+            .collect();
+        assert_eq!(
+            vec![
+                (SyntaxT::Paragraph, 0, 55),
+                (SyntaxT::Comment, 55, 65),
+                (SyntaxT::Paragraph, 65, 81),
+            ],
+            elements
+        );
     }
 }
@@ -14,33 +14,27 @@
 //    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::rc::Rc;
 
 use regex::Regex;
 
-use crate::babel::REGEX_BABEL_CALL;
-use crate::cursor::Cursor;
-use crate::data::{Handle, Syntax, SyntaxNode, SyntaxT};
-
-use crate::blocks::{
-    REGEX_BLOCK_BEGIN, REGEX_COLON_OR_EOL, REGEX_DYNAMIC_BLOCK, REGEX_STARTS_WITH_HASHTAG,
+use crate::cursor::{line_number, Cursor};
+use crate::data::{Handle, Interval, Syntax, SyntaxNode, SyntaxT};
+use crate::emphasis::UnclosedEmphasis;
+use crate::headline::TodoKeywords;
+use crate::list::ListStruct;
+use crate::regexps::{
+    REGEX_BABEL_CALL, REGEX_BLOCK_BEGIN, REGEX_CLOCK_LINE, REGEX_COLON_OR_EOL, REGEX_DIARY_SEXP,
+    REGEX_DRAWER, REGEX_DYNAMIC_BLOCK, REGEX_FIXED_WIDTH, REGEX_FOOTNOTE_DEFINITION,
+    REGEX_HEADLINE_SHORT, REGEX_HORIZONTAL_RULE, REGEX_ITEM, REGEX_KEYWORD,
+    REGEX_LATEX_BEGIN_ENVIRIONMENT, REGEX_PLANNING_LINE, REGEX_PROPERTY_DRAWER,
+    REGEX_STARTS_WITH_HASHTAG, REGEX_TABLE_BORDER, REGEX_TABLE_PRE_BORDER, REGEX_TABLE_RULE,
 };
-use crate::drawer::REGEX_DRAWER;
-use crate::headline::REGEX_CLOCK_LINE;
-use crate::headline::REGEX_HEADLINE_SHORT;
-use crate::headline::REGEX_PLANNING_LINE;
-use crate::headline::REGEX_PROPERTY_DRAWER;
-use crate::keyword::*;
-use crate::latex::REGEX_LATEX_BEGIN_ENVIRIONMENT;
-use crate::list::*;
-use crate::markup::REGEX_FIXED_WIDTH;
-use crate::markup::REGEX_FOOTNOTE_DEFINITION;
-use crate::markup::REGEX_HORIZONTAL_RULE;
-use crate::planning::REGEX_DIARY_SEXP;
-use crate::table::{REGEX_TABLE_BORDER, REGEX_TABLE_PRE_BORDER, REGEX_TABLE_RULE};
+use crate::settings::BufferSettings;
 
 /// determines the depth of the recursion.
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum ParseGranularity {
     /// Only parse headlines.
     Headline,
@@ -67,10 +61,60 @@ pub enum ParserMode {
     PropertyDrawer,
 }
 
+/// A problem found in the input that does not prevent it from being parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Part of the input the diagnostic refers to
+    pub location: Interval,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Line the diagnostic starts at in `input`, the text it was
+    /// produced for, counting from 1 the same way Emacs does.
+    pub fn line_number(&self, input: &str) -> usize {
+        line_number(input, self.location.start)
+    }
+}
+
 pub struct Parser<'a> {
     pub cursor: RefCell<Cursor<'a>>,
     pub input: &'a str,
     pub granularity: ParseGranularity,
+
+    /// Languages that are considered valid for source blocks.
+    /// When set, every source block with a language outside of this
+    /// set produces a diagnostic. Parsing is not affected.
+    pub src_languages: Option<HashSet<String>>,
+
+    /// Diagnostics collected while parsing
+    pub diagnostics: RefCell<Vec<Diagnostic>>,
+
+    /// Last opening emphasis markers found to be unclosed.
+    /// Keeps object parsing linear in the length of a line.
+    pub unclosed_emphasis: RefCell<Vec<UnclosedEmphasis>>,
+
+    /// TODO keywords recognized in headlines
+    pub todo_keywords: TodoKeywords,
+
+    /// When set, only headlines are recursed into. Contents of any other
+    /// element keep their bounds but are parsed on demand,
+    /// see `force_contents`.
+    pub lazy_contents: bool,
+
+    /// Minimum offset, in columns, from the bullet of an item to the
+    /// bullet of its sub-items. When None, sub-items have to be
+    /// indented up to the contents of the item, past its bullet.
+    pub list_indent_offset: Option<usize>,
+
+    /// Whether comment lines may be indented, as in org. When unset,
+    /// only lines starting with `#` at column 0 are comments and an
+    /// indented `# foo` line is parsed as a paragraph.
+    pub allow_indented_comments: bool,
+
+    /// Tree of the whole input used by structural motion, parsed
+    /// on the first motion, see `element_at_point`
+    pub(crate) element_tree: RefCell<Option<Handle<'a>>>,
 }
 
 macro_rules! looking_at {
@@ -88,16 +132,63 @@ macro_rules! capturing_at {
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str, granularity: ParseGranularity) -> Parser {
         Parser {
-            cursor: RefCell::new(Cursor::new(input, 0)),
+            cursor: RefCell::new(Cursor::at_start(input)),
             input,
             granularity,
+            src_languages: None,
+            diagnostics: RefCell::new(vec![]),
+            unclosed_emphasis: RefCell::new(vec![]),
+            lazy_contents: false,
+            list_indent_offset: None,
+            allow_indented_comments: true,
+            todo_keywords: TodoKeywords::default(),
+            element_tree: RefCell::new(None),
         }
     }
 
+    /// Makes parsing follow buffer `settings`, e.g. TODO keywords
+    /// defined with `#+TODO:`
+    pub fn apply_settings(&mut self, settings: &BufferSettings) {
+        if let Some(keywords) = &settings.todo_keywords {
+            self.todo_keywords = keywords.clone();
+        }
+    }
+
+    /// Records a diagnostic about the part of input between `start` and `end`
+    pub fn report(&self, start: usize, end: usize, message: String) {
+        self.diagnostics.borrow_mut().push(Diagnostic {
+            location: Interval { start, end },
+            message,
+        });
+    }
+
+    /// Moves cursor over blank lines that follow an element and
+    /// returns the position where the element ends along with the number
+    /// of blank lines skipped. Cursor is expected to be at the beginning
+    /// of the line following the last line of the element.
+    ///
+    /// Almost every element parser ends with this sequence:
+    /// (pos-before-blank (progn (forward-line) (point)))
+    /// (end (progn (skip-chars-forward " \r\t\n" limit)
+    ///             (if (eobp) (point) (line-beginning-position))))
+    /// :post-blank (count-lines pos-before-blank end)
+    pub fn skip_post_blank(&self, limit: usize) -> (usize, usize) {
+        let mut c = self.cursor.borrow_mut();
+        let pos_before_blank = c.pos();
+        c.skip_chars_forward(" \r\t\n", Some(limit));
+        let end = if c.pos() == self.input.len() {
+            c.pos()
+        } else {
+            c.goto_line_begin()
+        };
+        (end, c.count_lines(pos_before_blank, end))
+    }
+
     /// Returns parser mode according to given `element` and `is_parent`
     /// `element` is AllElements variant representing the type of an element
     /// containing next element if `is_parent` is true, or before it
-    /// otherwise.
+    /// otherwise. None means no special mode: elements are parsed
+    /// the same way anywhere, e.g. no node property is recognized.
     /// <br>
     /// Original function name: org-element--next-mode
     /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L4273
@@ -128,13 +219,14 @@ impl<'a> Parser<'a> {
 
     /// org-element-parse-buffer
     /// Parses input from beginning to the end
-    pub fn parse_buffer(&'a self) -> SyntaxNode {
+    pub fn parse_buffer(&self) -> SyntaxNode<'a> {
         self.cursor.borrow_mut().set(0);
         self.cursor.borrow_mut().skip_whitespace();
 
         let end = self.input.len();
         let mut root = SyntaxNode::create_root();
-        root.children = RefCell::new(self.parse_elements(0, end, ParserMode::FirstSection, None));
+        root.children =
+            RefCell::new(self.parse_elements(0, end, Some(ParserMode::FirstSection), None));
         root
     }
 
@@ -153,12 +245,12 @@ impl<'a> Parser<'a> {
     ///     (beg end mode structure granularity visible-only acc)
     /// TODO do not forget to fix child-parent and parent-child links on tree updates
     fn parse_elements(
-        &'a self,
+        &self,
         beg: usize,
         end: usize,
-        mut mode: ParserMode,
-        structure: Option<Rc<ListStruct>>,
-    ) -> Vec<Handle> {
+        mut mode: Option<ParserMode>,
+        structure: Option<Rc<ListStruct<'a>>>,
+    ) -> Vec<Handle<'a>> {
         let pos = self.cursor.borrow_mut().pos();
         self.cursor.borrow_mut().set(beg);
 
@@ -188,7 +280,7 @@ impl<'a> Parser<'a> {
 
             // Recurse into element's children if it has contents
             if element.content_location.is_some() {
-                let content_location = element.content_location.unwrap();
+                let syntax = SyntaxT::from(&element.data);
 
                 // If this is a Greater element:
                 // parse it between `contents_begin' and `contents_end'
@@ -197,56 +289,91 @@ impl<'a> Parser<'a> {
                 //    in order to get sub-level headings.
                 // 2. Granularity is Element or Object
                 // 3. This is Section and Granularity is GreaterElement
-                if SyntaxT::from(&element.data).is_greater_element() {
-                    if (SyntaxT::Headline == SyntaxT::from(&element.data))
-                        || (self.granularity == ParseGranularity::Element
-                            || self.granularity == ParseGranularity::Object)
-                        || ((SyntaxT::Section == SyntaxT::from(&element.data))
-                            && (self.granularity == ParseGranularity::GreaterElement))
-                    {
-                        // (and (memq type '(item plain-list))
-                        // (org-element-property :structure element))
-                        let list_sturct = match &element.data {
-                            Syntax::PlainList(d) => Some(d.structure.clone()),
-                            _ => None,
-                        };
-
-                        //  Possibly switch to a special mode.
-                        // (org-element--next-mode type t)
-                        let new_mode =
-                            Parser::next_mode(SyntaxT::from(&element.data), true).unwrap_or(mode);
-
-                        element.children.replace(self.parse_elements(
-                            content_location.start,
-                            content_location.end,
-                            new_mode,
-                            list_sturct,
-                        ));
-                    }
-                }
-                // Any other element with contents, if granularity allows it
-                else {
-                    // (org-element--parse-objects
-                    //    cbeg (org-element-property :contents-end element)
-                    //    element (org-element-restriction type))))
-                    if let ParseGranularity::Object = &self.granularity {
-                        element.children.replace(self.parse_objects(
-                            content_location.start,
-                            content_location.end,
-                            |that| SyntaxT::from(&element.data).can_contain(that),
-                        ));
-                    }
+                // Any other element containing objects, if granularity allows it
+                let recurse = (SyntaxT::Headline == syntax)
+                    || (!self.lazy_contents && self.recurses_into(syntax));
+                if recurse {
+                    element.children.replace(self.parse_contents(&element));
                 }
             }
-            if let Some(m) = Parser::next_mode(SyntaxT::from(&element.data), false) {
-                mode = m
-            }
+            // (setq mode (org-element--next-mode mode type nil))
+            mode = Parser::next_mode(SyntaxT::from(&element.data), false);
             elements.push(Rc::new(element));
         }
         self.cursor.borrow_mut().set(pos);
         elements
     }
 
+    /// Whether contents of elements of type `syntax` are parsed
+    /// at parser's granularity
+    fn recurses_into(&self, syntax: SyntaxT) -> bool {
+        if syntax.is_greater_element() {
+            (SyntaxT::Headline == syntax)
+                || self.granularity == ParseGranularity::Element
+                || self.granularity == ParseGranularity::Object
+                || ((SyntaxT::Section == syntax)
+                    && (self.granularity == ParseGranularity::GreaterElement))
+        } else {
+            self.granularity == ParseGranularity::Object
+        }
+    }
+
+    /// Returns children of `node`, a node of the tree built by this
+    /// parser. When they were left out because of `lazy_contents`,
+    /// they are parsed exactly as they would be without it, following
+    /// parser's granularity and settings, and kept in the node.
+    pub fn force_contents(&self, node: &SyntaxNode<'a>) -> Vec<Handle<'a>> {
+        if node.children.borrow().is_empty()
+            && node.content_location.is_some()
+            && self.recurses_into(SyntaxT::from(&node.data))
+        {
+            node.children.replace(self.parse_contents(node));
+        }
+        node.children.borrow().clone()
+    }
+
+    /// Parses contents of `element` into a list of its children.
+    /// Greater elements contain elements, parsed in the mode the
+    /// element switches to, if any. Other elements contain objects.
+    /// Returns nothing if `element` has no contents.
+    pub(crate) fn parse_contents(&self, element: &SyntaxNode<'a>) -> Vec<Handle<'a>> {
+        let syntax = SyntaxT::from(&element.data);
+        let content_location = match element.content_location {
+            Some(content_location) => content_location,
+            None => return vec![],
+        };
+
+        if syntax.is_greater_element() {
+            // (and (memq type '(item plain-list))
+            // (org-element-property :structure element))
+            let list_sturct = match &element.data {
+                Syntax::PlainList(d) => Some(d.structure.clone()),
+                Syntax::Item(d) => Some(d.structure.clone()),
+                _ => None,
+            };
+
+            //  Possibly switch to a special mode.
+            // (org-element--next-mode type t)
+            let new_mode = Parser::next_mode(syntax, true);
+
+            self.parse_elements(
+                content_location.start,
+                content_location.end,
+                new_mode,
+                list_sturct,
+            )
+        } else if syntax.is_object_container() {
+            // (org-element--parse-objects
+            //    cbeg (org-element-property :contents-end element)
+            //    element (org-element-restriction type))))
+            self.parse_objects(content_location.start, content_location.end, |that| {
+                syntax.can_contain(that)
+            })
+        } else {
+            vec![]
+        }
+    }
+
     /// Parse the element starting at cursor position (point).
     /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L3833
     /// (defun org-element--current-element (limit &optional granularity mode structure)
@@ -266,28 +393,29 @@ impl<'a> Parser<'a> {
     fn current_element(
         &self,
         limit: usize,
-        mode: ParserMode,
-        structure: Option<Rc<ListStruct>>,
+        mode: Option<ParserMode>,
+        structure: Option<Rc<ListStruct<'a>>>,
     ) -> SyntaxNode<'a> {
         let pos = self.cursor.borrow().pos();
 
-        let raw_secondary_p = self.granularity == ParseGranularity::Object;
+        // When granularity is broader than `object`, secondary values are not parsed.
+        let raw_secondary_p = self.granularity != ParseGranularity::Object;
 
         let get_current_element = || -> SyntaxNode<'a> {
             use crate::parser::ParserMode::*;
 
             // Item
-            if mode == Item {
+            if mode == Some(Item) {
                 return self.item_parser(structure, raw_secondary_p);
             }
 
             // Table Row.
-            if mode == TableRow {
+            if mode == Some(TableRow) {
                 return self.table_row_parser();
             }
 
             // Node Property.
-            if mode == NodeProperty {
+            if mode == Some(NodeProperty) {
                 return self.node_property_parser(limit);
             }
 
@@ -297,11 +425,11 @@ impl<'a> Parser<'a> {
             }
 
             // Sections (must be checked after headline).
-            if mode == Section {
+            if mode == Some(Section) {
                 return self.section_parser(limit);
             }
 
-            if mode == FirstSection {
+            if mode == Some(FirstSection) {
                 let pos = self.cursor.borrow().pos();
                 let lim = self.cursor.borrow_mut().next_headline().unwrap_or(limit);
                 self.cursor.borrow_mut().set(pos);
@@ -317,7 +445,7 @@ impl<'a> Parser<'a> {
                 let is_match_planning = c.looking_at(&*REGEX_PLANNING_LINE).is_some();
                 drop(c);
 
-                if mode == Planning && is_prev_line_headline && is_match_planning {
+                if mode == Some(Planning) && is_prev_line_headline && is_match_planning {
                     return self.planning_parser(limit);
                 }
             }
@@ -325,15 +453,17 @@ impl<'a> Parser<'a> {
             // Property drawer.
             {
                 let mut c = self.cursor.borrow_mut();
-                let delta = if mode == Planning { 0 } else { -1 };
+                let delta = if mode == Some(Planning) { 0 } else { -1 };
                 let maybe_headline_offset = c.line_beginning_position(Some(delta));
                 let maybe_star = c.char_after(maybe_headline_offset);
-                let is_prev_line_headline = Some('*') == maybe_star;
+                // A property drawer may also start the zeroth section.
+                let is_buffer_start = mode == Some(Planning) && c.pos() == 0;
+                let is_prev_line_headline = Some('*') == maybe_star || is_buffer_start;
 
                 let is_match_property_drawer = c.looking_at(&*REGEX_PROPERTY_DRAWER).is_some();
                 drop(c);
 
-                if (mode == Planning || mode == PropertyDrawer)
+                if (mode == Some(Planning) || mode == Some(PropertyDrawer))
                     && is_prev_line_headline
                     && is_match_property_drawer
                 {
@@ -344,7 +474,8 @@ impl<'a> Parser<'a> {
             // When not at bol, point is at the beginning of an item or
             // a footnote definition: next item is always a paragraph.
             if !self.cursor.borrow().is_bol() {
-                return self.paragraph_parser(limit, self.cursor.borrow().pos(), None);
+                let pos = self.cursor.borrow().pos();
+                return self.paragraph_parser(limit, pos, None);
             }
 
             // Clock.
@@ -384,16 +515,21 @@ impl<'a> Parser<'a> {
             }
 
             // Inline Comments, Blocks, Babel Calls, Dynamic Blocks and Keywords.
-            if let Some(m) = looking_at!(REGEX_STARTS_WITH_HASHTAG, self) {
-                self.cursor.borrow_mut().set(m.end());
-                if looking_at!(REGEX_COLON_OR_EOL, self).is_some() {
+            let hashtag = looking_at!(REGEX_STARTS_WITH_HASHTAG, self).map(|m| m.end());
+            if let Some(offset) = hashtag {
+                let pos = self.cursor.borrow().pos();
+                self.cursor.borrow_mut().set(pos + offset);
+                if looking_at!(REGEX_COLON_OR_EOL, self).is_some()
+                    && (self.allow_indented_comments || offset == 1)
+                {
                     self.cursor.borrow_mut().goto_line_begin();
                     return self.comment_parser(limit, aff_start, maybe_aff);
                 }
 
-                if let Some(cap) = capturing_at!(REGEX_BLOCK_BEGIN, self) {
+                let block = capturing_at!(REGEX_BLOCK_BEGIN, self)
+                    .map(|cap| cap.get(1).unwrap().as_str().to_ascii_uppercase());
+                if let Some(name) = block {
                     self.cursor.borrow_mut().goto_line_begin();
-                    let name = cap.get(1).unwrap().as_str().to_owned().to_ascii_uppercase();
                     match name.as_ref() {
                         "CENTER" => return self.center_block_parser(limit, aff_start, maybe_aff),
                         "COMMENT" => return self.comment_block_parser(limit, aff_start, maybe_aff),
@@ -478,16 +614,509 @@ impl<'a> Parser<'a> {
     /// Eventually, if both ACC and PARENT are nil, the common parent is
     /// the list of objects itself."
     /// (defun org-element--parse-objects (beg end acc restriction &optional parent)
+    ///
+    /// Text between objects becomes `PlainText` nodes.
     pub fn parse_objects(
         &self,
         beg: usize,
         end: usize,
         restriction: impl Fn(SyntaxT) -> bool,
-    ) -> Vec<Handle> //acc
+    ) -> Vec<Handle<'a>> //acc
     {
-        let pos = self.cursor.borrow().pos();
-        // TODO write parse_objects function #8
-        self.cursor.borrow_mut().set(pos);
-        unimplemented!();
+        self.parse_objects_restricted(beg, end, &restriction)
+    }
+
+    /// Recursively parse objects in STRING and return structure.
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L4019
+    ///
+    /// RESTRICTION is a symbol limiting the object types that will be
+    /// looked after."
+    /// (defun org-element-parse-secondary-string (string restriction &optional parent)
+    ///
+    /// Locations of the objects are relative to the beginning of `string`.
+    pub fn parse_secondary_string(
+        string: &'a str,
+        restriction: impl Fn(SyntaxT) -> bool,
+    ) -> Vec<Handle<'a>> {
+        let parser = Parser::new(string, ParseGranularity::Object);
+        parser.parse_objects(0, string.len(), restriction)
+    }
+
+    /// Restriction is a trait object here, otherwise every level of
+    /// recursion would instantiate the function with a new closure type.
+    fn parse_objects_restricted(
+        &self,
+        beg: usize,
+        end: usize,
+        restriction: &dyn Fn(SyntaxT) -> bool,
+    ) -> Vec<Handle<'a>> {
+        // (narrow-to-region beg end)
+        // Object parsers look at the text up to `end` only
+        let narrowed = Cursor::new(&self.input[..end], beg);
+        let saved = self.cursor.replace(narrowed);
+
+        let mut objects: Vec<Handle<'a>> = vec![];
+        let mut pos = beg;
+        while pos < end {
+            let object = match self.object_lexer(restriction) {
+                Some(object) => object,
+                None => break,
+            };
+
+            // Text before any object.
+            if pos < object.location.start {
+                objects.push(Rc::new(self.plain_text(pos, object.location.start)));
+            }
+
+            // Fill contents of the object if possible.
+            // Verbatim contexts are never parsed, neither are
+            // scripts without brackets.
+            let syntax = SyntaxT::from(&object.data);
+            if let (Some(contents), true) = (object.content_location, syntax.is_object_container())
+            {
+                object.children.replace(if object.is_literal_script() {
+                    vec![Rc::new(self.plain_text(contents.start, contents.end))]
+                } else {
+                    self.parse_objects_restricted(contents.start, contents.end, &|that| {
+                        syntax.can_contain(that)
+                    })
+                });
+            }
+
+            pos = object.location.end;
+            self.cursor.borrow_mut().set(pos);
+            objects.push(Rc::new(object));
+        }
+
+        // Text after last object.
+        if pos < end {
+            objects.push(Rc::new(self.plain_text(pos, end)));
+        }
+
+        self.cursor.replace(saved);
+        objects
+    }
+
+    fn plain_text(&self, start: usize, end: usize) -> SyntaxNode<'a> {
+        SyntaxNode::new(
+            Syntax::PlainText(&self.input[start..end]),
+            Interval { start, end },
+            None,
+            0,
+            None,
+        )
+    }
+
+    /// Parses an object of type `t` at point
+    fn object_parser(&self, t: SyntaxT) -> Option<SyntaxNode<'a>> {
+        match t {
+            SyntaxT::Bold => self.bold_parser(),
+            SyntaxT::Citation => self.citation_parser(),
+            SyntaxT::Code => self.code_parser(),
+            SyntaxT::Entity => self.entity_parser(),
+            SyntaxT::FootnoteReference => self.footnote_reference_parser(),
+            SyntaxT::InlineSrcBlock => self.inline_src_block_parser(),
+            SyntaxT::Italic => self.italic_parser(),
+            SyntaxT::LatexFragment => self.latex_fragment_parser(),
+            SyntaxT::LineBreak => self.line_break_parser(),
+            SyntaxT::Link => self.link_parser(),
+            SyntaxT::StatisticsCookie => self.statistics_cookie_parser(),
+            SyntaxT::StrikeThrough => self.strike_through_parser(),
+            SyntaxT::Subscript => self.subscript_parser(),
+            SyntaxT::Superscript => self.superscript_parser(),
+            SyntaxT::Timestamp => self.timestamp_parser(),
+            SyntaxT::Underline => self.underline_parser(),
+            SyntaxT::Verbatim => self.verbatim_parser(),
+            _ => None,
+        }
+    }
+
+    /// Return next object in current buffer or nil.
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L4432
+    ///
+    /// RESTRICTION is a list of object types, as symbols, that should be
+    /// looked after.  This function assumes that the buffer is narrowed
+    /// to an appropriate container (e.g., a paragraph)."
+    /// (defun org-element--object-lexer (restriction)
+    ///
+    /// Candidates are tried from the cursor position onwards, the first
+    /// one successfully parsed is returned.
+    fn object_lexer(&self, restriction: &dyn Fn(SyntaxT) -> bool) -> Option<SyntaxNode<'a>> {
+        let (text, start) = {
+            let c = self.cursor.borrow();
+            (c.data(), c.pos())
+        };
+
+        // Table rows only contain cells, which start right at point.
+        if restriction(SyntaxT::TableCell) {
+            return self.table_cell_parser();
+        }
+
+        // All object candidates start with an ASCII character,
+        // so it is safe to look at bytes.
+        for offset in 0..text.len() - start {
+            let candidates = object_candidates(&text.as_bytes()[start + offset..]);
+            let found = candidates
+                .iter()
+                .filter(|&&t| restriction(t))
+                .find_map(|&t| {
+                    self.cursor.borrow_mut().set(start + offset);
+                    self.object_parser(t)
+                });
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+}
+
+/// Returns types of the objects that may start at the beginning of `s`,
+/// in the order their parsers have to be tried: the first one to
+/// succeed wins. E.g. `[[` can only open a link, so that neither a
+/// footnote reference nor a citation consumes part of it.
+/// elisp: `org-element--object-lexer`
+#[rustfmt::skip]
+fn object_candidates(s: &[u8]) -> &'static [SyntaxT] {
+    use SyntaxT::*;
+    match s[0] {
+        b'*'                              => &[Bold],
+        b'/'                              => &[Italic],
+        b'_'                              => &[Underline, Subscript],
+        b'^'                              => &[Superscript],
+        b'+'                              => &[StrikeThrough],
+        b'='                              => &[Verbatim],
+        b'~'                              => &[Code],
+        b'$'                              => &[LatexFragment],
+        b'<'                              => &[Timestamp, Link],
+        b'\\' if s.starts_with(b"\\\\")   => &[LineBreak],
+        b'\\'                             => &[Entity, LatexFragment],
+        b'[' if s.starts_with(b"[[")      => &[Link],
+        b'['                              => &[FootnoteReference, Citation, Timestamp, StatisticsCookie],
+        b's'                              => &[InlineSrcBlock],
+        _                                 => &[],
+    }
+}
+
+mod test {
+    use crate::data::{Handle, Interval, Syntax, SyntaxNode, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+
+    fn in_paragraph(that: SyntaxT) -> bool {
+        SyntaxT::Paragraph.can_contain(that)
+    }
+
+    fn types(objects: &[Handle]) -> Vec<SyntaxT> {
+        objects.iter().map(|o| SyntaxT::from(&o.data)).collect()
+    }
+
+    #[test]
+    fn secondary_string_line_break() {
+        let text = "first line\\\\  \nsecond \\\\ line";
+        let objects = Parser::parse_secondary_string(text, in_paragraph);
+        assert_eq!(
+            vec![SyntaxT::PlainText, SyntaxT::LineBreak, SyntaxT::PlainText],
+            types(&objects)
+        );
+        assert_eq!(Interval { start: 10, end: 15 }, objects[1].location);
+        match &objects[2].data {
+            Syntax::PlainText(text) => assert_eq!("second \\\\ line", *text),
+            _ => panic!("expected plain text"),
+        }
+
+        let objects = Parser::parse_secondary_string("a\\\\\\", in_paragraph);
+        assert_eq!(vec![SyntaxT::PlainText], types(&objects));
+
+        let objects = Parser::parse_secondary_string("a\\\\", |t| SyntaxT::Headline.can_contain(t));
+        assert_eq!(vec![SyntaxT::PlainText], types(&objects));
+    }
+
+    #[test]
+    fn verbatim_context() {
+        for text in &["=[[link]]=", "~*not bold*~", "=a /b/ [fn:1] c="] {
+            let objects = Parser::parse_secondary_string(text, in_paragraph);
+            assert_eq!(1, objects.len());
+            assert!(objects[0].children.borrow().is_empty());
+            assert_eq!(
+                &text[1..text.len() - 1],
+                match &objects[0].data {
+                    Syntax::Verbatim(v) => v.value,
+                    Syntax::Code(c) => c.value,
+                    _ => panic!("expected verbatim or code"),
+                }
+            );
+        }
+
+        let text = "#+BEGIN_SRC emacs-lisp\n(*a* =b= [fn:1])\n#+END_SRC\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let section = root.children.borrow()[0].clone();
+        let block = section.children.borrow()[0].clone();
+        assert_eq!(SyntaxT::SrcBlock, SyntaxT::from(&block.data));
+        assert!(block.children.borrow().is_empty());
+    }
+
+    #[test]
+    fn secondary_string_timestamp() {
+        let text = "Meeting <2019-03-05 Tue 10:00> in *room* 1\\\\";
+        let objects = Parser::parse_secondary_string(text, in_paragraph);
+        assert_eq!(
+            vec![
+                SyntaxT::PlainText,
+                SyntaxT::Timestamp,
+                SyntaxT::PlainText,
+                SyntaxT::Bold,
+                SyntaxT::PlainText,
+                SyntaxT::LineBreak,
+            ],
+            types(&objects)
+        );
+        assert_eq!(Interval { start: 8, end: 31 }, objects[1].location);
+        assert_eq!(Some("<2019-03-05 Tue 10:00>"), objects[1].raw_value());
+    }
+
+    #[test]
+    fn plain_text_coalescing() {
+        let text = "a *b* c d *e* f";
+        let objects = Parser::parse_secondary_string(text, in_paragraph);
+        let nodes: Vec<_> = objects
+            .iter()
+            .map(|o| (SyntaxT::from(&o.data), o.location.start, o.location.end))
+            .collect();
+        assert_eq!(
+            vec![
+                (SyntaxT::PlainText, 0, 2),
+                (SyntaxT::Bold, 2, 6),
+                (SyntaxT::PlainText, 6, 10),
+                (SyntaxT::Bold, 10, 14),
+                (SyntaxT::PlainText, 14, 15),
+            ],
+            nodes
+        );
+        let texts: Vec<_> = objects
+            .iter()
+            .filter_map(|o| match o.data {
+                Syntax::PlainText(text) => Some(text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec!["a ", "c d ", "f"], texts);
+        // Blanks after an object belong to it
+        assert_eq!(1, objects[1].post_blank);
+
+        let bold = objects[3].children.borrow();
+        assert_eq!(1, bold.len());
+        assert_eq!(Interval { start: 11, end: 12 }, bold[0].location);
+    }
+
+    #[test]
+    fn long_lines() {
+        use std::time::{Duration, Instant};
+
+        let started = Instant::now();
+        let stars = "*".repeat(100_000);
+        let parser = Parser::new(&stars, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let paragraph = &section.children.borrow()[0];
+        assert_eq!(SyntaxT::Paragraph, SyntaxT::from(&paragraph.data));
+        // Like `org-emph-re`, the first and last stars delimit bold text
+        let bold = &paragraph.children.borrow()[0];
+        assert_eq!(vec![SyntaxT::Bold], types(&paragraph.children.borrow()));
+        assert_eq!(vec![SyntaxT::PlainText], types(&bold.children.borrow()));
+
+        // Every marker opens an emphasis that is never closed
+        let text = "*a /b ".repeat(20_000) + "\\\\ a ".repeat(20_000).as_str();
+        let objects = Parser::parse_secondary_string(&text, in_paragraph);
+        assert_eq!(vec![SyntaxT::PlainText], types(&objects));
+
+        let objects = Parser::parse_secondary_string("*a *b* /c", in_paragraph);
+        assert_eq!(vec![SyntaxT::Bold, SyntaxT::PlainText], types(&objects));
+
+        // Far more than linear parsing needs, even in debug mode
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn lazy_contents() {
+        fn outline(node: &SyntaxNode, depth: usize, acc: &mut Vec<String>) {
+            acc.push(format!(
+                "{}{:?} {:?} {:?}",
+                " ".repeat(depth),
+                SyntaxT::from(&node.data),
+                node.location,
+                node.content_location
+            ));
+            for child in node.children.borrow().iter() {
+                outline(child, depth + 1, acc);
+            }
+        }
+
+        fn force<'a>(parser: &Parser<'a>, node: &SyntaxNode<'a>) {
+            for child in parser.force_contents(node).iter() {
+                force(parser, child);
+            }
+        }
+
+        let text = concat!(
+            "Intro *bold*\n",
+            "* One\n",
+            ":NOTES:\n",
+            "Drawer /text/\n",
+            ":END:\n",
+            "| a | b |\n",
+            "** Two\n",
+            "Body =code=\n"
+        );
+
+        let eager = Parser::new(text, ParseGranularity::Object).parse_buffer();
+        let mut expected = vec![];
+        outline(&eager, 0, &mut expected);
+
+        let mut parser = Parser::new(text, ParseGranularity::Object);
+        parser.lazy_contents = true;
+        let lazy = parser.parse_buffer();
+        let mut structure = vec![];
+        outline(&lazy, 0, &mut structure);
+
+        // Only headlines are recursed into, bounds are the eager ones
+        let kinds: Vec<_> = structure
+            .iter()
+            .map(|line| line.split(" Interval").next().unwrap())
+            .collect();
+        assert_eq!(
+            vec![
+                "OrgData",
+                " Section",
+                " Headline",
+                "  Section",
+                "  Headline",
+                "   Section"
+            ],
+            kinds
+        );
+        assert!(structure.iter().all(|line| expected.contains(line)));
+        assert_eq!(27, expected.len());
+
+        // Forcing contents of every node gives the eager tree
+        force(&parser, &lazy);
+        let mut forced = vec![];
+        outline(&lazy, 0, &mut forced);
+        assert_eq!(expected, forced);
+    }
+
+    #[test]
+    fn lazy_contents_settings() {
+        fn outline<'a>(parser: &Parser<'a>, node: &SyntaxNode<'a>, acc: &mut Vec<String>) {
+            acc.push(format!(
+                "{:?} {:?}",
+                SyntaxT::from(&node.data),
+                node.location
+            ));
+            for child in parser.force_contents(node).iter() {
+                outline(parser, child, acc);
+            }
+        }
+
+        let text = "* H\n  # foo\n:NOTES:\n  # bar\n:END:\n";
+        let parse = |lazy_contents| {
+            let mut parser = Parser::new(text, ParseGranularity::Element);
+            parser.allow_indented_comments = false;
+            parser.lazy_contents = lazy_contents;
+            let root = parser.parse_buffer();
+            let mut acc = vec![];
+            outline(&parser, &root, &mut acc);
+            acc
+        };
+
+        // Forced contents follow granularity and settings of the parser
+        let eager = parse(false);
+        assert_eq!(eager, parse(true));
+        assert_eq!("Paragraph Interval { start: 4, end: 12 }", eager[3]);
+        assert_eq!("Paragraph Interval { start: 20, end: 28 }", eager[5]);
+        assert_eq!(6, eager.len());
+    }
+
+    #[test]
+    fn node_property_mode() {
+        let text = "* H\n:PROPERTIES:\n:KEY: val\n:END:\n:KEY: val\n:LOGBOOK:\n:KEY: val\n:END:\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let headline = root.children.borrow()[0].clone();
+        let section = headline.children.borrow()[0].clone();
+        let elements = section.children.borrow();
+        assert_eq!(
+            vec![SyntaxT::PropertyDrawer, SyntaxT::Paragraph, SyntaxT::Drawer],
+            types(&elements)
+        );
+        assert_eq!(
+            vec![SyntaxT::NodeProperty],
+            types(&elements[0].children.borrow())
+        );
+        assert_eq!(
+            vec![SyntaxT::Paragraph],
+            types(&elements[2].children.borrow())
+        );
+
+        let text = ":KEY: val\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = root.children.borrow()[0].clone();
+        assert_eq!(vec![SyntaxT::Paragraph], types(&section.children.borrow()));
+    }
+
+    #[test]
+    fn colon_lines() {
+        let text = ": code\n:\n\n:LOGBOOK:\nNote\n:END:\n\n:not a drawer\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = root.children.borrow()[0].clone();
+        let elements = section.children.borrow();
+        assert_eq!(
+            vec![SyntaxT::FixedWidth, SyntaxT::Drawer, SyntaxT::Paragraph],
+            types(&elements)
+        );
+        assert_eq!(Interval { start: 0, end: 10 }, elements[0].location);
+        assert_eq!(Interval { start: 10, end: 32 }, elements[1].location);
+        assert_eq!(Interval { start: 32, end: 46 }, elements[2].location);
+    }
+
+    #[test]
+    fn object_priority() {
+        let parse = |text| {
+            let objects = Parser::parse_secondary_string(text, in_paragraph);
+            objects
+                .iter()
+                .map(|o| (SyntaxT::from(&o.data), o.location.start, o.location.end))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![(SyntaxT::Link, 0, 5), (SyntaxT::FootnoteReference, 5, 11)],
+            parse("[[l]][fn:1]")
+        );
+        assert_eq!(
+            vec![(SyntaxT::FootnoteReference, 0, 6), (SyntaxT::Link, 6, 11)],
+            parse("[fn:1][[l]]")
+        );
+        assert_eq!(
+            vec![(SyntaxT::StatisticsCookie, 0, 5), (SyntaxT::Link, 5, 10)],
+            parse("[50%][[l]]")
+        );
+        assert_eq!(
+            vec![(SyntaxT::Timestamp, 0, 16), (SyntaxT::Link, 16, 31)],
+            parse("<2020-01-01 Wed><https://x.org>")
+        );
+        assert_eq!(
+            vec![
+                (SyntaxT::Citation, 0, 9),
+                (SyntaxT::FootnoteReference, 9, 16),
+                (SyntaxT::Link, 16, 21),
+            ],
+            parse("[cite:@k][fn::x][[l]]")
+        );
+        // A link is not an object of link descriptions, the brackets
+        // stay plain text
+        let objects = Parser::parse_secondary_string("[[a][[[b]]]]", in_paragraph);
+        assert_eq!(vec![SyntaxT::Link, SyntaxT::PlainText], types(&objects));
     }
 }
@@ -14,31 +14,184 @@
 //    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::affiliated::AffiliatedData;
-use crate::data::SyntaxNode;
+use crate::data::{
+    ClockData, ClockStatus, DiarySexpData, Interval, PlanningData, Syntax, SyntaxNode,
+};
+use crate::headline::{ORG_CLOSED_STRING, ORG_DEADLINE_STRING};
 use crate::parser::Parser;
-use regex::Regex;
-
-lazy_static! {
-    pub static ref REGEX_DIARY_SEXP: Regex = Regex::new(r"%%\(").unwrap();
-}
+use crate::regexps::{REGEX_CLOCK_DURATION, REGEX_PLANNING_KEYWORD};
 
 impl<'a> Parser<'a> {
-    // TODO implement planning_parser
+    /// Parse a planning.
+    ///
+    /// LIMIT bounds the search.
+    ///
+    /// Return a list whose CAR is `planning' and CDR is a plist
+    /// containing `:closed', `:deadline', `:scheduled', `:begin',
+    /// `:end', `:post-blank' and `:post-affiliated' keywords."
+    /// (defun org-element-planning-parser (limit)
     pub fn planning_parser(&self, limit: usize) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let begin = c.pos();
+        let eol = c.line_end_position(None);
+        drop(c);
+        let data = self.planning_data(begin, eol);
+
+        let mut c = self.cursor.borrow_mut();
+        c.set(eol);
+        let before_blank = c.goto_next_line();
+        c.skip_chars_forward(" \r\t\n", Some(limit));
+        c.skip_chars_backward(" \t", None);
+        if !c.is_bol() {
+            let eol = c.line_end_position(None);
+            c.set(eol);
+        }
+        let end = c.pos();
+        let post_blank = c.count_lines(before_blank, end);
+
+        SyntaxNode::new(
+            Syntax::Planning(Box::new(data)),
+            Interval { start: begin, end },
+            None,
+            post_blank,
+            None,
+        )
     }
-    // TODO implement clock_line_parser
+
+    /// Reads timestamps following planning keywords between `begin`
+    /// and `eol`, the bounds of a planning line. Cursor is left where
+    /// it was.
+    pub(crate) fn planning_data(&self, begin: usize, eol: usize) -> PlanningData<'a> {
+        let pos = self.cursor.borrow().pos();
+        let mut data = PlanningData::default();
+        for cap in REGEX_PLANNING_KEYWORD.captures_iter(&self.input[begin..eol]) {
+            let mut c = self.cursor.borrow_mut();
+            c.set(begin + cap.get(0).unwrap().end());
+            c.skip_chars_forward(" \t", Some(eol));
+            drop(c);
+            let time = match self.timestamp_parser().map(|node| node.data) {
+                Some(Syntax::Timestamp(timestamp)) => Some(*timestamp),
+                _ => None,
+            };
+            match &cap[1] {
+                ORG_CLOSED_STRING => data.closed = time,
+                ORG_DEADLINE_STRING => data.deadline = time,
+                _ => data.scheduled = time,
+            }
+        }
+        self.cursor.borrow_mut().set(pos);
+        data
+    }
+
+    /// Parse a clock.
+    ///
+    /// LIMIT bounds the search.
+    ///
+    /// Return a list whose CAR is `clock' and CDR is a plist containing
+    /// `:status', `:value', `:time', `:begin', `:end', `:post-blank' and
+    /// `:post-affiliated' as keywords."
+    /// (defun org-element-clock-parser (limit)
     pub fn clock_line_parser(&self, limit: usize) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let begin = c.pos();
+        let eol = c.line_end_position(None);
+        c.search_forward("CLOCK:", Some(eol), None);
+        c.skip_chars_forward(" \t", Some(eol));
+        drop(c);
+
+        let value = match self.timestamp_parser().map(|node| node.data) {
+            Some(Syntax::Timestamp(timestamp)) => Some(*timestamp),
+            _ => None,
+        };
+        let duration = REGEX_CLOCK_DURATION
+            .captures(&self.input[begin..eol])
+            .map(|cap| cap.get(1).unwrap().as_str());
+        let status = match duration {
+            Some(_) => ClockStatus::Closed,
+            None => ClockStatus::Running,
+        };
+
+        // Unlike other elements, blank lines after a clock are counted
+        // up to the last one, not to the beginning of the next element.
+        let mut c = self.cursor.borrow_mut();
+        c.set(eol);
+        let before_blank = c.goto_next_line();
+        c.skip_chars_forward(" \r\t\n", Some(limit));
+        c.skip_chars_backward(" \t", None);
+        if !c.is_bol() {
+            let eol = c.line_end_position(None);
+            c.set(eol);
+        }
+        let end = c.pos();
+        let post_blank = c.count_lines(before_blank, end);
+
+        SyntaxNode::new(
+            Syntax::Clock(Box::new(ClockData {
+                duration,
+                status,
+                value,
+            })),
+            Interval { start: begin, end },
+            None,
+            post_blank,
+            None,
+        )
     }
 
-    // TODO implement diary_sexp_parser
+    /// Parse a diary sexp.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `diary-sexp' and CDR is a plist
+    /// containing `:begin', `:end', `:value', `:post-blank' and
+    /// `:post-affiliated' keywords."
+    /// (defun org-element-diary-sexp-parser (limit affiliated)
     pub fn diary_sexp_parser(
         &self,
         limit: usize,
         start: usize,
-        affiliated: Option<AffiliatedData>,
+        affiliated: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let post_affiliated = c.pos();
+        let eol = c.line_end_position(None);
+        let value = self.input[post_affiliated..eol].trim_end_matches(|c| c == ' ' || c == '\t');
+        c.goto_next_line();
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::DiarySexp(Box::new(DiarySexpData { value })),
+            Interval { start, end },
+            None,
+            post_blank,
+            affiliated,
+        )
+    }
+}
+
+mod test {
+    use crate::data::{Interval, Syntax};
+    use crate::parser::{ParseGranularity, Parser};
+
+    #[test]
+    fn diary_sexp() {
+        let text = "%%(diary-anniversary 10 31 1948) Arthur's birthday \t\n\nText";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let node = parser.diary_sexp_parser(text.len(), 0, None);
+        assert_eq!(Interval { start: 0, end: 54 }, node.location);
+        assert_eq!(1, node.post_blank);
+        match &node.data {
+            Syntax::DiarySexp(d) => {
+                assert_eq!(
+                    "%%(diary-anniversary 10 31 1948) Arthur's birthday",
+                    d.value
+                )
+            }
+            _ => panic!("expected diary sexp"),
+        }
     }
 }
@@ -0,0 +1,136 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Property lists
+//!
+//! Dynamic block arguments, babel header arguments and `#+ATTR_`
+//! keywords are written as `:key value :key2 "quoted value"`.
+
+/// Reads `:key value` pairs from `s`, in order of appearance, keys
+/// without their colon. A value is everything up to the next key,
+/// with spaces around it removed, so it is empty for flags. Double
+/// quotes group words, a colon inside them does not start a key and
+/// a value that is a single quoted string loses its quotes, escaped
+/// quotes inside are kept as they are written. Repeated
+/// keys are all kept, text before the first key is ignored.
+/// elisp: `org-babel-parse-header-arguments`
+pub fn parse_plist(s: &str) -> Vec<(String, String)> {
+    let mut plist = vec![];
+    let mut key: Option<&str> = None;
+    let mut value: Vec<(usize, usize)> = vec![];
+    let mut push = |key: Option<&str>, value: &[(usize, usize)]| {
+        if let Some(key) = key {
+            let value = match value {
+                [] => "",
+                [(start, end)] => unquote(&s[*start..*end]),
+                _ => &s[value[0].0..value[value.len() - 1].1],
+            };
+            plist.push((key.to_string(), value.to_string()));
+        }
+    };
+    for (start, end) in tokens(s) {
+        let token = &s[start..end];
+        if token.starts_with(':') && token.len() > 1 {
+            push(key, &value);
+            key = Some(&token[1..]);
+            value.clear();
+        } else {
+            value.push((start, end));
+        }
+    }
+    push(key, &value);
+    plist
+}
+
+/// Returns bounds of the words of `s`, separated by whitespace
+/// outside of double quotes
+fn tokens(s: &str) -> Vec<(usize, usize)> {
+    let mut tokens = vec![];
+    let mut start = None;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, ch) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if quoted {
+            match ch {
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+        } else if ch.is_whitespace() {
+            if let Some(start) = start.take() {
+                tokens.push((start, i));
+            }
+        } else {
+            start.get_or_insert(i);
+            quoted = ch == '"';
+        }
+    }
+    if let Some(start) = start {
+        tokens.push((start, s.len()));
+    }
+    tokens
+}
+
+/// Removes double quotes around `s`, if any
+fn unquote(s: &str) -> &str {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        &s[1..s.len() - 1]
+    } else {
+        s
+    }
+}
+
+mod test {
+    use super::parse_plist;
+
+    fn pairs(plist: &[(&str, &str)]) -> Vec<(String, String)> {
+        plist
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_plist_values() {
+        assert_eq!(
+            pairs(&[("key", "value"), ("key2", "quoted value")]),
+            parse_plist(":key value :key2 \"quoted value\"")
+        );
+        assert_eq!(
+            pairs(&[
+                ("title", "a :b \\\"c\\\""),
+                ("var", "x=\"1 2\""),
+                ("cmd", "ls  -l")
+            ]),
+            parse_plist("ignored :title \"a :b \\\"c\\\"\" :var x=\"1 2\" :cmd ls  -l ")
+        );
+        assert!(parse_plist("no keys : here").is_empty());
+    }
+
+    #[test]
+    fn parse_plist_flags_and_repeats() {
+        assert_eq!(
+            pairs(&[
+                ("noweb", ""),
+                ("var", "a=1"),
+                ("var", "b=2"),
+                ("exports", "")
+            ]),
+            parse_plist(":noweb :var a=1\t:var b=2  :exports")
+        );
+    }
+}
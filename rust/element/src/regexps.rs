@@ -0,0 +1,762 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Regular expressions used by the parser.
+//!
+//! Every regex is defined here, so that parsers and dispatch code reference
+//! a single place and tests can exercise them directly. Where an
+//! equivalent exists in org-mode its elisp name is mentioned in the docs.
+//!
+//! Regexes used with `Cursor::looking_at` are matched against the current
+//! line only (unless they can match a newline, see `is_multiline_regex`).
+
+use crate::headline::{ORG_CLOSED_STRING, ORG_DEADLINE_STRING, ORG_SCHEDULED_STRING};
+use regex::{Regex, RegexSet};
+
+lazy_static! {
+
+    // ===== Generic =====
+
+    pub static ref REGEX_EMPTY_LINE: Regex = Regex::new(r"^[ \t]*$").unwrap();
+
+    // ===== Headlines =====
+
+    /// Matches the stars of a headline and the whitespace run separating
+    /// them from the rest of the line. Both spaces and tabs are accepted
+    /// as a separator, so the end of the match is where the title begins.
+    pub static ref REGEX_HEADLINE_SHORT: Regex = Regex::new(r"^\*+[ \t]+").unwrap();
+
+    // TODO document why is it needed and what are the consequences of using multiline regex
+    /// Same as `REGEX_HEADLINE_SHORT` but matches at the beginning of any line.
+    /// Separator is restricted to `[ \t]` so that a line consisting only
+    /// of stars does not match across the newline.
+    pub static ref REGEX_HEADLINE_MULTILINE: Regex = Regex::new(r"(?m)^\*+[ \t]+").unwrap();
+
+    /// Matches a headline, putting stars and text into groups.
+    /// Stars are put in group 1 and the trimmed body in group 2.
+    /// elisp: `org-heading-regexp`
+    pub static ref REGEX_HEADING: Regex = Regex::new(r"^(\*+)(?:[ \t]+(.*?))?[ \t]*$").unwrap();
+
+    /// Matches headline's priority cookie e.g. [#A] along with whitespace
+    /// that follows it. Priority is captured to group 1
+    pub static ref REGEX_HEADLINE_PRIORITY: Regex = Regex::new(r"^\[#(.)\][ \t]*").unwrap();
+
+    /// Matches tags at the end of a headline, e.g. :work:urgent:
+    /// Tags including surrounding colons are captured to group 1
+    pub static ref REGEX_HEADLINE_TAGS: Regex = Regex::new(r"[ \t]+(:[[:alnum:]_@#%:]+:)[ \t]*$").unwrap();
+
+    /// Matches a tag definition in `#+TAGS:`, e.g. @work(w).
+    /// Tag, or a regexp in braces, is captured to group 1, fast
+    /// access key to group 2.
+    /// elisp: `org-tag-string-to-alist`
+    pub static ref REGEX_TAG_SPEC: Regex = Regex::new(r"^([[:alnum:]_@#%]+|\{.+?\})(?:\((.)\))?$").unwrap();
+
+    /// Matches a line with planning info.
+    /// Matched keyword is in group 1
+    pub static ref REGEX_PLANNING_LINE: Regex = Regex::new(
+        &format!(r"^[ \t]*((?:{}|{}|{}):)",
+            ORG_CLOSED_STRING, ORG_DEADLINE_STRING, ORG_SCHEDULED_STRING ))
+        .unwrap();
+
+    /// Matches a planning keyword, anywhere in a planning line.
+    /// Keyword, without the colon, is in group 1
+    pub static ref REGEX_PLANNING_KEYWORD: Regex = Regex::new(
+        &format!(r"({}|{}|{}):",
+            ORG_CLOSED_STRING, ORG_DEADLINE_STRING, ORG_SCHEDULED_STRING ))
+        .unwrap();
+
+    /// Matches an entire property drawer
+    /// Requires multiline match
+    /// correspond to org-property-drawer-re in org.el
+    pub static ref REGEX_PROPERTY_DRAWER: Regex = Regex::new(
+        r"(?im)^[ \t]*:PROPERTIES:[ \t]*\n(?:[ \t]*:\S+:(?: .*)?[ \t]*\n)*?[ \t]*:END:[ \t]*$")
+            .unwrap();
+
+    /// Matches a node property line, i.e. a line in a property drawer.
+    /// Property's key, possibly ending with `+`, is in group 1,
+    /// its value, if any, in group 2.
+    /// elisp: `org-property-re`
+    pub static ref REGEX_NODE_PROPERTY: Regex =
+        Regex::new(r"^[ \t]*:(\S+?):(?:[ \t]+(.*?))?[ \t]*$").unwrap();
+
+    pub static ref REGEX_CLOCK_LINE: Regex = Regex::new(r"^[ \t]*CLOCK:").unwrap();
+
+    /// Matches the duration of a closed clock, after the ` => ` separator.
+    pub static ref REGEX_CLOCK_DURATION: Regex = Regex::new(r" => [ \t]*(\S+)[ \t]*$").unwrap();
+
+    // ===== Affiliated keywords and keywords =====
+
+   /// Regexp matching any affiliated keyword
+   ///
+   /// This is different from original implementation for several reasons:
+   ///
+   /// ERE regexes support explicit numbering for capture groups,
+   /// which also can be repetetive. Rust's regex is PCRE, and does
+   /// not have such feature.
+   ///
+   /// Secondly, it uses different capture group arrangement to
+   /// simplify parsing and serialization of the keywords
+   ///
+   /// Thirdly, translation from old keywords already built-in
+   ///
+   /// Capture groups are named after each Keyword respectively.
+   ///
+   /// If you want to use group numbers:
+   /// CAPTION keyword is captured to group 1
+   /// RESULTS keyword is captured to group 2
+   ///
+   /// Secondary value of CAPTION or RESULTS is captured to group 3
+   ///
+   /// HEADER keyword is captured to group 4
+   /// PLOT keyword is captured to group 5
+   /// NAME keyword is captured to group 6
+   /// ATTR_ exported attribute is captured to group 7
+   ///
+   /// Warning! If you add more keywords then you must update this regex!
+   /// Original elisp implementation dynamically creates this regex based on
+   /// definitions lists of dual,regular and attribute keywords.
+   /// While this is possible to do in rust, and maybe it will be required in
+   /// the future, for now due laziness and lack of time static regex will be used.
+   ///
+   /// elisp: `org-element--affiliated-re`
+   pub static ref REGEX_AFFILIATED: Regex = Regex::new(
+           &format!(
+              r"(?i)^[ \t]*{}|{}|{}|{}|{}[ \t]*",
+              r"#\+(?:(?:(?P<CAPTION>CAPTION)|(?P<RESULTS>RESULTS?))(?:\[(?P<SECONDARY>.*)\])?",   // DUAL
+              r"(?P<HEADER>HEADERS?)",
+              r"(?P<PLOT>PLOT)",
+              r"(?P<NAME>(?:DATA|LABEL|NAME|RESNAME|(?:S(?:OURC|RCNAM)|TBLNAM)E))",
+              r"(?P<ATTR>ATTR_[-_A-Za-z0-9]+)):")
+       ).unwrap();
+
+    /// Used to identify the  Inline Comments, Blocks, Babel Calls, Dynamic Blocks and Keywords.
+    pub static ref REGEX_STARTS_WITH_HASHTAG: Regex = Regex::new(r"^[ \t]*#").unwrap();
+
+    /// Used to identify Comments. Used together with REGEX_STARTS_WITH_HASHTAG
+    pub static ref REGEX_COLON_OR_EOL: Regex = Regex::new(r"^(?: |$)").unwrap();
+
+    /// Matches a whole comment line: a pound sign followed by a space or
+    /// the end of line.
+    pub static ref REGEX_COMMENT_LINE: Regex = Regex::new(r"^[ \t]*#(?: |$)").unwrap();
+
+    /// Used to identify Keywords. Used together with REGEX_STARTS_WITH_HASHTAG
+    pub static ref REGEX_KEYWORD: Regex = Regex::new(r"^\+\S+:").unwrap();
+
+    /// Matches a whole keyword line.
+    /// Key is captured to group 1 and value to group 2.
+    /// Used by `org-element-keyword-parser`
+    pub static ref REGEX_KEYWORD_LINE: Regex = Regex::new(r"^[ \t]*#\+(\S*):[ \t]*(.*)").unwrap();
+
+    /// Used to identify Babel Calls. Used together with REGEX_STARTS_WITH_HASHTAG
+    pub static ref REGEX_BABEL_CALL: Regex = Regex::new(r"(?i)^\+CALL:").unwrap();
+
+    // ===== Blocks =====
+
+    /// Used to identify center, comment, example, export, quote, source, verse
+    /// and special blocks. Used together with REGEX_STARTS_WITH_HASHTAG
+    pub static ref REGEX_BLOCK_BEGIN: Regex = Regex::new(r"(?i)^\+BEGIN_(\S+)").unwrap();
+
+    /// Matches the opening line of any block. Block's name is captured to group 1
+    pub static ref REGEX_BLOCK_BEGIN_LINE: Regex = Regex::new(r"(?i)^[ \t]*#\+BEGIN_(\S+)").unwrap();
+
+    /// Matches the closing line of any block. Block's name is captured to group 1
+    pub static ref REGEX_BLOCK_END_LINE: Regex = Regex::new(r"(?i)^[ \t]*#\+END_(\S+)[ \t]*$").unwrap();
+
+    pub static ref REGEX_DYNAMIC_BLOCK: Regex = Regex::new(r"(?i)^\+BEGIN:? ").unwrap();
+
+    /// Matches the opening line of a dynamic block.
+    /// Block's name is captured to group 1, its arguments to group 2
+    pub static ref REGEX_DYNAMIC_BLOCK_BEGIN: Regex = Regex::new(r"(?i)^[ \t]*#\+BEGIN:[ \t]*(\S+)(.*)").unwrap();
+
+    /// Matches the closing line of a dynamic block
+    pub static ref REGEX_DYNAMIC_BLOCK_END: Regex = Regex::new(r"(?im)^[ \t]*#\+END:?[ \t]*$").unwrap();
+
+    /// Matches the beginning of an inline source block, e.g. src_sh{ls}.
+    /// Group 1 contains language.
+    pub static ref REGEX_INLINE_SRC_BLOCK: Regex = Regex::new(r"^src_([^ \t\n\[{]+)[{\[]").unwrap();
+
+    /// Matches the opening line of a source block.
+    /// Group 1 contains language, group 2 switches and group 3 parameters.
+    pub static ref REGEX_SRC_BLOCK_BEGIN: Regex = Regex::new(
+        r#"(?i)^[ \t]*#\+BEGIN_SRC(?: +(\S+))?((?: +(?:-(?:l ".+"|[ikr])|[-+]n(?: *[0-9]+)?))+)?(.*)[ \t]*$"#)
+        .unwrap();
+
+    /// Matches the closing line of a source block
+    pub static ref REGEX_SRC_BLOCK_END: Regex = Regex::new(r"(?im)^[ \t]*#\+END_SRC[ \t]*$").unwrap();
+
+    /// Matches the opening line of an export block.
+    /// Group 1 contains the backend, if any.
+    pub static ref REGEX_EXPORT_BLOCK_BEGIN: Regex =
+        Regex::new(r"(?i)^[ \t]*#\+BEGIN_EXPORT(?:[ \t]+(\S+))?[ \t]*$").unwrap();
+
+    /// Matches the opening line of an example block.
+    /// Group 1 contains switches.
+    pub static ref REGEX_EXAMPLE_BLOCK_BEGIN: Regex = Regex::new(r"(?i)^[ \t]*#\+BEGIN_EXAMPLE(?: +(.*))?").unwrap();
+
+    /// Matches a noweb reference in a source block, e.g. <<name>>.
+    /// Name is captured to group 1.
+    /// elisp: `org-babel-noweb-wrap`
+    pub static ref REGEX_NOWEB_REFERENCE: Regex = Regex::new(r"<<([^\s<>](?:[^\n]*?[^\s<>])?)>>").unwrap();
+
+    /// Matches commas protecting lines that would otherwise be parsed
+    /// as headlines or keywords inside of a block.
+    /// Comma is captured to group 1
+    pub static ref REGEX_ESCAPED_CODE_LINE: Regex = Regex::new(r"(?m)^[ \t]*(,)(?:\*|,*#\+)").unwrap();
+
+    /// Matches lines that need a protecting comma inside of a block.
+    /// Indentation is captured to group 1, rest of the line start to group 2
+    pub static ref REGEX_CODE_LINE_TO_ESCAPE: Regex = Regex::new(r"(?m)^([ \t]*)(,*(?:\*|#\+))").unwrap();
+
+    /// Block switches analysis. Used on the switches part of the opening line
+    pub static ref REGEX_SWITCH_NUMBER_LINES: Regex = Regex::new(r"([-+])n(?: *([0-9]+))?\b").unwrap();
+    pub static ref REGEX_SWITCH_PRESERVE_INDENT: Regex = Regex::new(r"-i\b").unwrap();
+    pub static ref REGEX_SWITCH_LABEL_FORMAT: Regex = Regex::new(r#"-l +"([^"\n]+)""#).unwrap();
+    pub static ref REGEX_SWITCH_REMOVE_LABELS: Regex = Regex::new(r"-r\b").unwrap();
+    pub static ref REGEX_SWITCH_KEEP_LABELS: Regex = Regex::new(r"-k\b").unwrap();
+
+    // ===== Drawers =====
+
+    /// Matches first or last line of a drawer
+    /// Group 1 contains drawer's name or \"END\"
+    pub static ref REGEX_DRAWER: Regex = Regex::new(r"^[ \t]*:((?:\w|[-_])+):[ \t]*$").unwrap();
+
+    // ===== LaTeX =====
+
+    /// Matches an entity, e.g. \alpha or \alpha{}. Name is captured to
+    /// group 1, what follows it to group 2.
+    /// Used by `org-element-entity-parser`
+    pub static ref REGEX_ENTITY: Regex =
+        Regex::new(r"^\\(there4|sup[123]|frac[13][24]|[a-zA-Z]+)($|\{\}|[^[:alpha:]])").unwrap();
+
+    /// Matches the name of a LaTeX command, e.g. \textbf or \section*
+    pub static ref REGEX_LATEX_COMMAND: Regex = Regex::new(r"^\\[a-zA-Z]+\*?").unwrap();
+
+    /// Regexp matching the beginning of a LaTeX environment.
+    /// The environment is captured by the first group.
+    pub static ref REGEX_LATEX_BEGIN_ENVIRIONMENT: Regex = Regex::new(r"^[ \t]*\\begin\{([A-Za-z0-9*]+)\}").unwrap();
+
+    // ===== Lists =====
+
+// (defconst org-list-end-re "^[ \t]*\n[ \t]*\n"
+//   "Regex matching the end of a plain list.")
+//
+// (defconst org-list-full-item-re
+//   (concat "^[ \t]*\\(\\(?:[-+*]\\|\\(?:[0-9]+\\|[A-Za-z]\\)[.)]\\)\\(?:[ \t]+\\|$\\)\\)"
+// 	  "\\(?:\\[@\\(?:start:\\)?\\([0-9]+\\|[A-Za-z]\\)\\][ \t]*\\)?"
+// 	  "\\(?:\\(\\[[ X-]\\]\\)\\(?:[ \t]+\\|$\\)\\)?"
+// 	  "\\(?:\\(.*\\)[ \t]+::\\(?:[ \t]+\\|$\\)\\)?")
+//   "Matches a list item and puts everything into groups:
+// group 1: bullet
+// group 2: counter
+// group 3: checkbox
+// group 4: description tag")
+//
+// (defun org-item-re ()
+//   "Return the correct regular expression for plain lists."
+//   (let ((term (cond
+// 	       ((eq org-plain-list-ordered-item-terminator t) "[.)]")
+// 	       ((=  org-plain-list-ordered-item-terminator ?\)) ")")
+// 	       ((=  org-plain-list-ordered-item-terminator ?.) "\\.")
+// 	       (t "[.)]")))
+// 	(alpha (if org-list-allow-alphabetical "\\|[A-Za-z]" "")))
+//     (concat "\\([ \t]*\\([-+]\\|\\(\\([0-9]+" alpha "\\)" term
+// 	    "\\)\\)\\|[ \t]+\\*\\)\\([ \t]+\\|$\\)")))
+//
+// (defsubst org-item-beginning-re ()
+//   "Regexp matching the beginning of a plain list item."
+//   (concat "^" (org-item-re)))
+
+    /// Matches the beginning of a plain list item.
+    /// Bullet is followed by a run of spaces or tabs that is part
+    /// of the match, so the end of the match is where the item text begins.
+    /// elisp: `org-item-re`
+    pub static ref REGEX_ITEM : Regex = Regex::new(r"^([ \t]*([-+]|(([0-9]+)[.)]))|[ \t]+\*)([ \t]+|$)").unwrap();
+
+    /// Matches a list item and puts everything into groups:
+    /// group 1: bullet
+    /// group 2: counter
+    /// group 3: checkbox
+    /// group 4: description tag
+    /// elisp: `org-list-full-item-re`
+    pub static ref REGEX_LIST_FULL_ITEM: Regex = Regex::new(concat!(
+        r"^[ \t]*((?:[-+*]|(?:[0-9]+|[A-Za-z])[.)])(?:[ \t]+|$))",
+        r"(?:\[@(?:start:)?([0-9]+|[A-Za-z])\][ \t]*)?",
+        r"(?:(\[[ X-]\])(?:[ \t]+|$))?",
+        r"(?:(.*)[ \t]+::(?:[ \t]+|$))?"))
+        .unwrap();
+
+    /// Matches the opening line of any block, dynamic blocks included.
+    /// Group 1 is what follows `#+BEGIN`, i.e., `:` or `_NAME`
+    pub static ref REGEX_ANY_BLOCK_BEGIN: Regex = Regex::new(r"(?i)^[ \t]*#\+BEGIN(:|_\S+)").unwrap();
+
+    /// Regex matching the end of a plain list.
+    /// elisp: `org-list-end-re`
+    pub static ref REGEX_LIST_END: Regex = Regex::new(r"^[ \t]*\n[ \t]*\n").unwrap();
+
+    // ===== Markup =====
+
+    pub static ref REGEX_HORIZONTAL_RULE: Regex = Regex::new(r"^[ \t]*-{5,}[ \t]*$").unwrap();
+
+    /// Regular expression matching the definition of a footnote.
+    /// Match group 1 contains definition's label
+    pub static ref REGEX_FOOTNOTE_DEFINITION: Regex = Regex::new(r"^\[fn:([-_[:word:]]+)\]").unwrap();
+
+    /// Regular expression matching the beginning of a footnote reference.
+    /// Inline references, i.e. `[fn:label:definition]` and `[fn::definition]`,
+    /// capture label to group 1, if any, and the colon to group 2.
+    /// Standard references, i.e. `[fn:label]`, capture label to group 3.
+    /// elisp: `org-footnote-re`
+    pub static ref REGEX_FOOTNOTE_REFERENCE: Regex = Regex::new(
+        r"^\[fn:(?:([-_[:word:]]+)?(:)|([-_[:word:]]+)\])").unwrap();
+
+    /// Statistics cookie, e.g. [1/3] or [33%]
+    pub static ref REGEX_STATISTICS_COOKIE: Regex = Regex::new(r"^\[[0-9]*(?:%|/[0-9]*)\]").unwrap();
+
+    /// Opening of a citation, e.g. [cite/style: with the style,
+    /// if any, in group 1.
+    /// elisp: `org-element-citation-prefix-re`
+    pub static ref REGEX_CITATION_PREFIX: Regex =
+        Regex::new(r"^\[cite(?:/([/_a-z0-9-]+))?:[\t\n ]*").unwrap();
+
+    /// Citation key, e.g. @doe2020, with the key without `@` in group 1.
+    /// elisp: `org-element-citation-key-re`
+    pub static ref REGEX_CITATION_KEY: Regex = Regex::new(r"@([!#-+./:<>-@^-`{-~\w-]+)").unwrap();
+
+    /// Script without brackets following a `_` or `^`, e.g. `2` in x^2
+    /// or `-1,t` in y_-1,t
+    /// elisp: `org-match-substring-regexp`
+    pub static ref REGEX_SCRIPT_PLAIN: Regex = Regex::new(r"^(?:\*|[+-]?(?:[^\W_]|[.,\\])*[^\W_])").unwrap();
+
+    /// Fixed Width Areas
+    /// A “fixed-width line” start with a colon character and a whitespace or an end of line.
+    /// Fixed width areas can contain any number of consecutive fixed-width lines.
+    pub static ref REGEX_FIXED_WIDTH: Regex = Regex::new(r"^[ \t]*:( |$)").unwrap();
+
+    /// Bold text e.g. *bold*.
+    /// Emphasis including markers is captured to group 1, contents to group 2
+    /// elisp: `org-emph-re`
+    pub static ref REGEX_EMPH_BOLD: Regex = emphasis_regex('*');
+
+    /// Italic text e.g. /italic/. See `REGEX_EMPH_BOLD` for capture groups.
+    pub static ref REGEX_EMPH_ITALIC: Regex = emphasis_regex('/');
+
+    /// Underlined text e.g. _underline_. See `REGEX_EMPH_BOLD` for capture groups.
+    pub static ref REGEX_EMPH_UNDERLINE: Regex = emphasis_regex('_');
+
+    /// Strike-through text e.g. +strike-through+. See `REGEX_EMPH_BOLD` for capture groups.
+    pub static ref REGEX_EMPH_STRIKE_THROUGH: Regex = emphasis_regex('+');
+
+    /// Verbatim text e.g. =verbatim=. See `REGEX_EMPH_BOLD` for capture groups.
+    /// elisp: `org-verbatim-re`
+    pub static ref REGEX_VERBATIM: Regex = emphasis_regex('=');
+
+    /// Code e.g. ~code~. See `REGEX_EMPH_BOLD` for capture groups.
+    /// elisp: `org-verbatim-re`
+    pub static ref REGEX_CODE: Regex = emphasis_regex('~');
+
+    // ===== Links =====
+
+    /// Matches a link in double brackets, with an optional description.
+    /// Link is captured to group 1, description to group 2.
+    /// elisp: `org-link-bracket-re`
+    pub static ref REGEX_LINK_BRACKET: Regex = Regex::new(
+        r"\[\[((?:[^\[\]\\]|\\(?:\\\\)*[\[\]]|\\+[^\[\]])+)\](?:\[((?s:.+?))\])?\]").unwrap();
+
+    /// Matches a link in angle brackets e.g. <https://orgmode.org>.
+    /// Link type is captured to group 1 and path to group 2.
+    /// elisp: `org-link-angle-re`
+    pub static ref REGEX_LINK_ANGLE: Regex = Regex::new(r"<([a-zA-Z][a-zA-Z0-9+-]*):([^>\n]*)>").unwrap();
+
+    // ===== Planning, clocks and diary =====
+
+    pub static ref REGEX_DIARY_SEXP: Regex = Regex::new(r"^%%\(").unwrap();
+
+    // ===== Paragraphs =====
+
+    /// Regexp to separate paragraphs in an Org buffer.
+    /// In the case of lines starting with "#" and ":", this regexp
+    /// is not sufficient to know if point is at a paragraph ending.
+    /// See `paragraph_parser` for more information.
+    /// elisp: `org-element-paragraph-separate`
+    pub static ref REGEX_PARAGRAPH_SEPARATE: Regex = Regex::new(concat!(
+        r"(?i)^(?:",
+        // Headlines, inlinetasks.
+        r"\*+ |",
+        // Footnote definitions.
+        r"\[fn:[-_[:word:]]+\]|",
+        // Diary sexps.
+        r"%%\(|",
+        r"[ \t]*(?:",
+        // Empty lines.
+        r"$|",
+        // Tables (any type).
+        r"\||",
+        r"\+(?:-+\+)+[ \t]*$|",
+        // Comments, keyword-like or block-like constructs.
+        // Blocks and keywords with dual values need to be double-checked.
+        r"#(?: |$|\+(?:BEGIN_\S+|\S+(?:\[.*\])?:[ \t]*))|",
+        // Drawers (any type) and fixed-width areas.
+        // Drawers need to be double-checked.
+        r":(?: |$|[-_[:word:]]+:[ \t]*$)|",
+        // Horizontal rules.
+        r"-{5,}[ \t]*$|",
+        // LaTeX environments.
+        r"\\begin\{[A-Za-z0-9*]+\}|",
+        // Clock lines.
+        r"CLOCK:|",
+        // Lists.
+        r"(?:[-+*]|[0-9]+[.)])(?:[ \t]|$)",
+        r"))"
+    )).unwrap();
+
+    /// Matches a keyword with a secondary value in brackets.
+    /// Keyword is captured to group 1
+    pub static ref REGEX_KEYWORD_WITH_SECONDARY: Regex = Regex::new(r"^[ \t]*#\+(\S+)\[.*\]:").unwrap();
+
+    /// Matches the closing line of a drawer
+    pub static ref REGEX_DRAWER_END: Regex = Regex::new(r"(?im)^[ \t]*:END:[ \t]*$").unwrap();
+
+    // ===== Tables =====
+
+    pub static ref REGEX_TABLE_BORDER: Regex = Regex::new(r"^[ \t]*\|").unwrap();
+    pub static ref REGEX_TABLE_RULE: Regex = Regex::new(r"^[ \t]*\+(-+\+)+[ \t]*$").unwrap();
+    pub static ref REGEX_TABLE_PRE_BORDER: Regex = Regex::new(r"^[ \t]*($|[^|])").unwrap();
+
+    /// Matches a rule in an Org table.
+    /// elisp: `org-table-hline-regexp`
+    pub static ref REGEX_TABLE_HLINE: Regex = Regex::new(r"^[ \t]*\|-").unwrap();
+
+    /// Matches a line of formulas following a table.
+    /// Formulas are captured to group 1.
+    pub static ref REGEX_TABLE_TBLFM: Regex = Regex::new(r"(?i)^[ \t]*#\+TBLFM: +(.*?)[ \t]*$").unwrap();
+
+    /// Matches a table cell up to the closing bar or the end of line.
+    /// Cell contents, without surrounding blanks, are captured to group 1.
+    pub static ref REGEX_TABLE_CELL: Regex = Regex::new(r"^[ \t]*(.*?)[ \t]*(?:\||$)").unwrap();
+
+    /// Matches a column cookie, e.g. `<r12>`, alignment in group 1 and width in group 2.
+    pub static ref REGEX_TABLE_COOKIE: Regex = Regex::new(r"^<([lrc])?([0-9]+)?>$").unwrap();
+
+    // ===== Timestamps =====
+
+    /// Matches a diary timestamp. Sexp is captured to group 1
+    pub static ref REGEX_TIMESTAMP_DIARY: Regex = Regex::new(r"^<%%(\([^>\n]*\))>").unwrap();
+
+    /// Matches an active or an inactive timestamp.
+    /// Contents between angle brackets are captured to group 1,
+    /// contents between square brackets are captured to group 2.
+    pub static ref REGEX_TIMESTAMP: Regex = Regex::new(r"^(?:<([^<>\n]+)>|\[([^\[\]\n]+)\])").unwrap();
+
+    /// Matches the date part of a timestamp contents, with optional
+    /// day name and time. Year, month and day are captured to groups
+    /// 1, 2 and 3. Hour and minute are captured to groups 4 and 5.
+    /// elisp: `org-ts-regexp0`
+    pub static ref REGEX_TIMESTAMP_DATE: Regex = Regex::new(
+        r"^([0-9]{4})-([0-9]{2})-([0-9]{2})(?: +[^\]+0-9>\r\n -]+)?(?: +([0-9]{1,2}):([0-9]{2}))?")
+        .unwrap();
+
+    /// Matches time range within a single timestamp e.g. 10:00-12:30.
+    /// Ending hour and minute are captured to groups 1 and 2.
+    pub static ref REGEX_TIMESTAMP_TIME_RANGE: Regex = Regex::new(
+        r"[012]?[0-9]:[0-5][0-9](?:-([012]?[0-9]):([0-5][0-9]))").unwrap();
+
+    /// Matches repeater cookie. Mark, value and unit are captured to
+    /// groups 1, 2 and 3.
+    pub static ref REGEX_TIMESTAMP_REPEATER: Regex = Regex::new(r"([.+]?\+)([0-9]+)([hdwmy])").unwrap();
+
+    /// Matches warning delay cookie. Group 1 is not empty for `first` type
+    /// warnings, value and unit are captured to groups 2 and 3.
+    pub static ref REGEX_TIMESTAMP_WARNING: Regex = Regex::new(r"(-)?-([0-9]+)([hdwmy])").unwrap();
+
+    /// Matches an active or inactive timestamp anywhere in a string.
+    /// Date and the rest of timestamp contents are captured to group 1.
+    /// elisp: `org-ts-regexp-both`
+    pub static ref REGEX_TS_BOTH: Regex = Regex::new(
+        r"[\[<]([0-9]{4}-[0-9]{2}-[0-9]{2}(?: [^\]>\n]*?)?)[\]>]").unwrap();
+
+    // ===== Dispatch =====
+
+    /// Classifies a line by the element it can start.
+    /// Patterns are ordered by priority, the first one that matches
+    /// wins. `DISPATCH_LINE_KINDS` holds the kind for each pattern index.
+    /// Used by `Cursor::current_line_kind`
+    pub static ref DISPATCH_REGEX_SET: RegexSet = RegexSet::new(&[
+        r"^\*+[ \t]+",
+        r"^[ \t]*CLOCK:",
+        &format!(r"^[ \t]*(?:{}|{}|{}):", ORG_CLOSED_STRING, ORG_DEADLINE_STRING, ORG_SCHEDULED_STRING),
+        r"(?i)^[ \t]*#\+BEGIN:(?: |$)",
+        r"(?i)^[ \t]*#\+BEGIN_\S+",
+        r"(?i)^[ \t]*#\+END(?:_\S+|:)[ \t]*$",
+        r"(?i)^[ \t]*#\+CALL:",
+        r"^[ \t]*#\+\S+:",
+        r"^[ \t]*#(?: |$)",
+        r"^[ \t]*:(?:\w|[-_])+:[ \t]*$",
+        r"^[ \t]*:(?: |$)",
+        r"^\[fn:[-_\w]+\]",
+        r"^[ \t]*-{5,}[ \t]*$",
+        r"^%%\(",
+        r"^[ \t]*\|",
+        r"^(?:[ \t]*(?:[-+]|[0-9]+[.)])|[ \t]+\*)(?:[ \t]+|$)",
+        r"^[ \t]*\\begin\{[A-Za-z0-9*]+\}",
+        r"^[ \t]*$",
+    ]).unwrap();
+}
+
+/// Kind of an element a line can start, as determined by `DISPATCH_REGEX_SET`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineKind {
+    Headline,
+    Clock,
+    Planning,
+    DynamicBlockBegin,
+    BlockBegin,
+    BlockEnd,
+    BabelCall,
+    Keyword,
+    Comment,
+    Drawer,
+    FixedWidth,
+    FootnoteDefinition,
+    HorizontalRule,
+    DiarySexp,
+    TableRow,
+    Item,
+    LatexEnvironment,
+    Empty,
+    /// None of the above, usually a part of a paragraph
+    Text,
+}
+
+/// Line kind for each pattern of `DISPATCH_REGEX_SET`, in the same order
+pub const DISPATCH_LINE_KINDS: [LineKind; 18] = [
+    LineKind::Headline,
+    LineKind::Clock,
+    LineKind::Planning,
+    LineKind::DynamicBlockBegin,
+    LineKind::BlockBegin,
+    LineKind::BlockEnd,
+    LineKind::BabelCall,
+    LineKind::Keyword,
+    LineKind::Comment,
+    LineKind::Drawer,
+    LineKind::FixedWidth,
+    LineKind::FootnoteDefinition,
+    LineKind::HorizontalRule,
+    LineKind::DiarySexp,
+    LineKind::TableRow,
+    LineKind::Item,
+    LineKind::LatexEnvironment,
+    LineKind::Empty,
+];
+
+/// Builds a regex matching emphasis delimited by `marker`.
+///
+/// Emphasis has to be preceded by whitespace, one of `-('"{` or line
+/// beginning and followed by whitespace, one of `-.,:!?;'")}[` or line end.
+/// Contents can not start or end with a whitespace and can span over
+/// two lines at most.
+///
+/// Original regex uses a back reference to the marker, which is not
+/// supported by rust's regex crate, so every marker gets its own regex.
+/// elisp: `org-set-emph-re` with default `org-emphasis-regexp-components`
+fn emphasis_regex(marker: char) -> Regex {
+    let m = regex::escape(&marker.to_string());
+    Regex::new(&format!(
+        r#"(?m)(?:^|[-\s('"{{])({m}([^\s]|[^\s].*?(?:\n.*?)?[^\s]){m})(?:[-\s.,:!?;'")}}\[]|$)"#,
+        m = m
+    ))
+    .unwrap()
+}
+
+mod test {
+    use super::*;
+
+    fn matched<'t>(re: &Regex, text: &'t str, group: usize) -> Option<&'t str> {
+        re.captures(text)
+            .and_then(|c| c.get(group))
+            .map(|m| m.as_str())
+    }
+
+    #[test]
+    fn heading() {
+        assert_eq!(Some("**"), matched(&REGEX_HEADING, "** TODO Title", 1));
+        assert_eq!(
+            Some("TODO Title"),
+            matched(&REGEX_HEADING, "** TODO Title  ", 2)
+        );
+        assert_eq!(Some("Title"), matched(&REGEX_HEADING, "*\tTitle", 2));
+        assert!(REGEX_HEADING.is_match("*"));
+        assert!(!REGEX_HEADING.is_match("  * Title"));
+        assert!(!REGEX_HEADING.is_match("*bold* text"));
+    }
+
+    #[test]
+    fn list_full_item() {
+        let text = "3. [@7] [X] term :: description";
+        assert_eq!(Some("3. "), matched(&REGEX_LIST_FULL_ITEM, text, 1));
+        assert_eq!(Some("7"), matched(&REGEX_LIST_FULL_ITEM, text, 2));
+        assert_eq!(Some("[X]"), matched(&REGEX_LIST_FULL_ITEM, text, 3));
+        assert_eq!(Some("term"), matched(&REGEX_LIST_FULL_ITEM, text, 4));
+        assert_eq!(None, matched(&REGEX_LIST_FULL_ITEM, "- item", 4));
+        assert!(!REGEX_LIST_FULL_ITEM.is_match("-item"));
+        assert!(REGEX_LIST_END.is_match("\n  \nfoo"));
+        assert!(!REGEX_LIST_END.is_match("\nfoo\n"));
+    }
+
+    #[test]
+    fn keyword_line() {
+        assert_eq!(
+            Some("AUTHOR"),
+            matched(&REGEX_KEYWORD_LINE, "#+AUTHOR: Me", 1)
+        );
+        assert_eq!(Some("Me"), matched(&REGEX_KEYWORD_LINE, "#+AUTHOR: Me", 2));
+        assert!(!REGEX_KEYWORD_LINE.is_match("#+not a keyword"));
+        assert!(!REGEX_KEYWORD_LINE.is_match("# comment: no"));
+    }
+
+    #[test]
+    fn block_lines() {
+        assert_eq!(
+            Some("src"),
+            matched(&REGEX_BLOCK_BEGIN_LINE, "  #+begin_src rust", 1)
+        );
+        assert_eq!(
+            Some("SRC"),
+            matched(&REGEX_BLOCK_END_LINE, "#+END_SRC  ", 1)
+        );
+        assert!(!REGEX_BLOCK_END_LINE.is_match("#+END_SRC code"));
+        assert!(!REGEX_BLOCK_BEGIN_LINE.is_match("#+BEGIN: dynamic"));
+    }
+
+    #[test]
+    fn terminators() {
+        for line in &[":END:", ":end:", "  :End: \t"] {
+            assert!(REGEX_DRAWER_END.is_match(line), "{}", line);
+        }
+        for line in &["#+END_SRC", "#+end_src  ", "\t#+End_Src"] {
+            assert!(REGEX_SRC_BLOCK_END.is_match(line), "{}", line);
+            assert!(REGEX_BLOCK_END_LINE.is_match(line), "{}", line);
+        }
+        for line in &["#+END:", "#+end", " #+End: "] {
+            assert!(REGEX_DYNAMIC_BLOCK_END.is_match(line), "{}", line);
+        }
+        assert!(!REGEX_DRAWER_END.is_match(":END: text"));
+        assert!(!REGEX_SRC_BLOCK_END.is_match("#+END_SRCX"));
+
+        let drawer = ":properties:\n:ID: 1\n  :end:  \nText";
+        assert_eq!(
+            Some(29),
+            REGEX_PROPERTY_DRAWER.find(drawer).map(|m| m.end())
+        );
+        assert!(!REGEX_PROPERTY_DRAWER.is_match(":PROPERTIES:\n:ID: 1\n:END: text\n"));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(
+            Some("2019-06-01 Sat 10:00"),
+            matched(&REGEX_TS_BOTH, "at <2019-06-01 Sat 10:00> or", 1)
+        );
+        assert_eq!(
+            Some("2019-06-01"),
+            matched(&REGEX_TS_BOTH, "[2019-06-01]", 1)
+        );
+        assert!(!REGEX_TS_BOTH.is_match("<2019-6-1>"));
+        assert!(REGEX_TIMESTAMP_DIARY.is_match("<%%(diary-float t 4 2)>"));
+        assert!(!REGEX_TIMESTAMP_DIARY.is_match("<2019-06-01>"));
+    }
+
+    #[test]
+    fn links() {
+        let text = "see [[https://orgmode.org][Org mode]] now";
+        assert_eq!(
+            Some("https://orgmode.org"),
+            matched(&REGEX_LINK_BRACKET, text, 1)
+        );
+        assert_eq!(Some("Org mode"), matched(&REGEX_LINK_BRACKET, text, 2));
+        assert_eq!(
+            Some("file:a.org"),
+            matched(&REGEX_LINK_BRACKET, "[[file:a.org]]", 1)
+        );
+        assert!(!REGEX_LINK_BRACKET.is_match("[not a link]"));
+        assert_eq!(
+            Some("https"),
+            matched(&REGEX_LINK_ANGLE, "<https://orgmode.org>", 1)
+        );
+        assert!(!REGEX_LINK_ANGLE.is_match("<2019-06-01>"));
+    }
+
+    #[test]
+    fn emphasis() {
+        assert_eq!(Some("*bold*"), matched(&REGEX_EMPH_BOLD, "a *bold* b", 1));
+        assert_eq!(Some("b"), matched(&REGEX_EMPH_BOLD, "*b*", 2));
+        assert_eq!(
+            Some("two words"),
+            matched(&REGEX_EMPH_ITALIC, "(/two words/)", 2)
+        );
+        assert_eq!(Some("x"), matched(&REGEX_EMPH_UNDERLINE, "_x_.", 2));
+        assert_eq!(
+            Some("gone"),
+            matched(&REGEX_EMPH_STRIKE_THROUGH, "+gone+", 2)
+        );
+        assert_eq!(Some("a=b"), matched(&REGEX_VERBATIM, "=a=b=", 2));
+        assert_eq!(Some("code"), matched(&REGEX_CODE, "~code~", 2));
+        assert_eq!(
+            Some("over\ntwo"),
+            matched(&REGEX_EMPH_BOLD, "*over\ntwo*", 2)
+        );
+
+        assert!(!REGEX_EMPH_BOLD.is_match("a*bold*"));
+        assert!(!REGEX_EMPH_BOLD.is_match("* not bold*"));
+        assert!(!REGEX_EMPH_BOLD.is_match("*not bold *"));
+        assert!(!REGEX_EMPH_BOLD.is_match("*not*bold"));
+        assert!(!REGEX_EMPH_BOLD.is_match("*over\nthree\nlines*"));
+    }
+
+    #[test]
+    fn dispatch_set() {
+        let kind = |line: &str| {
+            DISPATCH_REGEX_SET
+                .matches(line)
+                .iter()
+                .next()
+                .map_or(LineKind::Text, |i| DISPATCH_LINE_KINDS[i])
+        };
+        assert_eq!(DISPATCH_LINE_KINDS.len(), DISPATCH_REGEX_SET.len());
+        assert_eq!(LineKind::Headline, kind("** Title"));
+        assert_eq!(LineKind::Text, kind("**bold** text"));
+        assert_eq!(LineKind::Clock, kind("CLOCK: [2019-06-01]"));
+        assert_eq!(LineKind::Planning, kind("  DEADLINE: <2019-06-01>"));
+        assert_eq!(LineKind::DynamicBlockBegin, kind("#+BEGIN: clocktable"));
+        assert_eq!(LineKind::BlockBegin, kind("#+begin_src rust"));
+        assert_eq!(LineKind::BlockEnd, kind("#+END_SRC"));
+        assert_eq!(LineKind::BlockEnd, kind("#+END:"));
+        assert_eq!(LineKind::BabelCall, kind("#+CALL: foo()"));
+        assert_eq!(LineKind::Keyword, kind("#+TITLE: Org"));
+        assert_eq!(LineKind::Comment, kind("# comment"));
+        assert_eq!(LineKind::Text, kind("#hashtag"));
+        assert_eq!(LineKind::Drawer, kind(":LOGBOOK:"));
+        assert_eq!(LineKind::FixedWidth, kind(": code"));
+        assert_eq!(LineKind::FootnoteDefinition, kind("[fn:1] Note"));
+        assert_eq!(LineKind::HorizontalRule, kind("-----"));
+        assert_eq!(LineKind::DiarySexp, kind("%%(diary-anniversary 1 1 2000)"));
+        assert_eq!(LineKind::TableRow, kind("| a | b |"));
+        assert_eq!(LineKind::Item, kind("- item"));
+        assert_eq!(LineKind::Item, kind("10) item"));
+        assert_eq!(LineKind::LatexEnvironment, kind("\\begin{equation}"));
+        assert_eq!(LineKind::Empty, kind("  \t"));
+        assert_eq!(LineKind::Text, kind("Just some text"));
+    }
+}
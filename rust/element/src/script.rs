@@ -0,0 +1,207 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Subscript and Superscript
+//! https://orgmode.org/worg/dev/org-syntax.html#Subscript_and_Superscript
+//!
+//! Pattern for subscript is:
+//!
+//! CHAR_SCRIPT
+//!
+//! Pattern for superscript is:
+//!
+//! CHAR^SCRIPT
+//!
+//! CHAR is any non-whitespace character.
+//!
+//! SCRIPT can be *, an expression enclosed in parenthesis (resp. curly
+//! brackets), containing balanced parenthesis (resp. curly brackets),
+//! or the pattern SIGN CHARS FINAL, see `REGEX_SCRIPT_PLAIN`.
+//!
+//! Contents enclosed in curly brackets can contain other objects,
+//! e.g. a_{x^2}. Any other script is taken literally.
+
+use crate::babel::parse_paired_brackets;
+use crate::data::{Interval, SubscriptData, SuperscriptData, Syntax, SyntaxNode};
+use crate::parser::Parser;
+use crate::regexps::REGEX_SCRIPT_PLAIN;
+
+impl<'a> SyntaxNode<'a> {
+    /// Whether the node is a subscript or a superscript without
+    /// curly brackets, contents of which are never parsed.
+    pub(crate) fn is_literal_script(&self) -> bool {
+        match &self.data {
+            Syntax::Subscript(s) => !s.use_brackets_p,
+            Syntax::Superscript(s) => !s.use_brackets_p,
+            _ => false,
+        }
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Common part of the script parsers.
+    /// Returns object's location, contents location, post blank
+    /// and whether contents are enclosed in curly brackets.
+    fn script_at(&self, mark: char) -> Option<(Interval, Interval, usize, bool)> {
+        let mut c = self.cursor.borrow_mut();
+        let text = c.data();
+        let begin = c.pos();
+        if !text[begin..].starts_with(mark) {
+            return None;
+        }
+        // (unless (bolp) (backward-char))
+        // (looking-at org-match-substring-regexp)
+        match text[..begin].chars().next_back() {
+            Some(prev) if !prev.is_whitespace() => (),
+            _ => return None,
+        }
+
+        let script = begin + mark.len_utf8();
+        let rest = &text[script..];
+        let (contents, use_brackets_p) = if let Some((_, len)) = parse_paired_brackets(rest, '{') {
+            let contents = Interval {
+                start: script + 1,
+                end: script + len - 1,
+            };
+            (contents, true)
+        } else {
+            let len = parse_paired_brackets(rest, '(')
+                .map(|(_, len)| len)
+                .or_else(|| REGEX_SCRIPT_PLAIN.find(rest).map(|m| m.end()))?;
+            let contents = Interval {
+                start: script,
+                end: script + len,
+            };
+            (contents, false)
+        };
+
+        c.set(contents.end + if use_brackets_p { 1 } else { 0 });
+        let post_blank = c.skip_chars_forward(" \t", None);
+        let location = Interval {
+            start: begin,
+            end: c.pos(),
+        };
+        Some((location, contents, post_blank, use_brackets_p))
+    }
+
+    /// Parse subscript at point, if any.
+    ///
+    /// When at a subscript object, return a list whose car is
+    /// `subscript' and cdr a plist with `:begin', `:end',
+    /// `:contents-begin', `:contents-end', `:use-brackets-p' and
+    /// `:post-blank' as keywords.  Otherwise, return nil.
+    ///
+    /// Assume point is at the underscore."
+    /// (defun org-element-subscript-parser ()
+    pub fn subscript_parser(&self) -> Option<SyntaxNode<'a>> {
+        let (location, contents, post_blank, use_brackets_p) = self.script_at('_')?;
+        Some(SyntaxNode::new(
+            Syntax::Subscript(Box::new(SubscriptData { use_brackets_p })),
+            location,
+            Some(contents),
+            post_blank,
+            None,
+        ))
+    }
+
+    /// Parse superscript at point, if any.
+    /// Assume point is at the caret.
+    /// (defun org-element-superscript-parser ()
+    pub fn superscript_parser(&self) -> Option<SyntaxNode<'a>> {
+        let (location, contents, post_blank, use_brackets_p) = self.script_at('^')?;
+        Some(SyntaxNode::new(
+            Syntax::Superscript(Box::new(SuperscriptData { use_brackets_p })),
+            location,
+            Some(contents),
+            post_blank,
+            None,
+        ))
+    }
+}
+
+mod test {
+    use crate::data::{Handle, Interval, Syntax, SyntaxT};
+    use crate::parser::Parser;
+
+    fn objects(text: &str) -> Vec<Handle> {
+        Parser::parse_secondary_string(text, |t| SyntaxT::Paragraph.can_contain(t))
+    }
+
+    fn brackets(node: &Handle) -> bool {
+        match &node.data {
+            Syntax::Subscript(s) => s.use_brackets_p,
+            Syntax::Superscript(s) => s.use_brackets_p,
+            _ => panic!("expected script"),
+        }
+    }
+
+    #[test]
+    fn nested_scripts() {
+        let parsed = objects("a_{b^{c}} d");
+        assert_eq!(3, parsed.len());
+        let subscript = &parsed[1];
+        assert_eq!(SyntaxT::Subscript, SyntaxT::from(&subscript.data));
+        assert!(brackets(subscript));
+        assert_eq!(Interval { start: 1, end: 10 }, subscript.location);
+        assert_eq!(
+            Some(Interval { start: 3, end: 8 }),
+            subscript.content_location
+        );
+        assert_eq!(1, subscript.post_blank);
+
+        let children = subscript.children.borrow();
+        assert_eq!(2, children.len());
+        let superscript = &children[1];
+        assert_eq!(SyntaxT::Superscript, SyntaxT::from(&superscript.data));
+        assert!(brackets(superscript));
+        assert_eq!(Interval { start: 4, end: 8 }, superscript.location);
+        let text = superscript.children.borrow()[0].clone();
+        match text.data {
+            Syntax::PlainText(text) => assert_eq!("c", text),
+            _ => panic!("expected plain text"),
+        }
+    }
+
+    #[test]
+    fn plain_scripts() {
+        let parsed = objects("x^2 y_-1,t a_\\alpha _no");
+        let scripts: Vec<_> = parsed
+            .iter()
+            .filter(|o| SyntaxT::from(&o.data) != SyntaxT::PlainText)
+            .collect();
+        assert_eq!(3, scripts.len());
+        assert!(scripts.iter().all(|s| !brackets(s)));
+        assert_eq!(Interval { start: 1, end: 4 }, scripts[0].location);
+        assert_eq!(Interval { start: 5, end: 11 }, scripts[1].location);
+
+        // Contents are literal
+        let children = scripts[2].children.borrow().clone();
+        assert_eq!(1, children.len());
+        match children[0].data {
+            Syntax::PlainText(text) => assert_eq!("\\alpha", text),
+            _ => panic!("expected plain text"),
+        }
+
+        // Parenthesis are kept in contents
+        let parsed = objects("f_(x)");
+        assert_eq!(
+            Some(Interval { start: 2, end: 5 }),
+            parsed[1].content_location
+        );
+        assert!(objects("a_ b")
+            .iter()
+            .all(|o| SyntaxT::from(&o.data) == SyntaxT::PlainText));
+    }
+}
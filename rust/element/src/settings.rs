@@ -0,0 +1,284 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Buffer settings
+//! https://orgmode.org/manual/In_002dbuffer-Settings.html
+//!
+//! Keywords like `#+TAGS:` configure the whole buffer wherever they
+//! appear in it, except inside blocks. `#+SETUPFILE:` pulls in the
+//! settings of another file, which contents are fetched by a resolver.
+
+use crate::headline::{TodoKeywords, ORG_ARCHIVE_TAG};
+use crate::regexps::{
+    REGEX_BLOCK_BEGIN_LINE, REGEX_BLOCK_END_LINE, REGEX_KEYWORD_LINE, REGEX_TAG_SPEC,
+};
+
+/// Settings collected from the keywords of a buffer
+#[derive(Debug, Default, PartialEq)]
+pub struct BufferSettings {
+    /// Tags from `#+TAGS:`, in order of appearance. Consecutive
+    /// keywords are separated by `TagSpec::Newline`.
+    /// elisp: `org-tag-alist`
+    pub tags: Vec<TagSpec>,
+
+    /// TODO keywords from `#+TODO:`, `#+SEQ_TODO:` and `#+TYP_TODO:`,
+    /// or None if the buffer defines none.
+    /// elisp: `org-todo-keywords-1`
+    pub todo_keywords: Option<TodoKeywords>,
+
+    /// Where subtrees are archived, from the last `#+ARCHIVE:`, if any.
+    /// elisp: `org-archive-location`
+    pub archive_location: Option<String>,
+
+    /// Tag marking archived subtrees, or None for `ORG_ARCHIVE_TAG`.
+    /// No keyword sets it, it is a variable in Org too.
+    /// elisp: `org-archive-tag`
+    pub archive_tag: Option<String>,
+
+    /// Link abbreviations from `#+LINK:`, as (abbreviation, replacement)
+    /// pairs in order of appearance.
+    /// elisp: `org-link-abbrev-alist-local`
+    pub link_abbrevs: Vec<(String, String)>,
+}
+
+/// Fetches the contents of the file named by a `#+SETUPFILE:` keyword,
+/// or returns None when there is no such file.
+pub type SetupFileResolver<'r> = &'r dyn Fn(&str) -> Option<String>;
+
+/// Entry of a tag list, as `org-tag-alist` stores them
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagSpec {
+    /// Tag, or a regexp in braces matching tags of a group,
+    /// with its fast access key, if any
+    Tag { name: String, key: Option<char> },
+    /// `{`, following tags are mutually exclusive (:startgroup)
+    StartGroup,
+    /// `}` (:endgroup)
+    EndGroup,
+    /// `[`, a group that is not mutually exclusive (:startgrouptag)
+    StartGroupTag,
+    /// `]` (:endgrouptag)
+    EndGroupTag,
+    /// `:`, tags following the first tag of a group are its
+    /// members (:grouptags)
+    GroupTags,
+    /// Line break in the fast tag selection interface (:newline)
+    Newline,
+}
+
+impl BufferSettings {
+    /// Collects settings from keywords found in `text`
+    /// elisp: `org-set-regexps-and-options`
+    ///
+    /// `#+SETUPFILE:` keywords are left alone, see `collect_with`.
+    pub fn collect(text: &str) -> BufferSettings {
+        let mut settings = BufferSettings::default();
+        settings.collect_into(text, None, &mut vec![]);
+        settings
+    }
+
+    /// Collects settings from keywords found in `text`, along with
+    /// settings of the files its `#+SETUPFILE:` keywords point to,
+    /// as returned by `resolver`. Setup files are read in place of
+    /// the keyword and a file that is already being read is skipped,
+    /// so recursive setup files terminate.
+    pub fn collect_with(text: &str, resolver: SetupFileResolver) -> BufferSettings {
+        let mut settings = BufferSettings::default();
+        settings.collect_into(text, Some(resolver), &mut vec![]);
+        settings
+    }
+
+    /// `visiting` holds names of the setup files being read
+    fn collect_into(
+        &mut self,
+        text: &str,
+        resolver: Option<SetupFileResolver>,
+        visiting: &mut Vec<String>,
+    ) {
+        let mut block: Option<String> = None;
+        for line in text.lines() {
+            if let Some(name) = &block {
+                let closed = REGEX_BLOCK_END_LINE
+                    .captures(line)
+                    .map_or(false, |cap| cap[1].eq_ignore_ascii_case(name));
+                if closed {
+                    block = None;
+                }
+            } else if let Some(cap) = REGEX_BLOCK_BEGIN_LINE.captures(line) {
+                block = Some(cap[1].to_string());
+            } else if let Some(cap) = REGEX_KEYWORD_LINE.captures(line) {
+                let value = cap[2].trim();
+                if !cap[1].eq_ignore_ascii_case("SETUPFILE") {
+                    self.add_keyword(&cap[1], value);
+                    continue;
+                }
+                let file = value.trim_matches('"');
+                if visiting.iter().any(|f| f == file) {
+                    continue;
+                }
+                if let Some(contents) = resolver.and_then(|resolve| resolve(file)) {
+                    visiting.push(file.to_string());
+                    self.collect_into(&contents, resolver, visiting);
+                    visiting.pop();
+                }
+            }
+        }
+    }
+
+    /// Returns the tag marking archived subtrees
+    pub fn archive_tag(&self) -> &str {
+        self.archive_tag.as_deref().unwrap_or(ORG_ARCHIVE_TAG)
+    }
+
+    /// Updates settings according to keyword `key` with `value`.
+    /// Keywords that are not settings are ignored.
+    pub fn add_keyword(&mut self, key: &str, value: &str) {
+        if key.eq_ignore_ascii_case("TAGS") {
+            if !self.tags.is_empty() {
+                self.tags.push(TagSpec::Newline);
+            }
+            self.tags.extend(parse_tags(value));
+        } else if ["TODO", "SEQ_TODO", "TYP_TODO"]
+            .iter()
+            .any(|k| key.eq_ignore_ascii_case(k))
+        {
+            let sequence = TodoKeywords::from_sequence(value);
+            match &mut self.todo_keywords {
+                Some(keywords) => {
+                    keywords.todo.extend(sequence.todo);
+                    keywords.done.extend(sequence.done);
+                }
+                None => self.todo_keywords = Some(sequence),
+            }
+        } else if key.eq_ignore_ascii_case("ARCHIVE") {
+            self.archive_location = Some(value.to_string());
+        } else if key.eq_ignore_ascii_case("LINK") {
+            let mut words = value.splitn(2, |c| c == ' ' || c == '\t');
+            let abbrev = words.next().unwrap_or("");
+            let replacement = words.next().unwrap_or("").trim();
+            if !abbrev.is_empty() && !replacement.is_empty() {
+                self.link_abbrevs
+                    .push((abbrev.to_string(), replacement.to_string()));
+            }
+        }
+    }
+}
+
+/// Parses the value of a `#+TAGS:` keyword.
+/// Words that are neither tags nor group markers are ignored.
+/// elisp: `org-tag-string-to-alist`
+pub fn parse_tags(value: &str) -> Vec<TagSpec> {
+    value
+        .split_whitespace()
+        .filter_map(|word| match word {
+            "{" => Some(TagSpec::StartGroup),
+            "}" => Some(TagSpec::EndGroup),
+            "[" => Some(TagSpec::StartGroupTag),
+            "]" => Some(TagSpec::EndGroupTag),
+            ":" => Some(TagSpec::GroupTags),
+            "\\n" => Some(TagSpec::Newline),
+            _ => REGEX_TAG_SPEC.captures(word).map(|cap| TagSpec::Tag {
+                name: cap[1].to_string(),
+                key: cap.get(2).and_then(|m| m.as_str().chars().next()),
+            }),
+        })
+        .collect()
+}
+
+mod test {
+    use super::{parse_tags, BufferSettings, TagSpec};
+    use crate::data::Syntax;
+    use crate::parser::{ParseGranularity, Parser};
+
+    fn tag(name: &str, key: Option<char>) -> TagSpec {
+        TagSpec::Tag {
+            name: name.to_string(),
+            key,
+        }
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(
+            vec![
+                tag("@work", Some('w')),
+                tag("@home", Some('h')),
+                TagSpec::StartGroup,
+                tag("a", None),
+                TagSpec::GroupTags,
+                tag("b", None),
+                tag("c", None),
+                TagSpec::EndGroup,
+            ],
+            parse_tags("@work(w) @home(h) { a : b c }")
+        );
+        assert_eq!(
+            vec![tag("{P@.+}", None), tag("ok", None)],
+            parse_tags("{P@.+} not-a-tag ok")
+        );
+    }
+
+    #[test]
+    fn collect() {
+        let text = "#+TAGS: a(a) b\n\
+                    #+begin_example\n#+TAGS: ignored\n#+end_example\n\
+                    * Headline\n  #+tags: [ c : d ]\n";
+        assert_eq!(
+            vec![
+                tag("a", Some('a')),
+                tag("b", None),
+                TagSpec::Newline,
+                TagSpec::StartGroupTag,
+                tag("c", None),
+                TagSpec::GroupTags,
+                tag("d", None),
+                TagSpec::EndGroupTag,
+            ],
+            BufferSettings::collect(text).tags
+        );
+    }
+
+    #[test]
+    fn setupfile() {
+        let resolver = |file: &str| match file {
+            "todo.setup" => Some("#+TODO: NEXT | FINISHED\n#+SETUPFILE: tags.setup\n".to_string()),
+            "tags.setup" => Some("#+TAGS: a\n#+SETUPFILE: todo.setup\n".to_string()),
+            _ => None,
+        };
+        let text = "#+SETUPFILE: \"todo.setup\"\n#+SETUPFILE: missing.setup\n\
+                    * NEXT Write\n* TODO Plain\n";
+
+        let settings = BufferSettings::collect_with(text, &resolver);
+        assert_eq!(vec![tag("a", None)], settings.tags);
+        let keywords = settings.todo_keywords.clone().unwrap();
+        assert_eq!(vec!["NEXT"], keywords.todo);
+        assert_eq!(vec!["FINISHED"], keywords.done);
+        assert_eq!(None, BufferSettings::collect(text).todo_keywords);
+
+        let mut parser = Parser::new(text, ParseGranularity::Element);
+        parser.apply_settings(&settings);
+        let root = parser.parse_buffer();
+        let keywords: Vec<_> = root
+            .children
+            .borrow()
+            .iter()
+            .filter_map(|node| match &node.data {
+                Syntax::Headline(h) => Some(h.todo_keyword),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![Some("NEXT"), None], keywords);
+    }
+}
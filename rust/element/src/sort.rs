@@ -0,0 +1,213 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Sorting of sibling headlines, like `org-sort-entries` does.
+
+use crate::data::{Syntax, SyntaxNode};
+use std::cmp::Ordering;
+
+/// What sibling headlines are sorted by
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortKey {
+    /// Alphabetically by title, ignoring case
+    Alpha,
+    /// By value of the named property. Values are compared as numbers
+    /// when all of them are numbers, and as strings otherwise.
+    /// Values such as `NaN` or `inf` are numbers, but no finite ones,
+    /// headlines having them are sorted as if they had no value.
+    Property(String),
+}
+
+/// Returns the text of `parent`, a node obtained by parsing `src`, with
+/// its child headlines, along with their subtrees, sorted by `key`.
+/// Sorting is stable. Headlines without the value to sort by go after
+/// the others, unless `missing_last` is false.
+///
+/// `parent` is either a headline or the root of the document, text
+/// before the first child headline is kept in place.
+///
+/// elisp: `org-sort-entries`
+pub fn sort_headlines(parent: &SyntaxNode, src: &str, key: &SortKey, missing_last: bool) -> String {
+    let (begin, end) = match parent.data {
+        Syntax::OrgData => (0, src.len()),
+        _ => (parent.location.start, parent.location.end),
+    };
+    let children = parent.children.borrow();
+    let headlines: Vec<_> = children
+        .iter()
+        .filter_map(|child| match &child.data {
+            Syntax::Headline(h) => Some((child.location, h)),
+            _ => None,
+        })
+        .collect();
+    let first = match headlines.first() {
+        Some((location, _)) => location.start,
+        None => return src[begin..end].to_string(),
+    };
+
+    let mut values: Vec<Option<String>> = headlines
+        .iter()
+        .map(|(_, h)| match key {
+            SortKey::Alpha => Some(h.raw_value.to_lowercase()),
+            SortKey::Property(name) => h.get_property(name).map(|v| v.to_string()),
+        })
+        .collect();
+    let numbers: Option<Vec<Option<f64>>> = values
+        .iter()
+        .map(|value| match value {
+            Some(value) => match value.trim().parse::<f64>() {
+                Ok(n) if n.is_finite() => Some(Some(n)),
+                // NaN or infinity, not a value to sort by
+                Ok(_) => Some(None),
+                Err(_) => None,
+            },
+            None => Some(None),
+        })
+        .collect();
+    if let Some(numbers) = &numbers {
+        for (value, number) in values.iter_mut().zip(numbers.iter()) {
+            if number.is_none() {
+                *value = None;
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..headlines.len()).collect();
+    let missing = if missing_last {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    };
+    order.sort_by(|&a, &b| match (&values[a], &values[b]) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => missing,
+        (Some(_), None) => missing.reverse(),
+        (Some(x), Some(y)) => match &numbers {
+            Some(numbers) => numbers[a]
+                .partial_cmp(&numbers[b])
+                .unwrap_or(Ordering::Equal),
+            None => x.cmp(y),
+        },
+    });
+
+    let last = headlines.last().unwrap().0.end;
+    let mut result = src[begin..first].to_string();
+    for i in order {
+        let location = headlines[i].0;
+        result.push_str(&src[location.start..location.end]);
+        if !result.ends_with('\n') {
+            result.push('\n');
+        }
+    }
+    if !src[..last].ends_with('\n') {
+        result.pop();
+    }
+    result.push_str(&src[last..end]);
+    result
+}
+
+mod test {
+    use crate::parser::{ParseGranularity, Parser};
+    use crate::sort::{sort_headlines, SortKey};
+
+    #[test]
+    fn sort_by_property() {
+        let text = concat!(
+            "Intro\n",
+            "* Ten\n",
+            ":PROPERTIES:\n",
+            ":EFFORT: 10\n",
+            ":END:\n",
+            "* None\n",
+            "* Two\n",
+            ":PROPERTIES:\n",
+            ":Effort:  2\n",
+            ":END:\n",
+            "** Child\n",
+            "* Three\n",
+            ":PROPERTIES:\n",
+            ":EFFORT: 3\n",
+            ":END:"
+        );
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let effort = SortKey::Property("EFFORT".to_string());
+
+        let titles = |sorted: &str| -> Vec<String> {
+            sorted
+                .lines()
+                .filter(|line| line.starts_with("* "))
+                .map(|line| line[2..].to_string())
+                .collect()
+        };
+
+        let sorted = sort_headlines(&root, text, &effort, true);
+        assert_eq!(vec!["Two", "Three", "Ten", "None"], titles(&sorted));
+        assert_eq!(text.len(), sorted.len());
+        assert!(sorted.starts_with("Intro\n* Two\n"));
+        assert!(sorted.contains("** Child\n* Three\n"));
+        assert!(sorted.ends_with("* None"));
+
+        let sorted = sort_headlines(&root, text, &effort, false);
+        assert_eq!(vec!["None", "Two", "Three", "Ten"], titles(&sorted));
+
+        let sorted = sort_headlines(&root, text, &SortKey::Alpha, true);
+        assert_eq!(vec!["None", "Ten", "Three", "Two"], titles(&sorted));
+
+        // Non-finite numbers count as missing values
+        let text = text.replace(":EFFORT: 10", ":EFFORT: NaN");
+        let root = Parser::new(&text, ParseGranularity::Element).parse_buffer();
+        let sorted = sort_headlines(&root, &text, &effort, true);
+        assert_eq!(vec!["Two", "Three", "Ten", "None"], titles(&sorted));
+        let sorted = sort_headlines(&root, &text, &effort, false);
+        assert_eq!(vec!["Ten", "None", "Two", "Three"], titles(&sorted));
+        let text = text.replace(":EFFORT: NaN", ":EFFORT: -inf");
+        let root = Parser::new(&text, ParseGranularity::Element).parse_buffer();
+        let sorted = sort_headlines(&root, &text, &effort, false);
+        assert_eq!(vec!["Ten", "None", "Two", "Three"], titles(&sorted));
+    }
+
+    #[test]
+    fn sort_lexicographic() {
+        let text = concat!(
+            "* Top\n",
+            "** B\n",
+            ":PROPERTIES:\n",
+            ":EFFORT: 1:30\n",
+            ":END:\n",
+            "** A\n",
+            ":PROPERTIES:\n",
+            ":EFFORT: 0:45\n",
+            ":END:\n",
+            "* Next\n"
+        );
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let top = &root.children.borrow()[0];
+        let effort = SortKey::Property("EFFORT".to_string());
+        let sorted = concat!(
+            "* Top\n",
+            "** A\n",
+            ":PROPERTIES:\n",
+            ":EFFORT: 0:45\n",
+            ":END:\n",
+            "** B\n",
+            ":PROPERTIES:\n",
+            ":EFFORT: 1:30\n",
+            ":END:\n"
+        );
+        assert_eq!(sorted, sort_headlines(top, text, &effort, true));
+    }
+}
@@ -16,20 +16,21 @@
 // TODO add table related docs
 
 use crate::affiliated::AffiliatedData;
-use crate::data::SyntaxNode;
+use crate::data::{Handle, Interval, Syntax, SyntaxNode, SyntaxT};
+use crate::formula::{align, parse_lines, Line};
 use crate::parser::Parser;
-use regex::Regex;
-
-lazy_static! {
-    pub static ref REGEX_TABLE_BORDER: Regex = Regex::new(r"[ \t]*\|").unwrap();
-    pub static ref REGEX_TABLE_RULE: Regex = Regex::new(r"[ \t]*\+(-+\+)+[ \t]*$").unwrap();
-    pub static ref REGEX_TABLE_PRE_BORDER: Regex = Regex::new(r"^[ \t]*($|[^|])").unwrap();
-}
+use crate::regexps::{REGEX_TABLE_CELL, REGEX_TABLE_COOKIE, REGEX_TABLE_HLINE, REGEX_TABLE_TBLFM};
 
 #[derive(Debug)]
 pub struct TableData<'a> {
-    /// Formulas associated to the table, if any (string or nil).
-    tblfm: Option<&'a str>,
+    /// Formulas associated to the table, if any (list of strings).
+    /// One entry per `#+TBLFM:` line, in the order of appearance.
+    pub tblfm: Vec<&'a str>,
+
+    /// Width of each column, from the first cookie giving one, e.g.
+    /// `<10>` or `<r12>`, in a row made of cookies only. Wider cells
+    /// are displayed truncated.
+    pub widths: Vec<Option<usize>>,
     //Table's origin (symbol table.el, org).
     // type_s
 
@@ -39,30 +40,543 @@ pub struct TableData<'a> {
 
 #[derive(Debug)]
 pub struct TableRowData {
-    table_row_type: TableRowType,
+    pub table_row_type: TableRowType,
 }
 
 /// Row's type (symbol standard, rule).
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TableRowType {
     Standard,
     Rule,
 }
 
 impl<'a> Parser<'a> {
-    // TODO implement table_row_parser
-    // https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L2637
+    /// Parse table row at point.
+    ///
+    /// Return a list whose car is `table-row' and cdr is a plist
+    /// containing `:begin', `:end', `:contents-begin', `:contents-end',
+    /// `:type', `:post-blank' and `:post-affiliated' keywords."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L2637
+    /// (defun org-element-table-row-parser (_)
     pub fn table_row_parser(&self) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let begin = c.pos();
+        let table_row_type = if c.looking_at(&*REGEX_TABLE_HLINE).is_some() {
+            TableRowType::Rule
+        } else {
+            TableRowType::Standard
+        };
+        let end = c.goto_next_line();
+        let line = &self.input[begin..end];
+
+        // A table rule has no contents.
+        let content_location = match table_row_type {
+            TableRowType::Rule => None,
+            TableRowType::Standard => line.find('|').map(|bar| Interval {
+                start: begin + bar + 1,
+                end: begin + line.trim_end().len(),
+            }),
+        };
+
+        SyntaxNode::new(
+            Syntax::TableRow(Box::new(TableRowData { table_row_type })),
+            Interval { start: begin, end },
+            content_location,
+            0,
+            None,
+        )
     }
 
-    // TODO implement table_parser
+    /// Parse a table at point.
+    ///
+    /// LIMIT bounds the search.  AFFILIATED is a list of which CAR is
+    /// the buffer position at the beginning of the first affiliated
+    /// keyword and CDR is a plist of affiliated keywords along with
+    /// their value.
+    ///
+    /// Return a list whose CAR is `table' and CDR is a plist containing
+    /// `:begin', `:end', `:tblfm', `:type', `:contents-begin',
+    /// `:contents-end', `:value', `:post-blank' and `:post-affiliated'
+    /// keywords.
+    ///
+    /// Assume point is at the beginning of the table."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L2574
+    /// (defun org-element-table-parser (limit affiliated)
+    ///
+    /// NB: table.el style tables are not supported
     pub fn table_parser(
         &self,
         limit: usize,
         start: usize,
-        maybe_aff: Option<AffiliatedData>,
+        maybe_aff: Option<AffiliatedData<'a>>,
     ) -> SyntaxNode<'a> {
-        unimplemented!()
+        let mut c = self.cursor.borrow_mut();
+        let table_begin = c.pos();
+
+        // Table ends at the first line not starting with a bar
+        while c.pos() < limit {
+            let line_begin = c.pos();
+            let line_end = c.goto_next_line().min(limit);
+            if !self.input[line_begin..line_end]
+                .trim_start_matches(|c| c == ' ' || c == '\t')
+                .starts_with('|')
+            {
+                c.set(line_begin);
+                break;
+            }
+        }
+        let table_end = c.pos();
+
+        let widths = column_widths(&self.input[table_begin..table_end]);
+
+        let mut tblfm = vec![];
+        while c.pos() < limit {
+            match c.capturing_at(&*REGEX_TABLE_TBLFM) {
+                Some(captures) => tblfm.push(captures.get(1).unwrap().as_str()),
+                None => break,
+            }
+            c.goto_next_line();
+        }
+        drop(c);
+        let (end, post_blank) = self.skip_post_blank(limit);
+
+        SyntaxNode::new(
+            Syntax::Table(Box::new(TableData { tblfm, widths })),
+            Interval { start, end },
+            Some(Interval {
+                start: table_begin,
+                end: table_end,
+            }),
+            post_blank,
+            maybe_aff,
+        )
+    }
+
+    /// Parses `cell`, the contents of a table cell, into objects.
+    /// Only objects allowed in a table cell are recognized, in particular
+    /// line breaks, statistics cookies and inline source blocks or babel
+    /// calls are not, since they could be confused with formulas.
+    /// Footnote references are allowed, as in Org.
+    pub fn parse_cell(cell: &'a str) -> Vec<Handle<'a>> {
+        Parser::parse_secondary_string(cell, |that| SyntaxT::TableCell.can_contain(that))
+    }
+
+    /// Parse table cell at point.
+    ///
+    /// Return a list whose car is `table-cell' and cdr is a plist
+    /// containing `:begin', `:end', `:contents-begin', `:contents-end'
+    /// and `:post-blank' keywords."
+    /// (defun org-element-table-cell-parser ()
+    pub fn table_cell_parser(&self) -> Option<SyntaxNode<'a>> {
+        let c = self.cursor.borrow();
+        let begin = c.pos();
+        if begin >= c.data().len() {
+            return None;
+        }
+        let captures = c.capturing_at(&*REGEX_TABLE_CELL)?;
+        let contents = captures.get(1).unwrap();
+        Some(SyntaxNode::new(
+            Syntax::TableCell,
+            Interval {
+                start: begin,
+                end: begin + captures.get(0).unwrap().end(),
+            },
+            Some(Interval {
+                start: begin + contents.start(),
+                end: begin + contents.end(),
+            }),
+            0,
+            None,
+        ))
+    }
+}
+
+impl<'a> TableData<'a> {
+    /// Checks if `cell`, in `column` counting from 0, is wider than the
+    /// width of its column, if any, so that it is displayed truncated
+    pub fn is_truncated(&self, column: usize, cell: &str) -> bool {
+        match self.widths.get(column) {
+            Some(Some(width)) => display_width(cell.trim()) > *width,
+            _ => false,
+        }
+    }
+}
+
+/// Reads column widths from the cookie rows of table `contents`,
+/// rows which non empty cells are all cookies
+fn column_widths(contents: &str) -> Vec<Option<usize>> {
+    let mut widths: Vec<Option<usize>> = vec![];
+    for line in parse_lines(contents) {
+        let cells = match line {
+            Line::Row(cells) => cells,
+            Line::Rule => continue,
+        };
+        let cookies: Option<Vec<_>> = cells
+            .iter()
+            .map(|cell| match cell.as_str() {
+                "" => Some(None),
+                cell => REGEX_TABLE_COOKIE.captures(cell).map(Some),
+            })
+            .collect();
+        let cookies = match cookies {
+            Some(cookies) if cookies.iter().any(Option::is_some) => cookies,
+            _ => continue,
+        };
+        if widths.len() < cookies.len() {
+            widths.resize(cookies.len(), None);
+        }
+        for (width, cookie) in widths.iter_mut().zip(cookies) {
+            let cookie_width = cookie
+                .and_then(|cap| cap.get(2))
+                .and_then(|m| m.as_str().parse().ok());
+            *width = width.or(cookie_width);
+        }
+    }
+    widths
+}
+
+/// Number of columns `s` takes on screen: characters of East Asian
+/// wide scripts take two, combining marks and zero width characters
+/// none.
+/// elisp: `string-width`
+pub fn display_width(s: &str) -> usize {
+    s.chars()
+        .map(|ch| match ch as u32 {
+            0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+            0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xA000..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1F64F
+            | 0x1F900..=0x1F9FF
+            | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Returns the text of `table`, a node obtained by parsing `src`, with
+/// rows and columns swapped, aligned like `org-table-align` does.
+/// Rows shorter than the longest one are padded with empty cells first.
+///
+/// Rules are dropped, as Org does, unless `keep_rules` is set: each rule
+/// then becomes an empty column, so that groups of rows remain visually
+/// separated. Formulas are not kept, since their references would no
+/// longer be valid. Indentation of the table is preserved.
+/// Returns an empty string if `table` is not a table.
+///
+/// elisp: `org-table-transpose-table-at-point`
+pub fn transpose_table(table: &SyntaxNode, src: &str, keep_rules: bool) -> String {
+    let contents = match (&table.data, table.content_location) {
+        (Syntax::Table(_), Some(contents)) => contents,
+        _ => return String::new(),
+    };
+    let text = &src[contents.start..contents.end];
+    let rows: Vec<Vec<String>> = parse_lines(text)
+        .into_iter()
+        .filter_map(|line| match line {
+            Line::Row(cells) => Some(cells),
+            Line::Rule if keep_rules => Some(vec![]),
+            Line::Rule => None,
+        })
+        .collect();
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+
+    let transposed: Vec<Line> = (0..columns)
+        .map(|column| {
+            Line::Row(
+                rows.iter()
+                    .map(|row| row.get(column).cloned().unwrap_or_default())
+                    .collect(),
+            )
+        })
+        .collect();
+
+    let indent_len = text.len() - text.trim_start_matches(|c| c == ' ' || c == '\t').len();
+    let mut result = String::with_capacity(text.len());
+    align(&transposed, &text[..indent_len], rows.len(), &mut result);
+    if !text.ends_with('\n') {
+        result.pop();
+    }
+    result
+}
+
+/// Returns the row and the column, counting from 0, of the cell of
+/// `table`, a node obtained by parsing `src`, containing `pos`, along with
+/// the interval of the cell contents, without surrounding blanks.
+/// Rules are counted as rows only if `count_rules` is set. A cell spans
+/// from the bar before it, excluded, to the bar after it, included.
+/// Returns None if `pos` is on a rule, or outside of any cell.
+///
+/// elisp: `org-table-current-dline`, `org-table-current-column`
+pub fn cell_at(
+    table: &SyntaxNode,
+    src: &str,
+    pos: usize,
+    count_rules: bool,
+) -> Option<(usize, usize, Interval)> {
+    let contents = match (&table.data, table.content_location) {
+        (Syntax::Table(_), Some(contents)) => contents,
+        _ => return None,
+    };
+    if pos < contents.start || pos >= contents.end {
+        return None;
+    }
+
+    let mut row = 0;
+    let mut line_begin = contents.start;
+    while line_begin < contents.end {
+        let line_end = src[line_begin..contents.end]
+            .find('\n')
+            .map_or(contents.end, |i| line_begin + i);
+        let line = &src[line_begin..line_end];
+        let is_rule = REGEX_TABLE_HLINE.is_match(line);
+        if pos <= line_end {
+            if is_rule {
+                return None;
+            }
+            let mut cell_begin = line_begin + line.find('|')? + 1;
+            let mut column = 0;
+            while cell_begin < line_end && cell_begin <= pos {
+                let captures = REGEX_TABLE_CELL.captures(&src[cell_begin..line_end])?;
+                let cell_end = cell_begin + captures.get(0).unwrap().end();
+                if pos < cell_end {
+                    let cell = captures.get(1).unwrap();
+                    return Some((
+                        row,
+                        column,
+                        Interval {
+                            start: cell_begin + cell.start(),
+                            end: cell_begin + cell.end(),
+                        },
+                    ));
+                }
+                cell_begin = cell_end;
+                column += 1;
+            }
+            return None;
+        }
+        if !is_rule || count_rules {
+            row += 1;
+        }
+        line_begin = line_end + 1;
+    }
+    None
+}
+
+mod test {
+    use crate::data::{Handle, Interval, StringOrObject, Syntax, SyntaxT};
+    use crate::parser::{ParseGranularity, Parser};
+    use crate::table::{cell_at, display_width, transpose_table, TableRowType};
+    use std::borrow::Cow;
+
+    #[test]
+    fn table_parser() {
+        let text = "| a | b |\n|---+---|\n| 1 | 2 |\n#+TBLFM: $2=$1*2 \n#+tblfm: @1$1=1\n\nafter";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let table = parser.table_parser(text.len(), 0, None);
+        assert_eq!(Interval { start: 0, end: 65 }, table.location);
+        assert_eq!(Some(Interval { start: 0, end: 30 }), table.content_location);
+        assert_eq!(1, table.post_blank);
+        match &table.data {
+            Syntax::Table(t) => assert_eq!(vec!["$2=$1*2", "@1$1=1"], t.tblfm),
+            _ => panic!("expected table"),
+        }
+
+        parser.cursor.borrow_mut().set(10);
+        let rule = parser.table_row_parser();
+        assert_eq!(Interval { start: 10, end: 20 }, rule.location);
+        assert_eq!(None, rule.content_location);
+        let row = parser.table_row_parser();
+        assert_eq!(Interval { start: 20, end: 30 }, row.location);
+        assert_eq!(Some(Interval { start: 21, end: 29 }), row.content_location);
+        match (&rule.data, &row.data) {
+            (Syntax::TableRow(r), Syntax::TableRow(s)) => {
+                assert_eq!(TableRowType::Rule, r.table_row_type);
+                assert_eq!(TableRowType::Standard, s.table_row_type);
+            }
+            _ => panic!("expected table rows"),
+        }
+    }
+
+    #[test]
+    fn width_cookies() {
+        let text = "| <l8>     |      | <r5>  |\n\
+                    | wide cell | x    | 日本語 |\n\
+                    | <2>      | <10> | <c>   |\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let table = parser.table_parser(text.len(), 0, None);
+        let t = match &table.data {
+            Syntax::Table(t) => t,
+            _ => panic!("expected table"),
+        };
+        assert_eq!(vec![Some(8), Some(10), Some(5)], t.widths);
+        assert!(t.is_truncated(0, "wide cell"));
+        assert!(!t.is_truncated(0, " narrow "));
+        assert!(t.is_truncated(2, "日本語"));
+        assert!(!t.is_truncated(2, "abcde"));
+        assert!(!t.is_truncated(3, "no such column"));
+        assert_eq!((6, 1), (display_width("日本語"), display_width("e\u{301}")));
+
+        let text = "| <l> | a <5> |\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        match &parser.table_parser(text.len(), 0, None).data {
+            Syntax::Table(t) => assert!(t.widths.is_empty()),
+            _ => panic!("expected table"),
+        }
+    }
+
+    #[test]
+    fn table_cells() {
+        let text = "|  a | *b* |  |";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let cells = parser.parse_objects(1, text.len(), |t| t == SyntaxT::TableCell);
+        let contents: Vec<_> = cells
+            .iter()
+            .map(|cell| {
+                &text[cell.content_location.unwrap().start..cell.content_location.unwrap().end]
+            })
+            .collect();
+        assert_eq!(vec!["a", "*b*", ""], contents);
+        assert_eq!(Interval { start: 1, end: 6 }, cells[0].location);
+    }
+
+    #[test]
+    fn transpose() {
+        let src = "  | a | b | c |\n  |---+---+---|\n  | 1 | 22 |\n#+TBLFM: $3=1\nafter";
+        let parser = Parser::new(src, ParseGranularity::Element);
+        let table = parser.table_parser(src.len(), 0, None);
+        assert_eq!(
+            concat!("  | a |  1 |\n", "  | b | 22 |\n", "  | c |    |\n"),
+            transpose_table(&table, src, false)
+        );
+        assert_eq!(
+            concat!(
+                "  | a |   |  1 |\n",
+                "  | b |   | 22 |\n",
+                "  | c |   |    |\n"
+            ),
+            transpose_table(&table, src, true)
+        );
+
+        let src = "| a | b | c |\n| d | e | f |";
+        let parser = Parser::new(src, ParseGranularity::Element);
+        let table = parser.table_parser(src.len(), 0, None);
+        assert_eq!(
+            "| a | d |\n| b | e |\n| c | f |",
+            transpose_table(&table, src, false)
+        );
+    }
+
+    #[test]
+    fn cell_at_position() {
+        let src = "Text\n| a |  bb |\n|---+-----|\n| 1 | 2\n#+TBLFM: $2=1\n";
+        let parser = Parser::new(src, ParseGranularity::Element);
+        parser.cursor.borrow_mut().set(5);
+        let table = parser.table_parser(src.len(), 5, None);
+        let cell = |pos| cell_at(&table, src, pos, false);
+
+        assert_eq!(Some((0, 0, Interval { start: 7, end: 8 })), cell(6));
+        assert_eq!(Some((0, 0, Interval { start: 7, end: 8 })), cell(9));
+        assert_eq!(Some((0, 1, Interval { start: 12, end: 14 })), cell(10));
+        assert_eq!(Some((0, 1, Interval { start: 12, end: 14 })), cell(15));
+        assert_eq!(Some((1, 0, Interval { start: 31, end: 32 })), cell(30));
+        assert_eq!(Some((1, 1, Interval { start: 35, end: 36 })), cell(35));
+        // Rows count rules on demand
+        assert_eq!(
+            Some((2, 1, Interval { start: 35, end: 36 })),
+            cell_at(&table, src, 34, true)
+        );
+
+        // Outside of cells
+        assert_eq!(None, cell(5));
+        assert_eq!(None, cell(16));
+        assert_eq!(None, cell(36));
+        assert_eq!(None, cell(20));
+        assert_eq!(None, cell(40));
+        assert_eq!(None, cell(0));
+    }
+
+    #[test]
+    fn cell_restriction() {
+        let types = |objects: &[Handle]| -> Vec<SyntaxT> {
+            objects.iter().map(|o| SyntaxT::from(&o.data)).collect()
+        };
+        let objects = Parser::parse_cell("*bold* [fn:1] a\\\\");
+        assert_eq!(
+            vec![
+                SyntaxT::Bold,
+                SyntaxT::FootnoteReference,
+                SyntaxT::PlainText
+            ],
+            types(&objects)
+        );
+
+        // Rows only contain cells, cells contain objects
+        let text = "| *b* | [fn:1] |\n|---|\n";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let table = &section.children.borrow()[0];
+        let rows = table.children.borrow();
+        assert_eq!(
+            vec![SyntaxT::TableCell, SyntaxT::TableCell],
+            types(&rows[0].children.borrow())
+        );
+        assert_eq!(Vec::<SyntaxT>::new(), types(&rows[1].children.borrow()));
+        let cells = rows[0].children.borrow();
+        assert_eq!(vec![SyntaxT::Bold], types(&cells[0].children.borrow()));
+        assert_eq!(
+            vec![SyntaxT::FootnoteReference],
+            types(&cells[1].children.borrow())
+        );
+    }
+
+    #[test]
+    fn table_affiliated_keywords() {
+        let text =
+            "Text\n\n#+NAME: tbl\n#+CAPTION[Short]: Long caption\n#+caption: Second\n| a |\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let table = &section.children.borrow()[1];
+        assert_eq!(SyntaxT::Table, SyntaxT::from(&table.data));
+        assert_eq!(
+            Interval {
+                start: 6,
+                end: text.len()
+            },
+            table.location
+        );
+        assert_eq!(
+            Some(Interval { start: 67, end: 73 }),
+            table.content_location
+        );
+
+        assert_eq!(Some("tbl"), table.name());
+        let caption = table.caption();
+        assert_eq!(2, caption.len());
+        assert_eq!(
+            StringOrObject::Raw(Cow::from("Long caption")),
+            caption[0].value
+        );
+        assert_eq!(
+            Some(StringOrObject::Raw(Cow::from("Short"))),
+            caption[0].secondary
+        );
+        assert_eq!(StringOrObject::Raw(Cow::from("Second")), caption[1].value);
+        assert_eq!(None, caption[1].secondary);
+
+        let parser = Parser::new("| a |\n", ParseGranularity::Element);
+        let table = parser.table_parser(6, 0, None);
+        assert_eq!(None, table.name());
+        assert!(table.caption().is_empty());
     }
 }
@@ -0,0 +1,752 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Tangling, i.e. extraction of source code from source blocks,
+//! like `org-babel-tangle` does.
+//! https://orgmode.org/manual/Extracting-Source-Code.html
+//!
+//! Blocks are written to the file named by their `:tangle` header
+//! argument. With `:noweb yes`, a `<<name>>` reference is replaced with
+//! the code of the blocks named `name`, either with `#+NAME:` or with
+//! `:noweb-ref`.
+//!
+//! The same expansion goes into the hash of a block evaluated
+//! with `:cache yes`, stored in its `#+RESULTS[HASH]:` keyword.
+
+use std::collections::HashMap;
+
+use crate::blocks::normalize_block_contents;
+use crate::blocks::SrcBlockData;
+use crate::data::{Syntax, SyntaxNode, SyntaxT};
+use crate::parser::{ParseGranularity, Parser};
+use crate::plist::parse_plist;
+use crate::regexps::REGEX_NOWEB_REFERENCE;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TangleError {
+    /// Block named so refers to itself, maybe through other blocks
+    NowebCycle(String),
+    /// Node is not one of the source blocks of the tree
+    NotASourceBlock,
+}
+
+/// When noweb references are expanded
+#[derive(Clone, Copy)]
+enum Context {
+    Tangle,
+    Eval,
+}
+
+/// Source block along with its header arguments
+struct Block {
+    begin: usize,
+    code: String,
+    /// Code as evaluated, without its final newline and de-indented
+    /// unless the block preserves indentation
+    /// elisp: `org-babel--normalize-body`
+    body: String,
+    /// Header arguments in order of appearance, a repeated one
+    /// keeping the place of its first occurrence
+    params: Vec<(String, String)>,
+    args: HashMap<String, String>,
+}
+
+impl Block {
+    fn arg(&self, name: &str) -> Option<&str> {
+        self.args.get(name).map(|v| v.as_str())
+    }
+
+    /// Noweb references are expanded in `context`
+    /// elisp: `org-babel-noweb-p`
+    fn expands_noweb(&self, context: Context) -> bool {
+        match (self.arg("noweb"), context) {
+            (Some("yes"), _) | (Some("no-export"), _) | (Some("strip-export"), _) => true,
+            (Some("tangle"), Context::Tangle) => true,
+            (Some("eval"), Context::Eval) | (Some("strip-tangle"), Context::Eval) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Adds header arguments `params`, as (name, value) pairs, to `args`.
+/// Arguments appearing later override earlier ones.
+fn header_args(params: impl Iterator<Item = (String, String)>, args: &mut Vec<(String, String)>) {
+    for (name, value) in params {
+        match args.iter_mut().find(|(n, _)| *n == name) {
+            Some(arg) => arg.1 = value,
+            None => args.push((name, value)),
+        }
+    }
+}
+
+/// Returns header arguments written for source block `node`, by name,
+/// without the colon, in the order org merges them: parameters of the
+/// opening line come first, `#+HEADER:` lines override them.
+/// elisp: `org-babel-get-src-block-info`
+fn own_params(node: &SyntaxNode, data: &SrcBlockData) -> Vec<(String, String)> {
+    let headers = node.affiliated.iter().flat_map(|a| a.header.iter());
+    data.parameters
+        .into_iter()
+        .chain(headers.map(|h| h.as_ref()))
+        .flat_map(parse_plist)
+        .collect()
+}
+
+/// Collects source blocks of the tree, in document order.
+/// Contents left out by a lazy parser are parsed, but not kept.
+fn collect_blocks<'a>(node: &SyntaxNode<'a>, src: &'a str, blocks: &mut Vec<Block>) {
+    if let Syntax::SrcBlock(data) = &node.data {
+        let mut params = vec![];
+        header_args(own_params(node, data).into_iter(), &mut params);
+        let mut args: HashMap<_, _> = params.iter().cloned().collect();
+        if let Some(name) = node.name() {
+            args.entry("noweb-ref".to_string())
+                .or_insert_with(|| name.to_string());
+        }
+        blocks.push(Block {
+            begin: node.location.start,
+            code: normalize_block_contents(&data.value, data.preserve_indent),
+            body: {
+                let value = data.value.strip_suffix('\n').unwrap_or(&data.value);
+                if data.preserve_indent {
+                    value.to_string()
+                } else {
+                    remove_indentation(value)
+                }
+            },
+            params,
+            args,
+        });
+    }
+    let mut children = node.children.borrow().clone();
+    if children.is_empty() && SyntaxT::from(&node.data).is_greater_element() {
+        children = Parser::new(src, ParseGranularity::Element).parse_contents(node);
+    }
+    for child in children.iter() {
+        collect_blocks(child, src, blocks);
+    }
+}
+
+/// Returns code of the blocks named `name`, joined by newlines,
+/// with their noweb references expanded. `expanding` holds names
+/// of the blocks being expanded.
+fn expand_reference(
+    name: &str,
+    blocks: &[Block],
+    context: Context,
+    expanding: &mut Vec<String>,
+) -> Result<String, TangleError> {
+    if expanding.iter().any(|n| n == name) {
+        return Err(TangleError::NowebCycle(name.to_string()));
+    }
+    expanding.push(name.to_string());
+    let mut parts = vec![];
+    for block in blocks.iter().filter(|b| b.arg("noweb-ref") == Some(name)) {
+        parts.push(expand(block, blocks, context, expanding)?);
+    }
+    expanding.pop();
+    Ok(parts.join("\n"))
+}
+
+/// Returns block's code with its noweb references expanded, if they
+/// have to be in `context`. Text before a reference on its line
+/// prefixes every line of the expansion. Unknown references expand
+/// to nothing.
+/// elisp: `org-babel-expand-noweb-references`
+fn expand(
+    block: &Block,
+    blocks: &[Block],
+    context: Context,
+    expanding: &mut Vec<String>,
+) -> Result<String, TangleError> {
+    let code = match context {
+        Context::Tangle => block.code.clone(),
+        Context::Eval => format!("{}\n", block.body),
+    };
+    if !block.expands_noweb(context) {
+        return Ok(code);
+    }
+    let mut result = String::with_capacity(code.len());
+    for line in code.lines() {
+        let mut last = 0;
+        for cap in REGEX_NOWEB_REFERENCE.captures_iter(line) {
+            let whole = cap.get(0).unwrap();
+            let prefix = &line[..whole.start()];
+            result.push_str(&line[last..whole.start()]);
+            let expansion = expand_reference(&cap[1], blocks, context, expanding)?;
+            let expansion = expansion.trim_end_matches('\n');
+            for (i, expanded) in expansion.split('\n').enumerate() {
+                if i > 0 {
+                    result.push('\n');
+                    result.push_str(prefix);
+                }
+                result.push_str(expanded);
+            }
+            last = whole.end();
+        }
+        result.push_str(&line[last..]);
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+/// Returns code of the source blocks of `root`, the tree obtained by
+/// parsing `src`, grouped by their `:tangle` target, in order of first
+/// appearance. Blocks without the header argument, or with `:tangle no`,
+/// are left out. Target of `:tangle yes` blocks, a file named after the
+/// document, is None.
+///
+/// Code of a block is de-indented, unless it preserves indentation,
+/// and blocks of a target are separated by a blank line.
+///
+/// elisp: `org-babel-tangle-collect-blocks`
+pub fn tangle<'a>(
+    root: &SyntaxNode<'a>,
+    src: &'a str,
+) -> Result<Vec<(Option<String>, String)>, TangleError> {
+    let mut blocks = vec![];
+    collect_blocks(root, src, &mut blocks);
+
+    let mut targets: Vec<(Option<String>, String)> = vec![];
+    for block in blocks.iter() {
+        let target = match block.arg("tangle") {
+            None | Some("no") => continue,
+            Some("yes") => None,
+            Some(file) => Some(file.to_string()),
+        };
+        let mut expanding: Vec<String> = block
+            .arg("noweb-ref")
+            .map(String::from)
+            .into_iter()
+            .collect();
+        let code = expand(block, &blocks, Context::Tangle, &mut expanding)?;
+        match targets.iter_mut().find(|(t, _)| *t == target) {
+            Some((_, text)) => {
+                text.push('\n');
+                text.push_str(&code);
+            }
+            None => targets.push((target, code)),
+        }
+    }
+    Ok(targets)
+}
+
+/// Header arguments of every source block, before its own ones
+/// elisp: `org-babel-default-header-args`
+const DEFAULT_HEADER_ARGS: &[(&str, &str)] = &[
+    ("session", "none"),
+    ("results", "replace"),
+    ("exports", "code"),
+    ("cache", "no"),
+    ("noweb", "no"),
+    ("hlines", "no"),
+    ("tangle", "no"),
+];
+
+/// Values of `:results` of which only one of each group is kept
+/// elisp: `org-babel-common-header-args-w-values`
+const RESULTS_GROUPS: &[&[&str]] = &[
+    &["file", "list", "vector", "table", "scalar", "verbatim"],
+    &[
+        "raw", "html", "latex", "org", "code", "pp", "drawer", "link", "graphics",
+    ],
+    &["replace", "silent", "none", "discard", "append", "prepend"],
+    &["output", "value"],
+];
+
+/// Values of `:exports` of which only one is kept
+const EXPORTS_GROUPS: &[&[&str]] = &[&["code", "results", "both", "none"]];
+
+/// Values of `:results` and `:exports` that do not change results
+const UNHASHED_WORDS: &[&str] = &["replace", "silent", "none", "discard", "append", "prepend"];
+
+/// Value of a header argument, as org reads it
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    /// Printed representation of a number
+    Number(String),
+    Symbol(&'static str),
+    List(Vec<String>),
+    /// Variable name and value, from `:var`
+    Var(String, Box<Value>),
+}
+
+impl Value {
+    /// Reads `s` as a number if it is one, as a string otherwise.
+    /// Double quotes around a string are removed. Lisp expressions
+    /// are not evaluated.
+    /// elisp: `org-babel-read`
+    fn read(s: &str) -> Value {
+        let inner = s
+            .trim()
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .filter(|inner| !inner.replace("\\\"", "").contains('"'));
+        if let Some(inner) = inner {
+            return Value::String(inner.replace("\\\"", "\"").replace("\\\\", "\\"));
+        }
+        if let Ok(n) = s.parse::<i64>() {
+            return Value::Number(n.to_string());
+        }
+        let numeric = s.chars().all(|c| c.is_ascii_digit() || "-+.e".contains(c));
+        match s.parse::<f64>() {
+            Ok(n) if numeric && n.is_finite() => Value::Number(format!("{:?}", n)),
+            _ => Value::String(s.to_string()),
+        }
+    }
+
+    /// elisp: `(format "%S" value)`
+    fn print(&self) -> String {
+        match self {
+            Value::String(s) => print_string(s),
+            Value::Number(n) => n.clone(),
+            Value::Symbol(s) => s.to_string(),
+            Value::List(items) => {
+                let items: Vec<_> = items.iter().map(|i| print_string(i)).collect();
+                format!("({})", items.join(" "))
+            }
+            Value::Var(name, value) => format!("({} . {})", name, value.print()),
+        }
+    }
+}
+
+fn print_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Adds `words` to `kept`, removing the words a word excludes
+/// according to `groups`
+fn merge_words(groups: &[&[&str]], kept: &mut Vec<String>, words: &str) {
+    for word in words.split_whitespace() {
+        if let Some(group) = groups.iter().find(|g| g.contains(&word)) {
+            kept.retain(|k| !group.contains(&k.as_str()));
+        }
+        kept.retain(|k| k != word);
+        kept.push(word.to_string());
+    }
+}
+
+/// Returns `words` without the ones that do not change results, sorted
+fn hashed_words(words: &[String]) -> Vec<String> {
+    let mut words: Vec<String> = words
+        .iter()
+        .filter(|w| !UNHASHED_WORDS.contains(&w.as_str()))
+        .cloned()
+        .collect();
+    words.sort();
+    words
+}
+
+/// Returns header arguments of source block `node` merged with the
+/// default ones and completed with summary arguments, `result-params`
+/// and `result-type`, as (name, value) pairs. A None value is nil.
+/// See `own_params` for the precedence of block's own arguments.
+/// elisp: `org-babel-merge-params`, `org-babel-process-params`
+fn processed_params(node: &SyntaxNode, data: &SrcBlockData) -> Vec<(String, Option<Value>)> {
+    let mut params: Vec<(String, Option<Value>)> = vec![];
+    let mut results = vec![];
+    let mut exports = vec![];
+    let mut vars: Vec<(String, Value)> = vec![];
+    for (name, value) in DEFAULT_HEADER_ARGS {
+        match *name {
+            "results" => merge_words(RESULTS_GROUPS, &mut results, value),
+            "exports" => merge_words(EXPORTS_GROUPS, &mut exports, value),
+            _ => params.push((name.to_string(), Some(Value::String(value.to_string())))),
+        }
+    }
+    for (name, value) in own_params(node, data) {
+        match name.as_str() {
+            "results" => merge_words(RESULTS_GROUPS, &mut results, &value),
+            "exports" => merge_words(EXPORTS_GROUPS, &mut exports, &value),
+            "var" => {
+                for assignment in value.split(',') {
+                    let mut parts = assignment.splitn(2, '=');
+                    if let (Some(var), Some(val)) = (parts.next(), parts.next()) {
+                        let var = var.trim().to_string();
+                        vars.retain(|(v, _)| *v != var);
+                        vars.push((var, Value::read(val.trim())));
+                    }
+                }
+            }
+            _ => {
+                let value = if value.is_empty() {
+                    None
+                } else {
+                    Some(Value::read(&value))
+                };
+                params.retain(|(n, _)| *n != name);
+                params.push((name, value));
+            }
+        }
+    }
+
+    let result_type = if results.iter().any(|w| w == "output") {
+        "output"
+    } else {
+        "value"
+    };
+    let mut processed: Vec<(String, Option<Value>)> = vars
+        .into_iter()
+        .map(|(var, value)| ("var".to_string(), Some(Value::Var(var, Box::new(value)))))
+        .collect();
+    processed.push(("colname-names".to_string(), None));
+    processed.push(("rowname-names".to_string(), None));
+    processed.push((
+        "result-params".to_string(),
+        Some(Value::List(results.clone())),
+    ));
+    processed.push(("result-type".to_string(), Some(Value::Symbol(result_type))));
+    processed.push((
+        "results".to_string(),
+        Some(Value::String(results.join(" "))),
+    ));
+    processed.push((
+        "exports".to_string(),
+        Some(Value::String(exports.join(" "))),
+    ));
+    processed.extend(params);
+    processed
+}
+
+/// Removes indentation common to the non-blank lines of `code` and
+/// empties blank lines, unless a line is not indented at all.
+/// Indentation is counted in characters.
+/// elisp: `org-remove-indentation`
+fn remove_indentation(code: &str) -> String {
+    let indentation =
+        |line: &str| line.len() - line.trim_start_matches(|c| c == ' ' || c == '\t').len();
+    let blank = |line: &str| line.trim().is_empty();
+    let indent = code
+        .split('\n')
+        .filter(|l| !blank(l))
+        .map(indentation)
+        .min();
+    if indent == Some(0) {
+        return code.to_string();
+    }
+    code.split('\n')
+        .map(|line| {
+            if blank(line) {
+                ""
+            } else {
+                &line[indent.unwrap()..]
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the text org hashes for source block `node`, `block` being
+/// the same block among `blocks`
+/// elisp: `org-babel-sha1-hash`
+fn hash_input(
+    node: &SyntaxNode,
+    data: &SrcBlockData,
+    block: &Block,
+    blocks: &[Block],
+) -> Result<String, TangleError> {
+    let mut params = processed_params(node, data);
+    // Sort is stable, variables keep their order.
+    params.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let printed: Vec<String> = params
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = match (name.as_str(), value.as_ref()?) {
+                (_, Value::String(s)) if s.is_empty() => return None,
+                (_, Value::List(items)) if items.is_empty() => return None,
+                ("result-params", Value::List(items)) => {
+                    let words = hashed_words(items);
+                    if words.is_empty() {
+                        return None;
+                    }
+                    Value::List(words)
+                }
+                ("results", Value::String(s)) | ("exports", Value::String(s)) => {
+                    let words: Vec<String> = s.split_whitespace().map(String::from).collect();
+                    Value::String(hashed_words(&words).join(" "))
+                }
+                (_, value) => value.clone(),
+            };
+            Some(value.print())
+        })
+        .collect();
+
+    let mut expanding: Vec<String> = block
+        .arg("noweb-ref")
+        .map(String::from)
+        .into_iter()
+        .collect();
+    let body = expand(block, blocks, Context::Eval, &mut expanding)?;
+    let body = body.strip_suffix('\n').unwrap_or(&body);
+    // `org-babel-expand-body:generic'
+    let arg = |name: &str| {
+        params.iter().find_map(|(n, v)| match v {
+            Some(Value::String(s)) if n == name => Some(s.as_str()),
+            _ => None,
+        })
+    };
+    let expanded: Vec<&str> = arg("prologue")
+        .into_iter()
+        .chain(Some(body))
+        .chain(arg("epilogue"))
+        .collect();
+    Ok(format!("{}-{}", printed.join(":"), expanded.join("\n")))
+}
+
+/// Returns the hash org stores in the results of source block `node`,
+/// part of `root`, the tree obtained by parsing `src`, when it is
+/// evaluated with `:cache yes`. Results are up to date as long as it
+/// matches the hash of their `#+RESULTS[HASH]:` keyword, see
+/// `SyntaxNode::results_hash`.
+///
+/// Hashed text is `PARAMS-BODY`. PARAMS are the header arguments of
+/// the block merged with the default ones, sorted by name and printed
+/// as elisp values, joined with colons, nil and empty ones left out.
+/// Words `replace`, `silent`, `none`, `discard`, `append` and `prepend`
+/// are removed from `:results` and `:exports`, remaining words are
+/// sorted. BODY is the code of the block without its final newline,
+/// de-indented and with noweb references expanded if `:noweb` allows
+/// it on evaluation, between the `:prologue` and `:epilogue`, if any.
+///
+/// Languages defining default header arguments or an expansion of
+/// their own, e.g. emacs-lisp, as well as header arguments from
+/// properties, lisp values and `:var` references to other blocks are
+/// not supported, they change the hash org computes. So do variables
+/// assigned at the beginning of the body by languages like sh.
+///
+/// elisp: `org-babel-sha1-hash`
+pub fn src_block_hash<'a>(
+    root: &SyntaxNode<'a>,
+    node: &SyntaxNode,
+    src: &'a str,
+) -> Result<String, TangleError> {
+    let data = match &node.data {
+        Syntax::SrcBlock(data) => data,
+        _ => return Err(TangleError::NotASourceBlock),
+    };
+    let mut blocks = vec![];
+    collect_blocks(root, src, &mut blocks);
+    let block = blocks
+        .iter()
+        .find(|b| b.begin == node.location.start)
+        .ok_or(TangleError::NotASourceBlock)?;
+    let input = hash_input(node, data, block, &blocks)?;
+    Ok(sha1(input.as_bytes()))
+}
+
+/// SHA-1 digest of `data`, as a lowercase hexadecimal string
+/// elisp: `sha1`
+fn sha1(data: &[u8]) -> String {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (word, bytes) in w.iter_mut().zip(chunk.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i / 20 {
+                0 => ((b & c) | (!b & d), 0x5A82_7999),
+                1 => (b ^ c ^ d, 0x6ED9_EBA1),
+                2 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, v) in h.iter_mut().zip(&[a, b, c, d, e]) {
+            *h = h.wrapping_add(*v);
+        }
+    }
+    h.iter().map(|v| format!("{:08x}", v)).collect()
+}
+
+mod test {
+    use super::{collect_blocks, hash_input, sha1, src_block_hash, tangle, TangleError};
+    use crate::data::Syntax;
+    use crate::parser::{ParseGranularity, Parser};
+
+    #[test]
+    fn tangle_noweb() {
+        let text = "#+NAME: greet\n\
+                    #+BEGIN_SRC sh\n  echo hello\n  echo world\n#+END_SRC\n\
+                    \n\
+                    #+BEGIN_SRC sh :tangle run.sh :noweb yes\n\
+                    main() {\n    <<greet>>\n}\n#+END_SRC\n\
+                    \n\
+                    #+HEADER: :tangle run.sh\n\
+                    #+BEGIN_SRC sh\n  main <<greet>>\n#+END_SRC\n\
+                    \n\
+                    #+BEGIN_SRC sh :tangle yes\nls\n#+END_SRC\n\
+                    \n\
+                    #+BEGIN_SRC sh :tangle no\nrm\n#+END_SRC\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        assert_eq!(
+            Ok(vec![
+                (
+                    Some("run.sh".to_string()),
+                    "main() {\n    echo hello\n    echo world\n}\n\nmain <<greet>>\n".to_string()
+                ),
+                (None, "ls\n".to_string()),
+            ]),
+            tangle(&root, text)
+        );
+    }
+
+    #[test]
+    fn tangle_header_precedence() {
+        let text = "#+HEADER: :tangle a.sh\n#+begin_src sh :tangle b.sh\nls\n#+end_src\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        assert_eq!(
+            Ok(vec![(Some("a.sh".to_string()), "ls\n".to_string())]),
+            tangle(&root, text)
+        );
+
+        // Lazy contents are read, not kept in the tree
+        let text = ":NOTES:\n#+begin_src sh :tangle a.sh\nls\n#+end_src\n:END:\n";
+        let mut parser = Parser::new(text, ParseGranularity::Element);
+        parser.lazy_contents = true;
+        let root = parser.parse_buffer();
+        assert_eq!(
+            Ok(vec![(Some("a.sh".to_string()), "ls\n".to_string())]),
+            tangle(&root, text)
+        );
+        let section = root.children.borrow()[0].clone();
+        assert!(section.children.borrow().is_empty());
+    }
+
+    #[test]
+    fn tangle_noweb_cycle() {
+        let text = "#+NAME: a\n#+BEGIN_SRC sh :noweb yes\n<<b>>\n#+END_SRC\n\
+                    #+NAME: b\n#+BEGIN_SRC sh :noweb yes :tangle b.sh\n<<a>>\n#+END_SRC\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        assert_eq!(
+            Err(TangleError::NowebCycle("b".to_string())),
+            tangle(&root, text)
+        );
+    }
+
+    #[test]
+    fn sha1_digest() {
+        assert_eq!("da39a3ee5e6b4b0d3255bfef95601890afd80709", sha1(b""));
+        assert_eq!("a9993e364706816aba3e25717850c26c9cd0d89d", sha1(b"abc"));
+        let long = "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        assert_eq!(
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+            sha1(long.as_bytes())
+        );
+    }
+
+    #[test]
+    fn src_block_hash_results() {
+        let text = "#+NAME: two\n#+BEGIN_SRC sh\necho 2\n#+END_SRC\n\n\
+                    #+HEADER: :exports both\n\
+                    #+BEGIN_SRC sh :results replace output :cache yes :noweb eval\n\
+                    \x20 echo 1\n  <<two>>\n#+END_SRC\n\n\
+                    #+RESULTS[b0412a34b340b138fdceb4890ad7037a1a0ca420]:\n1\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let children = section.children.borrow();
+
+        // Arguments sorted by name: cache, exports, hlines, noweb,
+        // result-params, result-type, results, session and tangle
+        let expected = "\"yes\":\"both\":\"no\":\"eval\":(\"output\"):output:\"output\":\"none\":\"no\"-echo 1\necho 2";
+        let data = match &children[1].data {
+            Syntax::SrcBlock(data) => data,
+            _ => panic!("expected src block"),
+        };
+        let mut blocks = vec![];
+        collect_blocks(&root, text, &mut blocks);
+        assert_eq!(
+            Ok(expected.to_string()),
+            hash_input(&children[1], data, &blocks[1], &blocks)
+        );
+        // `sha1sum` of the expected input
+        let hash = src_block_hash(&root, &children[1], text).unwrap();
+        assert_eq!("b0412a34b340b138fdceb4890ad7037a1a0ca420", hash);
+        assert_eq!(Some(hash.as_str()), children[2].results_hash());
+        assert_eq!(None, children[1].results_hash());
+
+        // Default `:results replace' leaves an empty value
+        let data = match &children[0].data {
+            Syntax::SrcBlock(data) => data,
+            _ => panic!("expected src block"),
+        };
+        assert_eq!(
+            Ok("\"no\":\"code\":\"no\":\"no\":value:\"\":\"none\":\"no\"-echo 2".to_string()),
+            hash_input(&children[0], data, &blocks[0], &blocks)
+        );
+
+        assert_eq!(
+            Err(TangleError::NotASourceBlock),
+            src_block_hash(&root, &children[2], text)
+        );
+    }
+
+    #[test]
+    fn hashed_params() {
+        let text = "#+HEADER: :var y=\"a b\"\n\
+                    #+BEGIN_SRC python :var x=1, y=2 :session 3 :results discard table :exports none :prologue import os\n\
+                    print(x)\n#+END_SRC\n";
+        let parser = Parser::new(text, ParseGranularity::Element);
+        let root = parser.parse_buffer();
+        let section = &root.children.borrow()[0];
+        let node = section.children.borrow()[0].clone();
+        let data = match &node.data {
+            Syntax::SrcBlock(data) => data,
+            _ => panic!("expected src block"),
+        };
+        let mut blocks = vec![];
+        collect_blocks(&root, text, &mut blocks);
+        assert_eq!(
+            Ok(
+                "\"no\":\"\":\"no\":\"no\":\"import os\":(\"table\"):value:\"table\":3:\"no\":\
+                (x . 1):(y . \"a b\")-import os\nprint(x)"
+                    .to_string()
+            ),
+            hash_input(&node, data, &blocks[0], &blocks)
+        );
+    }
+}
@@ -0,0 +1,590 @@
+//    This file is part of org-rs.
+//
+//    org-rs is free software: you can redistribute it and/or modify
+//    it under the terms of the GNU General Public License as published by
+//    the Free Software Foundation, either version 3 of the License, or
+//    (at your option) any later version.
+//
+//    org-rs is distributed in the hope that it will be useful,
+//    but WITHOUT ANY WARRANTY; without even the implied warranty of
+//    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//    GNU General Public License for more details.
+//
+//    You should have received a copy of the GNU General Public License
+//    along with org-rs.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Timestamps
+//! https://orgmode.org/worg/dev/org-syntax.html#Timestamp
+//!
+//! There are seven possible patterns for timestamps:
+//!
+//! <%%(SEXP)>                                                     (diary)
+//! <DATE TIME REPEATER-OR-DELAY>                                  (active)
+//! [DATE TIME REPEATER-OR-DELAY]                                  (inactive)
+//! <DATE TIME REPEATER-OR-DELAY>--<DATE TIME REPEATER-OR-DELAY>   (active range)
+//! <DATE TIME-TIME REPEATER-OR-DELAY>                             (active range)
+//! [DATE TIME REPEATER-OR-DELAY]--[DATE TIME REPEATER-OR-DELAY]   (inactive range)
+//! [DATE TIME-TIME REPEATER-OR-DELAY]                             (inactive range)
+//!
+//! SEXP can contain any character excepted > and \n.
+//!
+//! DATE follows the pattern YYYY-MM-DD DAYNAME. Y, M and D are digits.
+//! DAYNAME can contain any non whitespace-character besides +, -, ], >,
+//! a digit or \n. It is only informative and is not used to compute the date.
+//!
+//! TIME follows the pattern H:MM. H can be one or two digit long and can start with 0.
+//!
+//! REPEATER-OR-DELAY follows the pattern MARK VALUE UNIT where MARK is
+//! +, ++, .+ (repeater) or -, -- (warning delay), VALUE is a number and
+//! UNIT is a character among h, d, w, m and y.
+
+use crate::data::{
+    Interval, RepeaterType, Syntax, SyntaxNode, SyntaxT, TimeUnit, TimestampData, TimestampType,
+    WarningType,
+};
+use crate::parser::Parser;
+use crate::regexps::{
+    REGEX_TIMESTAMP, REGEX_TIMESTAMP_DATE, REGEX_TIMESTAMP_DIARY, REGEX_TIMESTAMP_REPEATER,
+    REGEX_TIMESTAMP_TIME_RANGE, REGEX_TIMESTAMP_WARNING,
+};
+#[cfg(feature = "chrono")]
+use chrono::{NaiveDate, NaiveDateTime};
+use regex::Captures;
+
+fn time_unit(unit: &str) -> TimeUnit {
+    match unit {
+        "h" => TimeUnit::Hour,
+        "d" => TimeUnit::Day,
+        "w" => TimeUnit::Week,
+        "m" => TimeUnit::Month,
+        _ => TimeUnit::Year,
+    }
+}
+
+fn number(caps: &Captures, group: usize) -> Option<usize> {
+    caps.get(group).and_then(|m| m.as_str().parse().ok())
+}
+
+/// Returns timestamps found in the tree under `root`, a node obtained
+/// by parsing `src`, in document order.
+///
+/// With `active_only` inactive timestamps and ranges are left out. Diary
+/// timestamps are written between angle brackets and count as active.
+/// With `skip_clocks` timestamps of clock lines and anything inside
+/// a `LOGBOOK` drawer are left out as well.
+///
+/// Contents of elements parsed with `ParseGranularity::Element` are
+/// searched in `src`, so the tree need not include objects.
+pub fn collect_timestamps<'a>(
+    root: &SyntaxNode<'a>,
+    src: &'a str,
+    active_only: bool,
+    skip_clocks: bool,
+) -> Vec<TimestampData<'a>> {
+    fn walk<'a>(
+        node: &SyntaxNode<'a>,
+        src: &'a str,
+        skip_clocks: bool,
+        acc: &mut Vec<TimestampData<'a>>,
+    ) {
+        match &node.data {
+            Syntax::Timestamp(timestamp) => acc.push((**timestamp).clone()),
+            Syntax::Clock(clock) => {
+                if !skip_clocks {
+                    acc.extend(clock.value.clone());
+                }
+                return;
+            }
+            Syntax::Drawer(drawer)
+                if skip_clocks && drawer.drawer_name.eq_ignore_ascii_case("LOGBOOK") =>
+            {
+                return;
+            }
+            _ => {}
+        }
+
+        let children = node.children.borrow();
+        match node.content_location {
+            Some(contents)
+                if children.is_empty() && SyntaxT::from(&node.data).is_object_container() =>
+            {
+                // Objects are parsed as in the element, so that no
+                // timestamp is found within verbatim text or links
+                let syntax = SyntaxT::from(&node.data);
+                let text = &src[contents.start..contents.end];
+                for object in Parser::parse_secondary_string(text, |that| syntax.can_contain(that))
+                {
+                    walk(&object, src, skip_clocks, acc);
+                }
+            }
+            _ => {
+                for child in children.iter() {
+                    walk(child, src, skip_clocks, acc);
+                }
+            }
+        }
+    }
+
+    let mut timestamps = vec![];
+    walk(root, src, skip_clocks, &mut timestamps);
+    if active_only {
+        timestamps.retain(|timestamp| match timestamp.type_s {
+            TimestampType::Active | TimestampType::ActiveRange | TimestampType::Diary => true,
+            TimestampType::Inactive | TimestampType::InactiveRange => false,
+        });
+    }
+    timestamps
+}
+
+#[cfg(feature = "chrono")]
+impl<'a> TimestampData<'a> {
+    /// Returns the start date of the timestamp, or None for diary
+    /// timestamps and invalid dates
+    pub fn to_naive_date(&self) -> Option<NaiveDate> {
+        self.to_naive_range().map(|(start, _)| start.date())
+    }
+
+    /// Returns the start of the timestamp, at midnight when it has no
+    /// time, or None for diary timestamps and invalid dates
+    pub fn to_naive_datetime(&self) -> Option<NaiveDateTime> {
+        self.to_naive_range().map(|(start, _)| start)
+    }
+
+    /// Returns the start and the end of the timestamp, same as the start
+    /// unless it is a range, or None for diary timestamps and invalid
+    /// dates. Missing times are midnight.
+    pub fn to_naive_range(&self) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        if self.type_s == TimestampType::Diary {
+            return None;
+        }
+        let datetime = |year: usize, month, day, hour: Option<usize>, minute: Option<usize>| {
+            NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32)?.and_hms_opt(
+                hour.unwrap_or(0) as u32,
+                minute.unwrap_or(0) as u32,
+                0,
+            )
+        };
+        let start = datetime(
+            self.year_start,
+            self.month_start,
+            self.day_start,
+            self.hour_start,
+            self.minute_start,
+        )?;
+        let end = datetime(
+            self.year_end,
+            self.month_end,
+            self.day_end,
+            self.hour_end,
+            self.minute_end,
+        )?;
+        Some((start, end))
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Parse time stamp at point, if any.
+    ///
+    /// When at a time stamp, return a list whose car is `timestamp', and
+    /// cdr a plist with `:type', `:raw-value', `:year-start',
+    /// `:month-start', `:day-start', `:hour-start', `:minute-start',
+    /// `:year-end', `:month-end', `:day-end', `:hour-end',
+    /// `:minute-end', `:repeater-type', `:repeater-value',
+    /// `:repeater-unit', `:warning-type', `:warning-value',
+    /// `:warning-unit', `:begin', `:end' and `:post-blank' properties.
+    /// Otherwise, return nil.
+    ///
+    /// Assume point is at the beginning of the timestamp."
+    /// https://code.orgmode.org/bzg/org-mode/src/master/lisp/org-element.el#L3955
+    /// (defun org-element-timestamp-parser ()
+    ///
+    /// Diary timestamps keep their sexp in `diary_sexp` and are never
+    /// interpreted as calendar dates. Contents that do not start with a
+    /// date are not a timestamp. Two timestamps joined by `--` make a single
+    /// range only when both are active or both are inactive.
+    pub fn timestamp_parser(&self) -> Option<SyntaxNode<'a>> {
+        let (begin, rest) = {
+            let c = self.cursor.borrow();
+            (c.pos(), &c.data()[c.pos()..])
+        };
+
+        let (raw_value, data) = if let Some(cap) = REGEX_TIMESTAMP_DIARY.captures(rest) {
+            let raw_value = cap.get(0).unwrap().as_str();
+            let data = TimestampData {
+                day_end: 0,
+                day_start: 0,
+                hour_end: None,
+                hour_start: None,
+                minute_end: None,
+                minute_start: None,
+                month_end: 0,
+                month_start: 0,
+                raw_value,
+                diary_sexp: Some(cap.get(1).unwrap().as_str()),
+                repeater_type: None,
+                repeater_unit: None,
+                repeater_value: None,
+                type_s: TimestampType::Diary,
+                warning_type: None,
+                warning_unit: None,
+                warning_value: None,
+                year_end: 0,
+                year_start: 0,
+            };
+            (raw_value, data)
+        } else {
+            let cap = REGEX_TIMESTAMP.captures(rest)?;
+            let activep = cap.get(1).is_some();
+            let date_start = cap.get(1).or_else(|| cap.get(2)).unwrap().as_str();
+
+            let date = REGEX_TIMESTAMP_DATE.captures(date_start)?;
+            let time_range = REGEX_TIMESTAMP_TIME_RANGE
+                .captures(date_start)
+                .map(|c| (number(&c, 1), number(&c, 2)));
+
+            // A range made of two timestamps of the same kind
+            let first_end = cap.get(0).unwrap().end();
+            let date_end = rest[first_end..]
+                .strip_prefix("--")
+                .and_then(|after| REGEX_TIMESTAMP.captures(after))
+                .filter(|end| end.get(1).is_some() == activep)
+                .and_then(|end| {
+                    let len = end.get(0).unwrap().end();
+                    let contents = end.get(1).or_else(|| end.get(2)).unwrap().as_str();
+                    REGEX_TIMESTAMP_DATE
+                        .captures(contents)
+                        .map(|date| (len, date))
+                });
+            let raw_value = match &date_end {
+                Some((len, _)) => &rest[..first_end + 2 + len],
+                None => &rest[..first_end],
+            };
+
+            let type_s = match (activep, time_range.is_some() || date_end.is_some()) {
+                (true, true) => TimestampType::ActiveRange,
+                (true, false) => TimestampType::Active,
+                (false, true) => TimestampType::InactiveRange,
+                (false, false) => TimestampType::Inactive,
+            };
+
+            let repeater = REGEX_TIMESTAMP_REPEATER.captures(raw_value);
+            let warning = REGEX_TIMESTAMP_WARNING.captures(raw_value);
+
+            let year_start = number(&date, 1)?;
+            let month_start = number(&date, 2)?;
+            let day_start = number(&date, 3)?;
+            let hour_start = number(&date, 4);
+            let minute_start = number(&date, 5);
+
+            let (year_end, month_end, day_end, hour_end, minute_end) = match &date_end {
+                Some((_, end)) => (
+                    number(end, 1)?,
+                    number(end, 2)?,
+                    number(end, 3)?,
+                    number(end, 4),
+                    number(end, 5),
+                ),
+                None => (
+                    year_start,
+                    month_start,
+                    day_start,
+                    time_range.and_then(|r| r.0).or(hour_start),
+                    time_range.and_then(|r| r.1).or(minute_start),
+                ),
+            };
+
+            let data = TimestampData {
+                day_end,
+                day_start,
+                hour_end,
+                hour_start,
+                minute_end,
+                minute_start,
+                month_end,
+                month_start,
+                raw_value,
+                diary_sexp: None,
+                repeater_type: repeater.as_ref().map(|c| match c.get(1).unwrap().as_str() {
+                    "++" => RepeaterType::CatchUp,
+                    ".+" => RepeaterType::Restart,
+                    _ => RepeaterType::Cumulate,
+                }),
+                repeater_unit: repeater
+                    .as_ref()
+                    .map(|c| time_unit(c.get(3).unwrap().as_str())),
+                repeater_value: repeater.as_ref().and_then(|c| number(c, 2)),
+                type_s,
+                warning_type: warning.as_ref().map(|c| match c.get(1) {
+                    Some(_) => WarningType::First,
+                    None => WarningType::All,
+                }),
+                warning_unit: warning
+                    .as_ref()
+                    .map(|c| time_unit(c.get(3).unwrap().as_str())),
+                warning_value: warning.as_ref().and_then(|c| number(c, 2)),
+                year_end,
+                year_start,
+            };
+            (raw_value, data)
+        };
+
+        let mut c = self.cursor.borrow_mut();
+        c.set(begin + raw_value.len());
+        let post_blank = c.skip_chars_forward(" \t", None);
+        let end = c.pos();
+        drop(c);
+
+        Some(SyntaxNode::new(
+            Syntax::Timestamp(Box::new(data)),
+            Interval { start: begin, end },
+            None,
+            post_blank,
+            None,
+        ))
+    }
+}
+
+mod test {
+    use crate::data::{
+        RepeaterType, Syntax, SyntaxNode, SyntaxT, TimeUnit, TimestampData, TimestampType,
+        WarningType,
+    };
+    use crate::parser::{ParseGranularity, Parser};
+    use crate::timestamp::collect_timestamps;
+
+    fn timestamp<'a>(node: &'a SyntaxNode) -> &'a TimestampData<'a> {
+        match &node.data {
+            Syntax::Timestamp(ts) => ts,
+            _ => panic!("expected timestamp"),
+        }
+    }
+
+    #[test]
+    fn diary_timestamp() {
+        let text = "<%%(org-float t 4 2)> text";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.timestamp_parser().unwrap();
+        assert_eq!(0, node.location.start);
+        assert_eq!(22, node.location.end);
+        assert_eq!(1, node.post_blank);
+
+        let ts = timestamp(&node);
+        assert_eq!(TimestampType::Diary, ts.type_s);
+        assert_eq!("<%%(org-float t 4 2)>", ts.raw_value);
+        assert_eq!(Some("(org-float t 4 2)"), ts.diary_sexp);
+        assert_eq!(0, ts.year_start);
+        assert_eq!(None, ts.hour_start);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn chrono_conversion() {
+        use chrono::NaiveDate;
+
+        let convert = |text: &str| {
+            let parser = Parser::new(text, ParseGranularity::Object);
+            let node = parser.timestamp_parser().unwrap();
+            let ts = timestamp(&node);
+            (
+                ts.to_naive_date(),
+                ts.to_naive_datetime(),
+                ts.to_naive_range(),
+            )
+        };
+
+        let day = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let (date, datetime, range) = convert("<2024-01-15 Mon>");
+        assert_eq!(Some(day), date);
+        assert_eq!(day.and_hms_opt(0, 0, 0), datetime);
+        assert_eq!(Some((datetime.unwrap(), datetime.unwrap())), range);
+
+        let (date, datetime, _) = convert("[2024-01-15 Mon 9:30]");
+        assert_eq!(Some(day), date);
+        assert_eq!(day.and_hms_opt(9, 30, 0), datetime);
+
+        let (_, _, range) = convert("<2024-01-15 Mon 10:00-12:15>");
+        let (start, end) = range.unwrap();
+        assert_eq!(
+            (
+                day.and_hms_opt(10, 0, 0).unwrap(),
+                day.and_hms_opt(12, 15, 0).unwrap()
+            ),
+            (start, end)
+        );
+        let (_, _, range) = convert("<2024-01-30 Tue>--<2024-02-02 Fri>");
+        let (start, end) = range.unwrap();
+        assert!(start < end);
+        assert_eq!(3, (end - start).num_days());
+
+        assert_eq!((None, None, None), convert("<%%(diary-float t 4 2)>"));
+        assert_eq!(None, convert("<2024-02-30 Fri>").0);
+    }
+
+    #[test]
+    fn date_timestamp() {
+        let text = "<2024-01-15 Mon 10:00>";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.timestamp_parser().unwrap();
+        assert_eq!(text.len(), node.location.end);
+
+        let ts = timestamp(&node);
+        assert_eq!(TimestampType::Active, ts.type_s);
+        assert_eq!(None, ts.diary_sexp);
+        assert_eq!((2024, 1, 15), (ts.year_start, ts.month_start, ts.day_start));
+        assert_eq!((Some(10), Some(0)), (ts.hour_start, ts.minute_start));
+        assert_eq!((2024, 1, 15), (ts.year_end, ts.month_end, ts.day_end));
+    }
+
+    #[test]
+    fn inactive_time_range() {
+        let text = "[2024-01-15 10:00-12:30 +1w]";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.timestamp_parser().unwrap();
+        let ts = timestamp(&node);
+        assert_eq!(TimestampType::InactiveRange, ts.type_s);
+        assert_eq!((Some(12), Some(30)), (ts.hour_end, ts.minute_end));
+        assert_eq!(Some(1), ts.repeater_value);
+    }
+
+    #[test]
+    fn repeater_and_warning() {
+        let cases = vec![
+            (
+                "<2024-01-15 Mon +1w -2d>",
+                (RepeaterType::Cumulate, 1, TimeUnit::Week),
+                (WarningType::All, 2, TimeUnit::Day),
+            ),
+            (
+                "<2024-01-15 Mon 10:00-12:30 ++2m --3h>",
+                (RepeaterType::CatchUp, 2, TimeUnit::Month),
+                (WarningType::First, 3, TimeUnit::Hour),
+            ),
+            (
+                "[2024-01-15 .+10y -1w]",
+                (RepeaterType::Restart, 10, TimeUnit::Year),
+                (WarningType::All, 1, TimeUnit::Week),
+            ),
+        ];
+        for (text, (rtype, rvalue, runit), (wtype, wvalue, wunit)) in cases {
+            let parser = Parser::new(text, ParseGranularity::Object);
+            let node = parser.timestamp_parser().unwrap();
+            let ts = timestamp(&node);
+            assert_eq!(Some(rtype), ts.repeater_type, "{}", text);
+            assert_eq!(Some(rvalue), ts.repeater_value, "{}", text);
+            assert_eq!(Some(runit), ts.repeater_unit, "{}", text);
+            assert_eq!(Some(wtype), ts.warning_type, "{}", text);
+            assert_eq!(Some(wvalue), ts.warning_value, "{}", text);
+            assert_eq!(Some(wunit), ts.warning_unit, "{}", text);
+        }
+
+        let parser = Parser::new("<2024-01-15 Mon 10:00-12:30>", ParseGranularity::Object);
+        let node = parser.timestamp_parser().unwrap();
+        let ts = timestamp(&node);
+        assert_eq!((None, None), (ts.repeater_type, ts.warning_type));
+    }
+
+    #[test]
+    fn timestamp_range() {
+        let text = "<2024-01-01 Mon 9:00>--<2024-01-03 Wed 17:30> next";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.timestamp_parser().unwrap();
+        assert_eq!(46, node.location.end);
+        let ts = timestamp(&node);
+        assert_eq!(TimestampType::ActiveRange, ts.type_s);
+        assert_eq!(
+            "<2024-01-01 Mon 9:00>--<2024-01-03 Wed 17:30>",
+            ts.raw_value
+        );
+        assert_eq!((2024, 1, 1), (ts.year_start, ts.month_start, ts.day_start));
+        assert_eq!((2024, 1, 3), (ts.year_end, ts.month_end, ts.day_end));
+        assert_eq!((Some(9), Some(17)), (ts.hour_start, ts.hour_end));
+        assert_eq!(Some(30), ts.minute_end);
+
+        let text = "[2024-01-01]--[2024-02-01]";
+        let parser = Parser::new(text, ParseGranularity::Object);
+        let node = parser.timestamp_parser().unwrap();
+        let ts = timestamp(&node);
+        assert_eq!(TimestampType::InactiveRange, ts.type_s);
+        assert_eq!(text, ts.raw_value);
+        assert_eq!((2, None), (ts.month_end, ts.hour_end));
+
+        // Two timestamps, not a range
+        for text in &[
+            "<2024-01-01> -- <2024-01-03>",
+            "<2024-01-01>--[2024-01-03]",
+            "<2024-01-01>--<not a date>",
+        ] {
+            let objects =
+                Parser::parse_secondary_string(text, |t| SyntaxT::Paragraph.can_contain(t));
+            let ts = timestamp(&objects[0]);
+            assert_eq!(TimestampType::Active, ts.type_s, "{}", text);
+            assert_eq!("<2024-01-01>", ts.raw_value);
+            assert_eq!(1, ts.day_end);
+        }
+    }
+
+    #[test]
+    fn not_a_timestamp() {
+        for text in &["<not a date>", "[fn:1]", "<2024-01-15", "<%%(diary"] {
+            let parser = Parser::new(text, ParseGranularity::Object);
+            assert!(parser.timestamp_parser().is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn collect() {
+        let text = r"Meeting <2024-01-01 Mon 10:00> noted [2024-01-02 Tue].
+:LOGBOOK:
+CLOCK: [2024-01-03 Wed 9:00]--[2024-01-03 Wed 10:00] =>  1:00
+Note taken on [2024-01-04 Thu]
+:END:
+CLOCK: [2024-01-05 Fri 9:00]
+
+Due <2024-01-06 Sat>--<2024-01-07 Sun> or <%%(diary-float t 4 2)>.
+Not =<2024-02-01 Thu>= nor [[x][<2024-02-02 Fri>]], but *<2024-02-03 Sat>*.
+";
+        let raw = |timestamps: Vec<TimestampData>| -> Vec<String> {
+            timestamps.iter().map(|t| t.raw_value.to_string()).collect()
+        };
+
+        for granularity in vec![ParseGranularity::Element, ParseGranularity::Object] {
+            let parser = Parser::new(text, granularity);
+            let root = parser.parse_buffer();
+            assert_eq!(
+                vec![
+                    "<2024-01-01 Mon 10:00>",
+                    "[2024-01-02 Tue]",
+                    "[2024-01-03 Wed 9:00]--[2024-01-03 Wed 10:00]",
+                    "[2024-01-04 Thu]",
+                    "[2024-01-05 Fri 9:00]",
+                    "<2024-01-06 Sat>--<2024-01-07 Sun>",
+                    "<%%(diary-float t 4 2)>",
+                    "<2024-02-03 Sat>",
+                ],
+                raw(collect_timestamps(&root, text, false, false))
+            );
+            assert_eq!(
+                vec![
+                    "<2024-01-01 Mon 10:00>",
+                    "[2024-01-02 Tue]",
+                    "<2024-01-06 Sat>--<2024-01-07 Sun>",
+                    "<%%(diary-float t 4 2)>",
+                    "<2024-02-03 Sat>",
+                ],
+                raw(collect_timestamps(&root, text, false, true))
+            );
+            assert_eq!(
+                vec![
+                    "<2024-01-01 Mon 10:00>",
+                    "<2024-01-06 Sat>--<2024-01-07 Sun>",
+                    "<%%(diary-float t 4 2)>",
+                    "<2024-02-03 Sat>",
+                ],
+                raw(collect_timestamps(&root, text, true, true))
+            );
+            assert!(collect_timestamps(&root, text, true, false)
+                .iter()
+                .all(|t| t.type_s != TimestampType::Inactive));
+        }
+    }
+}